@@ -11,6 +11,16 @@ pub struct ConfigFile {
     pub target_platform: Option<String>,
     pub embed_scripts: Option<bool>,
     pub compress_output: Option<bool>,
+    pub embed_fonts: Option<bool>,
+    pub embed_resources: Option<bool>,
+    /// Encode fractional numbers as fixed point even for targets with an FPU
+    pub fixed_point: Option<bool>,
+    /// Write colors with 16 bits per channel for wide-gamut renderers
+    pub extended_color: Option<bool>,
+    /// Largest embedded file per resource type, e.g. `image = 524288`
+    pub resource_size_limits: Option<HashMap<String, u64>>,
+    /// Write a `.krb.map` source map next to every output
+    pub debug_info: Option<bool>,
     pub include_directories: Option<Vec<String>>,
     pub custom_variables: Option<HashMap<String, String>>,
     pub max_file_size: Option<u64>,
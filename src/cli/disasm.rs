@@ -0,0 +1,723 @@
+// FILE: src/cli/disasm.rs
+
+//! `kryc disasm`: pretty-print a decoded KRB file with string indices resolved
+//!
+//! The same renderings back `kryc compile --format debug` (annotated listing)
+//! and `--format json` (structured JSON with symbolic names).
+
+use crate::core::color::{color_size, format_color};
+use crate::core::effects::{decode_filters, decode_shadows, format_filters, format_shadows};
+use crate::core::expression::Calc;
+use crate::core::gradient::Gradient;
+use crate::core::types::{ElementType, FontStyle, ResourceFormat, ResourceType, ScriptLanguage, ValueType};
+use crate::core::*;
+use crate::error::{CompilerError, Result};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+pub fn handle_disasm_command(matches: &clap::ArgMatches) -> Result<()> {
+    let input_path = matches.get_one::<String>("input").unwrap();
+    let data = std::fs::read(input_path).map_err(|e| CompilerError::FileNotFound {
+        path: format!("{}: {}", input_path, e),
+    })?;
+    let file = KrbReader::new(&data)?.with_debug_map(KrbDebugMap::load(input_path)?).read()?;
+    let listing = disassemble(&file);
+
+    if let Some(output_path) = matches.get_one::<String>("output") {
+        std::fs::write(output_path, listing)?;
+        println!("📝 Disassembly written to {}", output_path);
+    } else {
+        print!("{}", listing);
+    }
+    Ok(())
+}
+
+/// Render a decoded file as an annotated listing
+pub fn disassemble(file: &KrbFile) -> String {
+    let mut out = String::new();
+    let header = &file.header;
+
+    let stored = if header.stored_size != header.total_size {
+        format!(" ({} stored)", header.stored_size)
+    } else {
+        String::new()
+    };
+    let _ = writeln!(
+        out,
+        "KRB v{}.{}  {} bytes{}  flags 0x{:04X} [{}]",
+        header.version.0,
+        header.version.1,
+        header.total_size,
+        stored,
+        header.flags,
+        flag_names(header.flags).join(", ")
+    );
+
+    section(&mut out, "Strings", file.strings.len(), header.string_offset);
+    for (index, string) in file.strings.iter().enumerate() {
+        let _ = writeln!(out, "  {:#06x}  [{}] {:?}", string.offset, index, string.text);
+    }
+
+    section(&mut out, "Elements", file.elements.len(), header.element_offset);
+    if !file.elements.is_empty() {
+        write_element(&mut out, file, &file.elements, 0, 1);
+    }
+
+    section(&mut out, "Styles", file.styles.len(), header.style_offset);
+    for style in &file.styles {
+        let _ = writeln!(
+            out,
+            "  {:#06x}  style {} {}{}",
+            style.offset,
+            style.id,
+            string_ref(file, style.name_index),
+            source_comment(file.style_location(style.id))
+        );
+        for property in &style.properties {
+            write_property(&mut out, file, property, 2);
+        }
+    }
+
+    section(&mut out, "Components", file.components.len(), header.component_offset);
+    for component in &file.components {
+        let _ = writeln!(out, "  {:#06x}  component {}", component.offset, string_ref(file, component.name_index));
+        for property in &component.properties {
+            let _ = writeln!(
+                out,
+                "  {:#06x}      {} : {} = {:?}",
+                property.offset,
+                string_ref(file, property.name_index),
+                value_type_name(property.value_type_hint),
+                String::from_utf8_lossy(&property.default_value)
+            );
+        }
+        if !component.template.is_empty() {
+            write_element(&mut out, file, &component.template, 0, 2);
+        }
+    }
+
+    section(&mut out, "Animations", file.animations.len(), header.animation_offset);
+    for (index, animation) in file.animations.iter().enumerate() {
+        let _ = writeln!(out, "  {:#06x}  [{}] keyframes {}", animation.offset, index, string_ref(file, animation.name_index));
+        for keyframe in &animation.keyframes {
+            let _ = writeln!(out, "  {:#06x}      {}%", keyframe.offset, keyframe.position);
+            for property in &keyframe.properties {
+                write_property(&mut out, file, property, 4);
+            }
+        }
+    }
+
+    section(&mut out, "Scripts", file.scripts.len(), header.script_offset);
+    for script in &file.scripts {
+        let language = ScriptLanguage::from_u8(script.language_id)
+            .map(|l| format!("{:?}", l))
+            .unwrap_or_else(|| format!("Language(0x{:02X})", script.language_id));
+        let storage = if script.storage_format == 0 { "inline" } else { "external" };
+        let _ = writeln!(
+            out,
+            "  {:#06x}  script {} {} {} ({} bytes)",
+            script.offset,
+            language,
+            string_ref(file, script.name_index),
+            storage,
+            script.data_size
+        );
+        for &entry_point in &script.entry_points {
+            let _ = writeln!(out, "            entry {}", string_ref(file, entry_point));
+        }
+    }
+
+    section(&mut out, "Resources", file.resources.len(), header.resource_offset);
+    for resource in &file.resources {
+        let kind = ResourceType::from_u8(resource.resource_type)
+            .map(|t| format!("{:?}", t))
+            .unwrap_or_else(|| format!("Type(0x{:02X})", resource.resource_type));
+        let format = ResourceFormat::from_u8(resource.format)
+            .map(|f| format!("{:?}", f))
+            .unwrap_or_else(|| format!("Format(0x{:02X})", resource.format));
+        let _ = writeln!(
+            out,
+            "  {:#06x}  {} {} {} data {}",
+            resource.offset,
+            kind,
+            format,
+            string_ref(file, resource.name_index),
+            string_ref(file, resource.data_string_index)
+        );
+        if let Some(font) = &resource.font {
+            let _ = writeln!(out, "            face {}", font_face_text(file, font));
+        }
+        if !resource.data.is_empty() {
+            let _ = writeln!(out, "            ({} bytes inline)", resource.data.len());
+        }
+        if let Some(shared) = resource.shared_with {
+            let _ = writeln!(out, "            (data of resource {})", shared);
+        }
+    }
+
+    section(&mut out, "Template variables", file.template_variables.len(), header.template_variable_offset);
+    for variable in &file.template_variables {
+        let _ = writeln!(
+            out,
+            "  {:#06x}  {} : {} = {}",
+            variable.offset,
+            string_ref(file, variable.name_index),
+            value_type_name(variable.value_type),
+            string_ref(file, variable.default_value_index)
+        );
+    }
+
+    section(&mut out, "Template bindings", file.template_bindings.len(), header.template_binding_offset);
+    for binding in &file.template_bindings {
+        let variables: Vec<String> = binding.variable_indices.iter().map(|&i| format!("var {}", i)).collect();
+        let _ = writeln!(
+            out,
+            "  {:#06x}  element {} {} <- {} [{}]",
+            binding.offset,
+            binding.element_index,
+            property_name(binding.property_id),
+            string_ref(file, binding.expression_index),
+            variables.join(", ")
+        );
+    }
+
+    section(&mut out, "Transforms", file.transforms.len(), header.transform_offset);
+    for (index, transform) in file.transforms.iter().enumerate() {
+        let kind = TransformType::from_u8(transform.transform_type)
+            .map(|t| format!("{:?}", t))
+            .unwrap_or_else(|| format!("Type(0x{:02X})", transform.transform_type));
+        let _ = writeln!(out, "  {:#06x}  [{}] {}", transform.offset, index, kind);
+        for property in &transform.properties {
+            let name = TransformPropertyType::from_u8(property.property_type)
+                .map(|t| format!("{:?}", t))
+                .unwrap_or_else(|| format!("Property(0x{:02X})", property.property_type));
+            let _ = writeln!(
+                out,
+                "  {:#06x}      {} = {}",
+                property.offset,
+                name,
+                format_value(file, property.value_type, &property.value)
+            );
+        }
+    }
+
+    // The theme table only exists in files that have themes
+    if header.has_feature(FLAG_HAS_THEMES) {
+        let table = &file.theme_table;
+        section(&mut out, "Themes", table.themes.len(), header.theme_offset);
+        for (index, slot) in table.slots.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "  {:#06x}  slot {} {} {}",
+                slot.offset,
+                index,
+                string_ref(file, slot.name_index),
+                value_type_name(slot.value_type)
+            );
+        }
+        for (index, theme) in table.themes.iter().enumerate() {
+            let _ = writeln!(out, "  {:#06x}  [{}] {}", theme.offset, index, string_ref(file, theme.name_index));
+            for (slot, value) in table.slots.iter().zip(&theme.values) {
+                let name = file.string(slot.name_index).unwrap_or("?");
+                let _ = writeln!(out, "              {} = {}", name, format_value(file, slot.value_type, value));
+            }
+        }
+    }
+
+    out
+}
+
+/// Render a decoded file as JSON with every string and type reference resolved
+pub fn to_json(file: &KrbFile) -> Value {
+    let header = &file.header;
+    json!({
+        "version": format!("{}.{}", header.version.0, header.version.1),
+        "flags": flag_names(header.flags),
+        "size": header.total_size,
+        "stored_size": header.stored_size,
+        "strings": file.strings.iter().map(|s| s.text.as_str()).collect::<Vec<_>>(),
+        "elements": if file.elements.is_empty() { Value::Null } else { element_json(file, &file.elements, 0) },
+        "styles": file.styles.iter().map(|style| json!({
+            "id": style.id,
+            "name": string_json(file, style.name_index),
+            "properties": style.properties.iter().map(|p| property_json(file, p)).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "components": file.components.iter().map(|component| json!({
+            "name": string_json(file, component.name_index),
+            "properties": component.properties.iter().map(|p| json!({
+                "name": string_json(file, p.name_index),
+                "type": value_type_name(p.value_type_hint),
+                "default": String::from_utf8_lossy(&p.default_value),
+            })).collect::<Vec<_>>(),
+            "template": if component.template.is_empty() { Value::Null } else { element_json(file, &component.template, 0) },
+        })).collect::<Vec<_>>(),
+        "animations": file.animations.iter().map(|animation| json!({
+            "name": string_json(file, animation.name_index),
+            "keyframes": animation.keyframes.iter().map(|keyframe| json!({
+                "offset": keyframe.position,
+                "properties": keyframe.properties.iter().map(|p| property_json(file, p)).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "scripts": file.scripts.iter().map(|script| json!({
+            "language": ScriptLanguage::from_u8(script.language_id).map(|l| format!("{:?}", l)),
+            "name": string_json(file, script.name_index),
+            "storage": if script.storage_format == 0 { "inline" } else { "external" },
+            "entry_points": script.entry_points.iter().map(|&i| string_json(file, i)).collect::<Vec<_>>(),
+            "code": if script.storage_format == 0 { Value::from(String::from_utf8_lossy(&script.code)) } else { Value::Null },
+            "resource": if script.storage_format == 0 { Value::Null } else { Value::from(script.data_size) },
+        })).collect::<Vec<_>>(),
+        "resources": file.resources.iter().map(|resource| json!({
+            "type": ResourceType::from_u8(resource.resource_type).map(|t| format!("{:?}", t)),
+            "format": ResourceFormat::from_u8(resource.format).map(|f| format!("{:?}", f)),
+            "name": string_json(file, resource.name_index),
+            "data": string_json(file, resource.data_string_index),
+            "font": resource.font.as_ref().map(|font| json!({
+                "weight": font.weight,
+                "style": FontStyle::from_u8(font.style).map(|s| s.to_name()),
+                "fallbacks": font.fallbacks.iter().map(|&i| string_json(file, i)).collect::<Vec<_>>(),
+            })),
+            "inline_size": if resource.data.is_empty() { Value::Null } else { Value::from(resource.data.len()) },
+            "shared_with": resource.shared_with,
+        })).collect::<Vec<_>>(),
+        "template_variables": file.template_variables.iter().map(|variable| json!({
+            "name": string_json(file, variable.name_index),
+            "type": value_type_name(variable.value_type),
+            "default": string_json(file, variable.default_value_index),
+        })).collect::<Vec<_>>(),
+        "template_bindings": file.template_bindings.iter().map(|binding| json!({
+            "element": binding.element_index,
+            "property": property_name(binding.property_id),
+            "expression": string_json(file, binding.expression_index),
+            "variables": binding.variable_indices.iter().map(|&i| {
+                file.template_variables.get(i as usize).map_or(Value::Null, |v| string_json(file, v.name_index))
+            }).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "transforms": file.transforms.iter().map(|transform| json!({
+            "type": TransformType::from_u8(transform.transform_type).map(|t| format!("{:?}", t)),
+            "properties": transform.properties.iter().map(|p| json!({
+                "name": TransformPropertyType::from_u8(p.property_type).map(|t| format!("{:?}", t)),
+                "type": value_type_name(p.value_type),
+                "value": value_json(file, p.value_type, &p.value),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "themes": file.theme_table.themes.iter().map(|theme| json!({
+            "name": string_json(file, theme.name_index),
+            "tokens": file.theme_table.slots.iter().zip(&theme.values).map(|(slot, value)| json!({
+                "name": string_json(file, slot.name_index),
+                "type": value_type_name(slot.value_type),
+                "value": value_json(file, slot.value_type, value),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn element_json(file: &KrbFile, elements: &[KrbElementRecord], index: usize) -> Value {
+    let element = &elements[index];
+    json!({
+        "type": ElementType::from_u8(element.element_type).map(|t| format!("{:?}", t)),
+        "id": if element.id_string_index == 0 { Value::Null } else { string_json(file, element.id_string_index) },
+        "style": file.style(element.style_id).map(|s| string_json(file, s.name_index)),
+        "position": [element.pos_x, element.pos_y],
+        "size": [element.width, element.height],
+        "layout": element.layout,
+        "checked": element.checked,
+        "properties": element.properties.iter().map(|p| property_json(file, p)).collect::<Vec<_>>(),
+        "custom_properties": element.custom_properties.iter().map(|p| json!({
+            "name": string_json(file, p.key_index),
+            "type": value_type_name(p.value_type),
+            "value": value_json(file, p.value_type, &p.value),
+        })).collect::<Vec<_>>(),
+        "states": element.state_property_sets.iter().map(|set| json!({
+            "states": state_names(set.state_flags),
+            "properties": set.properties.iter().map(|p| property_json(file, p)).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "events": element.events.iter().map(|event| json!({
+            "event": event_name(event.event_type),
+            "callback": string_json(file, event.callback_id),
+        })).collect::<Vec<_>>(),
+        "animations": element.animations.iter().map(|animation| json!({
+            "kind": if animation.kind == ANIMATION_KIND_TRANSITION { "transition" } else { "keyframes" },
+            "target": animation_target(file, animation),
+            "duration_ms": animation.duration_ms,
+            "delay_ms": animation.delay_ms,
+            "easing": Easing::from_u8(animation.easing).map(Easing::to_name),
+            "iterations": animation.iterations,
+            "direction": AnimationDirection::from_u8(animation.direction).map(AnimationDirection::to_name),
+        })).collect::<Vec<_>>(),
+        "children": element.children.iter().map(|&child| element_json(file, elements, child)).collect::<Vec<_>>(),
+    })
+}
+
+fn property_json(file: &KrbFile, property: &KrbPropertyRecord) -> Value {
+    json!({
+        "name": property_name(property.property_id),
+        "type": value_type_name(property.value_type),
+        "value": value_json(file, property.value_type, &property.value),
+    })
+}
+
+fn string_json(file: &KrbFile, index: u16) -> Value {
+    file.string(index).map_or(Value::Null, Value::from)
+}
+
+/// JSON counterpart of `format_value`: numbers stay numbers, references become text
+fn value_json(file: &KrbFile, value_type: u8, value: &[u8]) -> Value {
+    match (ValueType::from_u8(value_type), value) {
+        (Some(ValueType::Color), [_, _, _, _] | [_, _, _, _, _, _, _, _]) => Value::from(format_color(value)),
+        (Some(ValueType::Gradient), _) => Value::from(format_gradient(file, value)),
+        (Some(ValueType::Shadow), _) => Value::from(decode_shadows(value, color_size(file.header.flags)).map(|s| format_shadows(&s))),
+        (Some(ValueType::Filter), _) => Value::from(decode_filters(value, color_size(file.header.flags)).map(|f| format_filters(&f))),
+        (Some(ValueType::Calc), _) => Value::from(format_calc(file, value)),
+        (Some(ValueType::String | ValueType::TemplateVariable), _) => {
+            decode_index(value).map_or(Value::Null, |index| string_json(file, index))
+        }
+        (Some(ValueType::Resource), _) => {
+            decode_index(value).and_then(|index| resource_path(file, index)).map_or(Value::Null, Value::from)
+        }
+        (Some(ValueType::Byte | ValueType::Enum | ValueType::StyleId), [byte]) => Value::from(*byte),
+        (Some(ValueType::Bool), [byte]) => Value::from(*byte != 0),
+        (Some(ValueType::Short), [lo, hi]) => Value::from(u16::from_le_bytes([*lo, *hi])),
+        (Some(ValueType::Float | ValueType::Percentage), [a, b, c, d]) => Value::from(number(file, [*a, *b, *c, *d])),
+        (Some(ValueType::Percentage), [lo, hi]) => Value::from(u16::from_le_bytes([*lo, *hi]) as f64 / 256.0),
+        (Some(ValueType::CSSUnit), [a, b, c, d, unit]) => Value::from(css_unit(file, [*a, *b, *c, *d], *unit)),
+        (Some(ValueType::TransformMatrix), _) if value.len() % 4 == 0 => {
+            Value::from(value.chunks_exact(4).map(|n| number(file, [n[0], n[1], n[2], n[3]])).collect::<Vec<_>>())
+        }
+        (Some(ValueType::Transform), [lo, hi]) => Value::from(u16::from_le_bytes([*lo, *hi])),
+        (Some(ValueType::ThemeToken), _) => {
+            decode_index(value).and_then(|index| theme_token(file, index)).map_or(Value::Null, Value::from)
+        }
+        (Some(ValueType::Int), [a, b, c, d]) => Value::from(i32::from_le_bytes([*a, *b, *c, *d])),
+        (Some(ValueType::EdgeInsets), [top, right, bottom, left]) => json!([top, right, bottom, left]),
+        _ => Value::from(hex::encode(value)),
+    }
+}
+
+fn section(out: &mut String, title: &str, count: usize, offset: u32) {
+    let _ = writeln!(out, "\n== {} ({}) @ {:#06x}", title, count, offset);
+}
+
+fn write_element(out: &mut String, file: &KrbFile, elements: &[KrbElementRecord], index: usize, depth: usize) {
+    let element = &elements[index];
+    let indent = "  ".repeat(depth);
+    let kind = ElementType::from_u8(element.element_type)
+        .map(|t| format!("{:?}", t))
+        .unwrap_or_else(|| format!("Element(0x{:02X})", element.element_type));
+
+    let mut line = format!("  {:#06x}{}{}", element.offset, indent, kind);
+    if element.id_string_index != 0 {
+        let _ = write!(line, " id={}", string_ref(file, element.id_string_index));
+    }
+    if element.style_id != 0 {
+        let style_name = file
+            .style(element.style_id)
+            .map(|s| string_ref(file, s.name_index))
+            .unwrap_or_else(|| "<missing>".to_string());
+        let _ = write!(line, " style={} {}", element.style_id, style_name);
+    }
+    if element.pos_x != 0 || element.pos_y != 0 {
+        let _ = write!(line, " pos=({}, {})", element.pos_x, element.pos_y);
+    }
+    if element.width != 0 || element.height != 0 {
+        let _ = write!(line, " size=({}, {})", element.width, element.height);
+    }
+    if element.layout != 0 {
+        let _ = write!(line, " layout=0x{:02X}", element.layout);
+    }
+    if element.checked {
+        line.push_str(" checked");
+    }
+    line.push_str(&source_comment(file.element_location(index as u16)));
+    if let Some(component) = file.debug_map.as_ref().and_then(|map| map.element(index as u16)?.component.as_ref()) {
+        let _ = write!(line, " (from {})", component.component);
+    }
+    let _ = writeln!(out, "{}", line);
+
+    for property in &element.properties {
+        write_property(out, file, property, depth + 1);
+    }
+    for property in &element.custom_properties {
+        let _ = writeln!(
+            out,
+            "  {:#06x}{}{} = {}",
+            property.offset,
+            "  ".repeat(depth + 1),
+            string_ref(file, property.key_index),
+            format_value(file, property.value_type, &property.value)
+        );
+    }
+    for set in &element.state_property_sets {
+        let selector = match set.state_flags {
+            STATE_MEDIA => "@media".to_string(),
+            flags => format!("&:{}", state_names(flags).join("+")),
+        };
+        let _ = writeln!(out, "  {:#06x}{}{}", set.offset, "  ".repeat(depth + 1), selector);
+        for property in &set.properties {
+            write_property(out, file, property, depth + 2);
+        }
+    }
+    for event in &element.events {
+        let _ = writeln!(
+            out,
+            "  {:#06x}{}on {} -> {}",
+            event.offset,
+            "  ".repeat(depth + 1),
+            event_name(event.event_type),
+            string_ref(file, event.callback_id)
+        );
+    }
+    for animation in &element.animations {
+        let _ = writeln!(
+            out,
+            "  {:#06x}{}{}",
+            animation.offset,
+            "  ".repeat(depth + 1),
+            animation_text(file, animation)
+        );
+    }
+
+    for &child in &element.children {
+        write_element(out, file, elements, child, depth + 1);
+    }
+}
+
+/// `  ; main.kry:3:5` when a source map is attached
+fn source_comment(location: Option<&DebugLocation>) -> String {
+    location.map(|location| format!("  ; {}", location)).unwrap_or_default()
+}
+
+/// `animate fade_in 300ms ease_out delay 0ms x1 normal`, or `transition Opacity 200ms ...`
+/// The file a resource refers to
+fn resource_path(file: &KrbFile, index: u16) -> Option<&str> {
+    file.resources.get(index as usize).and_then(|resource| file.string(resource.data_string_index))
+}
+
+fn font_face_text(file: &KrbFile, font: &KrbFontFaceRecord) -> String {
+    let style = FontStyle::from_u8(font.style)
+        .map(|s| s.to_name().to_string())
+        .unwrap_or_else(|| format!("style(0x{:02X})", font.style));
+    let mut text = format!("{} {}", font.weight, style);
+    if !font.fallbacks.is_empty() {
+        let fallbacks: Vec<_> = font.fallbacks.iter().map(|&i| string_ref(file, i)).collect();
+        text.push_str(&format!(" fallback {}", fallbacks.join(", ")));
+    }
+    text
+}
+
+fn animation_text(file: &KrbFile, animation: &KrbAnimationRefRecord) -> String {
+    let kind = if animation.kind == ANIMATION_KIND_TRANSITION { "transition" } else { "animate" };
+    let easing = Easing::from_u8(animation.easing)
+        .map(|e| e.to_name().to_string())
+        .unwrap_or_else(|| format!("easing(0x{:02X})", animation.easing));
+    let direction = AnimationDirection::from_u8(animation.direction)
+        .map(|d| d.to_name().to_string())
+        .unwrap_or_else(|| format!("direction(0x{:02X})", animation.direction));
+    let iterations = match animation.iterations {
+        0 => "infinite".to_string(),
+        count => format!("x{}", count),
+    };
+    format!(
+        "{} {} {}ms {} delay {}ms {} {}",
+        kind,
+        animation_target(file, animation),
+        animation.duration_ms,
+        easing,
+        animation.delay_ms,
+        iterations,
+        direction
+    )
+}
+
+/// The keyframes name an animation plays, or the property a transition animates
+fn animation_target(file: &KrbFile, animation: &KrbAnimationRefRecord) -> String {
+    if animation.kind == ANIMATION_KIND_TRANSITION {
+        return u8::try_from(animation.target).map_or_else(|_| format!("Property(0x{:04X})", animation.target), property_name);
+    }
+    match file.animations.get(animation.target as usize) {
+        Some(keyframes) => file.string(keyframes.name_index).unwrap_or("<out of range>").to_string(),
+        None => format!("animation {} <missing>", animation.target),
+    }
+}
+
+fn write_property(out: &mut String, file: &KrbFile, property: &KrbPropertyRecord, depth: usize) {
+    let _ = writeln!(
+        out,
+        "  {:#06x}{}{} = {}",
+        property.offset,
+        "  ".repeat(depth),
+        property_name(property.property_id),
+        format_value(file, property.value_type, &property.value)
+    );
+}
+
+/// `#index "text"` for a string table reference
+pub(crate) fn string_ref(file: &KrbFile, index: u16) -> String {
+    match file.string(index) {
+        Some(text) => format!("#{} {:?}", index, text),
+        None => format!("#{} <out of range>", index),
+    }
+}
+
+pub(crate) fn property_name(id: u8) -> String {
+    PropertyId::from_u8(id)
+        .map(|p| format!("{:?}", p))
+        .unwrap_or_else(|| format!("Property(0x{:02X})", id))
+}
+
+pub(crate) fn value_type_name(value_type: u8) -> String {
+    ValueType::from_u8(value_type)
+        .map(|t| format!("{:?}", t))
+        .unwrap_or_else(|| format!("Type(0x{:02X})", value_type))
+}
+
+fn format_gradient(file: &KrbFile, value: &[u8]) -> Option<String> {
+    Gradient::decode(value, color_size(file.header.flags)).map(|gradient| gradient.to_string())
+}
+
+fn format_calc(file: &KrbFile, value: &[u8]) -> Option<String> {
+    Calc::decode(value, NumberFormat::from_flags(file.header.flags)).map(|calc| calc.to_string())
+}
+
+/// Decode a property value according to its value type
+pub(crate) fn format_value(file: &KrbFile, value_type: u8, value: &[u8]) -> String {
+    let decoded = match (ValueType::from_u8(value_type), value) {
+        (Some(ValueType::Color), _) => format_color(value),
+        (Some(ValueType::Gradient), _) => format_gradient(file, value),
+        (Some(ValueType::Shadow), _) => decode_shadows(value, color_size(file.header.flags)).map(|s| format_shadows(&s)),
+        (Some(ValueType::Filter), _) => decode_filters(value, color_size(file.header.flags)).map(|f| format_filters(&f)),
+        (Some(ValueType::Calc), _) => format_calc(file, value),
+        (Some(ValueType::String | ValueType::TemplateVariable), _) => decode_index(value).map(|index| string_ref(file, index)),
+        (Some(ValueType::Resource), _) => decode_index(value).map(|index| match resource_path(file, index) {
+            Some(path) => format!("#{} {:?}", index, path),
+            None => format!("#{} <out of range>", index),
+        }),
+        (Some(ValueType::Byte | ValueType::Enum | ValueType::StyleId), [byte]) => Some(byte.to_string()),
+        (Some(ValueType::Bool), [byte]) => Some((*byte != 0).to_string()),
+        (Some(ValueType::Short), [lo, hi]) => Some(u16::from_le_bytes([*lo, *hi]).to_string()),
+        (Some(ValueType::Float | ValueType::Percentage), [a, b, c, d]) => Some(number(file, [*a, *b, *c, *d]).to_string()),
+        // Opacity: 8.8 fixed point
+        (Some(ValueType::Percentage), [lo, hi]) => Some((u16::from_le_bytes([*lo, *hi]) as f64 / 256.0).to_string()),
+        (Some(ValueType::CSSUnit), [a, b, c, d, unit]) => Some(css_unit(file, [*a, *b, *c, *d], *unit)),
+        (Some(ValueType::TransformMatrix), _) if value.len() % 4 == 0 => {
+            let numbers: Vec<String> = value.chunks_exact(4).map(|n| number(file, [n[0], n[1], n[2], n[3]]).to_string()).collect();
+            Some(format!("[{}]", numbers.join(", ")))
+        }
+        (Some(ValueType::Transform), [lo, hi]) => Some(format!("#{}", u16::from_le_bytes([*lo, *hi]))),
+        (Some(ValueType::ThemeToken), _) => decode_index(value).map(|index| match theme_token(file, index) {
+            Some(token) => format!("#{} ${}", index, token),
+            None => format!("#{} <out of range>", index),
+        }),
+        (Some(ValueType::Int), [a, b, c, d]) => Some(i32::from_le_bytes([*a, *b, *c, *d]).to_string()),
+        (Some(ValueType::EdgeInsets), [top, right, bottom, left]) => {
+            Some(format!("[{}, {}, {}, {}]", top, right, bottom, left))
+        }
+        _ => None,
+    };
+    let decoded = decoded.unwrap_or_else(|| hex::encode(value));
+    format!("{} {}", value_type_name(value_type), decoded)
+}
+
+/// Name of the token held by a theme table slot
+fn theme_token(file: &KrbFile, slot: u16) -> Option<&str> {
+    file.string(file.theme_table.slots.get(slot as usize)?.name_index)
+}
+
+/// A 32-bit number in the file's encoding
+fn number(file: &KrbFile, bytes: [u8; 4]) -> f64 {
+    NumberFormat::from_flags(file.header.flags).decode(bytes)
+}
+
+fn css_unit(file: &KrbFile, bytes: [u8; 4], unit: u8) -> String {
+    match CSSUnit::from_u8(unit) {
+        Some(unit) => format!("{}{}", number(file, bytes), unit.suffix()),
+        None => format!("{} unit(0x{:02X})", number(file, bytes), unit),
+    }
+}
+
+fn flag_names(flags: u16) -> Vec<&'static str> {
+    [
+        (FLAG_HAS_STYLES, "HAS_STYLES"),
+        (FLAG_HAS_COMPONENT_DEFS, "HAS_COMPONENT_DEFS"),
+        (FLAG_HAS_ANIMATIONS, "HAS_ANIMATIONS"),
+        (FLAG_HAS_RESOURCES, "HAS_RESOURCES"),
+        (FLAG_COMPRESSED, "COMPRESSED"),
+        (FLAG_FIXED_POINT, "FIXED_POINT"),
+        (FLAG_EXTENDED_COLOR, "EXTENDED_COLOR"),
+        (FLAG_HAS_APP, "HAS_APP"),
+        (FLAG_HAS_SCRIPTS, "HAS_SCRIPTS"),
+        (FLAG_HAS_STATE_PROPERTIES, "HAS_STATE_PROPERTIES"),
+        (FLAG_HAS_TEMPLATE_VARIABLES, "HAS_TEMPLATE_VARIABLES"),
+        (FLAG_HAS_TRANSFORMS, "HAS_TRANSFORMS"),
+        (FLAG_WIDE_INDICES, "WIDE_INDICES"),
+        (FLAG_HAS_THEMES, "HAS_THEMES"),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, name)| *name)
+    .collect()
+}
+
+fn state_names(flags: u8) -> Vec<&'static str> {
+    [
+        (STATE_HOVER, "hover"),
+        (STATE_ACTIVE, "active"),
+        (STATE_FOCUS, "focus"),
+        (STATE_DISABLED, "disabled"),
+        (STATE_CHECKED, "checked"),
+        (STATE_MEDIA, "media"),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, name)| *name)
+    .collect()
+}
+
+pub(crate) fn event_name(event_type: u8) -> String {
+    match event_type {
+        EVENT_TYPE_CLICK => "click".to_string(),
+        EVENT_TYPE_PRESS => "press".to_string(),
+        EVENT_TYPE_RELEASE => "release".to_string(),
+        EVENT_TYPE_HOVER => "hover".to_string(),
+        EVENT_TYPE_FOCUS => "focus".to_string(),
+        EVENT_TYPE_BLUR => "blur".to_string(),
+        EVENT_TYPE_CHANGE => "change".to_string(),
+        EVENT_TYPE_SUBMIT => "submit".to_string(),
+        other => format!("event(0x{:02X})", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::test_support::compile;
+
+    #[test]
+    fn test_disassemble_resolves_strings() {
+        let data = compile(
+            "style \"card\" {\n    background_color: \"#FF0000FF\"\n}\n\nApp {\n    Container {\n        style: \"card\"\n        Button {\n            text: \"Go\"\n            onClick: \"go\"\n        }\n    }\n}\n",
+        )
+        .unwrap();
+        let listing = disassemble(&KrbReader::new(&data).unwrap().read().unwrap());
+
+        assert!(listing.contains("Container style=1 #0 \"card\""));
+        assert!(listing.contains("TextContent = String"));
+        assert!(listing.contains("on click -> "));
+        assert!(listing.contains("BackgroundColor = Color #FF0000FF"));
+    }
+
+    #[test]
+    fn test_json_uses_symbolic_names() {
+        let data = compile(
+            "style \"card\" {\n    background_color: \"#FF0000FF\"\n}\n\nApp {\n    Container {\n        style: \"card\"\n        Text {\n            text: \"Hello\"\n        }\n    }\n}\n",
+        )
+        .unwrap();
+        let json = to_json(&KrbReader::new(&data).unwrap().read().unwrap());
+
+        let container = &json["elements"]["children"][0];
+        assert_eq!(json["elements"]["type"], "App");
+        assert_eq!(container["style"], "card");
+        assert_eq!(container["children"][0]["properties"][0]["name"], "TextContent");
+        assert_eq!(container["children"][0]["properties"][0]["value"], "Hello");
+        assert_eq!(json["styles"][0]["properties"][0]["value"], "#FF0000FF");
+    }
+}
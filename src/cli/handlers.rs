@@ -1,14 +1,16 @@
 // FILE: src/cli/handlers.rs
 use crate::{
     cli::OutputFormat, // Import from the `cli` module
-    compile_file_with_options, CompilerError, CompilerOptions, Result,
+    compile_file_with_options, CompilationStats, CompilerError, CompilerOptions, Diagnostics, DiskFileProvider,
+    Result,
 };
 
-use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::io::Write;
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::fs;
 
 // --- COMPILE ---
@@ -42,11 +44,20 @@ fn compile_single_file(
     println!("🔨 Compiling {} -> {}", input_path, output_path);
 
     let compile_start = Instant::now();
-    let stats = compile_file_with_options(input_path, output_path, options)?;
+    let stats = compile_file_with_options(input_path, output_path, options)
+        .map_err(|e| report_failure(input_path, e))?;
     let compile_time = compile_start.elapsed();
 
-    println!("✅ Compilation successful!");
-    println!("   Output: {} bytes", stats.output_size);
+    report_warnings(&stats);
+    match stats.warnings.len() {
+        0 => println!("✅ Compilation successful!"),
+        count => println!("✅ Compilation successful! ({})", plural(count, "warning")),
+    }
+    if stats.output_size != stats.uncompressed_size {
+        println!("   Output: {} bytes ({} uncompressed)", stats.output_size, stats.uncompressed_size);
+    } else {
+        println!("   Output: {} bytes", stats.output_size);
+    }
     println!("   Time: {:.2}ms", compile_time.as_millis());
 
     if stats.source_size > 0 {
@@ -72,12 +83,45 @@ fn compile_single_file(
     Ok(())
 }
 
+/// Print the warnings of a successful build with their source snippets
+fn report_warnings(stats: &CompilationStats) {
+    if !stats.warnings.is_empty() {
+        let warnings: Diagnostics = stats.warnings.iter().cloned().collect();
+        eprintln!("{}", warnings.render(&DiskFileProvider));
+    }
+}
+
+/// Print everything a failed build reported, followed by a summary line.
+/// The returned `CompilerError::Diagnostics` tells `main` the error was already shown.
+fn report_failure(input_path: &str, error: CompilerError) -> CompilerError {
+    let diagnostics = Diagnostics::from(error);
+    eprintln!("{}", diagnostics.render(&DiskFileProvider));
+
+    let mut summary = format!("could not compile `{}` due to {}", input_path, plural(diagnostics.error_count(), "error"));
+    if diagnostics.warning_count() > 0 {
+        summary.push_str(&format!("; {} emitted", plural(diagnostics.warning_count(), "warning")));
+    }
+    eprintln!("error: {}", summary);
+    CompilerError::Diagnostics(diagnostics)
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Quiet period that ends a burst of editor save events
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
 fn watch_and_compile(
     input_path: &str,
     output_path: &str,
     options: CompilerOptions,
 ) -> Result<()> {
-    println!("👀 Watching {} for changes...", input_path);
+    println!("👀 Watching {} and its includes for changes...", input_path);
 
     let (tx, rx) = channel();
     let mut watcher = RecommendedWatcher::new(
@@ -97,45 +141,147 @@ fn watch_and_compile(
         ))
     })?;
 
-    watcher
-        .watch(Path::new(input_path), RecursiveMode::NonRecursive)
-        .map_err(|e| {
-            CompilerError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to watch file: {}", e),
-            ))
-        })?;
+    let mut watched_files = BTreeSet::new();
+    let mut watched_dirs = BTreeSet::new();
 
-    if let Err(e) = compile_file_with_options(input_path, output_path, options.clone()) {
-        eprintln!("❌ Initial compilation failed: {}", e);
-    } else {
-        println!("✅ Initial compilation successful");
+    match compile_file_with_options(input_path, output_path, options.clone()) {
+        Ok(stats) => {
+            report_warnings(&stats);
+            println!("✅ Initial compilation successful");
+            watched_files = watch_set(input_path, &options, Some(&stats), &watched_files);
+        }
+        Err(e) => {
+            report_failure(input_path, e);
+            watched_files = watch_set(input_path, &options, None, &watched_files);
+        }
     }
+    update_watched_dirs(&mut watcher, &mut watched_dirs, &watched_files)?;
 
     loop {
-        match rx.recv() {
-            Ok(_event) => {
-                println!("🔄 File changed, recompiling...");
-                match compile_file_with_options(input_path, output_path, options.clone()) {
-                    Ok(stats) => {
-                        println!(
-                            "✅ Recompiled successfully ({} bytes, {}ms)",
-                            stats.output_size, stats.compile_time_ms
-                        );
-                    }
-                    Err(e) => eprintln!("❌ Compilation failed: {}", e),
-                }
-            }
+        let first = match rx.recv() {
+            Ok(event) => event,
             Err(e) => {
                 eprintln!("Watch error: {}", e);
                 break;
             }
+        };
+
+        // Directories are watched so atomic saves (write + rename) are seen; keep only our files
+        let mut changed = BTreeSet::new();
+        let mut pending = Some(first);
+        while let Some(event) = pending {
+            if !matches!(event.kind, EventKind::Access(_)) {
+                changed.extend(event.paths.into_iter().filter(|p| watched_files.contains(p)));
+            }
+            pending = rx.recv_timeout(WATCH_DEBOUNCE).ok();
+        }
+        let Some(trigger) = changed.iter().next() else {
+            continue;
+        };
+
+        if changed.len() > 1 {
+            println!("🔄 {} changed (+{} more), recompiling...", trigger.display(), changed.len() - 1);
+        } else {
+            println!("🔄 {} changed, recompiling...", trigger.display());
+        }
+
+        let stats = match compile_file_with_options(input_path, output_path, options.clone()) {
+            Ok(stats) => {
+                report_warnings(&stats);
+                println!(
+                    "✅ Recompiled successfully ({} bytes, {}ms)",
+                    stats.output_size, stats.compile_time_ms
+                );
+                Some(stats)
+            }
+            Err(e) => {
+                report_failure(input_path, e);
+                None
+            }
+        };
+
+        let files = watch_set(input_path, &options, stats.as_ref(), &watched_files);
+        if files != watched_files {
+            for added in files.difference(&watched_files) {
+                println!("   + watching {}", added.display());
+            }
+            for removed in watched_files.difference(&files) {
+                println!("   - no longer watching {}", removed.display());
+            }
+            watched_files = files;
+            update_watched_dirs(&mut watcher, &mut watched_dirs, &watched_files)?;
         }
     }
 
     Ok(())
 }
 
+/// Files to watch after a build. A successful build reports its dependencies; after
+/// a failed one, keep what was watched and add whatever the include graph still reaches.
+fn watch_set(
+    input_path: &str,
+    options: &CompilerOptions,
+    stats: Option<&crate::CompilationStats>,
+    previous: &BTreeSet<PathBuf>,
+) -> BTreeSet<PathBuf> {
+    let mut files: BTreeSet<PathBuf> = match stats {
+        Some(stats) => stats.dependencies.iter().cloned().collect(),
+        None => {
+            let mut files = previous.clone();
+            let graph = crate::compiler::middle_end::preprocessor::Preprocessor::new()
+                .with_include_directories(&options.include_directories)
+                .process_includes_isolated(input_path);
+            if let Ok(graph) = graph {
+                files.extend(graph.modules.into_keys());
+            }
+            files
+        }
+    };
+    if let Ok(entry) = fs::canonicalize(input_path) {
+        files.insert(entry);
+    }
+    files
+}
+
+/// Directories holding `files`, with those that start and stop being watched
+#[derive(Debug, PartialEq)]
+struct DirChanges {
+    dirs: BTreeSet<PathBuf>,
+    added: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+}
+
+fn dir_changes(watched_dirs: &BTreeSet<PathBuf>, files: &BTreeSet<PathBuf>) -> DirChanges {
+    let dirs: BTreeSet<PathBuf> = files.iter().filter_map(|f| f.parent().map(Path::to_path_buf)).collect();
+    let added = dirs.difference(watched_dirs).cloned().collect();
+    let removed = watched_dirs.difference(&dirs).cloned().collect();
+    DirChanges { dirs, added, removed }
+}
+
+fn update_watched_dirs(
+    watcher: &mut RecommendedWatcher,
+    watched_dirs: &mut BTreeSet<PathBuf>,
+    files: &BTreeSet<PathBuf>,
+) -> Result<()> {
+    let DirChanges { dirs, added, removed } = dir_changes(watched_dirs, files);
+
+    for dir in &removed {
+        // The directory may already be gone; nothing left to unwatch then
+        let _ = watcher.unwatch(dir);
+    }
+    for dir in &added {
+        watcher.watch(dir, RecursiveMode::NonRecursive).map_err(|e| {
+            CompilerError::Io(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Failed to watch {}: {}", dir.display(), e),
+            ))
+        })?;
+    }
+
+    *watched_dirs = dirs;
+    Ok(())
+}
+
 // --- CHECK ---
 pub fn handle_check_command(matches: &clap::ArgMatches) -> Result<()> {
     let input_path = matches.get_one::<String>("input").unwrap();
@@ -155,12 +301,18 @@ fn check_single_file(input_path: &str) -> Result<()> {
         ..Default::default()
     };
     match compile_file_with_options(input_path, "/dev/null", options) {
-        Ok(_) => {
+        Ok(stats) if stats.warnings.is_empty() => {
             println!("✅ {} - No issues found", input_path);
             Ok(())
         }
+        Ok(stats) => {
+            report_warnings(&stats);
+            println!("⚠️  {} - {}", input_path, plural(stats.warnings.len(), "warning"));
+            Ok(())
+        }
         Err(e) => {
-            println!("❌ {} - {}", input_path, e);
+            let e = report_failure(input_path, e);
+            println!("❌ {} - could not be compiled", input_path);
             Err(e)
         }
     }
@@ -210,6 +362,92 @@ fn check_directory_recursive(dir_path: &str) -> Result<()> {
     }
 }
 
+// --- FMT ---
+pub fn handle_fmt_command(matches: &clap::ArgMatches) -> Result<()> {
+    let input_path = matches.get_one::<String>("input").unwrap();
+    let check = matches.get_flag("check");
+
+    let mut files = Vec::new();
+    if Path::new(input_path).is_dir() {
+        for entry in walkdir::WalkDir::new(input_path) {
+            let entry = entry.map_err(|e| {
+                CompilerError::Io(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!("Directory traversal error: {}", e),
+                ))
+            })?;
+            if entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "kry") {
+                files.push(entry.into_path());
+            }
+        }
+        files.sort();
+    } else {
+        files.push(PathBuf::from(input_path));
+    }
+
+    let mut unformatted = 0;
+    let mut error_files = 0;
+    for file in &files {
+        let display = file.display().to_string();
+        let source = fs::read_to_string(file)?;
+        let formatted = match crate::compiler::frontend::formatter::format_source(&source, &display) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                println!("❌ {} - {}", display, e);
+                error_files += 1;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            println!("✗ {} needs formatting", display);
+        } else {
+            fs::write(file, formatted)?;
+            println!("✏️  Formatted {}", display);
+        }
+    }
+
+    if check {
+        println!("\n📊 {} of {} files need formatting", unformatted, files.len());
+    }
+
+    if error_files > 0 {
+        Err(CompilerError::InvalidFormat {
+            message: format!("{} files could not be formatted", error_files),
+        })
+    } else if check && unformatted > 0 {
+        Err(CompilerError::InvalidFormat {
+            message: format!("{} files are not formatted", unformatted),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+// --- VERIFY ---
+pub fn handle_verify_command(matches: &clap::ArgMatches) -> Result<()> {
+    let input_path = matches.get_one::<String>("input").unwrap();
+    let data = fs::read(input_path).map_err(|e| CompilerError::FileNotFound {
+        path: format!("{}: {}", input_path, e),
+    })?;
+
+    let violations = crate::core::verify_krb(&data);
+    if violations.is_empty() {
+        println!("✅ {} is structurally valid", input_path);
+        return Ok(());
+    }
+
+    for violation in &violations {
+        println!("✗ {}", violation);
+    }
+    Err(CompilerError::InvalidFormat {
+        message: format!("{} failed verification with {} violations", input_path, violations.len()),
+    })
+}
+
 // --- ANALYZE ---
 pub fn handle_analyze_command(matches: &clap::ArgMatches) -> Result<()> {
     let input_path = matches.get_one::<String>("input").unwrap();
@@ -630,6 +868,7 @@ fn print_detailed_stats(stats: &crate::CompilationStats) -> Result<()> {
     println!("\n📊 Detailed Compilation Statistics:");
     println!("   Source size: {} bytes", stats.source_size);
     println!("   Output size: {} bytes", stats.output_size);
+    println!("   Uncompressed size: {} bytes", stats.uncompressed_size);
     println!("   Compression ratio: {:.1}%", (1.0 - stats.compression_ratio) * 100.0);
     println!("   Compile time: {}ms", stats.compile_time_ms);
     println!("   Peak memory: {} bytes", stats.peak_memory_usage);
@@ -648,13 +887,70 @@ fn print_detailed_stats(stats: &crate::CompilationStats) -> Result<()> {
 }
 
 fn output_debug_json(output_path: &str) -> Result<()> {
+    let data = fs::read(output_path)?;
+    let file = crate::core::KrbReader::new(&data)?.read()?;
+    let json = serde_json::to_string_pretty(&super::disasm::to_json(&file)).map_err(|e| CompilerError::CodeGen {
+        message: format!("JSON serialization error: {}", e),
+    })?;
+
     let json_path = Path::new(output_path).with_extension("json");
+    fs::write(&json_path, json + "\n")?;
     println!("   Debug JSON: {}", json_path.display());
     Ok(())
 }
 
 fn output_debug_info(output_path: &str) -> Result<()> {
+    let data = fs::read(output_path)?;
+    let file = crate::core::KrbReader::new(&data)?.read()?;
+
     let debug_path = Path::new(output_path).with_extension("debug");
+    fs::write(&debug_path, super::disasm::disassemble(&file))?;
     println!("   Debug info: {}", debug_path.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(paths: &[&Path]) -> BTreeSet<PathBuf> {
+        paths.iter().map(|p| p.to_path_buf()).collect()
+    }
+
+    #[test]
+    fn test_watch_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("lib")).unwrap();
+        fs::write(root.join("lib/card.kry"), "style \"card\" {\n}\n").unwrap();
+        let main = root.join("main.kry");
+        fs::write(&main, "@include \"lib/card.kry\"\n\nApp {\n}\n").unwrap();
+        let input = main.to_string_lossy();
+        let options = CompilerOptions::default();
+
+        // A successful build is watched exactly by its dependencies and the entry file
+        let stats = CompilationStats { dependencies: vec![root.join("lib/card.kry"), root.join("logo.png")], ..Default::default() };
+        let previous = set(&[&root.join("old.kry")]);
+        let files = watch_set(&input, &options, Some(&stats), &previous);
+        assert_eq!(files, set(&[&root.join("lib/card.kry"), &root.join("logo.png"), &main]));
+
+        // A failed build keeps what was watched and adds what the include graph reaches
+        let files = watch_set(&input, &options, None, &previous);
+        assert_eq!(files, set(&[&root.join("lib/card.kry"), &main, &root.join("old.kry")]));
+    }
+
+    #[test]
+    fn test_dir_changes() {
+        let root = Path::new("/project");
+        let watched = set(&[root, &root.join("lib")]);
+        let files = set(&[&root.join("main.kry"), &root.join("theme/colors.kry"), &root.join("theme/fonts.kry")]);
+
+        let changes = dir_changes(&watched, &files);
+        assert_eq!(changes.dirs, set(&[root, &root.join("theme")]));
+        assert_eq!(changes.added, [root.join("theme")]);
+        assert_eq!(changes.removed, [root.join("lib")]);
+
+        let unchanged = dir_changes(&changes.dirs, &files);
+        assert!(unchanged.added.is_empty() && unchanged.removed.is_empty());
+    }
+}
@@ -0,0 +1,664 @@
+//! Language server for KRY files
+//!
+//! Speaks JSON-RPC over stdio and is built on the regular compiler frontend:
+//! the preprocessor's `ModuleGraph` supplies the `@include` closure, `Lexer`
+//! and `Parser` locate definitions, and the compiler's analysis stages validate
+//! the combined module graph. Open editor buffers take precedence over disk.
+
+use crate::compiler::{self, frontend::{lexer::{Lexer, Token, TokenType}, parser::Parser}};
+use crate::compiler::middle_end::{module_context::{ModuleContext, ModuleGraph}, preprocessor::Preprocessor};
+use crate::core::PropertyId;
+use crate::core::types::ElementType;
+use crate::diagnostics::{Diagnostic, Diagnostics, Severity};
+use crate::error::{CompilerError, Result};
+use crate::CompilerOptions;
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Element header keys that are handled outside of `PropertyId`
+const HEADER_KEYS: &[&str] = &[
+    "id", "style", "pos_x", "pos_y", "onClick", "onPress", "onRelease", "onHover",
+    "onFocus", "onBlur", "onChange", "onSubmit", "animation", "transition",
+];
+
+const COMPLETION_KIND_CLASS: u32 = 7;
+const COMPLETION_KIND_PROPERTY: u32 = 10;
+const COMPLETION_KIND_VALUE: u32 = 12;
+const COMPLETION_KIND_STRUCT: u32 = 22;
+
+const ERROR_PARSE: i64 = -32700;
+const ERROR_METHOD_NOT_FOUND: i64 = -32601;
+const ERROR_INVALID_REQUEST: i64 = -32600;
+
+pub fn handle_lsp_command(cli: &super::EnhancedCli, matches: &clap::ArgMatches) -> Result<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let mut server = LanguageServer::with_options(cli.build_lsp_options(matches)?);
+    server.run(&mut stdin.lock(), &mut stdout.lock())
+}
+
+/// A named definition (style or component) found in a module
+#[derive(Debug, Clone)]
+struct Definition {
+    name: String,
+    path: PathBuf,
+    line: usize,
+    column: usize,
+    length: usize,
+}
+
+pub struct LanguageServer {
+    documents: HashMap<PathBuf, String>,
+    /// Files that received diagnostics on behalf of each analyzed root document
+    published: HashMap<PathBuf, HashSet<PathBuf>>,
+    /// Target platform, include directories and defines used to analyze documents
+    options: CompilerOptions,
+    shutdown_requested: bool,
+}
+
+impl LanguageServer {
+    pub fn new() -> Self {
+        Self::with_options(CompilerOptions::default())
+    }
+
+    pub fn with_options(options: CompilerOptions) -> Self {
+        Self {
+            documents: HashMap::new(),
+            published: HashMap::new(),
+            options,
+            shutdown_requested: false,
+        }
+    }
+
+    /// Serve requests until the client sends `exit` or closes the stream
+    pub fn run<R: BufRead, W: Write>(&mut self, reader: &mut R, writer: &mut W) -> Result<()> {
+        while let Some(body) = read_message(reader)? {
+            // A malformed body is answered with a parse error; the stream itself is still usable
+            let message: Value = match serde_json::from_slice(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    write_message(writer, &json!({
+                        "jsonrpc": "2.0",
+                        "id": Value::Null,
+                        "error": { "code": ERROR_PARSE, "message": format!("Malformed JSON-RPC message: {}", e) },
+                    }))?;
+                    continue;
+                }
+            };
+            for outgoing in self.handle_message(&message) {
+                write_message(writer, &outgoing)?;
+            }
+            if message.get("method").and_then(Value::as_str) == Some("exit") {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle one incoming message and return the messages to send back
+    pub fn handle_message(&mut self, message: &Value) -> Vec<Value> {
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let id = message.get("id").cloned();
+
+        match (method, id) {
+            ("initialize", Some(id)) => vec![response(id, json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "completionProvider": { "triggerCharacters": [":", " "] },
+                },
+                "serverInfo": { "name": crate::NAME, "version": crate::VERSION },
+            }))],
+            ("shutdown", Some(id)) => {
+                self.shutdown_requested = true;
+                vec![response(id, Value::Null)]
+            }
+            ("textDocument/definition", Some(id)) => {
+                let result = position_params(&params)
+                    .and_then(|(path, line, character)| self.definition(&path, line, character))
+                    .unwrap_or(Value::Null);
+                vec![response(id, result)]
+            }
+            ("textDocument/completion", Some(id)) => {
+                let items = position_params(&params)
+                    .map(|(path, line, character)| self.completion(&path, line, character))
+                    .unwrap_or_default();
+                vec![response(id, Value::Array(items))]
+            }
+            (_, Some(id)) => {
+                let code = if self.shutdown_requested { ERROR_INVALID_REQUEST } else { ERROR_METHOD_NOT_FOUND };
+                vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": code, "message": format!("Unsupported request: {}", method) },
+                })]
+            }
+            ("textDocument/didOpen", None) => {
+                let document = &params["textDocument"];
+                match (document["uri"].as_str(), document["text"].as_str()) {
+                    (Some(uri), Some(text)) => {
+                        let path = uri_to_path(uri);
+                        self.documents.insert(path.clone(), text.to_string());
+                        self.publish_diagnostics(&path)
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            ("textDocument/didChange", None) => {
+                let uri = params["textDocument"]["uri"].as_str();
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                match (uri, text) {
+                    (Some(uri), Some(text)) => {
+                        let path = uri_to_path(uri);
+                        self.documents.insert(path.clone(), text.to_string());
+                        self.publish_diagnostics(&path)
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            ("textDocument/didSave", None) => match params["textDocument"]["uri"].as_str() {
+                Some(uri) => self.publish_diagnostics(&uri_to_path(uri)),
+                None => Vec::new(),
+            },
+            ("textDocument/didClose", None) => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.documents.remove(&uri_to_path(uri));
+                }
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Text of a file, preferring the open editor buffer over disk
+    fn source_text(&self, path: &Path) -> Option<String> {
+        self.documents
+            .get(path)
+            .cloned()
+            .or_else(|| std::fs::read_to_string(path).ok())
+    }
+
+    /// Build the module graph for a document with open buffers overlaid on disk content
+    fn module_graph(&self, path: &Path) -> (ModuleGraph, Option<CompilerError>) {
+        let mut preprocessor = Preprocessor::new().with_include_directories(&self.options.include_directories);
+        let (mut graph, error) = match preprocessor.process_includes_isolated(&path.to_string_lossy()) {
+            Ok(graph) => (graph, None),
+            Err(e) => {
+                let mut graph = ModuleGraph::new(path.to_path_buf());
+                let mut module = ModuleContext::new(path.to_path_buf());
+                module.content = self.source_text(path).unwrap_or_default();
+                graph.add_module(module);
+                graph.compilation_order = vec![path.to_path_buf()];
+                (graph, Some(e))
+            }
+        };
+
+        for module in graph.modules.values_mut() {
+            if let Some(text) = self.documents.get(&module.file_path) {
+                module.content = text.clone();
+            }
+        }
+
+        (graph, error)
+    }
+
+    /// Run the frontend over a document and its includes, grouping diagnostics by file
+    pub fn diagnose(&self, path: &Path) -> HashMap<PathBuf, Vec<Value>> {
+        let mut diagnostics: HashMap<PathBuf, Vec<Value>> = HashMap::new();
+        diagnostics.insert(path.to_path_buf(), Vec::new());
+
+        let (graph, include_error) = self.module_graph(path);
+        // An unsaved buffer has no file on disk for the preprocessor to read; that is not an error
+        if let Some(error) = include_error.filter(|_| path.exists()) {
+            let text = self.source_text(path).unwrap_or_default();
+            let line = include_error_line(&text, &error.to_string());
+            diagnostics.entry(path.to_path_buf()).or_default().push(diagnostic(line, &error));
+        }
+
+        let mut syntax_ok = true;
+        for module in graph.get_ordered_modules() {
+            let file_name = module.file_path.to_string_lossy().to_string();
+            let result = Lexer::new(&module.content, file_name)
+                .tokenize()
+                .and_then(|tokens| Parser::new(tokens).parse());
+            if let Err(error) = result {
+                syntax_ok = false;
+                for item in Diagnostics::from(error).iter() {
+                    diagnostics.entry(module.file_path.clone()).or_default().push(located_diagnostic(item));
+                }
+            }
+        }
+
+        if syntax_ok {
+            // Errors carry the warnings found alongside them; a clean run may still have warnings
+            let reported = match compiler::analyze_module_graph(&graph, &path.to_string_lossy(), &self.options) {
+                Ok(analysis) => analysis.diagnostics,
+                Err(error) => Diagnostics::from(error),
+            };
+            for item in reported.iter() {
+                let target = item
+                    .span
+                    .as_ref()
+                    .map(|span| PathBuf::from(&span.file))
+                    .filter(|file| graph.modules.contains_key(file))
+                    .unwrap_or_else(|| path.to_path_buf());
+                diagnostics.entry(target).or_default().push(located_diagnostic(item));
+            }
+        }
+
+        diagnostics
+    }
+
+    fn publish_diagnostics(&mut self, path: &Path) -> Vec<Value> {
+        let diagnostics = self.diagnose(path);
+        let current: HashSet<PathBuf> = diagnostics.keys().cloned().collect();
+        let previous = self.published.insert(path.to_path_buf(), current.clone()).unwrap_or_default();
+
+        let mut files: Vec<(PathBuf, Vec<Value>)> = diagnostics.into_iter().collect();
+        files.extend(previous.difference(&current).map(|stale| (stale.clone(), Vec::new())));
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        files
+            .into_iter()
+            .map(|(file, items)| json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": path_to_uri(&file), "diagnostics": items },
+            }))
+            .collect()
+    }
+
+    /// Style and component definitions visible from a document
+    fn definitions(&self, path: &Path) -> (Vec<Definition>, Vec<Definition>) {
+        let (graph, _) = self.module_graph(path);
+        let mut styles = Vec::new();
+        let mut components = Vec::new();
+
+        for module in graph.get_ordered_modules() {
+            let tokens = match tokenize(&module.content, &module.file_path) {
+                Some(tokens) => tokens,
+                None => continue,
+            };
+            let significant: Vec<&Token> = tokens.iter().filter(|t| !is_trivia(t)).collect();
+            for pair in significant.windows(2) {
+                let found = match (&pair[0].token_type, &pair[1].token_type) {
+                    (TokenType::Style, TokenType::String(name)) => Some((&mut styles, name)),
+                    (TokenType::Define, TokenType::Identifier(name)) => Some((&mut components, name)),
+                    _ => None,
+                };
+                if let Some((list, name)) = found {
+                    list.push(Definition {
+                        name: name.clone(),
+                        path: module.file_path.clone(),
+                        line: pair[1].line,
+                        column: pair[1].column,
+                        length: pair[1].span.len(),
+                    });
+                }
+            }
+        }
+
+        (styles, components)
+    }
+
+    /// Resolve the style or component name under the cursor to its definition
+    pub fn definition(&self, path: &Path, line: usize, character: usize) -> Option<Value> {
+        let text = self.source_text(path)?;
+        let tokens = tokenize(&text, path)?;
+        let token = tokens
+            .iter()
+            .rev()
+            .find(|t| t.line == line + 1 && t.column <= character + 1)?;
+
+        let (styles, components) = self.definitions(path);
+        let target = match &token.token_type {
+            TokenType::String(name) => styles.into_iter().find(|d| &d.name == name),
+            TokenType::Identifier(name) => components.into_iter().find(|d| &d.name == name),
+            _ => None,
+        }?;
+
+        Some(json!({
+            "uri": path_to_uri(&target.path),
+            "range": range(target.line, target.column, target.length),
+        }))
+    }
+
+    /// Complete property, element and component names, or style names after `style:`
+    pub fn completion(&self, path: &Path, line: usize, character: usize) -> Vec<Value> {
+        let text = self.source_text(path).unwrap_or_default();
+        let prefix: String = text
+            .lines()
+            .nth(line)
+            .unwrap_or("")
+            .chars()
+            .take(character)
+            .collect();
+        let (styles, components) = self.definitions(path);
+
+        if let Some((key, _)) = prefix.split_once(':') {
+            return match key.trim() {
+                "style" | "extends" => styles
+                    .iter()
+                    .map(|style| json!({
+                        "label": style.name,
+                        "kind": COMPLETION_KIND_VALUE,
+                        "insertText": format!("\"{}\"", style.name),
+                    }))
+                    .collect(),
+                _ => Vec::new(),
+            };
+        }
+
+        let mut items: Vec<Value> = PropertyId::KNOWN_NAMES
+            .iter()
+            .chain(HEADER_KEYS)
+            .map(|name| json!({ "label": name, "kind": COMPLETION_KIND_PROPERTY }))
+            .collect();
+        items.extend(
+            ElementType::KNOWN_NAMES
+                .iter()
+                .map(|name| json!({ "label": name, "kind": COMPLETION_KIND_CLASS })),
+        );
+        items.extend(
+            components
+                .iter()
+                .map(|component| json!({ "label": component.name, "kind": COMPLETION_KIND_STRUCT })),
+        );
+        items
+    }
+}
+
+impl Default for LanguageServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn tokenize(text: &str, path: &Path) -> Option<Vec<Token>> {
+    Lexer::new(text, path.to_string_lossy().to_string()).tokenize().ok()
+}
+
+fn is_trivia(token: &Token) -> bool {
+    matches!(token.token_type, TokenType::Newline | TokenType::Comment(_))
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn range(line: usize, column: usize, length: usize) -> Value {
+    let line = line.saturating_sub(1);
+    let start = column.saturating_sub(1);
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": start + length },
+    })
+}
+
+fn diagnostic(line: usize, error: &CompilerError) -> Value {
+    json!({
+        "range": range(line.max(1), 1, 0),
+        "severity": 1,
+        "source": crate::NAME,
+        "message": error_message(error),
+    })
+}
+
+fn located_diagnostic(item: &Diagnostic) -> Value {
+    let (line, column, length) = item.span.as_ref().map_or((1, 1, 0), |span| (span.line.max(1), span.column.max(1), span.length));
+    let severity = match item.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+    };
+    json!({
+        "range": range(line, column, length),
+        "severity": severity,
+        "source": crate::NAME,
+        "message": item.message,
+    })
+}
+
+fn error_message(error: &CompilerError) -> String {
+    match error {
+        CompilerError::Parse { message, .. }
+        | CompilerError::Semantic { message, .. }
+        | CompilerError::Variable { message, .. }
+        | CompilerError::Component { message, .. }
+        | CompilerError::Script { message, .. } => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Line of the `@include` directive an include error refers to, or 0
+fn include_error_line(text: &str, message: &str) -> usize {
+    text.lines()
+        .position(|line| {
+            line.trim_start()
+                .strip_prefix("@include")
+                .and_then(|rest| rest.trim().strip_prefix('"'))
+                .and_then(|rest| rest.split('"').next())
+                .is_some_and(|include| !include.is_empty() && message.contains(include.trim_start_matches("./")))
+        })
+        .map_or(0, |index| index + 1)
+}
+
+fn position_params(params: &Value) -> Option<(PathBuf, usize, usize)> {
+    let uri = params["textDocument"]["uri"].as_str()?;
+    let line = params["position"]["line"].as_u64()? as usize;
+    let character = params["position"]["character"].as_u64()? as usize;
+    Some((uri_to_path(uri), line, character))
+}
+
+fn uri_to_path(uri: &str) -> PathBuf {
+    let raw = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&decoded).into_owned());
+    std::fs::canonicalize(&path).unwrap_or(path)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for ch in path.to_string_lossy().chars() {
+        match ch {
+            ' ' => uri.push_str("%20"),
+            '#' => uri.push_str("%23"),
+            '%' => uri.push_str("%25"),
+            _ => uri.push(ch),
+        }
+    }
+    uri
+}
+
+/// Read one `Content-Length` framed JSON-RPC message; `None` at end of stream
+fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                // Without a length the body cannot be told apart from the next headers
+                let length = value.trim().parse::<usize>().map_err(|_| CompilerError::InvalidFormat {
+                    message: format!("Invalid Content-Length header '{}'", value.trim()),
+                })?;
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn open(server: &mut LanguageServer, path: &Path, text: &str) -> Vec<Value> {
+        server.handle_message(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": path_to_uri(path), "languageId": "kry", "version": 1, "text": text } },
+        }))
+    }
+
+    #[test]
+    fn test_message_framing_roundtrip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" })).unwrap();
+        let mut reader = std::io::BufReader::new(buffer.as_slice());
+        let body = read_message(&mut reader).unwrap().unwrap();
+        let message: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(message["method"], "shutdown");
+        assert!(read_message(&mut reader).unwrap().is_none());
+
+        let mut reader = std::io::BufReader::new(b"Content-Length: twelve\r\n\r\n{}".as_slice());
+        let error = read_message(&mut reader).unwrap_err();
+        assert!(error.to_string().contains("Invalid Content-Length header 'twelve'"), "{}", error);
+    }
+
+    #[test]
+    fn test_malformed_message_gets_parse_error() {
+        let mut input = Vec::new();
+        write!(input, "Content-Length: 9\r\n\r\n{{\"id\": 1,").unwrap();
+        write_message(&mut input, &json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" })).unwrap();
+        let mut output = Vec::new();
+        LanguageServer::new().run(&mut std::io::BufReader::new(input.as_slice()), &mut output).unwrap();
+
+        let mut reader = std::io::BufReader::new(output.as_slice());
+        let error: Value = serde_json::from_slice(&read_message(&mut reader).unwrap().unwrap()).unwrap();
+        assert_eq!(error["error"]["code"], ERROR_PARSE);
+        let reply: Value = serde_json::from_slice(&read_message(&mut reader).unwrap().unwrap()).unwrap();
+        assert_eq!(reply["id"], 2);
+    }
+
+    #[test]
+    fn test_syntax_error_diagnostic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap().join("main.kry");
+        let mut server = LanguageServer::new();
+
+        let messages = open(&mut server, &path, "App {\n    text: \"ok\"\n    @bogus\n}\n");
+        let diagnostics = messages[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+    }
+
+    #[test]
+    fn test_color_functions_are_folded_before_analysis() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap().join("main.kry");
+        let mut server = LanguageServer::new();
+
+        let text = "@variables {\n    brand: hsl(220, 80%, 50%)\n}\n\nApp {\n    background_color: mix($brand, white, 25%)\n    Text {\n        text: \"Hi\"\n        text_color: contrast(alpha($brand, 0.5))\n    }\n}\n";
+        let messages = open(&mut server, &path, text);
+        assert_eq!(messages[0]["params"]["diagnostics"], json!([]));
+
+        let messages = open(&mut server, &path, "App {\n    width: 10px / 0\n}\n");
+        let diagnostics = messages[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+        assert!(diagnostics[0]["message"].as_str().unwrap().contains("division by zero"), "{}", diagnostics[0]["message"]);
+    }
+
+    #[test]
+    fn test_definition_across_include() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join("styles.kry"), "style \"card\" {\n    background_color: \"#FFFFFFFF\"\n}\n").unwrap();
+        let main = root.join("main.kry");
+        let text = "@include \"styles.kry\"\n\nApp {\n    Container {\n        style: \"card\"\n    }\n}\n";
+        fs::write(&main, text).unwrap();
+
+        let mut server = LanguageServer::new();
+        open(&mut server, &main, text);
+
+        let location = server.definition(&main, 4, 17).unwrap();
+        assert_eq!(location["uri"], path_to_uri(&root.join("styles.kry")));
+        assert_eq!(location["range"]["start"]["line"], 0);
+        assert_eq!(location["range"]["start"]["character"], 6);
+    }
+
+    #[test]
+    fn test_definition_through_include_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let lib = root.join("lib");
+        fs::create_dir(&lib).unwrap();
+        fs::write(lib.join("styles.kry"), "style \"card\" {\n    background_color: \"#FFFFFFFF\"\n}\n").unwrap();
+        let main = root.join("app").join("main.kry");
+        fs::create_dir(main.parent().unwrap()).unwrap();
+        let text = "@include \"styles.kry\"\n\nApp {\n    Container {\n        style: \"card\"\n    }\n}\n";
+        fs::write(&main, text).unwrap();
+
+        let mut options = CompilerOptions::default();
+        options.include_directories.push(lib.to_string_lossy().to_string());
+        let mut server = LanguageServer::with_options(options);
+        let messages = open(&mut server, &main, text);
+        assert!(messages.iter().all(|m| m["params"]["diagnostics"] == json!([])), "{:?}", messages);
+
+        let location = server.definition(&main, 4, 17).unwrap();
+        assert_eq!(location["uri"], path_to_uri(&lib.join("styles.kry")));
+    }
+
+    #[test]
+    fn test_completion_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap().join("main.kry");
+        let mut server = LanguageServer::new();
+        open(&mut server, &path, "style \"card\" {\n}\nApp {\n    \n    style: \n}\n");
+
+        let labels: Vec<String> = server
+            .completion(&path, 3, 4)
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect();
+        assert!(labels.contains(&"background_color".to_string()));
+        assert!(labels.contains(&"animation".to_string()) && labels.contains(&"transition".to_string()));
+        assert!(labels.contains(&"Container".to_string()));
+
+        let styles = server.completion(&path, 4, 11);
+        assert_eq!(styles.len(), 1);
+        assert_eq!(styles[0]["label"], "card");
+    }
+}
@@ -1,7 +1,9 @@
 // FILE: src/cli/mod.rs
 
 mod config;
+mod disasm;
 mod handlers;
+mod lsp;
 
 use crate::error::{CompilerError, Result};
 use crate::{CompilerOptions, TargetPlatform};
@@ -60,6 +62,10 @@ impl EnhancedCli {
             Some(("analyze", sub_matches)) => handlers::handle_analyze_command(sub_matches),
             Some(("init", sub_matches)) => handlers::handle_init_command(sub_matches),
             Some(("benchmark", sub_matches)) => handlers::handle_benchmark_command(sub_matches),
+            Some(("fmt", sub_matches)) => handlers::handle_fmt_command(sub_matches),
+            Some(("lsp", sub_matches)) => lsp::handle_lsp_command(self, sub_matches),
+            Some(("disasm", sub_matches)) => disasm::handle_disasm_command(sub_matches),
+            Some(("verify", sub_matches)) => handlers::handle_verify_command(sub_matches),
             _ => {
                 println!("No subcommand specified. Use --help for usage information.");
                 Ok(())
@@ -97,7 +103,14 @@ impl EnhancedCli {
                     .arg(Arg::new("format").short('f').long("format").value_parser(clap::value_parser!(OutputFormat)).default_value("krb").help("Output format"))
                     .arg(Arg::new("embed-scripts").long("embed-scripts").help("Embed scripts inline instead of external references").action(ArgAction::SetTrue))
                     .arg(Arg::new("compress").long("compress").help("Enable output compression").action(ArgAction::SetTrue))
+                    .arg(Arg::new("embed-fonts").long("embed-fonts").help("Embed font files inline instead of external references").action(ArgAction::SetTrue))
+                    .arg(Arg::new("embed-resources").long("embed-resources").help("Embed all image, font, sound and video files for a self-contained KRB").action(ArgAction::SetTrue))
+                    .arg(Arg::new("fixed-point").long("fixed-point").help("Encode fractional numbers as Q16.16 fixed point for renderers without an FPU").action(ArgAction::SetTrue))
+                    .arg(Arg::new("extended-color").long("extended-color").help("Write colors with 16 bits per channel for wide-gamut renderers").action(ArgAction::SetTrue))
+                    .arg(Arg::new("resource-limit").long("resource-limit").value_name("TYPE=SIZE").help("Largest embedded file for a resource type, e.g. image=512K").action(ArgAction::Append))
                     .arg(Arg::new("debug").short('d').long("debug").help("Enable debug mode with extra validation").action(ArgAction::SetTrue))
+                    .arg(Arg::new("verify-reproducible").long("verify-reproducible").help("Compile twice and fail if the outputs differ").action(ArgAction::SetTrue))
+                    .arg(Arg::new("debug-info").short('g').long("debug-info").help("Write a .krb.map source map next to the output").action(ArgAction::SetTrue))
                     .arg(Arg::new("include").short('I').long("include").value_name("DIR").help("Add include directory").action(ArgAction::Append))
                     .arg(Arg::new("define").short('D').long("define").value_name("VAR=VALUE").help("Define custom variable").action(ArgAction::Append))
                     .arg(Arg::new("stats").long("stats").help("Show detailed compilation statistics").action(ArgAction::SetTrue))
@@ -129,6 +142,30 @@ impl EnhancedCli {
                     .arg(Arg::new("iterations").short('n').long("iterations").value_name("N").help("Number of benchmark iterations").default_value("10"))
                     .arg(Arg::new("warmup").long("warmup").value_name("N").help("Number of warmup iterations").default_value("3")),
             )
+            .subcommand(
+                Command::new("fmt")
+                    .about("Format KRY files in place")
+                    .arg(Arg::new("input").help("Input KRY file or directory").required(true).index(1))
+                    .arg(Arg::new("check").long("check").help("Report unformatted files without modifying them").action(ArgAction::SetTrue)),
+            )
+            .subcommand(
+                Command::new("lsp")
+                    .about("Run the KRY language server over stdio")
+                    .arg(Arg::new("platform").short('p').long("platform").value_parser(clap::value_parser!(Platform)).default_value("universal").help("Target platform"))
+                    .arg(Arg::new("include").short('I').long("include").value_name("DIR").help("Add include directory").action(ArgAction::Append))
+                    .arg(Arg::new("define").short('D').long("define").value_name("VAR=VALUE").help("Define custom variable").action(ArgAction::Append)),
+            )
+            .subcommand(
+                Command::new("disasm")
+                    .about("Print a decoded KRB file with string references resolved")
+                    .arg(Arg::new("input").help("Input KRB file").required(true).index(1))
+                    .arg(Arg::new("output").short('o').long("output").help("Write the listing to a file").action(ArgAction::Set)),
+            )
+            .subcommand(
+                Command::new("verify")
+                    .about("Check every section and cross reference of a KRB file")
+                    .arg(Arg::new("input").help("Input KRB file").required(true).index(1)),
+            )
     }
 
     fn setup_logging(&self, verbose_count: u8) -> Result<()> {
@@ -154,6 +191,51 @@ impl EnhancedCli {
                 OptimizationLevel::Aggressive => 2,
             };
         }
+        self.apply_source_options(&mut options, matches)?;
+        options.debug_mode = matches.get_flag("debug");
+        options.verify_reproducible = matches.get_flag("verify-reproducible");
+        options.generate_debug_info =
+            matches.get_flag("debug-info") || self.config.debug_info.unwrap_or(false);
+        options.embed_scripts =
+            matches.get_flag("embed-scripts") || self.config.embed_scripts.unwrap_or(false);
+        options.compress_output =
+            matches.get_flag("compress") || self.config.compress_output.unwrap_or(false);
+        options.embed_fonts =
+            matches.get_flag("embed-fonts") || self.config.embed_fonts.unwrap_or(false);
+        options.embed_resources =
+            matches.get_flag("embed-resources") || self.config.embed_resources.unwrap_or(false);
+        options.fixed_point =
+            matches.get_flag("fixed-point") || self.config.fixed_point.unwrap_or(false);
+        options.extended_color =
+            matches.get_flag("extended-color") || self.config.extended_color.unwrap_or(false);
+        if let Some(limits) = &self.config.resource_size_limits {
+            for (name, &limit) in limits {
+                options.resource_size_limits.insert(parse_resource_type(name)?, limit);
+            }
+        }
+        if let Some(limits) = matches.get_many::<String>("resource-limit") {
+            for limit in limits {
+                let (name, size) = limit.split_once('=').ok_or_else(|| CompilerError::InvalidFormat {
+                    message: format!("Invalid resource limit: {}. Use TYPE=SIZE format.", limit),
+                })?;
+                options.resource_size_limits.insert(parse_resource_type(name)?, parse_size(size)?);
+            }
+        }
+        if let Some(max_size) = self.config.max_file_size {
+            options.max_file_size = max_size;
+        }
+        Ok(options)
+    }
+
+    /// Options the language server analyzes sources with
+    pub fn build_lsp_options(&self, matches: &clap::ArgMatches) -> Result<CompilerOptions> {
+        let mut options = CompilerOptions::default();
+        self.apply_source_options(&mut options, matches)?;
+        Ok(options)
+    }
+
+    /// The target platform, include directories and defines, which decide how sources read
+    fn apply_source_options(&self, options: &mut CompilerOptions, matches: &clap::ArgMatches) -> Result<()> {
         if let Some(platform) = matches.get_one::<Platform>("platform") {
             options.target_platform = match platform {
                 Platform::Desktop => TargetPlatform::Desktop,
@@ -163,11 +245,6 @@ impl EnhancedCli {
                 Platform::Universal => TargetPlatform::Universal,
             };
         }
-        options.debug_mode = matches.get_flag("debug");
-        options.embed_scripts =
-            matches.get_flag("embed-scripts") || self.config.embed_scripts.unwrap_or(false);
-        options.compress_output =
-            matches.get_flag("compress") || self.config.compress_output.unwrap_or(false);
         if let Some(include_dirs) = matches.get_many::<String>("include") {
             options.include_directories.extend(include_dirs.cloned());
         }
@@ -190,9 +267,27 @@ impl EnhancedCli {
                 options.custom_variables.entry(key.clone()).or_insert_with(|| value.clone());
             }
         }
-        if let Some(max_size) = self.config.max_file_size {
-            options.max_file_size = max_size;
-        }
-        Ok(options)
+        Ok(())
     }
 }
+
+fn parse_resource_type(name: &str) -> Result<crate::core::types::ResourceType> {
+    crate::core::types::ResourceType::from_name(name).ok_or_else(|| CompilerError::InvalidFormat {
+        message: format!("Unknown resource type '{}'. Use image, font, sound, video or custom.", name),
+    })
+}
+
+/// A byte count with an optional K or M suffix
+fn parse_size(size: &str) -> Result<u64> {
+    let upper = size.trim().to_uppercase();
+    let (number, scale) = if let Some(number) = upper.strip_suffix('K') {
+        (number, 1024)
+    } else if let Some(number) = upper.strip_suffix('M') {
+        (number, 1024 * 1024)
+    } else {
+        (upper.as_str(), 1)
+    };
+    number.parse::<u64>().map(|n| n * scale).map_err(|_| CompilerError::InvalidFormat {
+        message: format!("Invalid size '{}'. Use a byte count such as 65536, 64K or 2M.", size),
+    })
+}
@@ -3,6 +3,7 @@
 // use crate::ast::*;
 use crate::error::{CompilerError, Result};
 use crate::core::*;
+use crate::core::types::{ResourceFormat, ValueType};
 use crate::compiler::backend::size_calculator::SizeCalculator;
 use byteorder::{LittleEndian, WriteBytesExt};
 use std::collections::HashMap;
@@ -10,8 +11,12 @@ use std::io::Cursor;
 
 pub struct CodeGenerator {
     output: Vec<u8>,
-    string_offsets: HashMap<u8, u32>,
+    string_offsets: HashMap<u16, u32>,
     element_offsets: HashMap<usize, u32>,
+    verify_output: bool,
+    compress_output: bool,
+    /// Width of indices and counts, chosen by `SizeCalculator`
+    width: IndexWidth,
 }
 
 impl CodeGenerator {
@@ -20,8 +25,24 @@ impl CodeGenerator {
             output: Vec::new(),
             string_offsets: HashMap::new(),
             element_offsets: HashMap::new(),
+            verify_output: false,
+            compress_output: false,
+            width: IndexWidth::Compact,
         }
     }
+
+    /// Run the structural verifier over the generated file before returning it
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify_output = verify;
+        self
+    }
+
+    /// Wrap the generated file in the compressed container (see `core::compression`)
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress_output = compress;
+        self
+    }
+
     fn write_header_with_offsets(&mut self, state: &CompilerState) -> Result<()> {
         // Magic number "KRB1"
         self.output.extend_from_slice(KRB_MAGIC);
@@ -40,12 +61,12 @@ impl CodeGenerator {
             .count() as u16;
         
         // Section counts
-        println!("DEBUG: Writing header_with_offsets counts - elements: {}, styles: {}, strings: {}, scripts: {}", 
+        log::debug!("Writing header_with_offsets counts - elements: {}, styles: {}, strings: {}, scripts: {}", 
                 main_element_count, state.styles.len(), state.strings.len(), state.scripts.len());
         self.output.write_u16::<LittleEndian>(main_element_count)?;
         self.output.write_u16::<LittleEndian>(state.styles.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.component_defs.len() as u16)?;
-        self.output.write_u16::<LittleEndian>(0)?; // animation count
+        self.output.write_u16::<LittleEndian>(state.animations.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.scripts.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.strings.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.resources.len() as u16)?;
@@ -68,6 +89,12 @@ impl CodeGenerator {
         // Total size
         self.output.write_u32::<LittleEndian>(state.total_size)?;
         
+        // Header extension: theme count and offset
+        if state.header_flags & FLAG_HAS_THEMES != 0 {
+            self.output.write_u16::<LittleEndian>(state.themes.len() as u16)?;
+            self.output.write_u32::<LittleEndian>(state.theme_offset)?;
+        }
+        
         Ok(())
     }
 
@@ -77,6 +104,7 @@ impl CodeGenerator {
         // 1. Calculate all section sizes first. This must be done before writing anything.
         let size_calculator = SizeCalculator::new();
         size_calculator.calculate_sizes(state)?;
+        self.width = IndexWidth::from_flags(state.header_flags);
 
         // 2. Write the header with the now-correctly-calculated offsets from the temp_state.
         self.write_header_with_offsets(state)?;
@@ -94,8 +122,8 @@ impl CodeGenerator {
         // Section: Component Definitions
         self.write_component_table(state)?;
         
-        // Section: Animation Data (currently empty/reserved)
-        // No write function needed if it's always empty.
+        // Section: Animation Table
+        self.write_animation_table(state)?;
 
         // Section: Script Table
         self.write_script_table(state)?;
@@ -112,6 +140,9 @@ impl CodeGenerator {
         // Section: Transform Data
         self.write_transform_table(state)?;
         
+        // Section: Theme Table
+        self.write_theme_table(state)?;
+        
         // 4. Final validation (optional but good practice)
         if self.output.len() != state.total_size as usize {
             return Err(CompilerError::CodeGen {
@@ -122,6 +153,20 @@ impl CodeGenerator {
             });
         }
 
+        if self.verify_output {
+            let violations = verify_krb(&self.output);
+            if !violations.is_empty() {
+                let report: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                return Err(CompilerError::CodeGen {
+                    message: format!("Generated KRB failed verification:\n{}", report.join("\n")),
+                });
+            }
+        }
+
+        if self.compress_output {
+            return compress_krb(&self.output);
+        }
+
         Ok(self.output.clone())
     }
     
@@ -143,12 +188,12 @@ impl CodeGenerator {
             .count() as u16;
         
         // Section counts
-        println!("DEBUG: Writing header counts - elements: {}, styles: {}, strings: {}, scripts: {}", 
+        log::debug!("Writing header counts - elements: {}, styles: {}, strings: {}, scripts: {}", 
                 main_element_count, state.styles.len(), state.strings.len(), state.scripts.len());
         self.output.write_u16::<LittleEndian>(main_element_count)?;
         self.output.write_u16::<LittleEndian>(state.styles.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.component_defs.len() as u16)?;
-        self.output.write_u16::<LittleEndian>(0)?; // animation count
+        self.output.write_u16::<LittleEndian>(state.animations.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.scripts.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.strings.len() as u16)?;
         self.output.write_u16::<LittleEndian>(state.resources.len() as u16)?;
@@ -172,11 +217,10 @@ impl CodeGenerator {
         
         // Write each string
         for (i, string_entry) in state.strings.iter().enumerate() {
-            self.string_offsets.insert(i as u8, self.output.len() as u32);
+            self.string_offsets.insert(i as u16, self.output.len() as u32);
             
-            // Length byte
-            let len = std::cmp::min(string_entry.text.len(), 255);
-            self.output.push(len as u8);
+            // Length
+            self.write_count(string_entry.text.len(), "string length")?;
             
             // String data (UTF-8)
             self.output.extend_from_slice(string_entry.text.as_bytes());
@@ -203,26 +247,26 @@ impl CodeGenerator {
         self.element_offsets.insert(element_index, self.output.len() as u32);
         
         // Debug output to track what's being written
-        println!("Writing element {}: type={:?} ({}), pos=({}, {}), size=({}, {}), id_string_index={}", 
+        log::debug!("Writing element {}: type={:?} ({}), pos=({}, {}), size=({}, {}), id_string_index={}", 
                 element_index, element.element_type, element.element_type as u8,
                 element.pos_x, element.pos_y, element.width, element.height, element.id_string_index);
         
-        // Element header (19 bytes)
+        // Element header (19 bytes, 27 with wide indices)
         self.output.push(element.element_type as u8);
-        self.output.push(element.id_string_index);
+        self.write_index(element.id_string_index);
         self.output.write_u16::<LittleEndian>(element.pos_x)?;
         self.output.write_u16::<LittleEndian>(element.pos_y)?;
         self.output.write_u16::<LittleEndian>(element.width)?;
         self.output.write_u16::<LittleEndian>(element.height)?;
         self.output.push(element.layout);
-        self.output.push(element.style_id);
+        self.write_index(element.style_id);
         self.output.push(if element.checked { 1 } else { 0 });
-        self.output.push(element.property_count);
-        self.output.push(element.child_count);
-        self.output.push(element.event_count);
-        self.output.push(element.animation_count);
-        self.output.push(element.custom_prop_count);
-        self.output.push(element.state_prop_count);
+        self.write_index(element.property_count);
+        self.write_index(element.child_count);
+        self.write_index(element.event_count);
+        self.write_index(element.animation_count);
+        self.write_index(element.custom_prop_count);
+        self.write_index(element.state_prop_count);
         
         // Write standard properties
         for prop in &element.krb_properties {
@@ -244,6 +288,11 @@ impl CodeGenerator {
             self.write_event(event)?;
         }
         
+        // Write animation references
+        for animation in &element.krb_animations {
+            self.write_animation_ref(animation)?;
+        }
+        
         // Write child offsets (placeholders for now)
         for _ in 0..element.child_count {
             self.output.write_u16::<LittleEndian>(0)?;
@@ -260,22 +309,49 @@ impl CodeGenerator {
     fn write_property(&mut self, prop: &KrbProperty) -> Result<()> {
         self.output.push(prop.property_id);
         self.output.push(prop.value_type as u8);
-        self.output.push(prop.size);
-        self.output.extend_from_slice(&prop.value);
-        Ok(())
+        self.write_value(prop.value_type, &prop.value)
     }
     
     fn write_custom_property(&mut self, prop: &KrbCustomProperty) -> Result<()> {
-        self.output.push(prop.key_index);
+        self.write_index(prop.key_index);
         self.output.push(prop.value_type as u8);
-        self.output.push(prop.size);
-        self.output.extend_from_slice(&prop.value);
+        self.write_value(prop.value_type, &prop.value)
+    }
+    
+    /// Write a property's size byte and data, re-encoding table indices in the file's width
+    fn write_value(&mut self, value_type: ValueType, value: &[u8]) -> Result<()> {
+        if let Some(index) = decode_index(value).filter(|_| value_type.holds_index()) {
+            self.output.push(self.width.bytes() as u8);
+            self.write_index(index);
+            return Ok(());
+        }
+        let size = u8::try_from(value.len()).map_err(|_| CompilerError::CodeGen {
+            message: format!("{:?} property value is {} bytes; at most 255 fit in a property", value_type, value.len()),
+        })?;
+        self.output.push(size);
+        self.output.extend_from_slice(value);
+        Ok(())
+    }
+    
+    /// Write a table index or count; `SizeCalculator` picked a width that holds every one
+    fn write_index(&mut self, value: u16) {
+        self.width.write(&mut self.output, value);
+    }
+    
+    /// Write a length as a count, failing instead of truncating when the width cannot hold it
+    fn write_count(&mut self, count: usize, what: &str) -> Result<()> {
+        if !IndexWidth::for_largest(count).is_some_and(|needed| needed <= self.width) {
+            return Err(CompilerError::CodeGen {
+                message: format!("{} of {} does not fit in {}-byte counts", what, count, self.width.bytes()),
+            });
+        }
+        self.write_index(count as u16);
         Ok(())
     }
     
     fn write_state_property_set(&mut self, state_set: &StatePropertySet) -> Result<()> {
         self.output.push(state_set.state_flags);
-        self.output.push(state_set.property_count);
+        self.write_index(state_set.property_count);
         
         for prop in &state_set.properties {
             self.write_property(prop)?;
@@ -286,25 +362,37 @@ impl CodeGenerator {
     
     fn write_event(&mut self, event: &KrbEvent) -> Result<()> {
         self.output.push(event.event_type);
-        self.output.push(event.callback_id);
+        self.write_index(event.callback_id);
+        Ok(())
+    }
+    
+    fn write_animation_ref(&mut self, animation: &KrbAnimationRef) -> Result<()> {
+        self.output.push(animation.kind);
+        self.write_index(animation.target);
+        self.output.write_u16::<LittleEndian>(animation.duration_ms)?;
+        self.output.write_u16::<LittleEndian>(animation.delay_ms)?;
+        self.output.push(animation.easing);
+        self.output.push(animation.iterations);
+        self.output.push(animation.direction);
         Ok(())
     }
     
     fn write_style_table(&mut self, state: &CompilerState) -> Result<()> {
-        // Deduplicate styles by ID to prevent writing duplicates
-        let mut unique_styles = std::collections::HashMap::new();
+        // Deduplicate styles by ID to prevent writing duplicates; the last entry
+        // for an ID wins and styles are written in ID order
+        let mut unique_styles = std::collections::BTreeMap::new();
         for style in &state.styles {
             unique_styles.insert(style.id, style);
         }
         
-        println!("Writing {} unique styles to KRB (from {} total)", unique_styles.len(), state.styles.len());
-        for (_, style) in unique_styles {
+        log::debug!("Writing {} unique styles to KRB (from {} total)", unique_styles.len(), state.styles.len());
+        for style in unique_styles.into_values() {
             // Style entry header
-            println!("Writing style '{}': id={}, name_index={}, props={}", 
+            log::debug!("Writing style '{}': id={}, name_index={}, props={}", 
                 style.source_name, style.id, style.name_index, style.properties.len());
-            self.output.push(style.id);
-            self.output.push(style.name_index);
-            self.output.push(style.properties.len() as u8);
+            self.write_index(style.id);
+            self.write_index(style.name_index);
+            self.write_count(style.properties.len(), "style property count")?;
             
             // Write properties
             for prop in &style.properties {
@@ -320,25 +408,24 @@ impl CodeGenerator {
             // Component header
             let name_index = state.strings.iter()
                 .position(|s| s.text == component.name)
-                .unwrap_or(0) as u8;
+                .unwrap_or(0) as u16;
             
-            self.output.push(name_index);
-            self.output.push(component.properties.len() as u8);
+            self.write_index(name_index);
+            self.write_count(component.properties.len(), "component property count")?;
             
             // Write property definitions
             for prop_def in &component.properties {
                 let prop_name_index = state.strings.iter()
                     .position(|s| s.text == prop_def.name)
-                    .unwrap_or(0) as u8;
+                    .unwrap_or(0) as u16;
                 
-                self.output.push(prop_name_index);
+                self.write_index(prop_name_index);
                 self.output.push(prop_def.value_type_hint as u8);
                 
                 // Default value
                 let default_bytes = prop_def.default_value.as_bytes();
-                let default_len = std::cmp::min(default_bytes.len(), 255);
-                self.output.push(default_len as u8);
-                self.output.extend_from_slice(&default_bytes[..default_len]);
+                self.write_count(default_bytes.len(), "component default value length")?;
+                self.output.extend_from_slice(default_bytes);
             }
             
             // Write template element (if any)
@@ -350,18 +437,35 @@ impl CodeGenerator {
         Ok(())
     }
     
+    fn write_animation_table(&mut self, state: &CompilerState) -> Result<()> {
+        for animation in &state.animations {
+            self.write_index(animation.name_index);
+            self.write_count(animation.keyframes.len(), "keyframe count")?;
+            
+            for keyframe in &animation.keyframes {
+                self.output.push(keyframe.offset);
+                self.write_count(keyframe.properties.len(), "keyframe property count")?;
+                for prop in &keyframe.properties {
+                    self.write_property(prop)?;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
     fn write_script_table(&mut self, state: &CompilerState) -> Result<()> {
         for script in &state.scripts {
             // Script header
             self.output.push(script.language_id as u8);
-            self.output.push(script.name_index);
+            self.write_index(script.name_index);
             self.output.push(script.storage_format);
-            self.output.push(script.entry_point_count);
+            self.write_index(script.entry_point_count);
             self.output.write_u16::<LittleEndian>(script.data_size)?;
             
             // Write entry points
             for entry_point in &script.entry_points {
-                self.output.push(entry_point.function_name_index);
+                self.write_index(entry_point.function_name_index);
             }
             
             // Write code data (if inline)
@@ -376,9 +480,26 @@ impl CodeGenerator {
     fn write_resource_table(&mut self, state: &CompilerState) -> Result<()> {
         for resource in &state.resources {
             self.output.push(resource.resource_type as u8);
-            self.output.push(resource.name_index);
+            self.write_index(resource.name_index);
             self.output.push(resource.format as u8);
-            self.output.push(resource.data_string_index);
+            self.write_index(resource.data_string_index);
+            
+            if let Some(font) = &resource.font {
+                self.output.write_u16::<LittleEndian>(font.weight)?;
+                self.output.push(font.style as u8);
+                self.write_count(font.fallback_indices.len(), "font fallback count")?;
+                for &fallback in &font.fallback_indices {
+                    self.write_index(fallback);
+                }
+            }
+            match resource.format {
+                ResourceFormat::Inline => {
+                    self.output.write_u32::<LittleEndian>(resource.data.len() as u32)?;
+                    self.output.extend_from_slice(&resource.data);
+                }
+                ResourceFormat::Shared => self.write_index(resource.shared_with.unwrap_or_default()),
+                ResourceFormat::External => {}
+            }
         }
         
         Ok(())
@@ -386,9 +507,9 @@ impl CodeGenerator {
     
     fn write_template_variable_table(&mut self, state: &CompilerState) -> Result<()> {
         for template_var in &state.template_variables {
-            self.output.push(template_var.name_index);
+            self.write_index(template_var.name_index);
             self.output.push(template_var.value_type as u8);
-            self.output.push(template_var.default_value_index);
+            self.write_index(template_var.default_value_index);
         }
         
         Ok(())
@@ -398,12 +519,12 @@ impl CodeGenerator {
         for binding in &state.template_bindings {
             self.output.write_u16::<LittleEndian>(binding.element_index)?;
             self.output.push(binding.property_id);
-            self.output.push(binding.template_expression_index);
-            self.output.push(binding.variable_count);
+            self.write_index(binding.template_expression_index);
+            self.write_index(binding.variable_count);
             
             // Write variable indices
             for &var_index in &binding.variable_indices {
-                self.output.push(var_index);
+                self.write_index(var_index);
             }
         }
         
@@ -414,14 +535,36 @@ impl CodeGenerator {
         for transform in &state.transforms {
             // Transform header
             self.output.push(transform.transform_type as u8);
-            self.output.push(transform.properties.len() as u8);
+            self.write_count(transform.properties.len(), "transform property count")?;
             
             // Write transform properties
             for prop in &transform.properties {
                 self.output.push(prop.property_type as u8);
                 self.output.push(prop.value_type as u8);
-                self.output.push(prop.value.len() as u8);
-                self.output.extend_from_slice(&prop.value);
+                self.write_value(prop.value_type, &prop.value)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn write_theme_table(&mut self, state: &CompilerState) -> Result<()> {
+        if state.themes.is_empty() {
+            return Ok(());
+        }
+        
+        // Slots: the token name and the type of every theme's value
+        self.write_count(state.theme_slots.len(), "theme slot count")?;
+        for slot in &state.theme_slots {
+            self.write_index(slot.name_index);
+            self.output.push(slot.value_type as u8);
+        }
+        
+        // Themes: the name, then one value per slot
+        for (theme_index, theme) in state.themes.iter().enumerate() {
+            self.write_index(theme.name_index);
+            for slot in &state.theme_slots {
+                self.write_value(slot.value_type, &slot.values[theme_index])?;
             }
         }
         
@@ -0,0 +1,72 @@
+// FILE: src/compiler/backend/debug_info.rs
+
+//! Builds the `.krb.map` source map from the final compiler state
+//!
+//! Indices must match what `CodeGenerator` writes, so the element walk mirrors
+//! `write_element_tree`: pre-order from the main root, children in state order.
+
+use crate::core::debug_info::*;
+use crate::core::properties::PropertyId;
+use crate::core::CompilerState;
+
+pub fn build_debug_map(state: &CompilerState) -> KrbDebugMap {
+    let mut map = KrbDebugMap::default();
+
+    if let Some(root) = state.elements.iter().position(|e| !e.is_definition_root && e.parent_index.is_none()) {
+        collect_elements(state, root, &mut map.elements);
+    }
+
+    map.styles = state.styles.iter()
+        .map(|style| StyleDebugInfo {
+            id: style.id,
+            name: style.source_name.clone(),
+            location: style.location.as_ref().map(DebugLocation::from),
+        })
+        .collect();
+
+    map.bindings = state.template_bindings.iter().enumerate()
+        .map(|(index, binding)| {
+            let element = state.elements.get(binding.element_index as usize);
+            let location = element.and_then(|element| {
+                element.source_properties.iter()
+                    .find(|p| PropertyId::from_name(&p.key) as u8 == binding.property_id)
+                    .and_then(|p| p.location.as_ref())
+                    .or(element.source_location.as_ref())
+            });
+            BindingDebugInfo {
+                index: index as u16,
+                element: binding.element_index,
+                expression: binding.template_expression.clone(),
+                location: location.map(DebugLocation::from),
+            }
+        })
+        .collect();
+
+    for (script_index, script) in state.scripts.iter().enumerate() {
+        for function in &script.entry_points {
+            map.entry_points.push(EntryPointDebugInfo {
+                script: script_index as u16,
+                function: function.function_name.clone(),
+                location: function.location.as_ref().or(script.location.as_ref()).map(DebugLocation::from),
+            });
+        }
+    }
+
+    map
+}
+
+fn collect_elements(state: &CompilerState, element_index: usize, elements: &mut Vec<ElementDebugInfo>) {
+    let element = &state.elements[element_index];
+    elements.push(ElementDebugInfo {
+        index: elements.len() as u16,
+        element_type: element.source_element_name.clone(),
+        location: element.source_location.as_ref().map(DebugLocation::from),
+        component: element.component_origin.as_ref().map(|origin| ComponentInstanceInfo {
+            component: origin.component.clone(),
+            location: origin.location.as_ref().map(DebugLocation::from),
+        }),
+    });
+    for &child in &element.children {
+        collect_elements(state, child, elements);
+    }
+}
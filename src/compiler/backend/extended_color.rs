@@ -0,0 +1,122 @@
+// FILE: src/compiler/backend/extended_color.rs
+
+//! 16-bit colors for wide-gamut renderers
+//!
+//! Colors are converted and folded with 8 bits per channel. When
+//! `CompilerOptions::extended_color` asks for it, this pass widens every color of the
+//! final state, including those of gradients, shadows and filters, to 16 bits per
+//! channel (see `core::color`) and sets `FLAG_EXTENDED_COLOR`.
+
+use crate::core::*;
+use crate::core::color::widen_color;
+use crate::core::effects::{widen_filters, widen_shadows};
+use crate::core::gradient::widen_gradient;
+use crate::core::types::ValueType;
+use crate::error::{CompilerError, Result};
+
+pub fn encode_extended_color(state: &mut CompilerState) -> Result<()> {
+    for element in &mut state.elements {
+        let properties = element.krb_properties.iter_mut()
+            .chain(element.state_property_sets.iter_mut().flat_map(|set| set.properties.iter_mut()));
+        for property in properties {
+            widen_property(property)?;
+        }
+        for property in &mut element.krb_custom_properties {
+            if widen(property.value_type, &mut property.value) {
+                property.size = value_size(property.value_type, &property.value)?;
+            }
+        }
+    }
+
+    let styles = state.styles.iter_mut().flat_map(|style| style.properties.iter_mut());
+    let keyframes = state.animations.iter_mut()
+        .flat_map(|animation| animation.keyframes.iter_mut())
+        .flat_map(|frame| frame.properties.iter_mut());
+    for property in styles.chain(keyframes) {
+        widen_property(property)?;
+    }
+
+    for slot in &mut state.theme_slots {
+        for value in &mut slot.values {
+            widen(slot.value_type, value);
+        }
+    }
+
+    state.header_flags |= FLAG_EXTENDED_COLOR;
+    Ok(())
+}
+
+fn widen_property(property: &mut KrbProperty) -> Result<()> {
+    if widen(property.value_type, &mut property.value) {
+        property.size = value_size(property.value_type, &property.value)?;
+    }
+    Ok(())
+}
+
+/// The size byte of a widened value, failing when the wider colors no longer fit
+fn value_size(value_type: ValueType, value: &[u8]) -> Result<u8> {
+    u8::try_from(value.len()).map_err(|_| CompilerError::CodeGen {
+        message: format!("{:?} value is {} bytes with 16-bit colors; at most 255 fit in a property", value_type, value.len()),
+    })
+}
+
+/// Widen a Color value, or the colors inside a Gradient, Shadow or Filter value; false for other values
+fn widen(value_type: ValueType, value: &mut Vec<u8>) -> bool {
+    let wide = match value_type {
+        ValueType::Color => widen_color(value),
+        ValueType::Gradient => widen_gradient(value),
+        ValueType::Shadow => widen_shadows(value),
+        ValueType::Filter => widen_filters(value),
+        _ => None,
+    };
+    wide.map(|wide| *value = wide).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_extended_color;
+    use crate::core::effects::{encode_filters, parse_filters};
+    use crate::core::properties::PropertyId;
+    use crate::core::types::ValueType;
+    use crate::core::{AnimationEntry, CompilerState, KeyframeEntry, KrbProperty};
+    use crate::compiler::test_support::compile_with;
+    use crate::{verify_krb, CompilerOptions, KrbReader, FLAG_EXTENDED_COLOR};
+
+    #[test]
+    fn test_colors_are_widened() {
+        let source = "@theme light {\n    ink: #102030\n}\n\nstyle \"card\" {\n    background_color: darken(white, 50%)\n    text_color: $ink\n}\n\nApp {\n    Container {\n        style: \"card\"\n        border_color: \"#FF000080\"\n    }\n}\n";
+        let compile = |extended_color| compile_with(source, CompilerOptions { extended_color, ..CompilerOptions::default() }).unwrap();
+        let (narrow, wide) = (compile(false), compile(true));
+        assert_eq!(verify_krb(&wide), Vec::new());
+
+        let file = KrbReader::new(&wide).unwrap().read().unwrap();
+        assert_ne!(file.header.flags & FLAG_EXTENDED_COLOR, 0);
+        let border = file.elements[1].properties.iter().find(|p| p.property_id == PropertyId::BorderColor as u8).unwrap();
+        assert_eq!(border.value, [0xFF, 0xFF, 0, 0, 0, 0, 0x80, 0x80]);
+        assert_eq!(file.styles[0].properties[0].value, [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xFF, 0xFF]);
+        assert_eq!(file.theme_table.themes[0].values[0], [0x10, 0x10, 0x20, 0x20, 0x30, 0x30, 0xFF, 0xFF]);
+
+        // Four more bytes for each of the two property colors and the theme's one
+        assert_eq!(wide.len(), narrow.len() + 12);
+        assert_eq!(KrbReader::new(&narrow).unwrap().read().unwrap().header.flags & FLAG_EXTENDED_COLOR, 0);
+    }
+
+    #[test]
+    fn test_values_that_outgrow_a_property_are_rejected() {
+        // 23 drop shadows fit in 254 bytes with 8-bit colors, but not with 16-bit ones
+        let shadow = parse_filters("drop-shadow(1px 1px red)", &|_: &str| None).unwrap().remove(0);
+        let value = encode_filters(&vec![shadow; 23]).unwrap();
+        let size = u8::try_from(value.len()).unwrap();
+        let property = KrbProperty { property_id: PropertyId::Filter as u8, value_type: ValueType::Filter, size, value };
+        let mut state = CompilerState::new();
+        state.animations.push(AnimationEntry {
+            name: "glow".to_string(),
+            name_index: 0,
+            keyframes: vec![KeyframeEntry { offset: 100, properties: vec![property] }],
+            calculated_size: 0,
+        });
+
+        let error = encode_extended_color(&mut state).unwrap_err().to_string();
+        assert!(error.contains("Filter value is 346 bytes with 16-bit colors"), "{}", error);
+    }
+}
@@ -0,0 +1,194 @@
+// FILE: src/compiler/backend/fixed_point.rs
+
+//! Fixed-point numbers for renderers without an FPU
+//!
+//! Properties are converted with f32 numbers. When the target platform requires it,
+//! or `CompilerOptions::fixed_point` asks for it, this pass re-encodes every number
+//! of the final state as Q16.16 (see `core::fixed_point`) and sets `FLAG_FIXED_POINT`.
+//! Numbers outside the Q16.16 range cannot be represented and are reported together.
+
+use crate::core::*;
+use crate::core::types::ValueType;
+use crate::diagnostics::{Diagnostic, Diagnostics, Span};
+use crate::error::Result;
+use std::collections::HashMap;
+
+/// Where a converted value came from, for error messages
+struct Origin {
+    name: String,
+    location: Option<Span>,
+}
+
+pub fn encode_fixed_point(state: &mut CompilerState) -> Result<()> {
+    let mut encoder = Encoder { diagnostics: Diagnostics::new(), transform_origins: HashMap::new() };
+
+    for element in &mut state.elements {
+        let Element { krb_properties, state_property_sets, krb_custom_properties, source_properties, source_location, .. } = element;
+        let properties = krb_properties.iter_mut()
+            .chain(state_property_sets.iter_mut().flat_map(|set| set.properties.iter_mut()));
+        for property in properties {
+            let origin = source_origin(property.property_id, source_properties, source_location);
+            encoder.convert_property(property, origin);
+        }
+        for property in krb_custom_properties {
+            let origin = Origin { name: "custom property".to_string(), location: source_location.clone() };
+            encoder.convert(property.value_type, &mut property.value, &origin);
+        }
+    }
+
+    for style in &mut state.styles {
+        for property in &mut style.properties {
+            let origin = source_origin(property.property_id, &style.source_properties, &style.location);
+            encoder.convert_property(property, origin);
+        }
+    }
+
+    for animation in &mut state.animations {
+        for frame in &mut animation.keyframes {
+            for property in &mut frame.properties {
+                let origin = Origin {
+                    name: format!("{} in animation '{}'", property_name(property.property_id), animation.name),
+                    location: None,
+                };
+                encoder.convert_property(property, origin);
+            }
+        }
+    }
+
+    for (index, transform) in state.transforms.iter_mut().enumerate() {
+        let origin = encoder.transform_origins.remove(&(index as u16))
+            .unwrap_or_else(|| Origin { name: "transform".to_string(), location: None });
+        for property in &mut transform.properties {
+            encoder.convert(property.value_type, &mut property.value, &origin);
+        }
+    }
+
+    for slot in &mut state.theme_slots {
+        for (theme, value) in state.themes.iter().zip(&mut slot.values) {
+            let origin = Origin {
+                name: format!("token '{}' of theme '{}'", slot.token, theme.name),
+                location: theme.token(&slot.token).and_then(|token| token.location.clone()),
+            };
+            encoder.convert(slot.value_type, value, &origin);
+        }
+    }
+
+    state.header_flags |= FLAG_FIXED_POINT;
+    encoder.diagnostics.into_result().map(|_| ())
+}
+
+struct Encoder {
+    diagnostics: Diagnostics,
+    /// The property that refers to each transform table entry
+    transform_origins: HashMap<u16, Origin>,
+}
+
+impl Encoder {
+    fn convert_property(&mut self, property: &mut KrbProperty, origin: Origin) {
+        if property.value_type == ValueType::Transform {
+            if let [lo, hi] = property.value[..] {
+                self.transform_origins.entry(u16::from_le_bytes([lo, hi])).or_insert(origin);
+            }
+            return;
+        }
+        self.convert(property.value_type, &mut property.value, &origin);
+    }
+
+    fn convert(&mut self, value_type: ValueType, value: &mut [u8], origin: &Origin) {
+        if let Err(number) = convert_to_fixed_point(value_type, value) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "{} value {} is outside the fixed-point range of -32768 to 32767.99998",
+                    origin.name, number
+                ))
+                .with_span(origin.location.clone()),
+            );
+        }
+    }
+}
+
+/// The source property a KRB property was converted from, or its owner when it was expanded from a shorthand
+fn source_origin(property_id: u8, source_properties: &[SourceProperty], owner: &Option<Span>) -> Origin {
+    match source_properties.iter().find(|p| PropertyId::from_name(&p.key) as u8 == property_id) {
+        Some(source) => Origin { name: source.key.clone(), location: source.location.clone().or_else(|| owner.clone()) },
+        None => Origin { name: property_name(property_id), location: owner.clone() },
+    }
+}
+
+fn property_name(property_id: u8) -> String {
+    PropertyId::from_u8(property_id).map_or_else(|| format!("property 0x{:02X}", property_id), |p| format!("{:?}", p))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::properties::PropertyId;
+    use crate::core::types::ValueType;
+    use crate::compiler::test_support::compile_with;
+    use crate::{CompilerOptions, KrbReader, NumberFormat, TargetPlatform, FLAG_FIXED_POINT};
+
+    const SOURCE: &str = "style \"row\" {\n    flex_grow: 1.5\n    line_height: 1.25\n}\n\nApp {\n    Container {\n        style: \"row\"\n        width: 50%\n        aspect_ratio: \"16/9\"\n        transform: \"translate(10px, -4.5px) rotate(45deg)\"\n        Text {\n            text: \"Hi\"\n            letter_spacing: 0.5em\n        }\n    }\n}\n";
+
+    /// The 4-byte numbers of properties and transforms, decoded with the file's own format
+    fn numbers(krb: &[u8]) -> Vec<f64> {
+        let file = KrbReader::new(krb).unwrap().read().unwrap();
+        let format = NumberFormat::from_flags(file.header.flags);
+        let decode = |value: &[u8]| format.decode([value[0], value[1], value[2], value[3]]);
+        let properties = file.elements.iter().flat_map(|e| &e.properties).chain(file.styles.iter().flat_map(|s| &s.properties));
+        let mut numbers: Vec<f64> = properties
+            .filter(|p| matches!(ValueType::from_u8(p.value_type), Some(ValueType::Float | ValueType::Percentage)) && p.value.len() == 4)
+            .map(|p| decode(&p.value))
+            .collect();
+        numbers.extend(file.transforms.iter().flat_map(|t| &t.properties).map(|p| decode(&p.value)));
+        numbers
+    }
+
+    #[test]
+    fn test_embedded_targets_use_fixed_point() {
+        let float = compile_with(SOURCE, CompilerOptions::default()).unwrap();
+        let embedded = CompilerOptions { target_platform: TargetPlatform::Embedded, ..CompilerOptions::default() };
+        let fixed = compile_with(SOURCE, embedded).unwrap();
+        let forced = compile_with(SOURCE, CompilerOptions { fixed_point: true, ..CompilerOptions::default() }).unwrap();
+
+        let flags = |krb: &[u8]| KrbReader::new(krb).unwrap().read().unwrap().header.flags;
+        assert_eq!(flags(&float) & FLAG_FIXED_POINT, 0);
+        assert_ne!(flags(&fixed) & FLAG_FIXED_POINT, 0);
+        assert_eq!(fixed, forced);
+        assert_eq!(float.len(), fixed.len());
+
+        // Both encodings hold the same numbers; 16/9 is rounded differently by each
+        let (float_numbers, fixed_numbers) = (numbers(&float), numbers(&fixed));
+        assert_eq!(float_numbers.len(), 8, "{:?}", float_numbers);
+        for (a, b) in float_numbers.iter().zip(&fixed_numbers) {
+            assert!((a - b).abs() < 1.0 / 65536.0, "{} != {}", a, b);
+        }
+        assert!(fixed_numbers.contains(&-4.5) && fixed_numbers.contains(&1.25));
+
+        let file = KrbReader::new(&fixed).unwrap().read().unwrap();
+        let container = &file.elements[1];
+        let aspect = container.properties.iter().find(|p| p.property_id == PropertyId::AspectRatio as u8).unwrap();
+        assert_eq!(aspect.value, NumberFormat::FixedPoint.encode(16.0 / 9.0).unwrap());
+        assert_eq!(file.transforms[0].properties[2].value[4], crate::CSSUnit::Degrees as u8);
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_located() {
+        let source = "App {\n    Container {\n        flex_grow: 40000\n        transform: \"scale(50000)\"\n    }\n}\n";
+        let options = || CompilerOptions { fixed_point: true, ..CompilerOptions::default() };
+        assert!(compile_with(source, CompilerOptions::default()).is_ok());
+
+        let crate::CompilerError::Diagnostics(diagnostics) = compile_with(source, options()).unwrap_err() else {
+            panic!("expected located errors");
+        };
+        let errors: Vec<_> = diagnostics.iter().collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].message.starts_with("flex_grow value 40000 is outside the fixed-point range"), "{}", errors[0].message);
+        assert_eq!(errors[0].span.as_ref().map(|s| s.line), Some(3));
+        assert!(errors[1].message.starts_with("transform value 50000"), "{}", errors[1].message);
+        assert_eq!(errors[1].span.as_ref().map(|s| s.line), Some(4));
+
+        let error = compile_with("App {\n    Container {\n        aspect_ratio: \"16/0\"\n    }\n}\n", options()).unwrap_err();
+        assert!(error.to_string().contains("main.kry:3:9: error: Invalid aspect ratio: 16/0"), "{}", error);
+        let error = compile_with("App {\n    Container {\n        transform: \"rotate(45deg\"\n    }\n}\n", options()).unwrap_err();
+        assert!(error.to_string().contains("main.kry:3:9: error: Invalid transform 'rotate(45deg'"), "{}", error);
+    }
+}
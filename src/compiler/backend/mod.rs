@@ -2,4 +2,7 @@
 
 // Declare the files within this module
 pub mod codegen;
+pub mod debug_info;
+pub mod extended_color;
+pub mod fixed_point;
 pub mod size_calculator;
@@ -1,5 +1,6 @@
 //! Calculate sizes and offsets for KRB generation
 
+use crate::diagnostics::Diagnostic;
 use crate::error::{CompilerError, Result};
 use crate::core::*;
 use crate::core::types::*;
@@ -12,32 +13,41 @@ impl SizeCalculator {
     }
     
     pub fn calculate_sizes(&self, state: &mut CompilerState) -> Result<()> {
+        // Every other size depends on how wide indices are written
+        let width = self.choose_index_width(state)?;
+        
         // Calculate string table size
-        self.calculate_string_table_size(state);
+        self.calculate_string_table_size(state, width);
         
         // Calculate element sizes
-        self.calculate_element_sizes(state)?;
+        self.calculate_element_sizes(state, width)?;
         
         // TODO: Extract header values from style properties (architectural optimization)
         // self.extract_header_values_from_styles(state)?;
         
         // Calculate style table size
-        self.calculate_style_table_size(state);
+        self.calculate_style_table_size(state, width);
         
         // Calculate component definition sizes
-        self.calculate_component_def_sizes(state);
+        self.calculate_component_def_sizes(state, width);
+        
+        // Calculate animation table size
+        self.calculate_animation_table_size(state, width);
         
         // Calculate script table size
-        self.calculate_script_table_size(state);
+        self.calculate_script_table_size(state, width);
         
         // Calculate resource table size
-        self.calculate_resource_table_size(state);
+        self.calculate_resource_table_size(state, width);
         
         // Calculate template variable sizes
-        self.calculate_template_variable_sizes(state);
+        self.calculate_template_variable_sizes(state, width);
         
         // Calculate transform data sizes
-        self.calculate_transform_sizes(state);
+        self.calculate_transform_sizes(state, width);
+        
+        // Calculate theme table size
+        self.calculate_theme_sizes(state, width);
         
         // Calculate section offsets
         self.calculate_section_offsets(state);
@@ -45,47 +55,67 @@ impl SizeCalculator {
         Ok(())
     }
     
-    fn calculate_string_table_size(&self, state: &mut CompilerState) {
+    /// Use wide indices only when some index or count does not fit in a byte,
+    /// recording the choice in the header flags
+    fn choose_index_width(&self, state: &mut CompilerState) -> Result<IndexWidth> {
+        let largest = largest_index_or_count(state);
+        let width = IndexWidth::for_largest(largest).ok_or_else(|| CompilerError::LimitExceeded {
+            limit_type: format!("table index or count ({} needed)", largest),
+            limit: u16::MAX as usize,
+        })?;
+        match width {
+            IndexWidth::Compact => state.header_flags &= !FLAG_WIDE_INDICES,
+            IndexWidth::Wide => state.header_flags |= FLAG_WIDE_INDICES,
+        }
+        Ok(width)
+    }
+    
+    fn calculate_string_table_size(&self, state: &mut CompilerState, width: IndexWidth) {
         let mut total_size = 0u32;
         
         for string_entry in &mut state.strings {
-            // Each string: 1 byte length + string data
-            let string_size = 1 + string_entry.text.len();
+            // Each string: length + string data
+            let string_size = width.bytes() + string_entry.text.len();
             total_size += string_size as u32;
         }
         
         state.total_string_data_size = total_size;
     }
     
-    fn calculate_element_sizes(&self, state: &mut CompilerState) -> Result<()> {
+    fn calculate_element_sizes(&self, state: &mut CompilerState, width: IndexWidth) -> Result<()> {
         let mut total_size = 0u32;
+        let index = width.bytes() as u32;
         
         for element in &mut state.elements {
-            let mut element_size = KRB_ELEMENT_HEADER_SIZE as u32;
+            let mut element_size = width.element_header_size() as u32;
             
             // Add property sizes
             for prop in &element.krb_properties {
-                element_size += 3 + prop.size as u32; // prop_id + value_type + size + data
+                element_size += property_size(prop, width); // prop_id + value_type + size + data
             }
             
             // Add custom property sizes
             for custom_prop in &element.krb_custom_properties {
-                element_size += 3 + custom_prop.size as u32; // key_index + value_type + size + data
+                // key_index + value_type + size + data
+                element_size += index + 2 + width.value_size(custom_prop.value_type, &custom_prop.value) as u32;
             }
             
             // Add state property set sizes
             for state_set in &element.state_property_sets {
-                element_size += 2; // state_flags + property_count
+                element_size += 1 + index; // state_flags + property_count
                 for prop in &state_set.properties {
-                    element_size += 3 + prop.size as u32;
+                    element_size += property_size(prop, width);
                 }
             }
             
             // Add event sizes
             for _event in &element.krb_events {
-                element_size += 2; // event_type + callback_id
+                element_size += 1 + index; // event_type + callback_id
             }
             
+            // Add animation reference sizes
+            element_size += (element.krb_animations.len() * width.animation_ref_size()) as u32;
+            
             // Add child offset space
             element_size += element.child_count as u32 * 2; // Each child offset is 2 bytes
             
@@ -97,14 +127,14 @@ impl SizeCalculator {
         Ok(())
     }
     
-    fn calculate_style_table_size(&self, state: &mut CompilerState) {
+    fn calculate_style_table_size(&self, state: &mut CompilerState, width: IndexWidth) {
         let mut total_size = 0u32;
         
         for style in &mut state.styles {
-            let mut style_size = 3u32; // id + name_index + property_count
+            let mut style_size = 3 * width.bytes() as u32; // id + name_index + property_count
             
             for prop in &style.properties {
-                style_size += 3 + prop.size as u32; // prop_id + value_type + size + data
+                style_size += property_size(prop, width); // prop_id + value_type + size + data
             }
             
             style.calculated_size = style_size;
@@ -114,15 +144,16 @@ impl SizeCalculator {
         state.total_style_data_size = total_size;
     }
     
-    fn calculate_component_def_sizes(&self, state: &mut CompilerState) {
+    fn calculate_component_def_sizes(&self, state: &mut CompilerState, width: IndexWidth) {
         let mut total_size = 0u32;
+        let index = width.bytes() as u32;
         
         for component in &mut state.component_defs {
-            let mut comp_size = 2u32; // name_index + property_count
+            let mut comp_size = 2 * index; // name_index + property_count
             
             // Add property definition sizes
             for prop_def in &component.properties {
-                comp_size += 3; // prop_name_index + value_type_hint + default_value_length
+                comp_size += 1 + 2 * index; // prop_name_index + value_type_hint + default_value_length
                 comp_size += prop_def.default_value.len() as u32;
             }
             
@@ -140,14 +171,36 @@ impl SizeCalculator {
         state.total_component_def_data_size = total_size;
     }
     
-    fn calculate_script_table_size(&self, state: &mut CompilerState) {
+    fn calculate_animation_table_size(&self, state: &mut CompilerState, width: IndexWidth) {
         let mut total_size = 0u32;
+        let index = width.bytes() as u32;
+        
+        for animation in &mut state.animations {
+            let mut animation_size = 2 * index; // name_index + keyframe_count
+            
+            for keyframe in &animation.keyframes {
+                animation_size += 1 + index; // offset + property_count
+                for prop in &keyframe.properties {
+                    animation_size += property_size(prop, width); // prop_id + value_type + size + data
+                }
+            }
+            
+            animation.calculated_size = animation_size;
+            total_size += animation_size;
+        }
+        
+        state.total_animation_data_size = total_size;
+    }
+    
+    fn calculate_script_table_size(&self, state: &mut CompilerState, width: IndexWidth) {
+        let mut total_size = 0u32;
+        let index = width.bytes() as u32;
         
         for script in &mut state.scripts {
-            let mut script_size = 6u32; // language_id + name_index + storage_format + entry_point_count + data_size (2 bytes)
+            let mut script_size = 4 + 2 * index; // language_id + name_index + storage_format + entry_point_count + data_size (2 bytes)
             
             // Add entry point sizes
-            script_size += script.entry_points.len() as u32; // Each entry point is 1 byte (function name index)
+            script_size += script.entry_points.len() as u32 * index; // Each entry point is a function name index
             
             // Add code data size (if inline)
             if script.storage_format == 0 { // ScriptStorageInline
@@ -161,11 +214,21 @@ impl SizeCalculator {
         state.total_script_data_size = total_size;
     }
     
-    fn calculate_resource_table_size(&self, state: &mut CompilerState) {
+    fn calculate_resource_table_size(&self, state: &mut CompilerState, width: IndexWidth) {
         let mut total_size = 0u32;
+        let index = width.bytes() as u32;
         
         for resource in &mut state.resources {
-            let resource_size = 4u32; // resource_type + name_index + format + data_string_index
+            let mut resource_size = 2 + 2 * index; // resource_type + name_index + format + data_string_index
+            if let Some(font) = &resource.font {
+                // weight + style + fallback_count + fallbacks
+                resource_size += 3 + index * (1 + font.fallback_indices.len() as u32);
+            }
+            match resource.format {
+                ResourceFormat::Inline => resource_size += 4 + resource.data.len() as u32, // data_size + data
+                ResourceFormat::Shared => resource_size += index, // index of the resource holding the data
+                ResourceFormat::External => {}
+            }
             resource.calculated_size = resource_size;
             total_size += resource_size;
         }
@@ -173,30 +236,32 @@ impl SizeCalculator {
         state.total_resource_table_size = total_size;
     }
     
-    fn calculate_template_variable_sizes(&self, state: &mut CompilerState) {
+    fn calculate_template_variable_sizes(&self, state: &mut CompilerState, width: IndexWidth) {
+        let index = width.bytes() as u32;
+        
         // Calculate template variables table size
         let mut template_var_size = 0u32;
         for _template_var in &state.template_variables {
-            // Each template variable: name_index (1) + value_type (1) + default_value_index (1) = 3 bytes
-            template_var_size += 3;
+            // Each template variable: name_index + value_type (1) + default_value_index
+            template_var_size += 1 + 2 * index;
         }
         state.total_template_variable_size = template_var_size;
         
         // Calculate template bindings table size
         let mut template_binding_size = 0u32;
         for binding in &state.template_bindings {
-            // Each binding: element_index (2) + property_id (1) + expression_index (1) + variable_count (1) + variable_indices
-            template_binding_size += 5 + binding.variable_indices.len() as u32;
+            // Each binding: element_index (2) + property_id (1) + expression_index + variable_count + variable_indices
+            template_binding_size += 3 + index * (2 + binding.variable_indices.len() as u32);
         }
         state.total_template_binding_size = template_binding_size;
     }
     
-    fn calculate_transform_sizes(&self, state: &mut CompilerState) {
+    fn calculate_transform_sizes(&self, state: &mut CompilerState, width: IndexWidth) {
         let mut total_size = 0u32;
         
         for transform in &state.transforms {
-            // Each transform: transform_type (1) + property_count (1) = 2 bytes
-            let mut transform_size = 2u32;
+            // Each transform: transform_type (1) + property_count
+            let mut transform_size = 1 + width.bytes() as u32;
             
             // Add property sizes
             for prop in &transform.properties {
@@ -209,22 +274,48 @@ impl SizeCalculator {
         
         state.total_transform_size = total_size;
     }
+    
+    /// The theme table, written only when there are themes; `FLAG_HAS_THEMES` records that
+    fn calculate_theme_sizes(&self, state: &mut CompilerState, width: IndexWidth) {
+        if state.themes.is_empty() {
+            state.header_flags &= !FLAG_HAS_THEMES;
+            state.total_theme_size = 0;
+            return;
+        }
+        state.header_flags |= FLAG_HAS_THEMES;
+        let index = width.bytes() as u32;
+        
+        // Slot count, then each slot: name_index + value_type (1)
+        let mut total_size = index + state.theme_slots.len() as u32 * (index + 1);
+        for theme_index in 0..state.themes.len() {
+            // Each theme: name_index, then size (1) + data for every slot
+            total_size += index;
+            for slot in &state.theme_slots {
+                total_size += 1 + width.value_size(slot.value_type, &slot.values[theme_index]) as u32;
+            }
+        }
+        
+        state.total_theme_size = total_size;
+    }
         
     pub fn calculate_section_offsets(&self, state: &mut CompilerState) {
         let mut current_offset = KRB_HEADER_SIZE as u32;
+        if state.header_flags & FLAG_HAS_THEMES != 0 {
+            current_offset += KRB_THEME_HEADER_SIZE as u32;
+        }
         
-        println!("DEBUG: Calculating offsets - header size = {} (0x{:X})", KRB_HEADER_SIZE, KRB_HEADER_SIZE);
+        log::debug!("Calculating offsets - header size = {} (0x{:X})", KRB_HEADER_SIZE, KRB_HEADER_SIZE);
         
         // The order of these additions MUST match the physical write order in codegen.
         
         // 1. String table
         state.string_offset = current_offset;
-        println!("DEBUG: String offset = {} (0x{:X}), string size = {}", current_offset, current_offset, state.total_string_data_size);
+        log::debug!("String offset = {} (0x{:X}), string size = {}", current_offset, current_offset, state.total_string_data_size);
         current_offset += state.total_string_data_size;
         
         // 2. Element tree
         state.element_offset = current_offset;
-        println!("DEBUG: Element offset = {} (0x{:X}), element size = {}", current_offset, current_offset, state.total_element_data_size);
+        log::debug!("Element offset = {} (0x{:X}), element size = {}", current_offset, current_offset, state.total_element_data_size);
         current_offset += state.total_element_data_size;
         
         // 3. Style table
@@ -237,9 +328,9 @@ impl SizeCalculator {
         state.component_def_offset = current_offset;
         current_offset += state.total_component_def_data_size;
         
-        // 5. Animations (currently reserved, size is 0)
+        // 5. Animations
         state.anim_offset = current_offset;
-        // current_offset += state.total_anim_data_size; // No size to add yet
+        current_offset += state.total_animation_data_size;
 
         // 6. Scripts
         state.script_offset = current_offset;
@@ -261,55 +352,70 @@ impl SizeCalculator {
         state.transform_offset = current_offset;
         current_offset += state.total_transform_size;
         
-        // 11. Total file size
+        // 11. Theme table
+        state.theme_offset = current_offset;
+        current_offset += state.total_theme_size;
+        
+        // 12. Total file size
         state.total_size = current_offset;
     }
-    /// Validate that all sizes are within limits
-    pub fn validate_limits(&self, state: &CompilerState) -> Result<()> {
+    /// Validate that all sizes are within the limits of the target platform
+    pub fn validate_limits(&self, state: &CompilerState, profile: &PlatformProfile) -> Result<()> {
         // Check element count
-        if state.elements.len() > MAX_ELEMENTS {
+        if state.elements.len() > profile.max_elements {
             return Err(CompilerError::LimitExceeded {
                 limit_type: "elements".to_string(),
-                limit: MAX_ELEMENTS,
+                limit: profile.max_elements,
             });
         }
         
         // Check string count
-        if state.strings.len() > MAX_STRINGS {
+        if state.strings.len() > profile.max_strings {
             return Err(CompilerError::LimitExceeded {
                 limit_type: "strings".to_string(),
-                limit: MAX_STRINGS,
+                limit: profile.max_strings,
             });
         }
         
         // Check style count
-        if state.styles.len() > MAX_STYLES {
+        if state.styles.len() > profile.max_styles {
             return Err(CompilerError::LimitExceeded {
                 limit_type: "styles".to_string(),
-                limit: MAX_STYLES,
+                limit: profile.max_styles,
             });
         }
         
         // Check component count
-        if state.component_defs.len() > MAX_COMPONENT_DEFS {
+        if state.component_defs.len() > profile.max_component_defs {
             return Err(CompilerError::LimitExceeded {
                 limit_type: "component definitions".to_string(),
-                limit: MAX_COMPONENT_DEFS,
+                limit: profile.max_component_defs,
+            });
+        }
+        
+        // Check animation count
+        if state.animations.len() > profile.max_animations {
+            return Err(CompilerError::LimitExceeded {
+                limit_type: "animations".to_string(),
+                limit: profile.max_animations,
             });
         }
         
         // Check resource count
-        if state.resources.len() > MAX_RESOURCES {
+        if state.resources.len() > profile.max_resources {
             return Err(CompilerError::LimitExceeded {
                 limit_type: "resources".to_string(),
-                limit: MAX_RESOURCES,
+                limit: profile.max_resources,
             });
         }
         
-        // Check total file size (2GB limit for safety)
-        if state.total_size > 2_147_483_648 {
+        // Check total file size against what the renderer can load
+        if state.total_size as u64 > profile.max_file_size {
             return Err(CompilerError::CodeGen {
-                message: "Generated file would exceed 2GB size limit".to_string(),
+                message: format!(
+                    "Generated file ({} bytes) exceeds the {} byte limit of the target platform",
+                    state.total_size, profile.max_file_size
+                ),
             });
         }
         
@@ -324,57 +430,21 @@ impl SizeCalculator {
                 });
             }
             
-            if element.krb_properties.len() > MAX_PROPERTIES {
-                return Err(CompilerError::LimitExceeded {
-                    limit_type: format!("properties for element {}", i),
-                    limit: MAX_PROPERTIES,
-                });
-            }
-            
-            if element.krb_custom_properties.len() > MAX_CUSTOM_PROPERTIES {
-                return Err(CompilerError::LimitExceeded {
-                    limit_type: format!("custom properties for element {}", i),
-                    limit: MAX_CUSTOM_PROPERTIES,
-                });
-            }
-            
-            if element.children.len() > MAX_CHILDREN {
-                return Err(CompilerError::LimitExceeded {
-                    limit_type: format!("children for element {}", i),
-                    limit: MAX_CHILDREN,
-                });
+            let counts = [
+                ("properties", element.krb_properties.len(), profile.max_properties),
+                ("custom properties", element.krb_custom_properties.len(), profile.max_custom_properties),
+                ("children", element.children.len(), profile.max_children),
+            ];
+            for (kind, count, limit) in counts {
+                if count > limit {
+                    return Err(element_limit_error(element, i, kind, count, limit));
+                }
             }
         }
         
         Ok(())
     }
     
-    /// Get size statistics for reporting
-    pub fn get_size_stats(&self, state: &CompilerState) -> SizeStatistics {
-        SizeStatistics {
-            total_size: state.total_size,
-            header_size: KRB_HEADER_SIZE as u32,
-            string_table_size: state.total_string_data_size,
-            element_tree_size: state.total_element_data_size,
-            style_table_size: state.total_style_data_size,
-            component_def_size: state.total_component_def_data_size,
-            script_table_size: state.total_script_data_size,
-            resource_table_size: state.total_resource_table_size,
-            template_variable_size: state.total_template_variable_size,
-            template_binding_size: state.total_template_binding_size,
-            transform_size: state.total_transform_size,
-            element_count: state.elements.len(),
-            string_count: state.strings.len(),
-            style_count: state.styles.len(),
-            component_count: state.component_defs.len(),
-            script_count: state.scripts.len(),
-            resource_count: state.resources.len(),
-            template_variable_count: state.template_variables.len(),
-            template_binding_count: state.template_bindings.len(),
-            transform_count: state.transforms.len(),
-        }
-    }
-    
     /// Extract style property values to element headers for renderer optimization
     /// This is the architecturally correct place - after element parsing but before finalization
     fn extract_header_values_from_styles(&self, state: &mut CompilerState) -> Result<()> {
@@ -425,84 +495,150 @@ impl SizeCalculator {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct SizeStatistics {
-    pub total_size: u32,
-    pub header_size: u32,
-    pub string_table_size: u32,
-    pub element_tree_size: u32,
-    pub style_table_size: u32,
-    pub component_def_size: u32,
-    pub script_table_size: u32,
-    pub resource_table_size: u32,
-    pub template_variable_size: u32,
-    pub template_binding_size: u32,
-    pub transform_size: u32,
-    pub element_count: usize,
-    pub string_count: usize,
-    pub style_count: usize,
-    pub component_count: usize,
-    pub script_count: usize,
-    pub resource_count: usize,
-    pub template_variable_count: usize,
-    pub template_binding_count: usize,
-    pub transform_count: usize,
+/// Encoded size of a property: prop_id + value_type + size + data
+fn property_size(prop: &KrbProperty, width: IndexWidth) -> u32 {
+    3 + width.value_size(prop.value_type, &prop.value) as u32
+}
+
+/// A per-element limit error, pointing at the element when its source is known
+fn element_limit_error(element: &Element, index: usize, kind: &str, count: usize, limit: usize) -> CompilerError {
+    match &element.source_location {
+        Some(location) => CompilerError::Diagnostics(std::iter::once(
+            Diagnostic::error(format!(
+                "{} has {} {}, over the limit of {} for the target platform",
+                element.source_element_name, count, kind, limit
+            ))
+            .with_span(Some(location.clone())),
+        ).collect()),
+        None => CompilerError::LimitExceeded {
+            limit_type: format!("{} for element {}", kind, index),
+            limit,
+        },
+    }
 }
 
-impl SizeStatistics {
-    pub fn print_breakdown(&self) {
-        println!("KRB Size Breakdown:");
-        println!("  Total size: {} bytes", self.total_size);
-        println!("  Header: {} bytes ({:.1}%)", 
-                self.header_size, 
-                self.header_size as f64 / self.total_size as f64 * 100.0);
-        println!("  String table: {} bytes ({:.1}%)", 
-                self.string_table_size,
-                self.string_table_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Element tree: {} bytes ({:.1}%)", 
-                self.element_tree_size,
-                self.element_tree_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Style table: {} bytes ({:.1}%)", 
-                self.style_table_size,
-                self.style_table_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Component defs: {} bytes ({:.1}%)", 
-                self.component_def_size,
-                self.component_def_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Script table: {} bytes ({:.1}%)", 
-                self.script_table_size,
-                self.script_table_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Resource table: {} bytes ({:.1}%)", 
-                self.resource_table_size,
-                self.resource_table_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Template variables: {} bytes ({:.1}%)", 
-                self.template_variable_size,
-                self.template_variable_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Template bindings: {} bytes ({:.1}%)", 
-                self.template_binding_size,
-                self.template_binding_size as f64 / self.total_size as f64 * 100.0);
-        println!("  Transform data: {} bytes ({:.1}%)", 
-                self.transform_size,
-                self.transform_size as f64 / self.total_size as f64 * 100.0);
-        
-        println!("\nCounts:");
-        println!("  Elements: {}", self.element_count);
-        println!("  Strings: {}", self.string_count);
-        println!("  Styles: {}", self.style_count);
-        println!("  Components: {}", self.component_count);
-        println!("  Scripts: {}", self.script_count);
-        println!("  Resources: {}", self.resource_count);
-        println!("  Template variables: {}", self.template_variable_count);
-        println!("  Template bindings: {}", self.template_binding_count);
-        println!("  Transforms: {}", self.transform_count);
+/// The largest value any index or count field of the file will hold
+fn largest_index_or_count(state: &CompilerState) -> usize {
+    let mut largest = 0usize;
+    let mut see = |value: usize| largest = largest.max(value);
+    let see_properties = |see: &mut dyn FnMut(usize), properties: &[KrbProperty]| {
+        see(properties.len());
+        for prop in properties {
+            if let Some(index) = prop.index() {
+                see(index as usize);
+            }
+        }
+    };
+    
+    see(state.strings.len().saturating_sub(1));
+    for string in &state.strings {
+        see(string.text.len());
+    }
+    
+    for element in &state.elements {
+        see(element.id_string_index as usize);
+        see(element.style_id as usize);
+        for count in [
+            element.property_count, element.child_count, element.event_count,
+            element.animation_count, element.custom_prop_count, element.state_prop_count,
+        ] {
+            see(count as usize);
+        }
+        see_properties(&mut see, &element.krb_properties);
+        see(element.children.len());
+        see(element.krb_events.len());
+        see(element.krb_animations.len());
+        see(element.krb_custom_properties.len());
+        see(element.state_property_sets.len());
+        for custom_prop in &element.krb_custom_properties {
+            see(custom_prop.key_index as usize);
+            if custom_prop.value_type.holds_index() {
+                see(decode_index(&custom_prop.value).unwrap_or_default() as usize);
+            }
+        }
+        for state_set in &element.state_property_sets {
+            see_properties(&mut see, &state_set.properties);
+        }
+        for event in &element.krb_events {
+            see(event.callback_id as usize);
+        }
+        for animation in &element.krb_animations {
+            see(animation.target as usize);
+        }
+    }
+    
+    for style in &state.styles {
+        see(style.id as usize);
+        see(style.name_index as usize);
+        see_properties(&mut see, &style.properties);
+    }
+    
+    for component in &state.component_defs {
+        see(component.properties.len());
+        for prop_def in &component.properties {
+            see(prop_def.default_value.len());
+        }
+    }
+    
+    see(state.animations.len().saturating_sub(1));
+    for animation in &state.animations {
+        see(animation.name_index as usize);
+        see(animation.keyframes.len());
+        for keyframe in &animation.keyframes {
+            see_properties(&mut see, &keyframe.properties);
+        }
+    }
+    
+    for script in &state.scripts {
+        see(script.name_index as usize);
+        see(script.entry_points.len());
+        for entry_point in &script.entry_points {
+            see(entry_point.function_name_index as usize);
+        }
+    }
+    
+    see(state.resources.len().saturating_sub(1));
+    for resource in &state.resources {
+        see(resource.name_index as usize);
+        see(resource.data_string_index as usize);
+        see(resource.shared_with.unwrap_or_default() as usize);
+        if let Some(font) = &resource.font {
+            see(font.fallback_indices.len());
+            for &fallback in &font.fallback_indices {
+                see(fallback as usize);
+            }
+        }
+    }
+    
+    see(state.theme_slots.len());
+    for slot in &state.theme_slots {
+        see(slot.name_index as usize);
+        for value in slot.values.iter().filter(|_| slot.value_type.holds_index()) {
+            see(decode_index(value).unwrap_or_default() as usize);
+        }
+    }
+    for theme in &state.themes {
+        see(theme.name_index as usize);
     }
     
-    pub fn compression_ratio(&self, original_size: u64) -> f64 {
-        if original_size > 0 {
-            self.total_size as f64 / original_size as f64
-        } else {
-            0.0
+    see(state.template_variables.len().saturating_sub(1));
+    for variable in &state.template_variables {
+        see(variable.name_index as usize);
+        see(variable.default_value_index as usize);
+    }
+    for binding in &state.template_bindings {
+        see(binding.template_expression_index as usize);
+        see(binding.variable_indices.len());
+        for &variable in &binding.variable_indices {
+            see(variable as usize);
         }
     }
+    
+    for transform in &state.transforms {
+        see(transform.properties.len());
+    }
+    
+    largest
 }
 
 #[cfg(test)]
@@ -533,10 +669,14 @@ mod tests {
             index: 2,
         });
         
-        calculator.calculate_string_table_size(&mut state);
+        calculator.calculate_string_table_size(&mut state, IndexWidth::Compact);
         
         // Expected: 1 + 0 + 1 + 5 + 1 + 5 = 13 bytes
         assert_eq!(state.total_string_data_size, 13);
+        
+        // Wide files spend two bytes on each length
+        calculator.calculate_string_table_size(&mut state, IndexWidth::Wide);
+        assert_eq!(state.total_string_data_size, 16);
     }
     
     #[test]
@@ -0,0 +1,151 @@
+// FILE: src/compiler/file_provider.rs
+
+//! File access used by the compiler for includes, external scripts and embedded resources
+//!
+//! The pipeline never touches the filesystem directly; it asks a `FileProvider`.
+//! `DiskFileProvider` backs the file-based API, while `MemoryFileProvider` lets
+//! editors and tests compile sources that only exist in memory.
+
+use crate::error::{CompilerError, Result};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Source of file contents for the compiler
+pub trait FileProvider {
+    /// The canonical identity of `path` if it names an existing file.
+    /// Two paths resolving to the same identity are treated as one module.
+    fn resolve(&self, path: &Path) -> Option<PathBuf>;
+
+    /// Read a file previously returned by `resolve`
+    fn read_to_string(&self, path: &Path) -> Result<String>;
+
+    /// Read a binary file previously returned by `resolve`, such as a font to embed
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        self.read_to_string(path).map(String::into_bytes)
+    }
+}
+
+/// Reads files from disk, identifying them by their canonical path
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskFileProvider;
+
+impl FileProvider for DiskFileProvider {
+    fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        if path.is_file() {
+            std::fs::canonicalize(path).ok()
+        } else {
+            None
+        }
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        std::fs::read_to_string(path).map_err(|e| CompilerError::FileNotFound {
+            path: format!("{}: {}", path.display(), e),
+        })
+    }
+
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        std::fs::read(path).map_err(|e| CompilerError::FileNotFound {
+            path: format!("{}: {}", path.display(), e),
+        })
+    }
+}
+
+/// Serves files from a map of paths to contents, which may be text or binary
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFileProvider {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFileProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a file
+    pub fn add_file(&mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+    }
+
+    /// Builder form of `add_file`
+    pub fn with_file(mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        self.add_file(path, contents);
+        self
+    }
+}
+
+impl FileProvider for MemoryFileProvider {
+    fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        let path = normalize(path);
+        self.files.contains_key(&path).then_some(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        String::from_utf8(self.read_bytes(path)?).map_err(|_| CompilerError::InvalidFormat {
+            message: format!("{} is not valid UTF-8", path.display()),
+        })
+    }
+
+    fn read_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files.get(&normalize(path)).cloned().ok_or_else(|| CompilerError::FileNotFound {
+            path: path.display().to_string(),
+        })
+    }
+}
+
+/// Lexically remove `.` and `..` components, since in-memory files have no real directories
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if normalized.file_name().is_some() {
+                    normalized.pop();
+                } else {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_paths_are_normalized() {
+        let provider = MemoryFileProvider::new().with_file("lib/card.kry", "style \"card\" {\n}\n");
+        assert_eq!(provider.resolve(Path::new("app/../lib/./card.kry")), Some(PathBuf::from("lib/card.kry")));
+        assert_eq!(provider.resolve(Path::new("card.kry")), None);
+        assert!(provider.read_to_string(Path::new("./lib/card.kry")).is_ok());
+    }
+
+    #[test]
+    fn test_memory_files_keep_binary_contents() {
+        let png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0xFF, 0x00];
+        let provider = MemoryFileProvider::new().with_file("logo.png", png.clone());
+        assert_eq!(provider.read_bytes(Path::new("logo.png")).unwrap(), png);
+        assert!(provider.read_to_string(Path::new("logo.png")).is_err());
+    }
+
+    #[test]
+    fn test_compile_source_in_memory() {
+        let provider = MemoryFileProvider::new()
+            .with_file("lib/card.kry", "style \"card\" {\n    background_color: \"#FF0000FF\"\n}\n");
+        let source = "@include \"../lib/card.kry\"\n\nApp {\n    Container {\n        style: \"card\"\n    }\n}\n";
+
+        let (krb, stats) =
+            crate::compile_source(source, "app/main.kry", &provider, crate::CompilerOptions::default()).unwrap();
+
+        let file = crate::core::KrbReader::new(&krb).unwrap().read().unwrap();
+        assert_eq!(stats.output_size, krb.len() as u64);
+        assert_eq!(stats.include_count, 2);
+        assert_eq!(file.styles.len(), 1);
+        assert!(stats.dependencies.contains(&PathBuf::from("lib/card.kry")));
+        assert!(!Path::new("app/main.kry").exists());
+    }
+}
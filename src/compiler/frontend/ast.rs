@@ -2,6 +2,7 @@
 
 use crate::core::*;
 use crate::core::types::*;
+use crate::diagnostics::Span;
 use std::collections::HashMap;
 
 /// AST node types
@@ -12,6 +13,7 @@ pub enum AstNode {
         directives: Vec<AstNode>,
         styles: Vec<AstNode>,
         fonts: Vec<AstNode>,
+        keyframes: Vec<AstNode>,
         components: Vec<AstNode>,
         scripts: Vec<AstNode>,
         app: Option<Box<AstNode>>,
@@ -27,12 +29,22 @@ pub enum AstNode {
         variables: HashMap<String, String>,
     },
     
+    /// @theme block: the value of each design token in one theme
+    Theme {
+        name: String,
+        tokens: Vec<AstProperty>,
+        /// Position of the theme name, for "defined here" notes
+        location: Option<Span>,
+    },
+    
     /// @script directive
     Script {
         language: String,
         name: Option<String>,
         source: ScriptSource,
         mode: Option<String>,
+        /// Where inline code starts, or the directive of an external script
+        location: Option<Span>,
     },
     
     /// style definition
@@ -41,12 +53,33 @@ pub enum AstNode {
         extends: Vec<String>,
         properties: Vec<AstProperty>,
         pseudo_selectors: Vec<PseudoSelector>,
+        media_queries: Vec<MediaQuery>,
+        /// Position of the style name, for "defined here" notes
+        location: Option<Span>,
+    },
+    
+    /// @keyframes definition
+    Keyframes {
+        name: String,
+        frames: Vec<Keyframe>,
+        /// Position of the keyframes name, for "defined here" notes
+        location: Option<Span>,
     },
     
-    /// font declaration
+    /// Placeholder for an item that failed to parse, left by error recovery
+    Error {
+        message: String,
+        location: Span,
+    },
+    
+    /// font declaration; several declarations of one family are its weight and style variants
     Font {
         name: String,
+        /// Font file, empty if a block declaration has no `src`
         path: String,
+        /// `weight`, `style` and `fallback` descriptors of the block form
+        properties: Vec<AstProperty>,
+        location: Option<Span>,
     },
     
     /// Define component
@@ -67,7 +100,12 @@ pub enum AstNode {
         element_type: String,
         properties: Vec<AstProperty>,
         pseudo_selectors: Vec<PseudoSelector>,
+        media_queries: Vec<MediaQuery>,
         children: Vec<AstNode>,
+        /// Position of the element type, when parsed from a file
+        location: Option<Span>,
+        /// The component usage this element was expanded from
+        origin: Option<ComponentOrigin>,
     },
     
     /// Template control flow structures
@@ -96,6 +134,8 @@ pub struct AstProperty {
     pub line: usize,
     pub template_variables: Vec<String>, // Variables used in {{}} syntax
     pub has_templates: bool, // Quick check if this property has template variables
+    /// Position of the property key in its source file, when parsed from one
+    pub location: Option<Span>,
 }
 
 /// Property value types
@@ -123,6 +163,10 @@ pub enum PropertyValue {
         name: String,
         args: Vec<PropertyValue>,
     },
+    /// `linear-gradient(...)` or `radial-gradient(...)`
+    Gradient(crate::core::gradient::Gradient),
+    /// Arithmetic on lengths in different units, left for the renderer
+    Calc(crate::core::expression::Calc),
 }
 
 /// Expression AST for complex value expressions
@@ -134,6 +178,21 @@ pub enum Expression {
     Integer(i64),
     Boolean(bool),
     Variable(String),
+    /// A number with a unit, such as `4px`, `1.5em` or `50%`
+    Dimension(f64, CSSUnit),
+    
+    /// Arithmetic operators
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Remainder(Box<Expression>, Box<Expression>),
+    Negate(Box<Expression>),
+    /// `min(...)`, `max(...)`, `clamp(...)` or `calc(...)`
+    Call {
+        name: String,
+        args: Vec<Expression>,
+    },
     
     /// Binary comparison operators
     NotEquals(Box<Expression>, Box<Expression>),
@@ -180,6 +239,8 @@ impl PropertyValue {
                 let arg_strs: Vec<String> = args.iter().map(|a| a.to_string()).collect();
                 format!("{}({})", name, arg_strs.join(", "))
             }
+            PropertyValue::Gradient(gradient) => gradient.to_string(),
+            PropertyValue::Calc(calc) => calc.to_string(),
         }
     }
     
@@ -235,6 +296,17 @@ impl Expression {
             Expression::Integer(i) => i.to_string(),
             Expression::Boolean(b) => b.to_string(),
             Expression::Variable(v) => format!("${}", v),
+            Expression::Dimension(n, unit) => format!("{}{}", n, unit.suffix()),
+            Expression::Add(left, right) => format!("{} + {}", left.operand(2, false), right.operand(2, true)),
+            Expression::Subtract(left, right) => format!("{} - {}", left.operand(2, false), right.operand(2, true)),
+            Expression::Multiply(left, right) => format!("{} * {}", left.operand(3, false), right.operand(3, true)),
+            Expression::Divide(left, right) => format!("{} / {}", left.operand(3, false), right.operand(3, true)),
+            Expression::Remainder(left, right) => format!("{} % {}", left.operand(3, false), right.operand(3, true)),
+            Expression::Negate(operand) => format!("-{}", operand.operand(4, false)),
+            Expression::Call { name, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.operand(0, false)).collect();
+                format!("{}({})", name, args.join(", "))
+            }
             Expression::NotEquals(left, right) => format!("{} != {}", left.to_string(), right.to_string()),
             Expression::EqualEquals(left, right) => format!("{} == {}", left.to_string(), right.to_string()),
             Expression::LessThan(left, right) => format!("{} < {}", left.to_string(), right.to_string()),
@@ -247,11 +319,60 @@ impl Expression {
         }
     }
     
+    /// How tightly this expression binds when written as an operand
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Ternary { .. } => 0,
+            Expression::NotEquals(..) | Expression::EqualEquals(..) | Expression::LessThan(..) |
+            Expression::LessThanOrEqual(..) | Expression::GreaterThan(..) | Expression::GreaterThanOrEqual(..) => 1,
+            Expression::Add(..) | Expression::Subtract(..) => 2,
+            Expression::Multiply(..) | Expression::Divide(..) | Expression::Remainder(..) => 3,
+            Expression::Negate(_) => 4,
+            _ => 5,
+        }
+    }
+    
+    /// This expression as an operand of an operator with `precedence`: strings are
+    /// quoted and looser expressions parenthesized, so the text parses back the same
+    fn operand(&self, precedence: u8, right: bool) -> String {
+        match self {
+            Expression::String(s) => format!("\"{}\"", s),
+            _ if self.precedence() < precedence || (right && self.precedence() == precedence) => format!("({})", self.to_string()),
+            _ => self.to_string(),
+        }
+    }
+    
+    /// Whether this expression does any arithmetic or calls `min`, `max`, `clamp` or `calc`
+    pub fn is_arithmetic(&self) -> bool {
+        match self {
+            Expression::Add(..) | Expression::Subtract(..) | Expression::Multiply(..) |
+            Expression::Divide(..) | Expression::Remainder(..) | Expression::Negate(_) |
+            Expression::Call { .. } => true,
+            Expression::NotEquals(left, right) |
+            Expression::EqualEquals(left, right) |
+            Expression::LessThan(left, right) |
+            Expression::LessThanOrEqual(left, right) |
+            Expression::GreaterThan(left, right) |
+            Expression::GreaterThanOrEqual(left, right) => left.is_arithmetic() || right.is_arithmetic(),
+            Expression::Ternary { condition, true_value, false_value } => {
+                condition.is_arithmetic() || true_value.is_arithmetic() || false_value.is_arithmetic()
+            }
+            _ => false,
+        }
+    }
+    
     /// Check if this expression contains template variables
     pub fn has_variables(&self) -> bool {
         match self {
             Expression::Variable(_) => true,
             Expression::String(s) => s.contains('$'),
+            Expression::Negate(operand) => operand.has_variables(),
+            Expression::Call { args, .. } => args.iter().any(|arg| arg.has_variables()),
+            Expression::Add(left, right) |
+            Expression::Subtract(left, right) |
+            Expression::Multiply(left, right) |
+            Expression::Divide(left, right) |
+            Expression::Remainder(left, right) |
             Expression::NotEquals(left, right) |
             Expression::EqualEquals(left, right) |
             Expression::LessThan(left, right) |
@@ -278,6 +399,17 @@ impl Expression {
                     // TODO: Implement proper variable extraction
                 }
             },
+            Expression::Negate(operand) => variables.extend(operand.extract_variables()),
+            Expression::Call { args, .. } => {
+                for arg in args {
+                    variables.extend(arg.extract_variables());
+                }
+            }
+            Expression::Add(left, right) |
+            Expression::Subtract(left, right) |
+            Expression::Multiply(left, right) |
+            Expression::Divide(left, right) |
+            Expression::Remainder(left, right) |
             Expression::NotEquals(left, right) |
             Expression::EqualEquals(left, right) |
             Expression::LessThan(left, right) |
@@ -315,6 +447,34 @@ pub struct PseudoSelector {
     pub line: usize,
 }
 
+/// `@media (min-width: ...) and (max-width: ...) { ... }`: properties that apply
+/// while the viewport width is within the bounds
+#[derive(Debug, Clone)]
+pub struct MediaQuery {
+    /// Bounds as written: a width such as `600px` or a breakpoint variable such as `$tablet`
+    pub min_width: Option<String>,
+    pub max_width: Option<String>,
+    pub properties: Vec<AstProperty>,
+    pub location: Option<Span>,
+}
+
+/// A component usage whose template produced an element
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentOrigin {
+    pub component: String,
+    /// Position of the usage, e.g. `Card { ... }`
+    pub location: Option<Span>,
+}
+
+/// One `N% { ... }` block of a @keyframes definition
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    /// Position in the animation, 0.0 to 100.0 (`from` is 0, `to` is 100)
+    pub offset: f64,
+    pub properties: Vec<AstProperty>,
+    pub line: usize,
+}
+
 /// Script source type
 #[derive(Debug, Clone)]
 pub enum ScriptSource {
@@ -330,6 +490,7 @@ impl AstProperty {
             line,
             template_variables: Vec::new(),
             has_templates: false,
+            location: None,
         }
     }
     
@@ -341,8 +502,14 @@ impl AstProperty {
             line,
             template_variables,
             has_templates,
+            location: None,
         }
     }
+
+    pub fn with_location(mut self, location: Span) -> Self {
+        self.location = Some(location);
+        self
+    }
     
     /// Get the cleaned value (without quotes if it was quoted)
     pub fn cleaned_value(&self) -> String {
@@ -0,0 +1,278 @@
+//! Source formatter for KRY files
+//!
+//! Formatting works on the lexer's token stream instead of the AST. Comments
+//! and newlines are tokens of their own and every token carries its source
+//! span, so comments, literal spellings and script bodies survive untouched
+//! while indentation, spacing and top-level block order are normalised.
+
+use crate::compiler::frontend::lexer::{Lexer, Token, TokenType};
+use crate::compiler::frontend::parser::Parser;
+use crate::error::{CompilerError, Result};
+
+const INDENT: &str = "    ";
+
+/// A top-level block together with the comments written directly above it
+struct Item {
+    rank: u8,
+    lines: Vec<Vec<Token>>,
+}
+
+/// Format KRY source, returning the formatted text
+pub fn format_source(source: &str, filename: &str) -> Result<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let tokens = Lexer::new(source, filename.to_string()).tokenize()?;
+    Parser::new(tokens.clone()).parse()?;
+
+    let (header, items, trailing) = split_items(split_lines(tokens));
+
+    let mut sections = Vec::new();
+    if !header.is_empty() {
+        sections.push(render_lines(&header, &chars));
+    }
+    sections.extend(items.iter().map(|item| render_lines(&item.lines, &chars)));
+    if !trailing.is_empty() {
+        sections.push(render_lines(&trailing, &chars));
+    }
+    sections.retain(|section| !section.is_empty());
+
+    let mut output = sections.join("\n\n");
+    output.push('\n');
+
+    // Never hand back something the compiler would reject
+    let reparsed = Lexer::new(&output, filename.to_string())
+        .tokenize()
+        .and_then(|tokens| Parser::new(tokens).parse());
+    if let Err(e) = reparsed {
+        return Err(CompilerError::InvalidFormat {
+            message: format!("Formatting {} produced invalid output: {}", filename, e),
+        });
+    }
+
+    Ok(output)
+}
+
+fn split_lines(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut lines = vec![Vec::new()];
+    for token in tokens {
+        match token.token_type {
+            TokenType::Newline => lines.push(Vec::new()),
+            TokenType::Eof => {}
+            _ => lines.last_mut().unwrap().push(token),
+        }
+    }
+    lines
+}
+
+/// Split lines into a file header, ordered top-level items and trailing comments
+fn split_items(lines: Vec<Vec<Token>>) -> (Vec<Vec<Token>>, Vec<Item>, Vec<Vec<Token>>) {
+    let mut header = Vec::new();
+    let mut items: Vec<Item> = Vec::new();
+    let mut pending: Vec<Vec<Token>> = Vec::new();
+    let mut current: Option<Item> = None;
+    let mut depth = 0usize;
+
+    for line in lines {
+        if let Some(item) = current.as_mut() {
+            depth = (depth as isize + depth_delta(&line)).max(0) as usize;
+            item.lines.push(line);
+            if depth == 0 {
+                items.push(current.take().unwrap());
+            }
+            continue;
+        }
+
+        let first = match line.iter().find(|t| !matches!(t.token_type, TokenType::Comment(_))) {
+            Some(token) => token.token_type.clone(),
+            None => {
+                pending.push(line);
+                continue;
+            }
+        };
+
+        // Comments separated from the first item by a blank line describe the whole file
+        if items.is_empty() && header.is_empty() {
+            if let Some(blank) = pending.iter().rposition(|l| l.is_empty()) {
+                header = pending.drain(..=blank).collect();
+            }
+        }
+        while pending.first().is_some_and(|l| l.is_empty()) {
+            pending.remove(0);
+        }
+
+        let mut item = Item { rank: block_rank(&first), lines: std::mem::take(&mut pending) };
+        depth = depth_delta(&line).max(0) as usize;
+        item.lines.push(line);
+        if depth == 0 {
+            items.push(item);
+        } else {
+            current = Some(item);
+        }
+    }
+    if let Some(item) = current {
+        items.push(item);
+    }
+
+    // Stable sort keeps the relative order of blocks of the same kind
+    items.sort_by_key(|item| item.rank);
+    (header, items, pending)
+}
+
+/// Canonical position of a top-level block: includes, variables and themes, fonts, styles and keyframes, components, scripts, UI
+fn block_rank(first: &TokenType) -> u8 {
+    match first {
+        TokenType::Include => 0,
+        TokenType::Variables | TokenType::Theme => 1,
+        TokenType::Font => 2,
+        TokenType::Style | TokenType::Keyframes => 3,
+        TokenType::Define => 4,
+        TokenType::Script | TokenType::Function => 5,
+        _ => 6,
+    }
+}
+
+fn opens_block(token: &TokenType) -> bool {
+    matches!(
+        token,
+        TokenType::LeftBrace | TokenType::LeftBracket | TokenType::LeftParen | TokenType::For | TokenType::If
+    )
+}
+
+fn closes_block(token: &TokenType) -> bool {
+    matches!(
+        token,
+        TokenType::RightBrace | TokenType::RightBracket | TokenType::RightParen | TokenType::End
+    )
+}
+
+fn depth_delta(line: &[Token]) -> isize {
+    line.iter()
+        .map(|t| {
+            if opens_block(&t.token_type) {
+                1
+            } else if closes_block(&t.token_type) {
+                -1
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+fn render_lines(lines: &[Vec<Token>], chars: &[char]) -> String {
+    let mut output: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    let mut pending_blank = false;
+
+    for line in lines {
+        let first = match line.first() {
+            Some(token) => &token.token_type,
+            None => {
+                pending_blank = !output.is_empty();
+                continue;
+            }
+        };
+
+        let dedent = closes_block(first) || matches!(first, TokenType::Elif | TokenType::Else);
+        let indent = INDENT.repeat(if dedent { depth.saturating_sub(1) } else { depth });
+
+        let after_opener = output
+            .last()
+            .is_some_and(|l| l.ends_with('{') || l.ends_with('[') || l.ends_with('('));
+        if pending_blank && !dedent && !after_opener {
+            output.push(String::new());
+        }
+        pending_blank = false;
+
+        output.push(render_line(line, chars, &indent));
+        depth = (depth as isize + depth_delta(line)).max(0) as usize;
+    }
+
+    output.join("\n")
+}
+
+fn render_line(line: &[Token], chars: &[char], indent: &str) -> String {
+    let mut text = String::from(indent);
+    let mut previous: Option<&Token> = None;
+    let mut script_ended_with_newline = false;
+
+    for token in line {
+        let source: String = chars[token.span.clone()].iter().collect();
+
+        if let TokenType::ScriptContent(_) = token.token_type {
+            // Script bodies are copied verbatim; only the closing brace is re-indented
+            let body = match source.rfind('\n') {
+                Some(newline) if source[newline + 1..].trim().is_empty() => &source[..=newline],
+                _ => source.as_str(),
+            };
+            script_ended_with_newline = body.ends_with('\n');
+            text.push_str(body);
+            previous = Some(token);
+            continue;
+        }
+
+        if let Some(prev) = previous {
+            if matches!(prev.token_type, TokenType::ScriptContent(_)) {
+                if script_ended_with_newline {
+                    text.push_str(indent);
+                }
+            } else if needs_space(prev, token) {
+                text.push(' ');
+            }
+        }
+        text.push_str(&source);
+        previous = Some(token);
+    }
+
+    text.trim_end().to_string()
+}
+
+fn needs_space(previous: &Token, current: &Token) -> bool {
+    use TokenType::*;
+    match (&previous.token_type, &current.token_type) {
+        (_, Colon | Comma | Semicolon | RightParen | RightBracket) => false,
+        (Colon | Comma | Semicolon, _) => true,
+        (LeftParen | LeftBracket | Dollar | Dot, _) | (_, Dot) => false,
+        (_, LeftBrace | Comment(_)) => true,
+        _ => previous.span.end < current.span.start,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reindent_and_property_spacing() {
+        let source = "App {\nwindow_title :\"Demo\"\n  Container {\n        padding:10\n}\n}\n";
+        let formatted = format_source(source, "test.kry").unwrap();
+        assert_eq!(
+            formatted,
+            "App {\n    window_title: \"Demo\"\n    Container {\n        padding: 10\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_are_preserved() {
+        let source = "# Header comment\n\nApp {\n    # inside\n    text: \"hi\" // trailing\n}\n";
+        let formatted = format_source(source, "test.kry").unwrap();
+        assert_eq!(formatted, source);
+    }
+
+    #[test]
+    fn test_top_level_block_order() {
+        let source = "App {\n}\n\n# Card component\nDefine Card {\n    Container {\n    }\n}\n\nstyle \"base\" {\n}\n\n@variables {\n    size: 10\n}\n";
+        let formatted = format_source(source, "test.kry").unwrap();
+        assert_eq!(
+            formatted,
+            "@variables {\n    size: 10\n}\n\nstyle \"base\" {\n}\n\n# Card component\nDefine Card {\n    Container {\n    }\n}\n\nApp {\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent() {
+        let source = "style \"a\" {\n  background_color: \"#FF0000\"\n  &:hover {\n  opacity: 0.5\n  }\n}\n\n\n\nApp {\n\n  Button {\n    text: \"Go\"\n  }\n\n}\n";
+        let once = format_source(source, "test.kry").unwrap();
+        assert_eq!(format_source(&once, "test.kry").unwrap(), once);
+        assert!(!once.contains("\n\n\n"));
+    }
+}
@@ -30,8 +30,12 @@ pub enum TokenType {
     // Directives
     Include,
     Variables,
+    Keyframes,
     Script,
     Function,
+    Platform,
+    Media,
+    Theme,
     
     // Template control flow
     For,
@@ -64,6 +68,13 @@ pub enum TokenType {
     GreaterThan,  // >
     GreaterThanOrEqual, // >=
     
+    // Arithmetic operators
+    Plus,         // +
+    Minus,        // -
+    Star,         // *
+    Slash,        // /
+    Percent,      // % on its own; directly after a number it makes a Percentage
+    
     // Ternary operator
     Question,     // ?
     
@@ -102,6 +113,15 @@ pub struct Token {
     pub line: usize,
     pub column: usize,
     pub filename: String,
+    /// Character offsets of the token in the source, so tools can recover the exact text
+    pub span: std::ops::Range<usize>,
+}
+
+impl Token {
+    /// Where this token appears in its original file, for diagnostics
+    pub fn location(&self) -> crate::diagnostics::Span {
+        crate::diagnostics::Span::new(self.filename.clone(), self.line, self.column, self.span.len())
+    }
 }
 
 impl fmt::Display for TokenType {
@@ -128,6 +148,10 @@ impl fmt::Display for TokenType {
             TokenType::Properties => write!(f, "Properties"),
             TokenType::Include => write!(f, "@include"),
             TokenType::Variables => write!(f, "@variables"),
+            TokenType::Keyframes => write!(f, "@keyframes"),
+            TokenType::Platform => write!(f, "@platform"),
+            TokenType::Media => write!(f, "@media"),
+            TokenType::Theme => write!(f, "@theme"),
             TokenType::Script => write!(f, "@script"),
             TokenType::Function => write!(f, "@function/@method/@func"),
             TokenType::For => write!(f, "@for"),
@@ -152,6 +176,11 @@ impl fmt::Display for TokenType {
             TokenType::LessThanOrEqual => write!(f, "<="),
             TokenType::GreaterThan => write!(f, ">"),
             TokenType::GreaterThanOrEqual => write!(f, ">="),
+            TokenType::Plus => write!(f, "+"),
+            TokenType::Minus => write!(f, "-"),
+            TokenType::Star => write!(f, "*"),
+            TokenType::Slash => write!(f, "/"),
+            TokenType::Percent => write!(f, "%"),
             TokenType::Question => write!(f, "?"),
             TokenType::Ampersand => write!(f, "&"),
             TokenType::Dollar => write!(f, "$"),
@@ -186,6 +215,8 @@ pub struct Lexer {
     line: usize,
     column: usize,
     filename: String,
+    /// Column where the token being lexed starts, used to locate lexing errors
+    token_column: usize,
     
     // Store original source for script content reading
     source: String,
@@ -207,6 +238,7 @@ impl Lexer {
             line: 1,
             column: 1,
             filename,
+            token_column: 1,
             source: input.to_string(),
             color_regex: Regex::new(r"^#[0-9A-Fa-f]{3,8}$").unwrap(),
             identifier_regex: Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap(),
@@ -222,6 +254,7 @@ impl Lexer {
             line: 1,
             column: 1,
             filename,
+            token_column: 1,
             source: input.to_string(),
             color_regex: Regex::new(r"^#[0-9A-Fa-f]{3,8}$").unwrap(),
             identifier_regex: Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap(),
@@ -232,10 +265,15 @@ impl Lexer {
 
     /// Get the original source location for error reporting
     fn get_source_location(&self) -> (String, usize) {
+        self.map_line(self.line)
+    }
+
+    /// Map a line of the lexed text back to its original file and line
+    fn map_line(&self, line: usize) -> (String, usize) {
         if let Some(ref source_map) = self.source_map {
-            source_map.resolve_location(self.line, &self.filename)
+            source_map.resolve_location(line, &self.filename)
         } else {
-            (self.filename.clone(), self.line)
+            (self.filename.clone(), line)
         }
     }
 
@@ -243,7 +281,7 @@ impl Lexer {
     fn parse_error(&self, message: impl Into<String>) -> CompilerError {
         let (file, line) = self.get_source_location();
         log::debug!("Parse error at combined line {}, mapped to {}:{}", self.line, file, line);
-        CompilerError::parse(file, line, message)
+        CompilerError::parse_at(file, line, self.token_column, message)
     }
     
     pub fn tokenize(&mut self) -> Result<Vec<Token>> {
@@ -272,12 +310,15 @@ impl Lexer {
                     tokens.push(token); // Add the opening brace
                     
                     // Now read script content as raw text
+                    let content_start = self.position;
                     let script_content = self.read_script_content()?;
+                    let content_end = content_start + script_content.chars().count();
                     tokens.push(Token {
                         token_type: TokenType::ScriptContent(script_content),
                         line: self.line,
                         column: self.column,
                         filename: self.filename.clone(),
+                        span: content_start..content_end,
                     });
                     
                     // Add the closing brace token
@@ -286,6 +327,7 @@ impl Lexer {
                         line: self.line,
                         column: self.column,
                         filename: self.filename.clone(),
+                        span: content_end..self.position,
                     });
                     
                     continue;
@@ -297,12 +339,15 @@ impl Lexer {
                     tokens.push(token); // Add the opening brace
                     
                     // Now read script content as raw text
+                    let content_start = self.position;
                     let script_content = self.read_script_content()?;
+                    let content_end = content_start + script_content.chars().count();
                     tokens.push(Token {
                         token_type: TokenType::ScriptContent(script_content),
                         line: self.line,
                         column: self.column,
                         filename: self.filename.clone(),
+                        span: content_start..content_end,
                     });
                     
                     // Add the closing brace token
@@ -311,6 +356,7 @@ impl Lexer {
                         line: self.line,
                         column: self.column,
                         filename: self.filename.clone(),
+                        span: content_end..self.position,
                     });
                     
                     continue;
@@ -329,11 +375,13 @@ impl Lexer {
             }
         }
         
+        let (filename, line) = self.map_line(self.line);
         tokens.push(Token {
             token_type: TokenType::Eof,
-            line: self.line,
+            line,
             column: self.column,
-            filename: self.filename.clone(),
+            filename,
+            span: self.position..self.position,
         });
         
         Ok(tokens)
@@ -348,6 +396,8 @@ impl Lexer {
         
         let start_line = self.line;
         let start_column = self.column;
+        let start_position = self.position;
+        self.token_column = start_column;
         let ch = self.advance();
         
         let token_type = match ch {
@@ -410,6 +460,9 @@ impl Lexer {
                 }
             }
             '?' => TokenType::Question,
+            '+' => TokenType::Plus,
+            '*' => TokenType::Star,
+            '%' => TokenType::Percent,
             '$' => TokenType::Dollar,
             '.' => {
                 // Check if this is part of a number (e.g., .5) or standalone dot
@@ -456,9 +509,7 @@ impl Lexer {
                     let comment = self.read_comment();
                     TokenType::Comment(comment)
                 } else {
-                    return Err(self.parse_error(
-                        format!("Unexpected character: '{}' (single slash not supported)", ch)
-                    ));
+                    TokenType::Slash
                 }
             }
             '"' => {
@@ -470,6 +521,10 @@ impl Lexer {
                 match directive.as_str() {
                     "@include" => TokenType::Include,
                     "@variables" => TokenType::Variables,
+                    "@keyframes" => TokenType::Keyframes,
+                    "@platform" => TokenType::Platform,
+                    "@media" => TokenType::Media,
+                    "@theme" => TokenType::Theme,
                     "@script" => {
                         // For @script, we need to read the script content specially
                         TokenType::Script
@@ -492,10 +547,7 @@ impl Lexer {
                 let number_str = self.read_number(ch)?;
                 self.parse_number_with_unit(number_str)?
             }
-            '-' => {
-                // Handle minus sign (for script content like Lua comments --)
-                TokenType::Identifier("-".to_string())
-            }
+            '-' => TokenType::Minus,
             ch if ch.is_alphabetic() || ch == '_' => {
                 let identifier = self.read_identifier(ch)?;
                 self.identify_keyword_or_identifier(identifier)
@@ -507,11 +559,13 @@ impl Lexer {
             }
         };
         
+        let (filename, line) = self.map_line(start_line);
         Ok(Some(Token {
             token_type,
-            line: start_line,
+            line,
             column: start_column,
-            filename: self.filename.clone(),
+            filename,
+            span: start_position..self.position,
         }))
     }
     
@@ -771,6 +825,10 @@ impl Lexer {
             if ch.is_alphanumeric() || ch == '_' || ch == '-' {
                 identifier.push(ch);
                 self.advance();
+            } else if ch == '@' && self.peek_next().is_some_and(|next| next.is_ascii_alphabetic()) {
+                // A platform override such as `font_size@mobile` is one property name
+                identifier.push(ch);
+                self.advance();
             } else {
                 break;
             }
@@ -923,7 +981,19 @@ mod tests {
             _ => panic!("Expected integer token"),
         }
     }
-    
+
+    #[test]
+    fn test_arithmetic_operators() {
+        let mut lexer = Lexer::new("100% - 20px + $a * 2 / 4 % 3 -1", "test.kry".to_string());
+        let tokens: Vec<TokenType> = lexer.tokenize().unwrap().into_iter().map(|t| t.token_type).collect();
+        assert_eq!(tokens, [
+            TokenType::Percentage(100.0), TokenType::Minus, TokenType::Pixels(20.0), TokenType::Plus,
+            TokenType::Dollar, TokenType::Identifier("a".to_string()), TokenType::Star, TokenType::Integer(2),
+            TokenType::Slash, TokenType::Integer(4), TokenType::Percent, TokenType::Integer(3),
+            TokenType::Integer(-1), TokenType::Eof,
+        ]);
+    }
+
     #[test]
     fn test_colors() {
         let mut lexer = Lexer::new("#FF0000 #ABC #12345678", "test.kry".to_string());
@@ -1,14 +1,70 @@
 //! Recursive descent parser for the KRY language
 
 use crate::compiler::frontend::ast::*;
+use crate::core::color::{evaluate_color, named_color, COLOR_FUNCTIONS};
+use crate::core::expression::EXPRESSION_FUNCTIONS;
+use crate::core::gradient::{Gradient, GradientStop, RadialShape, MAX_GRADIENT_STOPS};
+use crate::core::CSSUnit;
+use crate::core::util::clean_and_quote_value;
+use crate::diagnostics::{Diagnostic, Span};
 use crate::error::{CompilerError, Result};
-use crate::compiler::frontend::lexer::{Token, TokenType};
+use crate::compiler::frontend::lexer::{Lexer, Token, TokenType};
+use crate::TargetPlatform;
 use std::collections::HashMap;
 use regex::Regex;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<CompilerError>, // Syntax errors recovered from so far
+    last_error_at: Option<usize>, // Token index of the latest error, to suppress cascades
+    target_platform: TargetPlatform, // Selects `@platform` blocks and `key@platform` overrides
+}
+
+/// Top-level items of a file, or of a `@platform` block at the top level
+#[derive(Default)]
+struct FileItems {
+    directives: Vec<AstNode>,
+    styles: Vec<AstNode>,
+    fonts: Vec<AstNode>,
+    keyframes: Vec<AstNode>,
+    components: Vec<AstNode>,
+    scripts: Vec<AstNode>,
+    app: Option<Box<AstNode>>,
+    standalone_elements: Vec<AstNode>, // Collected for auto-wrapping in an App
+}
+
+impl FileItems {
+    fn extend(&mut self, other: FileItems) {
+        self.directives.extend(other.directives);
+        self.styles.extend(other.styles);
+        self.fonts.extend(other.fonts);
+        self.keyframes.extend(other.keyframes);
+        self.components.extend(other.components);
+        self.scripts.extend(other.scripts);
+        self.app = self.app.take().or(other.app);
+        self.standalone_elements.extend(other.standalone_elements);
+    }
+}
+
+/// Items of an element body, or of a `@platform` block inside one
+#[derive(Default)]
+struct ElementBody {
+    properties: Vec<AstProperty>,
+    platform_properties: Vec<AstProperty>, // From matching `@platform` blocks; they win over `properties`
+    pseudo_selectors: Vec<PseudoSelector>,
+    media_queries: Vec<MediaQuery>,
+    children: Vec<AstNode>,
+}
+
+/// Items of a style body, or of a `@platform` block inside one
+#[derive(Default)]
+struct StyleBody {
+    extends: Vec<String>,
+    properties: Vec<AstProperty>,
+    platform_properties: Vec<AstProperty>,
+    pseudo_selectors: Vec<PseudoSelector>,
+    media_queries: Vec<MediaQuery>,
 }
 
 impl Parser {
@@ -16,19 +72,108 @@ impl Parser {
         Self {
             tokens,
             current: 0,
+            errors: Vec::new(),
+            last_error_at: None,
+            target_platform: TargetPlatform::default(),
         }
     }
+
+    /// Keep the `@platform` blocks and overrides for `platform`. Blocks for other
+    /// platforms are still checked for syntax errors, then dropped. A universal
+    /// build keeps only unconditional content.
+    pub fn with_target_platform(mut self, platform: TargetPlatform) -> Self {
+        self.target_platform = platform;
+        self
+    }
     
+    /// Parse `source` as one expression, such as the text of an `@variables` entry;
+    /// `None` unless it is an expression from start to end
+    pub fn parse_expression(source: &str) -> Option<Expression> {
+        let tokens = Lexer::new(source, "<expression>".to_string()).tokenize().ok()?;
+        let mut parser = Self::new(tokens);
+        let expr = parser.parse_ternary_expression().ok()?;
+        while parser.match_token(&TokenType::Newline) {}
+        parser.is_at_end().then_some(expr)
+    }
+
+    /// Parse a gradient written as text, such as a quoted `background_image` value;
+    /// `None` if the text is not a gradient at all
+    pub fn parse_gradient_source(source: &str) -> Option<std::result::Result<Gradient, String>> {
+        let tokens = Lexer::new(source.trim(), "<gradient>".to_string()).tokenize().ok()?;
+        let mut parser = Self::new(tokens);
+        if !parser.is_gradient() {
+            return None;
+        }
+        let message = |error: CompilerError| match error {
+            CompilerError::Parse { message, .. } => message,
+            other => other.to_string(),
+        };
+        Some(match parser.parse_gradient() {
+            Ok(PropertyValue::Gradient(gradient)) if parser.is_at_end() => Ok(gradient),
+            Ok(_) => Err(format!("unexpected '{}' after the gradient", parser.peek().token_type)),
+            Err(error) => Err(message(error)),
+        })
+    }
+
+    /// Parse a whole file. Every syntax error is reported: a single one as is,
+    /// several as `CompilerError::Diagnostics`.
     pub fn parse(&mut self) -> Result<AstNode> {
-        let mut directives = Vec::new();
-        let mut styles = Vec::new();
-        let mut fonts = Vec::new();
-        let mut components = Vec::new();
-        let mut scripts = Vec::new();
-        let mut app = None;
-        let mut standalone_elements = Vec::new(); // Collect standalone elements for auto-wrapping
+        let (ast, mut errors) = self.parse_recovering();
+        match errors.len() {
+            0 => Ok(ast),
+            1 => Err(errors.remove(0)),
+            _ => Err(CompilerError::Diagnostics(errors.iter().map(Diagnostic::from_error).collect())),
+        }
+    }
+
+    /// Parse a whole file, recovering from syntax errors at element boundaries,
+    /// property lines, block ends and `@end`. Returns the partial AST, with
+    /// `AstNode::Error` in place of children that failed, and every error found.
+    pub fn parse_recovering(&mut self) -> (AstNode, Vec<CompilerError>) {
+        let ast = match self.parse_file() {
+            Ok(ast) => ast,
+            Err(error) => {
+                self.report(error);
+                AstNode::File {
+                    directives: Vec::new(),
+                    styles: Vec::new(),
+                    fonts: Vec::new(),
+                    keyframes: Vec::new(),
+                    components: Vec::new(),
+                    scripts: Vec::new(),
+                    app: None,
+                }
+            }
+        };
+        (ast, std::mem::take(&mut self.errors))
+    }
+
+    fn parse_file(&mut self) -> Result<AstNode> {
+        let mut items = FileItems::default();
+        self.parse_file_items(&mut items, false);
         
-        while !self.is_at_end() {
+        // Auto-create App wrapper if none exists and we have standalone elements at root level
+        let app = if items.app.is_none() && !items.standalone_elements.is_empty() {
+            self.create_default_app_wrapper(items.standalone_elements)?
+        } else {
+            items.app
+        };
+        
+        Ok(AstNode::File {
+            directives: items.directives,
+            styles: items.styles,
+            fonts: items.fonts,
+            keyframes: items.keyframes,
+            components: items.components,
+            scripts: items.scripts,
+            app,
+        })
+    }
+
+    /// Parse top-level items up to the end of the file, or up to the `}` closing
+    /// a top-level `@platform` block when `nested`
+    fn parse_file_items(&mut self, items: &mut FileItems, nested: bool) {
+        while !(self.is_at_end() || nested && self.check(&TokenType::RightBrace)) {
             // Skip newlines
             if self.match_token(&TokenType::Newline) {
                 continue;
@@ -40,94 +185,167 @@ impl Parser {
                 continue;
             }
             
+            // A failed item is reported and skipped; the next one is still parsed
             match &self.peek().token_type {
                 TokenType::Include => {
-                    directives.push(self.parse_include()?);
+                    items.directives.extend(self.recover(Self::parse_include));
                 }
                 TokenType::Variables => {
-                    directives.push(self.parse_variables()?);
+                    items.directives.extend(self.recover(Self::parse_variables));
+                }
+                TokenType::Theme => {
+                    items.directives.extend(self.recover(Self::parse_theme));
                 }
                 TokenType::Script => {
-                    scripts.push(self.parse_script()?);
+                    items.scripts.extend(self.recover(Self::parse_script));
                 }
                 TokenType::Function => {
-                    scripts.push(self.parse_function()?);
+                    items.scripts.extend(self.recover(Self::parse_function));
+                }
+                TokenType::Platform => {
+                    let location = self.peek().location();
+                    let mut block = FileItems::default();
+                    let matched = self.recover(|parser| {
+                        parser.parse_platform_block(|parser| parser.parse_file_items(&mut block, true))
+                    });
+                    if matched == Some(true) {
+                        if items.app.is_some() && block.app.is_some() {
+                            self.report(CompilerError::parse_at(
+                                location.file,
+                                location.line,
+                                location.column,
+                                "Multiple App elements found. Only one App element is allowed.",
+                            ));
+                            block.app = None;
+                        }
+                        items.extend(block);
+                    }
                 }
                 TokenType::For => {
                     // @for can appear at root level for generating multiple elements
-                    let for_node = self.parse_for()?;
-                    standalone_elements.push(for_node);
+                    let for_node = self.recover_node(Self::parse_for);
+                    items.standalone_elements.push(for_node);
                 }
                 TokenType::If => {
                     // @if can appear at root level for conditional elements
-                    let if_node = self.parse_if()?;
-                    standalone_elements.push(if_node);
+                    let if_node = self.recover_node(Self::parse_if);
+                    items.standalone_elements.push(if_node);
                 }
                 TokenType::Style => {
-                    styles.push(self.parse_style()?);
+                    items.styles.extend(self.recover(Self::parse_style));
                 }
                 TokenType::Font => {
-                    fonts.push(self.parse_font()?);
+                    items.fonts.extend(self.recover(Self::parse_font));
+                }
+                TokenType::Keyframes => {
+                    items.keyframes.extend(self.recover(Self::parse_keyframes));
                 }
                 TokenType::Define => {
-                    components.push(self.parse_component()?);
+                    items.components.extend(self.recover(Self::parse_component));
                 }
                 TokenType::App => {
-                    if app.is_some() {
-                        return Err(CompilerError::parse_legacy(
-                            self.peek().line,
+                    if items.app.is_some() {
+                        // Report it, but still parse it so its own mistakes are found
+                        self.report(Self::error_at(
+                            self.peek(),
                             "Multiple App elements found. Only one App element is allowed."
                         ));
+                        self.recover_node(Self::parse_element);
+                        continue;
                     }
-                    app = Some(Box::new(self.parse_element()?));
+                    items.app = Some(Box::new(self.recover_node(Self::parse_element)));
                 }
                 _ => {
                     // Try parsing as element (for component usage at root level or standalone elements)
                     if self.is_element_start() {
-                        if app.is_some() {
-                            return Err(CompilerError::parse_legacy(
-                                self.peek().line,
+                        if items.app.is_some() {
+                            self.report(Self::error_at(
+                                self.peek(),
                                 "Only one root element (App or component) is allowed."
                             ));
+                            self.recover_node(Self::parse_element);
+                            continue;
                         }
                         
-                        let element = self.parse_element()?;
+                        let element = self.recover_node(Self::parse_element);
                         // Check if this is a standalone element that needs App wrapping
                         if let AstNode::Element { element_type, .. } = &element {
                             if element_type == "App" {
-                                app = Some(Box::new(element));
+                                items.app = Some(Box::new(element));
                             } else {
                                 // This is a standalone element - collect it for auto-wrapping
-                                standalone_elements.push(element);
+                                items.standalone_elements.push(element);
                             }
+                        } else if matches!(element, AstNode::Error { .. }) {
+                            items.standalone_elements.push(element);
                         } else {
-                            app = Some(Box::new(element));
+                            items.app = Some(Box::new(element));
                         }
                     } else {
-                        return Err(CompilerError::parse_legacy(
-                            self.peek().line,
-                            format!("Unexpected token: {}", self.peek().token_type)
-                        ));
+                        self.skip_unexpected(format!("Unexpected token: {}", self.peek().token_type));
                     }
                 }
             }
         }
+    }
+
+    /// `@platform(mobile, desktop) { ... }`: the block's body is parsed by `parse_body`
+    /// whatever the target, so its mistakes are always found. Returns whether the
+    /// target platform is one of those listed.
+    fn parse_platform_block(&mut self, parse_body: impl FnOnce(&mut Self)) -> Result<bool> {
+        self.consume(TokenType::Platform, "Expected @platform")?;
+        self.consume(TokenType::LeftParen, "Expected '(' after @platform")?;
+        
+        let mut matched = false;
+        loop {
+            let name = match &self.peek().token_type {
+                TokenType::Identifier(name) => name.clone(),
+                _ => return Err(Self::error_at(self.peek(), "Expected platform name")),
+            };
+            matched |= self.platform_matches(&name)?;
+            self.advance();
+            if !self.match_token(&TokenType::Comma) {
+                break;
+            }
+        }
         
-        // Auto-create App wrapper if none exists and we have standalone elements at root level
-        let app = if app.is_none() && !standalone_elements.is_empty() {
-            self.create_default_app_wrapper(standalone_elements)?
-        } else {
-            app
-        };
-        
-        Ok(AstNode::File {
-            directives,
-            styles,
-            fonts,
-            components,
-            scripts,
-            app,
-        })
+        self.consume(TokenType::RightParen, "Expected ')' after platform names")?;
+        self.consume(TokenType::LeftBrace, "Expected '{' after @platform(...)")?;
+        parse_body(self);
+        self.close_block(TokenType::RightBrace, "Expected '}' after @platform block")?;
+        Ok(matched)
+    }
+
+    /// Whether `name`, the current token, names the target platform
+    fn platform_matches(&self, name: &str) -> Result<bool> {
+        match TargetPlatform::from_name(name) {
+            Some(platform) => Ok(platform == self.target_platform),
+            None => Err(Self::error_at(
+                self.peek(),
+                format!("Unknown platform '{}'. Use desktop, mobile, web or embedded.", name)
+            )),
+        }
+    }
+
+    /// Settle platform-specific values: `key@platform` overrides and the properties
+    /// of matching `@platform` blocks replace the unconditional `key`, wherever they
+    /// appear. Overrides for other platforms are dropped.
+    fn resolve_platform_properties(&self, properties: &mut Vec<AstProperty>, platform_properties: Vec<AstProperty>) {
+        let (overrides, mut unconditional): (Vec<_>, Vec<_>) =
+            std::mem::take(properties).into_iter().partition(|p| p.key.contains('@'));
+        let (block_overrides, mut specific): (Vec<_>, Vec<_>) =
+            platform_properties.into_iter().partition(|p| p.key.contains('@'));
+
+        for mut property in overrides.into_iter().chain(block_overrides) {
+            let (key, platform) = property.key.split_once('@').unwrap_or_default();
+            if TargetPlatform::from_name(platform) == Some(self.target_platform) {
+                property.key = key.to_string();
+                specific.push(property);
+            }
+        }
+        unconditional.retain(|property| !specific.iter().any(|s| s.key == property.key));
+        unconditional.extend(specific);
+        *properties = unconditional;
     }
     
 
@@ -149,8 +367,8 @@ impl Parser {
         
         let path = match &self.advance().token_type {
             TokenType::String(s) => s.clone(),
-            _ => return Err(CompilerError::parse_legacy(
-                self.previous().line,
+            _ => return Err(Self::error_at(
+                self.previous(),
                 "Expected string path after @include"
             )),
         };
@@ -176,8 +394,8 @@ impl Parser {
             }
             let name = match &self.advance().token_type {
                 TokenType::Identifier(name) => name.clone(),
-                _ => return Err(CompilerError::parse_legacy(
-                    self.previous().line,
+                _ => return Err(Self::error_at(
+                    self.previous(),
                     "Expected variable name"
                 )),
             };
@@ -186,7 +404,7 @@ impl Parser {
             
             self.skip_whitespace_and_comments();
 
-            let value = self.parse_value()?;
+            let value = if self.is_function_call() { self.parse_function_call()? } else { self.parse_value()? };
             variables.insert(name, value.to_string());
 
             self.skip_whitespace_and_comments();
@@ -198,13 +416,52 @@ impl Parser {
         Ok(AstNode::Variables { variables })
     }
     
+    /// `@theme dark { surface: "#202020FF" ... }`: one value for each design token
+    fn parse_theme(&mut self) -> Result<AstNode> {
+        self.consume(TokenType::Theme, "Expected '@theme'")?;
+
+        let name = match &self.peek().token_type {
+            TokenType::Identifier(name) | TokenType::String(name) => name.clone(),
+            _ => {
+                return Err(Self::error_at(
+                    self.peek(),
+                    format!("Expected theme name, but found {}", self.peek().token_type)
+                ));
+            }
+        };
+        let location = self.advance().location();
+
+        self.consume(TokenType::LeftBrace, "Expected '{' after theme name")?;
+
+        let mut tokens = Vec::new();
+        while !self.at_block_end(&TokenType::RightBrace) {
+            if self.match_token(&TokenType::Newline) {
+                continue;
+            }
+            if matches!(self.peek().token_type, TokenType::Comment(_)) {
+                self.advance();
+                continue;
+            }
+            tokens.extend(self.recover(Self::parse_property));
+        }
+
+        self.close_block(TokenType::RightBrace, "Expected '}' after theme tokens")?;
+        self.resolve_platform_properties(&mut tokens, Vec::new());
+
+        Ok(AstNode::Theme {
+            name,
+            tokens,
+            location: Some(location),
+        })
+    }
+    
     fn parse_script(&mut self) -> Result<AstNode> {
-        self.consume(TokenType::Script, "Expected @script")?;
+        let mut location = self.consume(TokenType::Script, "Expected @script")?.location();
         
         let language = match &self.advance().token_type {
             TokenType::String(lang) => lang.clone(),
-            _ => return Err(CompilerError::parse_legacy(
-                self.previous().line,
+            _ => return Err(Self::error_at(
+                self.previous(),
                 "Expected language string after @script"
             )),
         };
@@ -245,7 +502,8 @@ impl Parser {
         
         // Parse inline code if no external source
         if source.is_none() {
-            self.consume(TokenType::LeftBrace, "Expected '{' for script code")?;
+            let brace = self.consume(TokenType::LeftBrace, "Expected '{' for script code")?.location();
+            location = Span::new(brace.file, brace.line, brace.column + 1, 0);
             let code = self.parse_script_code()?;
             source = Some(ScriptSource::Inline(code));
             // Note: parse_script_code already handles the closing brace
@@ -256,6 +514,7 @@ impl Parser {
             name,
             source: source.unwrap(),
             mode,
+            location: Some(location),
         })
     }
     
@@ -359,12 +618,12 @@ impl Parser {
     }
     
     fn parse_function(&mut self) -> Result<AstNode> {
-        self.consume(TokenType::Function, "Expected @function")?;
+        let location = self.consume(TokenType::Function, "Expected @function")?.location();
         
         let language = match &self.advance().token_type {
             TokenType::String(lang) => lang.clone(),
-            _ => return Err(CompilerError::parse_legacy(
-                self.previous().line,
+            _ => return Err(Self::error_at(
+                self.previous(),
                 "Expected language string after @function"
             )),
         };
@@ -383,8 +642,8 @@ impl Parser {
                     continue;
                 }
             } else {
-                return Err(CompilerError::parse_legacy(
-                    self.previous().line,
+                return Err(Self::error_at(
+                    self.previous(),
                     "Expected parameter name"
                 ));
             }
@@ -406,6 +665,7 @@ impl Parser {
             name: Some(function_name.clone()),
             source: ScriptSource::Inline(full_code),
             mode: None,
+            location: Some(location),
         })
     }
     
@@ -416,20 +676,33 @@ impl Parser {
         let name = if let TokenType::String(name) = &self.peek().token_type {
             name.clone()
         } else {
-            return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            return Err(Self::error_at(
+                self.peek(),
                 format!("Expected style name string, but found {}", self.peek().token_type)
             ));
         };
-        self.advance();
+        let location = self.advance().location();
 
         self.consume(TokenType::LeftBrace, "Expected '{' after style name")?;
         
-        let mut extends = Vec::new();
-        let mut properties = Vec::new();
-        let mut pseudo_selectors = Vec::new();
+        let mut body = StyleBody::default();
+        self.parse_style_items(&mut body);
+        self.close_block(TokenType::RightBrace, "Expected '}' after style properties")?;
+        self.resolve_platform_properties(&mut body.properties, body.platform_properties);
         
-        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+        Ok(AstNode::Style {
+            name,
+            extends: body.extends,
+            properties: body.properties,
+            pseudo_selectors: body.pseudo_selectors,
+            media_queries: body.media_queries,
+            location: Some(location),
+        })
+    }
+
+    /// Parse the items of a style body up to its closing `}`
+    fn parse_style_items(&mut self, body: &mut StyleBody) {
+        while !self.at_block_end(&TokenType::RightBrace) {
             if self.match_token(&TokenType::Newline) {
                 continue;
             }
@@ -440,27 +713,33 @@ impl Parser {
             
             // Check for pseudo-selectors
             if matches!(self.peek().token_type, TokenType::PseudoSelector(_)) {
-                pseudo_selectors.push(self.parse_pseudo_selector()?);
-            } else {
-                let prop = self.parse_property()?;
-                
+                body.pseudo_selectors.extend(self.recover(Self::parse_pseudo_selector));
+            } else if self.check(&TokenType::Media) {
+                body.media_queries.extend(self.recover(Self::parse_media_query));
+            } else if self.check(&TokenType::Platform) {
+                let mut block = StyleBody::default();
+                let matched = self.recover(|parser| parser.parse_platform_block(|parser| parser.parse_style_items(&mut block)));
+                if matched == Some(true) {
+                    if !block.extends.is_empty() {
+                        body.extends = block.extends;
+                    }
+                    body.platform_properties.extend(block.properties);
+                    body.platform_properties.extend(block.platform_properties);
+                    body.pseudo_selectors.extend(block.pseudo_selectors);
+                    body.media_queries.extend(block.media_queries);
+                }
+            } else if let Some(prop) = self.recover(Self::parse_property) {
                 // Handle extends specially
                 if prop.key == "extends" {
-                    extends = self.parse_extends_value(&prop.value)?;
+                    match self.parse_extends_value(&prop.value) {
+                        Ok(names) => body.extends = names,
+                        Err(error) => self.report(error),
+                    }
                 } else {
-                    properties.push(prop);
+                    body.properties.push(prop);
                 }
             }
         }
-        
-        self.consume(TokenType::RightBrace, "Expected '}' after style properties")?;
-        
-        Ok(AstNode::Style {
-            name,
-            extends,
-            properties,
-            pseudo_selectors,
-        })
     }
     
     fn parse_extends_value(&self, value: &PropertyValue) -> Result<Vec<String>> {
@@ -512,6 +791,79 @@ impl Parser {
         }
     }
     
+    fn parse_keyframes(&mut self) -> Result<AstNode> {
+        self.consume(TokenType::Keyframes, "Expected '@keyframes'")?;
+
+        let name = match &self.peek().token_type {
+            TokenType::Identifier(name) | TokenType::String(name) => name.clone(),
+            _ => {
+                return Err(Self::error_at(
+                    self.peek(),
+                    format!("Expected keyframes name, but found {}", self.peek().token_type)
+                ));
+            }
+        };
+        let location = self.advance().location();
+
+        self.consume(TokenType::LeftBrace, "Expected '{' after keyframes name")?;
+
+        let mut frames = Vec::new();
+        while !self.at_block_end(&TokenType::RightBrace) {
+            if self.match_token(&TokenType::Newline) {
+                continue;
+            }
+            if matches!(self.peek().token_type, TokenType::Comment(_)) {
+                self.advance();
+                continue;
+            }
+            frames.extend(self.recover(Self::parse_keyframe));
+        }
+
+        self.close_block(TokenType::RightBrace, "Expected '}' after keyframes")?;
+
+        Ok(AstNode::Keyframes {
+            name,
+            frames,
+            location: Some(location),
+        })
+    }
+
+    /// `0% { ... }`, `from { ... }` or `to { ... }` inside @keyframes
+    fn parse_keyframe(&mut self) -> Result<Keyframe> {
+        let offset = match &self.peek().token_type {
+            TokenType::Percentage(offset) => *offset,
+            TokenType::Identifier(word) if word == "from" => 0.0,
+            TokenType::Identifier(word) if word == "to" => 100.0,
+            _ => {
+                return Err(Self::error_at(
+                    self.peek(),
+                    format!("Expected keyframe offset (such as 50%, from or to), but found {}", self.peek().token_type)
+                ));
+            }
+        };
+        let line = self.advance().line;
+
+        self.consume(TokenType::LeftBrace, "Expected '{' after keyframe offset")?;
+
+        let mut properties = Vec::new();
+        while !self.at_block_end(&TokenType::RightBrace) {
+            if self.match_token(&TokenType::Newline) {
+                continue;
+            }
+            if matches!(self.peek().token_type, TokenType::Comment(_)) {
+                self.advance();
+                continue;
+            }
+            properties.extend(self.recover(Self::parse_property));
+        }
+
+        self.close_block(TokenType::RightBrace, "Expected '}' after keyframe properties")?;
+        self.resolve_platform_properties(&mut properties, Vec::new());
+
+        Ok(Keyframe { offset, properties, line })
+    }
+
+    /// `font "Name" "path"`, or a block with `src`, `weight`, `style` and `fallback`
     fn parse_font(&mut self) -> Result<AstNode> {
         self.consume(TokenType::Font, "Expected 'font'")?;
         
@@ -519,27 +871,59 @@ impl Parser {
         let name = if let TokenType::String(name) = &self.peek().token_type {
             name.clone()
         } else {
-            return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            return Err(Self::error_at(
+                self.peek(),
                 format!("Expected font name string, but found {}", self.peek().token_type)
             ));
         };
-        self.advance();
+        let location = self.advance().location();
         
-        // Parse font path (second string)
-        let path = if let TokenType::String(path) = &self.peek().token_type {
-            path.clone()
-        } else {
-            return Err(CompilerError::parse_legacy(
-                self.peek().line,
-                format!("Expected font path string, but found {}", self.peek().token_type)
+        // Shorthand form: font path (second string)
+        if let TokenType::String(path) = &self.peek().token_type {
+            let path = path.clone();
+            self.advance();
+            return Ok(AstNode::Font {
+                name,
+                path,
+                properties: Vec::new(),
+                location: Some(location),
+            });
+        }
+        
+        if !self.check(&TokenType::LeftBrace) {
+            return Err(Self::error_at(
+                self.peek(),
+                format!("Expected font path string or '{{', but found {}", self.peek().token_type)
             ));
-        };
+        }
         self.advance();
         
+        let mut properties = Vec::new();
+        while !self.at_block_end(&TokenType::RightBrace) {
+            if self.match_token(&TokenType::Newline) {
+                continue;
+            }
+            if matches!(self.peek().token_type, TokenType::Comment(_)) {
+                self.advance();
+                continue;
+            }
+            properties.extend(self.recover(Self::parse_property));
+        }
+        
+        self.close_block(TokenType::RightBrace, "Expected '}' after font properties")?;
+        self.resolve_platform_properties(&mut properties, Vec::new());
+        
+        // The face's file is kept apart from its descriptors; a block without one
+        // is reported during semantic analysis
+        let path = properties.iter().position(|p: &AstProperty| p.key == "src")
+            .map(|index| properties.remove(index).cleaned_value())
+            .unwrap_or_default();
+        
         Ok(AstNode::Font {
             name,
             path,
+            properties,
+            location: Some(location),
         })
     }
     
@@ -549,8 +933,8 @@ impl Parser {
         let name = if let TokenType::Identifier(name) = &self.peek().token_type {
             name.clone()
         } else {
-            return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            return Err(Self::error_at(
+                self.peek(),
                 "Expected component name after 'Define'"
             ));
         };
@@ -562,7 +946,7 @@ impl Parser {
         let mut template = None;
         let mut functions = Vec::new();
         
-        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+        while !self.at_block_end(&TokenType::RightBrace) {
             if self.match_token(&TokenType::Newline) {
                 continue;
             }
@@ -573,35 +957,37 @@ impl Parser {
 
             
             if self.match_token(&TokenType::Properties) {
-                properties = self.parse_component_properties()?;
+                if let Some(block) = self.recover(Self::parse_component_properties) {
+                    properties = block;
+                }
             } else if self.check(&TokenType::Function) {
-                functions.push(self.parse_function()?);
+                functions.extend(self.recover(Self::parse_function));
             } else if self.check(&TokenType::Script) {
-                functions.push(self.parse_script()?);
+                functions.extend(self.recover(Self::parse_script));
             } else if self.is_element_start() {
                 if template.is_some() {
-                    return Err(CompilerError::parse_legacy(
-                        self.peek().line,
+                    self.report(Self::error_at(
+                        self.peek(),
                         "Component can only have one root template element"
                     ));
+                    self.recover_node(Self::parse_element);
+                    continue;
                 }
-                template = Some(Box::new(self.parse_element()?));
+                template = Some(Box::new(self.recover_node(Self::parse_element)));
             } else if matches!(self.peek().token_type, TokenType::Identifier(_)) {
                 // Parse direct property declaration (without Properties wrapper)
-                let property = self.parse_direct_component_property()?;
-                properties.push(property);
+                properties.extend(self.recover(Self::parse_direct_component_property));
             } else {
-                return Err(CompilerError::parse_legacy(
-                    self.peek().line,
+                self.skip_unexpected(
                     "Expected 'Properties' block, property declaration, '@function', '@script', or template element in component"
-                ));
+                );
             }
         }
         
-        self.consume(TokenType::RightBrace, "Expected '}' after component definition")?;
+        self.close_block(TokenType::RightBrace, "Expected '}' after component definition")?;
         
-        let template = template.ok_or_else(|| CompilerError::parse_legacy(
-            self.previous().line,
+        let template = template.ok_or_else(|| Self::error_at(
+            self.previous(),
             "Component must have a template element"
         ))?;
         
@@ -618,7 +1004,7 @@ impl Parser {
         
         let mut properties = Vec::new();
         
-        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+        while !self.at_block_end(&TokenType::RightBrace) {
             if self.match_token(&TokenType::Newline) {
                 continue;
             }
@@ -627,57 +1013,62 @@ impl Parser {
                 continue;
             }
             
-            let name = match &self.advance().token_type {
-                TokenType::Identifier(name) => name.clone(),
-                _ => return Err(CompilerError::parse_legacy(
-                    self.previous().line,
-                    "Expected property name"
-                )),
-            };
-            
-            let property_type = if self.match_token(&TokenType::Colon) {
-                // Old syntax: name: Type = value
-                match &self.advance().token_type {
-                    TokenType::Identifier(type_name) => Some(type_name.clone()),
-                    _ => return Err(CompilerError::parse_legacy(
-                        self.previous().line,
-                        "Expected property type after ':'"
-                    )),
-                }
-            } else {
-                // New syntax: name = value (infer type)
-                None
-            };
-            
-            self.consume(TokenType::Equals, "Expected '=' after property name or type")?;
-            
-            let default_value = Some(self.parse_value()?.to_string());
-            
-            // Infer type if not explicitly provided
-            let inferred_type = if property_type.is_none() {
-                Some(self.infer_type_from_value(&default_value.as_ref().unwrap())?)
-            } else {
-                property_type
-            };
-            
-            properties.push(ComponentProperty::new(
-                name,
-                inferred_type,
-                default_value,
-                self.previous().line,
-            ));
+            properties.extend(self.recover(Self::parse_component_property_line));
         }
         
-        self.consume(TokenType::RightBrace, "Expected '}' after component properties")?;
+        self.close_block(TokenType::RightBrace, "Expected '}' after component properties")?;
         
         Ok(properties)
     }
+
+    /// One `name: Type = value` or `name = value` line of a `Properties` block
+    fn parse_component_property_line(&mut self) -> Result<ComponentProperty> {
+        let name = match &self.advance().token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => return Err(Self::error_at(
+                self.previous(),
+                "Expected property name"
+            )),
+        };
+        
+        let property_type = if self.match_token(&TokenType::Colon) {
+            // Old syntax: name: Type = value
+            match &self.advance().token_type {
+                TokenType::Identifier(type_name) => Some(type_name.clone()),
+                _ => return Err(Self::error_at(
+                    self.previous(),
+                    "Expected property type after ':'"
+                )),
+            }
+        } else {
+            // New syntax: name = value (infer type)
+            None
+        };
+        
+        self.consume(TokenType::Equals, "Expected '=' after property name or type")?;
+        
+        let default_value = Some(self.parse_value()?.to_string());
+        
+        // Infer type if not explicitly provided
+        let inferred_type = if property_type.is_none() {
+            Some(self.infer_type_from_value(default_value.as_ref().unwrap())?)
+        } else {
+            property_type
+        };
+        
+        Ok(ComponentProperty::new(
+            name,
+            inferred_type,
+            default_value,
+            self.previous().line,
+        ))
+    }
     
     fn parse_direct_component_property(&mut self) -> Result<ComponentProperty> {
         let name = match &self.advance().token_type {
             TokenType::Identifier(name) => name.clone(),
-            _ => return Err(CompilerError::parse_legacy(
-                self.previous().line,
+            _ => return Err(Self::error_at(
+                self.previous(),
                 "Expected property name"
             )),
         };
@@ -740,96 +1131,117 @@ impl Parser {
             TokenType::NativeRendererView => "NativeRendererView".to_string(),
             TokenType::Button => "Button".to_string(),
             TokenType::Input => "Input".to_string(),
+            TokenType::List => "List".to_string(),
+            TokenType::Grid => "Grid".to_string(),
+            TokenType::Scrollable => "Scrollable".to_string(),
+            TokenType::Tabs => "Tabs".to_string(),
+            TokenType::Video => "Video".to_string(),
             TokenType::Identifier(name) => name.clone(),
-            _ => return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            _ => return Err(Self::error_at(
+                self.peek(),
                 "Expected element type"
             )),
         };
-        self.advance(); 
-        
-        let mut properties = Vec::new();
-        let mut pseudo_selectors = Vec::new();
-        let mut children = Vec::new();
+        let location = self.advance().location();
         
+        let mut body = ElementBody::default();
         if self.match_token(&TokenType::LeftBrace) {
-            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-                if self.match_token(&TokenType::Newline) {
-                    continue;
-                }
-                if matches!(self.peek().token_type, TokenType::Comment(_)) {
-                    self.advance();
-                    continue;
-                }
+            self.parse_element_items(&element_type, &mut body);
+            self.close_block(TokenType::RightBrace, "Expected '}' after element body")?;
+        }
+        self.resolve_platform_properties(&mut body.properties, body.platform_properties);
+        
+        Ok(AstNode::Element {
+            element_type,
+            properties: body.properties,
+            pseudo_selectors: body.pseudo_selectors,
+            media_queries: body.media_queries,
+            children: body.children,
+            location: Some(location),
+            origin: None,
+        })
+    }
 
-                if matches!(self.peek().token_type, TokenType::PseudoSelector(_)) {
-                    pseudo_selectors.push(self.parse_pseudo_selector()?);
-                } else if self.is_property() {
-                    properties.push(self.parse_property()?);
-                } else if self.is_element_start() {
-                    children.push(self.parse_element()?);
-                } else if matches!(self.peek().token_type, TokenType::For) {
-                    children.push(self.parse_for()?);
-                } else if matches!(self.peek().token_type, TokenType::If) {
-                    children.push(self.parse_if()?);
-                } else if matches!(self.peek().token_type, TokenType::String(_)) {
-                    // Handle shorthand syntax for Text elements: Text { "Hello" } → Text { text: "Hello" }
-                    if element_type == "Text" {
-                        let string_value = match &self.advance().token_type {
-                            TokenType::String(s) => s.clone(),
-                            _ => unreachable!(),
-                        };
-                        properties.push(AstProperty::new(
-                            "text".to_string(),
-                            PropertyValue::String(string_value),
-                            self.previous().line,
-                        ));
-                    } else {
-                        return Err(CompilerError::parse_legacy(
-                            self.peek().line,
-                            format!("String literal shorthand only supported for Text elements, not {}", element_type)
-                        ));
-                    }
-                } else if matches!(self.peek().token_type, TokenType::LeftBracket) {
-                    // Handle array shorthand syntax for Text elements: Text { ["Line 1", "Line 2"] } → Text { text: "Line 1\nLine 2" }
-                    if element_type == "Text" {
-                        let array_value = self.parse_array_literal()?;
-                        properties.push(AstProperty::new(
+    /// Parse the items of an element body up to its closing `}`
+    fn parse_element_items(&mut self, element_type: &str, body: &mut ElementBody) {
+        while !self.at_block_end(&TokenType::RightBrace) {
+            if self.match_token(&TokenType::Newline) {
+                continue;
+            }
+            if matches!(self.peek().token_type, TokenType::Comment(_)) {
+                self.advance();
+                continue;
+            }
+
+            if matches!(self.peek().token_type, TokenType::PseudoSelector(_)) {
+                body.pseudo_selectors.extend(self.recover(Self::parse_pseudo_selector));
+            } else if self.check(&TokenType::Media) {
+                body.media_queries.extend(self.recover(Self::parse_media_query));
+            } else if self.check(&TokenType::Platform) {
+                let mut block = ElementBody::default();
+                let matched = self.recover(|parser| {
+                    parser.parse_platform_block(|parser| parser.parse_element_items(element_type, &mut block))
+                });
+                if matched == Some(true) {
+                    body.platform_properties.extend(block.properties);
+                    body.platform_properties.extend(block.platform_properties);
+                    body.pseudo_selectors.extend(block.pseudo_selectors);
+                    body.media_queries.extend(block.media_queries);
+                    body.children.extend(block.children);
+                }
+            } else if self.is_property() {
+                body.properties.extend(self.recover(Self::parse_property));
+            } else if self.is_element_start() {
+                body.children.push(self.recover_node(Self::parse_element));
+            } else if matches!(self.peek().token_type, TokenType::For) {
+                body.children.push(self.recover_node(Self::parse_for));
+            } else if matches!(self.peek().token_type, TokenType::If) {
+                body.children.push(self.recover_node(Self::parse_if));
+            } else if matches!(self.peek().token_type, TokenType::String(_)) {
+                // Handle shorthand syntax for Text elements: Text { "Hello" } → Text { text: "Hello" }
+                if element_type == "Text" {
+                    let string_value = match &self.advance().token_type {
+                        TokenType::String(s) => s.clone(),
+                        _ => unreachable!(),
+                    };
+                    body.properties.push(AstProperty::new(
+                        "text".to_string(),
+                        PropertyValue::String(string_value),
+                        self.previous().line,
+                    ).with_location(self.previous().location()));
+                } else {
+                    self.skip_unexpected(
+                        format!("String literal shorthand only supported for Text elements, not {}", element_type)
+                    );
+                }
+            } else if matches!(self.peek().token_type, TokenType::LeftBracket) {
+                // Handle array shorthand syntax for Text elements: Text { ["Line 1", "Line 2"] } → Text { text: "Line 1\nLine 2" }
+                if element_type == "Text" {
+                    let location = self.peek().location();
+                    if let Some(array_value) = self.recover(Self::parse_array_literal) {
+                        body.properties.push(AstProperty::new(
                             "text".to_string(),
                             array_value,
                             self.previous().line,
-                        ));
-                    } else {
-                        return Err(CompilerError::parse_legacy(
-                            self.peek().line,
-                            format!("Array literal shorthand only supported for Text elements, not {}", element_type)
-                        ));
+                        ).with_location(location));
                     }
                 } else {
-                    return Err(CompilerError::parse_legacy(
-                        self.peek().line,
-                        format!("Unexpected token in element body: {}", self.peek().token_type)
-                    ));
+                    self.skip_unexpected(
+                        format!("Array literal shorthand only supported for Text elements, not {}", element_type)
+                    );
                 }
+            } else {
+                self.skip_unexpected(format!("Unexpected token in element body: {}", self.peek().token_type));
             }
-            
-            self.consume(TokenType::RightBrace, "Expected '}' after element body")?;
         }
-        
-        Ok(AstNode::Element {
-            element_type,
-            properties,
-            pseudo_selectors,
-            children,
-        })
     }
     
     fn parse_pseudo_selector(&mut self) -> Result<PseudoSelector> {
         // Extract the state from the pseudo-selector token
         let state = match &self.advance().token_type {
             TokenType::PseudoSelector(state) => state.clone(),
-            _ => return Err(CompilerError::parse_legacy(
-                self.previous().line,
+            _ => return Err(Self::error_at(
+                self.previous(),
                 "Expected pseudo-selector"
             )),
         };
@@ -845,10 +1257,68 @@ impl Parser {
         }
         
         self.consume(TokenType::RightBrace, "Expected '}' after pseudo-selector properties")?;
+        self.resolve_platform_properties(&mut properties, Vec::new());
         
         Ok(PseudoSelector::new(state, properties, self.previous().line))
     }
 
+    /// Parse `@media (min-width: 600px) and (max-width: $tablet) { properties }`
+    fn parse_media_query(&mut self) -> Result<MediaQuery> {
+        let location = self.consume(TokenType::Media, "Expected @media")?.location();
+        let mut query = MediaQuery { min_width: None, max_width: None, properties: Vec::new(), location: Some(location) };
+
+        loop {
+            self.consume(TokenType::LeftParen, "Expected '(' before a media feature")?;
+            let feature = match &self.peek().token_type {
+                TokenType::Identifier(name) if name == "min-width" || name == "max-width" => name.clone(),
+                other => return Err(Self::error_at(
+                    self.peek(),
+                    format!("Expected min-width or max-width, found {}", other)
+                )),
+            };
+            self.advance();
+            self.consume(TokenType::Colon, "Expected ':' after media feature")?;
+            let value = match self.advance().token_type.clone() {
+                TokenType::Pixels(width) => format!("{}px", width),
+                TokenType::Integer(width) => width.to_string(),
+                TokenType::Dollar => match &self.advance().token_type {
+                    TokenType::Identifier(name) => format!("${}", name),
+                    _ => return Err(Self::error_at(self.previous(), "Expected breakpoint name after '$'")),
+                },
+                other => return Err(Self::error_at(
+                    self.previous(),
+                    format!("Expected a width in px or a breakpoint variable, found {}", other)
+                )),
+            };
+            self.consume(TokenType::RightParen, "Expected ')' after media feature")?;
+            if feature == "min-width" {
+                query.min_width = Some(value);
+            } else {
+                query.max_width = Some(value);
+            }
+            if !matches!(&self.peek().token_type, TokenType::Identifier(word) if word == "and") {
+                break;
+            }
+            self.advance();
+        }
+
+        self.consume(TokenType::LeftBrace, "Expected '{' after media query")?;
+        while !self.at_block_end(&TokenType::RightBrace) {
+            if self.match_token(&TokenType::Newline) {
+                continue;
+            }
+            if matches!(self.peek().token_type, TokenType::Comment(_)) {
+                self.advance();
+                continue;
+            }
+            query.properties.extend(self.recover(Self::parse_property));
+        }
+        self.close_block(TokenType::RightBrace, "Expected '}' after media query properties")?;
+        self.resolve_platform_properties(&mut query.properties, Vec::new());
+
+        Ok(query)
+    }
+
     fn parse_property(&mut self) -> Result<AstProperty> {
         let key = match &self.peek().token_type {
             TokenType::Identifier(name) => name.clone(),
@@ -866,13 +1336,16 @@ impl Parser {
             TokenType::Identifier(name) => name.clone(),
             TokenType::App => "app".to_string(),
             _ => {
-                return Err(CompilerError::parse_legacy(
-                    self.peek().line,
+                return Err(Self::error_at(
+                    self.peek(),
                     format!("Expected property name, but found token: {}", self.peek().token_type)
                 ));
             }
         };
-        self.advance(); // Now consume the token we just processed
+        if let Some((_, platform)) = key.split_once('@') {
+            self.platform_matches(platform)?;
+        }
+        let mut location = self.advance().location(); // Now consume the token we just processed
     
         self.consume(TokenType::Colon, "Expected ':' after property name")?;
         
@@ -886,6 +1359,27 @@ impl Parser {
         } else {
             self.parse_value()?
         };
+
+        // A quoted gradient is the same value as an unquoted one
+        let quoted = match &value {
+            PropertyValue::String(text) => Some(clean_and_quote_value(text).0),
+            PropertyValue::Expression(expr) => match expr.as_ref() {
+                Expression::String(text) => Some(text.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let value = match quoted.filter(|_| key.split('@').next() == Some("background_image")).and_then(|text| Self::parse_gradient_source(&text)) {
+            Some(Ok(gradient)) => PropertyValue::Gradient(gradient),
+            Some(Err(message)) => return Err(Self::error_at(self.previous(), message)),
+            None => value,
+        };
+
+        // Underline `key: value` when the value ends on the key's line
+        let value_end = self.previous();
+        if value_end.line == location.line && value_end.column >= location.column {
+            location.length = value_end.column + value_end.span.len() - location.column;
+        }
         
         // Optional semicolon or comma
         self.match_token(&TokenType::Semicolon);
@@ -894,16 +1388,21 @@ impl Parser {
         // Extract template variables from the value
         let template_variables = value.extract_variables();
         
-        if template_variables.is_empty() {
-            Ok(AstProperty::new(key, value, self.previous().line))
+        let property = if template_variables.is_empty() {
+            AstProperty::new(key, value, self.previous().line)
         } else {
-            Ok(AstProperty::new_with_templates(key, value, self.previous().line, template_variables))
-        }
+            AstProperty::new_with_templates(key, value, self.previous().line, template_variables)
+        };
+        Ok(property.with_location(location))
     }
 
 
     fn is_function_call(&self) -> bool {
-        // A function call is an Identifier followed by a LeftParen
+        // A function call is an Identifier followed by a LeftParen; gradients and
+        // min/max/clamp/calc have their own syntax
+        if self.is_gradient() || self.is_expression_function() {
+            return false;
+        }
         if let TokenType::Identifier(_) = self.tokens[self.current].token_type {
             if self.current + 1 < self.tokens.len() {
                 if let TokenType::LeftParen = self.tokens[self.current + 1].token_type {
@@ -919,7 +1418,7 @@ impl Parser {
             name.clone()
         } else {
             // This should not happen if is_function_call() passed
-            return Err(CompilerError::parse_legacy(self.peek().line, "Expected function name"));
+            return Err(Self::error_at(self.peek(), "Expected function name"));
         };
         self.advance(); // consume name
 
@@ -928,8 +1427,8 @@ impl Parser {
         let mut args = Vec::new();
         if !self.check(&TokenType::RightParen) {
             loop {
-                // Each argument is itself a value (e.g., a variable, a string, a number)
-                args.push(self.parse_value()?);
+                // Each argument is itself a value (e.g., a variable, a string, a number) or a nested call
+                args.push(if self.is_function_call() { self.parse_function_call()? } else { self.parse_value()? });
                 
                 if !self.match_token(&TokenType::Comma) {
                     break;
@@ -942,11 +1441,217 @@ impl Parser {
         Ok(PropertyValue::FunctionCall { name, args })
     }
 
-    
+
+    /// `min(`, `max(`, `clamp(` or `calc(`, which are parsed as expressions
+    fn is_expression_function(&self) -> bool {
+        matches!(&self.peek().token_type, TokenType::Identifier(name) if EXPRESSION_FUNCTIONS.contains(&name.as_str()))
+            && matches!(self.tokens.get(self.current + 1).map(|t| &t.token_type), Some(TokenType::LeftParen))
+    }
+
+    fn is_gradient(&self) -> bool {
+        matches!(&self.peek().token_type, TokenType::Identifier(name) if name == "linear-gradient" || name == "radial-gradient")
+            && matches!(self.tokens.get(self.current + 1).map(|t| &t.token_type), Some(TokenType::LeftParen))
+    }
+
+    /// Parse `linear-gradient(...)` or `radial-gradient(...)`, checking stop colors and order
+    fn parse_gradient(&mut self) -> Result<PropertyValue> {
+        let name_token = self.advance().clone();
+        let TokenType::Identifier(name) = &name_token.token_type else {
+            return Err(Self::error_at(&name_token, "Expected a gradient"));
+        };
+        self.consume(TokenType::LeftParen, "Expected '(' after gradient name")?;
+        self.skip_whitespace_and_comments();
+
+        let mut gradient = if name == "radial-gradient" { self.parse_radial_geometry()? } else { self.parse_linear_geometry()? };
+        loop {
+            self.skip_whitespace_and_comments();
+            let stop = self.parse_gradient_stop(name, &gradient.stops)?;
+            gradient.stops.push(stop);
+            self.skip_whitespace_and_comments();
+            if !self.match_token(&TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ',' or ')' after gradient color stop")?;
+
+        if gradient.stops.len() < 2 {
+            return Err(Self::error_at(&name_token, format!("{}() needs at least 2 color stops", name)));
+        }
+        if gradient.stops.len() > MAX_GRADIENT_STOPS {
+            return Err(Self::error_at(
+                &name_token,
+                format!("{}() has {} color stops; at most {} are allowed", name, gradient.stops.len(), MAX_GRADIENT_STOPS),
+            ));
+        }
+        Ok(PropertyValue::Gradient(gradient))
+    }
+
+    /// `<angle>,` or `to <side> [<side>],`; without either the gradient points to the bottom
+    fn parse_linear_geometry(&mut self) -> Result<Gradient> {
+        let angle = match self.peek().token_type.clone() {
+            TokenType::Degrees(degrees) => degrees,
+            TokenType::Radians(radians) => radians.to_degrees(),
+            TokenType::Turns(turns) => turns * 360.0,
+            TokenType::Identifier(word) if word == "to" => {
+                self.advance();
+                let mut sides = Vec::new();
+                while let TokenType::Identifier(side) = &self.peek().token_type {
+                    sides.push(side.clone());
+                    self.advance();
+                }
+                let angle = match sides.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+                    ["top"] => 0.0,
+                    ["right"] => 90.0,
+                    ["bottom"] => 180.0,
+                    ["left"] => 270.0,
+                    ["top", "right"] | ["right", "top"] => 45.0,
+                    ["bottom", "right"] | ["right", "bottom"] => 135.0,
+                    ["bottom", "left"] | ["left", "bottom"] => 225.0,
+                    ["top", "left"] | ["left", "top"] => 315.0,
+                    _ => {
+                        return Err(Self::error_at(
+                            self.previous(),
+                            format!("'to {}' is not a gradient direction; use a side or a corner such as 'to top right'", sides.join(" ")),
+                        ))
+                    }
+                };
+                self.consume(TokenType::Comma, "Expected ',' after gradient direction")?;
+                return Ok(Gradient::linear(angle, Vec::new()));
+            }
+            _ => return Ok(Gradient::linear(180.0, Vec::new())),
+        };
+        self.advance();
+        self.consume(TokenType::Comma, "Expected ',' after gradient angle")?;
+        Ok(Gradient::linear(angle, Vec::new()))
+    }
+
+    /// `[circle | ellipse] [at <x> [<y>]],`; without them the gradient is a centered ellipse
+    fn parse_radial_geometry(&mut self) -> Result<Gradient> {
+        let shape = match &self.peek().token_type {
+            TokenType::Identifier(word) if word == "circle" => Some(RadialShape::Circle),
+            TokenType::Identifier(word) if word == "ellipse" => Some(RadialShape::Ellipse),
+            _ => None,
+        };
+        if shape.is_some() {
+            self.advance();
+        }
+
+        let mut center = None;
+        if matches!(&self.peek().token_type, TokenType::Identifier(word) if word == "at") {
+            self.advance();
+            let mut positions = Vec::new();
+            while positions.len() < 2 && !self.check(&TokenType::Comma) {
+                positions.push(self.parse_center_position()?);
+            }
+            center = Some(match positions[..] {
+                [(x, true), (y, false)] | [(y, false), (x, true)] => (x, y),
+                [(y, false)] => (50.0, y),
+                [(x, _)] => (x, 50.0),
+                [(x, true), (y, true)] => (x, y),
+                _ => return Err(Self::error_at(self.previous(), "Expected a gradient center such as '25% 75%' or 'top left' after 'at'")),
+            });
+        }
+
+        if shape.is_some() || center.is_some() {
+            self.consume(TokenType::Comma, "Expected ',' after gradient shape and position")?;
+        }
+        Ok(Gradient::radial(shape.unwrap_or_default(), center.unwrap_or((50.0, 50.0)), Vec::new()))
+    }
+
+    /// One coordinate of a radial gradient's center, and whether it can only be horizontal
+    /// (`left`/`right`) rather than only vertical (`top`/`bottom`); others go in either order
+    fn parse_center_position(&mut self) -> Result<(f64, bool)> {
+        let token = self.advance().clone();
+        let position = match &token.token_type {
+            TokenType::Percentage(p) => (*p, true),
+            TokenType::Integer(0) => (0.0, true),
+            TokenType::Identifier(word) => match word.as_str() {
+                "left" => (0.0, true),
+                "center" => (50.0, true),
+                "right" => (100.0, true),
+                "top" => (0.0, false),
+                "bottom" => (100.0, false),
+                _ => return Err(Self::error_at(&token, format!("'{}' is not a gradient position", word))),
+            },
+            other => return Err(Self::error_at(&token, format!("Expected a percentage or side for the gradient center, found {}", other))),
+        };
+        if !(0.0..=100.0).contains(&position.0) {
+            return Err(Self::error_at(&token, format!("gradient center {}% is outside 0% to 100%", position.0)));
+        }
+        Ok(position)
+    }
+
+    /// `<color> [<percentage>]`; a position may not come before that of an earlier stop
+    fn parse_gradient_stop(&mut self, gradient: &str, previous: &[GradientStop]) -> Result<GradientStop> {
+        let color = match self.peek().token_type.clone() {
+            TokenType::Color(hex) => {
+                self.advance();
+                hex
+            }
+            TokenType::Identifier(function) if self.is_function_call() => {
+                if !COLOR_FUNCTIONS.contains(&function.to_lowercase().as_str()) {
+                    return Err(Self::error_at(self.peek(), format!("'{}()' is not a color function", function)));
+                }
+                self.parse_function_call()?.to_string()
+            }
+            TokenType::Identifier(name) => {
+                if named_color(&name).is_none() {
+                    return Err(Self::error_at(self.peek(), format!("'{}' is not a color", name)));
+                }
+                self.advance();
+                name
+            }
+            TokenType::String(text) => {
+                if let Err(message) = evaluate_color(&text, &|_| None) {
+                    return Err(Self::error_at(self.peek(), message));
+                }
+                self.advance();
+                text
+            }
+            TokenType::Dollar => {
+                self.advance();
+                match &self.advance().token_type {
+                    TokenType::Identifier(name) => format!("${}", name),
+                    _ => return Err(Self::error_at(self.previous(), "Expected variable name after '$'")),
+                }
+            }
+            other => return Err(Self::error_at(self.peek(), format!("Expected a color stop in {}(), found {}", gradient, other))),
+        };
+
+        let position = match self.peek().token_type {
+            TokenType::Percentage(p) => Some(p),
+            TokenType::Integer(0) => Some(0.0),
+            TokenType::Comma | TokenType::RightParen | TokenType::Newline | TokenType::Comment(_) => None,
+            ref other => return Err(Self::error_at(self.peek(), format!("gradient stop positions must be percentages, found {}", other))),
+        };
+        if let Some(position) = position {
+            if !(0.0..=100.0).contains(&position) {
+                return Err(Self::error_at(self.peek(), format!("gradient stop position {}% is outside 0% to 100%", position)));
+            }
+            if let Some(before) = previous.iter().rev().find_map(|stop| stop.position).filter(|&before| position < before) {
+                return Err(Self::error_at(
+                    self.peek(),
+                    format!("gradient stop at {}% comes after one at {}%; stop positions must not decrease", position, before),
+                ));
+            }
+            self.advance();
+        }
+        Ok(GradientStop { color, position })
+    }
+
     fn parse_value(&mut self) -> Result<PropertyValue> {
-        // First try to parse as an expression (for ternary operators)
+        if self.is_gradient() {
+            return self.parse_gradient();
+        }
+
+        // First try to parse as an expression (for arithmetic and ternary operators);
+        // a lone number with a unit keeps its own value type
+        let start_position = self.current;
         if let Ok(expr) = self.try_parse_expression() {
-            return Ok(PropertyValue::Expression(Box::new(expr)));
+            if !matches!(expr, Expression::Dimension(..)) {
+                return Ok(PropertyValue::Expression(Box::new(expr)));
+            }
+            self.current = start_position;
         }
         
         let result = match &self.peek().token_type {
@@ -1034,8 +1739,8 @@ impl Parser {
                             self.consume(TokenType::RightBrace, "Expected '}' after variable name")?;
                             Ok(PropertyValue::Variable(value))
                         } else {
-                            Err(CompilerError::parse_legacy(
-                                self.previous().line,
+                            Err(Self::error_at(
+                                self.previous(),
                                 "Expected variable name in ${variable}"
                             ))
                         }
@@ -1047,8 +1752,8 @@ impl Parser {
                         Ok(PropertyValue::Variable(value))
                     },
                     _ => {
-                        Err(CompilerError::parse_legacy(
-                            self.peek().line,
+                        Err(Self::error_at(
+                            self.peek(),
                             "Expected variable name after '$' (use $variable or ${variable})"
                         ))
                     }
@@ -1068,8 +1773,8 @@ impl Parser {
                 Ok(PropertyValue::String(value))
             }
             _ => {
-                Err(CompilerError::parse_legacy(
-                    self.peek().line,
+                Err(Self::error_at(
+                    self.peek(),
                     format!("Expected a value, but found {}", self.peek().token_type)
                 ))
             }
@@ -1099,8 +1804,8 @@ impl Parser {
                     self.advance();
                     key
                 }
-                _ => return Err(CompilerError::parse_legacy(
-                    self.peek().line,
+                _ => return Err(Self::error_at(
+                    self.peek(),
                     "Expected property name in object"
                 )),
             };
@@ -1190,6 +1895,7 @@ impl Parser {
             // Known element types
             TokenType::App | TokenType::Container | TokenType::Text |
             TokenType::Link | TokenType::Image | TokenType::Canvas | TokenType::WasmView | TokenType::NativeRendererView | TokenType::Button | TokenType::Input => true,
+            TokenType::List | TokenType::Grid | TokenType::Scrollable | TokenType::Tabs | TokenType::Video => true,
             
             // For identifiers, check if they're followed by an opening brace (element)
             // rather than a colon (property)
@@ -1241,12 +1947,127 @@ impl Parser {
         &self.tokens[self.current - 1]
     }
     
+    /// A parse error located at `token`
+    fn error_at(token: &Token, message: impl Into<String>) -> CompilerError {
+        CompilerError::parse_at(token.filename.clone(), token.line, token.column, message)
+    }
+
+    /// Record a syntax error and keep going. Only the first error at a token is
+    /// kept, so one mistake doesn't cascade into a pile of reports.
+    fn report(&mut self, error: CompilerError) {
+        if self.last_error_at != Some(self.current) {
+            self.last_error_at = Some(self.current);
+            self.errors.push(error);
+        }
+    }
+
+    /// Parse one item of a block. On failure the error is recorded and the parser
+    /// skips to the next synchronisation point, so the rest of the file is still checked.
+    fn recover<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Option<T> {
+        let start = self.current;
+        match parse(self) {
+            Ok(item) => Some(item),
+            Err(error) => {
+                self.report(error);
+                self.synchronize(start);
+                None
+            }
+        }
+    }
+
+    /// `recover` for items that are nodes: a failed one leaves an `AstNode::Error` in its place
+    fn recover_node(&mut self, parse: impl FnOnce(&mut Self) -> Result<AstNode>) -> AstNode {
+        let start = self.current;
+        let location = self.peek().location();
+        match parse(self) {
+            Ok(node) => node,
+            Err(error) => {
+                let message = Diagnostic::from_error(&error).message;
+                self.report(error);
+                self.synchronize(start);
+                AstNode::Error { message, location }
+            }
+        }
+    }
+
+    /// Report the current token as unexpected and skip past it
+    fn skip_unexpected(&mut self, message: impl Into<String>) {
+        let start = self.current;
+        self.report(Self::error_at(self.peek(), message));
+        self.synchronize(start);
+    }
+
+    /// Skip to the next synchronisation point after an error in an item that began
+    /// at token `start`: the end of its line, past any block it opened, or the
+    /// `}`/`@end` closing the enclosing block, which is left for the caller.
+    fn synchronize(&mut self, start: usize) {
+        // Always make progress, or a token no rule accepts would be retried forever
+        if self.current == start && !self.is_at_end() {
+            self.advance();
+        }
+
+        let mut depth = 0usize;
+        // A broken `@for`/`@if` header also skips its body, through the matching `@end`
+        let mut directives = usize::from(matches!(self.tokens[start].token_type, TokenType::For | TokenType::If));
+        while !self.is_at_end() {
+            match self.peek().token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => return,
+                TokenType::RightBrace => depth -= 1,
+                TokenType::For | TokenType::If if directives > 0 => directives += 1,
+                TokenType::End if depth == 0 && directives > 0 => {
+                    directives -= 1;
+                    if directives == 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                TokenType::Newline if depth == 0 && directives == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenType::End | TokenType::Elif | TokenType::Else if depth == 0 && directives == 0 => return,
+                _ if depth == 0 && self.at_top_level_item() => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    /// Whether the next tokens start something only allowed at the top level of a
+    /// file. Inside a block this means its closing `}` is missing.
+    fn at_top_level_item(&self) -> bool {
+        let next = self.tokens.get(self.current + 1).map(|token| &token.token_type);
+        match self.peek().token_type {
+            TokenType::Include | TokenType::Variables | TokenType::Theme | TokenType::Keyframes | TokenType::Define => true,
+            TokenType::Style | TokenType::Font => matches!(next, Some(TokenType::String(_))),
+            TokenType::App => matches!(next, Some(TokenType::LeftBrace)),
+            _ => false,
+        }
+    }
+
+    /// Whether a block ends here: at its `closer`, or where the closer is missing
+    fn at_block_end(&self, closer: &TokenType) -> bool {
+        self.check(closer) || self.is_at_end() || self.at_top_level_item()
+    }
+
+    /// Consume a block's `}` or `@end`. If it is missing before a top-level item or
+    /// the end of the file, the error is recorded and the block treated as closed,
+    /// so whatever follows is still parsed.
+    fn close_block(&mut self, closer: TokenType, message: &str) -> Result<()> {
+        if !self.check(&closer) && (self.is_at_end() || self.at_top_level_item()) {
+            self.report(Self::error_at(self.peek(), format!("{}, got {}", message, self.peek().token_type)));
+            return Ok(());
+        }
+        self.consume(closer, message).map(|_| ())
+    }
+
     fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token> {
         if self.check(&token_type) {
             Ok(self.advance())
         } else {
-            Err(CompilerError::parse_legacy(
-                self.peek().line,
+            Err(Self::error_at(
+                self.peek(),
                 format!("{}, got {}", message, self.peek().token_type)
             ))
         }
@@ -1268,7 +2089,10 @@ impl Parser {
                 AstProperty::new("window_height".to_string(), PropertyValue::Integer(600), 1),
             ],
             pseudo_selectors: Vec::new(),
+            media_queries: Vec::new(),
             children: standalone_elements, // Wrap all standalone elements as children
+            location: None,
+            origin: None,
         };
         
         log::info!("Auto-created App wrapper for {} standalone elements", element_count);
@@ -1299,8 +2123,8 @@ impl Parser {
                                 name_parts.push(format!("${}", var_name));
                                 self.consume(TokenType::RightBrace, "Expected '}' after variable name")?;
                             } else {
-                                return Err(CompilerError::parse_legacy(
-                                    self.previous().line,
+                                return Err(Self::error_at(
+                                    self.previous(),
                                     "Expected variable name after '${'",
                                 ));
                             }
@@ -1312,8 +2136,8 @@ impl Parser {
                             self.advance(); // consume identifier
                         },
                         _ => {
-                            return Err(CompilerError::parse_legacy(
-                                self.peek().line,
+                            return Err(Self::error_at(
+                                self.peek(),
                                 "Expected variable name after '$' (use $variable or ${variable})"
                             ));
                         }
@@ -1324,8 +2148,8 @@ impl Parser {
                     break;
                 }
                 _ => {
-                    return Err(CompilerError::parse_legacy(
-                        self.peek().line,
+                    return Err(Self::error_at(
+                        self.peek(),
                         "Expected identifier, ${variable}, or '(' in function name"
                     ));
                 }
@@ -1333,8 +2157,8 @@ impl Parser {
         }
         
         if name_parts.is_empty() {
-            return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            return Err(Self::error_at(
+                self.peek(),
                 "Function name cannot be empty"
             ));
         }
@@ -1372,6 +2196,17 @@ impl Parser {
     
     /// Parse a single value (number, string, etc.) for shorthand parsing
     fn parse_single_value(&mut self) -> Result<PropertyValue> {
+        if self.is_function_call() {
+            return self.parse_function_call();
+        }
+        // `$gap * 2` or `(100% - 20px)`; values are separated by spaces, so `10 -5` stays two numbers
+        let start_position = self.current;
+        if let Ok(expr) = self.try_parse_expression() {
+            if expr.is_arithmetic() {
+                return Ok(PropertyValue::Expression(Box::new(expr)));
+            }
+            self.current = start_position;
+        }
         match &self.peek().token_type {
             TokenType::Number(n) => {
                 let value = *n;
@@ -1418,9 +2253,16 @@ impl Parser {
                 self.advance();
                 Ok(PropertyValue::Color(value))
             }
+            TokenType::Dollar => {
+                self.advance();
+                match &self.advance().token_type {
+                    TokenType::Identifier(name) => Ok(PropertyValue::Variable(name.clone())),
+                    _ => Err(Self::error_at(self.previous(), "Expected variable name after '$'")),
+                }
+            }
             _ => {
-                Err(CompilerError::parse_legacy(
-                    self.peek().line,
+                Err(Self::error_at(
+                    self.peek(),
                     format!("Expected a value, but found {}", self.peek().token_type)
                 ))
             }
@@ -1432,7 +2274,8 @@ impl Parser {
         matches!(self.peek().token_type,
             TokenType::Number(_) | TokenType::Integer(_) | TokenType::Pixels(_) |
             TokenType::Em(_) | TokenType::Rem(_) | TokenType::Percentage(_) |
-            TokenType::String(_) | TokenType::Identifier(_) | TokenType::Color(_)
+            TokenType::String(_) | TokenType::Identifier(_) | TokenType::Color(_) | TokenType::Dollar |
+            TokenType::LeftParen
         )
     }
 
@@ -1463,7 +2306,7 @@ impl Parser {
             Err(_) => {
                 // Backtrack if parsing fails
                 self.current = start_position;
-                Err(CompilerError::parse("Unknown".to_string(), 0, "Not an expression"))
+                Err(Self::error_at(self.peek(), "Not an expression"))
             }
         }
     }
@@ -1489,45 +2332,105 @@ impl Parser {
     
     /// Parse comparison expressions: ==, !=, <, <=, >, >=
     fn parse_comparison_expression(&mut self) -> Result<Expression> {
-        let left = self.parse_primary_expression()?;
+        let left = self.parse_additive_expression()?;
         
         match &self.peek().token_type {
             TokenType::NotEquals => {
                 self.advance();
-                let right = self.parse_primary_expression()?;
+                let right = self.parse_additive_expression()?;
                 Ok(Expression::NotEquals(Box::new(left), Box::new(right)))
             }
             TokenType::EqualEquals => {
                 self.advance();
-                let right = self.parse_primary_expression()?;
+                let right = self.parse_additive_expression()?;
                 Ok(Expression::EqualEquals(Box::new(left), Box::new(right)))
             }
             TokenType::LessThan => {
                 self.advance();
-                let right = self.parse_primary_expression()?;
+                let right = self.parse_additive_expression()?;
                 Ok(Expression::LessThan(Box::new(left), Box::new(right)))
             }
             TokenType::LessThanOrEqual => {
                 self.advance();
-                let right = self.parse_primary_expression()?;
+                let right = self.parse_additive_expression()?;
                 Ok(Expression::LessThanOrEqual(Box::new(left), Box::new(right)))
             }
             TokenType::GreaterThan => {
                 self.advance();
-                let right = self.parse_primary_expression()?;
+                let right = self.parse_additive_expression()?;
                 Ok(Expression::GreaterThan(Box::new(left), Box::new(right)))
             }
             TokenType::GreaterThanOrEqual => {
                 self.advance();
-                let right = self.parse_primary_expression()?;
+                let right = self.parse_additive_expression()?;
                 Ok(Expression::GreaterThanOrEqual(Box::new(left), Box::new(right)))
             }
             _ => Ok(left)
         }
     }
     
-    /// Parse primary expressions: literals, variables
+    /// Parse `+` and `-`; binary minus needs a space after it, since `-5` is a negative number
+    fn parse_additive_expression(&mut self) -> Result<Expression> {
+        let mut left = self.parse_multiplicative_expression()?;
+        loop {
+            let operator: fn(Box<Expression>, Box<Expression>) -> Expression = match self.peek().token_type {
+                TokenType::Plus => Expression::Add,
+                TokenType::Minus => Expression::Subtract,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_multiplicative_expression()?;
+            left = operator(Box::new(left), Box::new(right));
+        }
+    }
+    
+    /// Parse `*`, `/` and `%`
+    fn parse_multiplicative_expression(&mut self) -> Result<Expression> {
+        let mut left = self.parse_unary_expression()?;
+        loop {
+            let operator: fn(Box<Expression>, Box<Expression>) -> Expression = match self.peek().token_type {
+                TokenType::Star => Expression::Multiply,
+                TokenType::Slash => Expression::Divide,
+                TokenType::Percent => Expression::Remainder,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.parse_unary_expression()?;
+            left = operator(Box::new(left), Box::new(right));
+        }
+    }
+    
+    /// Parse a negated operand such as `-$gap`
+    fn parse_unary_expression(&mut self) -> Result<Expression> {
+        if self.match_token(&TokenType::Minus) {
+            let operand = self.parse_unary_expression()?;
+            return Ok(Expression::Negate(Box::new(operand)));
+        }
+        self.parse_primary_expression()
+    }
+    
+    /// Parse primary expressions: literals, variables, min/max/clamp/calc and parentheses
     fn parse_primary_expression(&mut self) -> Result<Expression> {
+        let unit = match self.peek().token_type {
+            TokenType::Pixels(n) => Some((n, CSSUnit::Pixels)),
+            TokenType::Em(n) => Some((n, CSSUnit::Em)),
+            TokenType::Rem(n) => Some((n, CSSUnit::Rem)),
+            TokenType::ViewportWidth(n) => Some((n, CSSUnit::ViewportWidth)),
+            TokenType::ViewportHeight(n) => Some((n, CSSUnit::ViewportHeight)),
+            TokenType::Percentage(n) => Some((n, CSSUnit::Percentage)),
+            TokenType::Degrees(n) => Some((n, CSSUnit::Degrees)),
+            TokenType::Radians(n) => Some((n, CSSUnit::Radians)),
+            TokenType::Turns(n) => Some((n, CSSUnit::Turns)),
+            _ => None,
+        };
+        if let Some((value, unit)) = unit {
+            self.advance();
+            return Ok(Expression::Dimension(value, unit));
+        }
+        if self.is_expression_function() {
+            return self.parse_expression_call();
+        }
+        
         match &self.peek().token_type {
             TokenType::String(s) => {
                 let value = s.clone();
@@ -1563,8 +2466,8 @@ impl Parser {
                             self.consume(TokenType::RightBrace, "Expected '}' after variable name")?;
                             Ok(Expression::Variable(value))
                         } else {
-                            Err(CompilerError::parse_legacy(
-                                self.previous().line,
+                            Err(Self::error_at(
+                                self.previous(),
                                 "Expected variable name in ${variable}"
                             ))
                         }
@@ -1576,8 +2479,8 @@ impl Parser {
                         Ok(Expression::Variable(value))
                     },
                     _ => {
-                        Err(CompilerError::parse_legacy(
-                            self.peek().line,
+                        Err(Self::error_at(
+                            self.peek(),
                             "Expected variable name after '$' (use $variable or ${variable})"
                         ))
                     }
@@ -1589,8 +2492,30 @@ impl Parser {
                 self.consume(TokenType::RightParen, "Expected ')' after expression")?;
                 Ok(expr)
             }
-            _ => Err(CompilerError::parse("Unknown".to_string(), 0, "Expected expression"))
+            _ => Err(Self::error_at(self.peek(), "Expected expression"))
+        }
+    }
+    
+    /// Parse `min(a, b, ...)`, `max(a, b, ...)`, `clamp(min, value, max)` or `calc(value)`
+    fn parse_expression_call(&mut self) -> Result<Expression> {
+        let name = match &self.advance().token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => return Err(Self::error_at(self.previous(), "Expected function name")),
+        };
+        self.consume(TokenType::LeftParen, "Expected '(' after function name")?;
+        
+        let mut args = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                args.push(self.parse_ternary_expression()?);
+                if !self.match_token(&TokenType::Comma) {
+                    break;
+                }
+            }
         }
+        
+        self.consume(TokenType::RightParen, "Expected ')' after function arguments")?;
+        Ok(Expression::Call { name, args })
     }
     
     /// Parse @for loop: @for variable in collection ... @end or @for index, variable in collection ... @end
@@ -1604,8 +2529,8 @@ impl Parser {
                 self.advance();
                 var
             }
-            _ => return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            _ => return Err(Self::error_at(
+                self.peek(),
                 "Expected variable name after '@for'"
             )),
         };
@@ -1619,8 +2544,8 @@ impl Parser {
                     self.advance();
                     var
                 }
-                _ => return Err(CompilerError::parse_legacy(
-                    self.peek().line,
+                _ => return Err(Self::error_at(
+                    self.peek(),
                     "Expected variable name after comma in '@for'"
                 )),
             };
@@ -1654,21 +2579,21 @@ impl Parser {
                         self.advance();
                         col
                     }
-                    _ => return Err(CompilerError::parse_legacy(
-                        self.peek().line,
+                    _ => return Err(Self::error_at(
+                        self.peek(),
                         "Expected variable name after '$'"
                     )),
                 }
             }
-            _ => return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            _ => return Err(Self::error_at(
+                self.peek(),
                 "Expected collection name, variable reference, or string after 'in'"
             )),
         };
         
         // Parse body until @end
         let mut body = Vec::new();
-        while !self.check(&TokenType::End) && !self.is_at_end() {
+        while !self.at_block_end(&TokenType::End) {
             if self.match_token(&TokenType::Newline) {
                 continue;
             }
@@ -1678,20 +2603,17 @@ impl Parser {
             }
             
             if self.is_element_start() {
-                body.push(self.parse_element()?);
+                body.push(self.recover_node(Self::parse_element));
             } else if matches!(self.peek().token_type, TokenType::For) {
-                body.push(self.parse_for()?);
+                body.push(self.recover_node(Self::parse_for));
             } else if matches!(self.peek().token_type, TokenType::If) {
-                body.push(self.parse_if()?);
+                body.push(self.recover_node(Self::parse_if));
             } else {
-                return Err(CompilerError::parse_legacy(
-                    self.peek().line,
-                    format!("Unexpected token in @for body: {}", self.peek().token_type)
-                ));
+                self.skip_unexpected(format!("Unexpected token in @for body: {}", self.peek().token_type));
             }
         }
         
-        self.consume(TokenType::End, "Expected '@end' after @for body")?;
+        self.close_block(TokenType::End, "Expected '@end' after @for body")?;
         
         Ok(AstNode::For {
             index_variable,
@@ -1717,15 +2639,15 @@ impl Parser {
                 self.advance();
                 cond
             }
-            _ => return Err(CompilerError::parse_legacy(
-                self.peek().line,
+            _ => return Err(Self::error_at(
+                self.peek(),
                 "Expected condition after '@if'"
             )),
         };
         
         // Parse then body
         let mut then_body = Vec::new();
-        while !self.check(&TokenType::Elif) && !self.check(&TokenType::Else) && !self.check(&TokenType::End) && !self.is_at_end() {
+        while !self.check(&TokenType::Elif) && !self.check(&TokenType::Else) && !self.at_block_end(&TokenType::End) {
             if self.match_token(&TokenType::Newline) {
                 continue;
             }
@@ -1735,16 +2657,13 @@ impl Parser {
             }
             
             if self.is_element_start() {
-                then_body.push(self.parse_element()?);
+                then_body.push(self.recover_node(Self::parse_element));
             } else if matches!(self.peek().token_type, TokenType::For) {
-                then_body.push(self.parse_for()?);
+                then_body.push(self.recover_node(Self::parse_for));
             } else if matches!(self.peek().token_type, TokenType::If) {
-                then_body.push(self.parse_if()?);
+                then_body.push(self.recover_node(Self::parse_if));
             } else {
-                return Err(CompilerError::parse_legacy(
-                    self.peek().line,
-                    format!("Unexpected token in @if body: {}", self.peek().token_type)
-                ));
+                self.skip_unexpected(format!("Unexpected token in @if body: {}", self.peek().token_type));
             }
         }
         
@@ -1765,15 +2684,15 @@ impl Parser {
                     self.advance();
                     cond
                 }
-                _ => return Err(CompilerError::parse_legacy(
-                    self.peek().line,
+                _ => return Err(Self::error_at(
+                    self.peek(),
                     "Expected condition after '@elif'"
                 )),
             };
             
             // Parse elif body
             let mut elif_body = Vec::new();
-            while !self.check(&TokenType::Elif) && !self.check(&TokenType::Else) && !self.check(&TokenType::End) && !self.is_at_end() {
+            while !self.check(&TokenType::Elif) && !self.check(&TokenType::Else) && !self.at_block_end(&TokenType::End) {
                 if self.match_token(&TokenType::Newline) {
                     continue;
                 }
@@ -1783,16 +2702,13 @@ impl Parser {
                 }
                 
                 if self.is_element_start() {
-                    elif_body.push(self.parse_element()?);
+                    elif_body.push(self.recover_node(Self::parse_element));
                 } else if matches!(self.peek().token_type, TokenType::For) {
-                    elif_body.push(self.parse_for()?);
+                    elif_body.push(self.recover_node(Self::parse_for));
                 } else if matches!(self.peek().token_type, TokenType::If) {
-                    elif_body.push(self.parse_if()?);
+                    elif_body.push(self.recover_node(Self::parse_if));
                 } else {
-                    return Err(CompilerError::parse_legacy(
-                        self.peek().line,
-                        format!("Unexpected token in @elif body: {}", self.peek().token_type)
-                    ));
+                    self.skip_unexpected(format!("Unexpected token in @elif body: {}", self.peek().token_type));
                 }
             }
             
@@ -1804,7 +2720,7 @@ impl Parser {
             self.advance(); // consume @else
             
             let mut else_body = Vec::new();
-            while !self.check(&TokenType::End) && !self.is_at_end() {
+            while !self.at_block_end(&TokenType::End) {
                 if self.match_token(&TokenType::Newline) {
                     continue;
                 }
@@ -1814,16 +2730,13 @@ impl Parser {
                 }
                 
                 if self.is_element_start() {
-                    else_body.push(self.parse_element()?);
+                    else_body.push(self.recover_node(Self::parse_element));
                 } else if matches!(self.peek().token_type, TokenType::For) {
-                    else_body.push(self.parse_for()?);
+                    else_body.push(self.recover_node(Self::parse_for));
                 } else if matches!(self.peek().token_type, TokenType::If) {
-                    else_body.push(self.parse_if()?);
+                    else_body.push(self.recover_node(Self::parse_if));
                 } else {
-                    return Err(CompilerError::parse_legacy(
-                        self.peek().line,
-                        format!("Unexpected token in @else body: {}", self.peek().token_type)
-                    ));
+                    self.skip_unexpected(format!("Unexpected token in @else body: {}", self.peek().token_type));
                 }
             }
             
@@ -1832,7 +2745,7 @@ impl Parser {
             None
         };
         
-        self.consume(TokenType::End, "Expected '@end' after @if")?;
+        self.close_block(TokenType::End, "Expected '@end' after @if")?;
         
         Ok(AstNode::If {
             condition,
@@ -2111,4 +3024,211 @@ mod tests {
             _ => panic!("Expected File with App"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_recovers_from_syntax_errors() {
+        let source = r##"
+style "card" {
+    background_color: "#FF0000FF"
+
+style "panel" {
+    font_size: 12
+}
+
+App {
+    Container {
+        ]
+        @for in items
+            Text { text: "x" }
+        @end
+        Text {
+            text: "still parsed"
+        }
+    }
+}
+"##;
+
+        let tokens = Lexer::new(source, "test.kry".to_string()).tokenize().unwrap();
+        let (ast, errors) = Parser::new(tokens.clone()).parse_recovering();
+
+        let lines: Vec<usize> = errors.iter().map(|e| match e {
+            CompilerError::Parse { line, .. } => *line,
+            other => panic!("unexpected error {:?}", other),
+        }).collect();
+        assert_eq!(lines, vec![5, 11, 12]);
+
+        let AstNode::File { styles, app: Some(app), .. } = ast else { panic!("Expected File with App") };
+        assert_eq!(styles.len(), 2);
+        let AstNode::Element { children, .. } = app.as_ref() else { panic!("Expected App element") };
+        let AstNode::Element { children, .. } = &children[0] else { panic!("Expected Container") };
+        assert!(matches!(&children[0], AstNode::Error { location, .. } if location.line == 12));
+        assert!(matches!(&children[1], AstNode::Element { element_type, .. } if element_type == "Text"));
+
+        assert!(matches!(Parser::new(tokens).parse(), Err(CompilerError::Diagnostics(d)) if d.error_count() == 3));
+    }
+
+    #[test]
+    fn test_top_level_platform_blocks() {
+        let source = r##"
+style "card" {
+    padding: 8
+    padding@mobile: 4
+    @platform(mobile) {
+        &:hover {
+            opacity: 0.5
+        }
+    }
+}
+
+@platform(mobile) {
+    style "touch" {
+        height: 48
+    }
+    App {
+        Text { text: "Phone" }
+    }
+}
+
+@platform(desktop, web) {
+    App {
+        Text { text: "Wide" }
+    }
+}
+"##;
+
+        let parse = |platform| {
+            let tokens = Lexer::new(source, "test.kry".to_string()).tokenize().unwrap();
+            Parser::new(tokens).with_target_platform(platform).parse().unwrap()
+        };
+        let text_of = |app: &AstNode| match app {
+            AstNode::Element { children, .. } => match &children[0] {
+                AstNode::Element { properties, .. } => properties[0].cleaned_value(),
+                other => panic!("Expected Text, got {:?}", other),
+            },
+            other => panic!("Expected App, got {:?}", other),
+        };
+
+        let AstNode::File { styles, app: Some(app), .. } = parse(TargetPlatform::Mobile) else { panic!("Expected File with App") };
+        assert_eq!(styles.len(), 2);
+        assert_eq!(text_of(&app), "Phone");
+        let AstNode::Style { properties, pseudo_selectors, .. } = &styles[0] else { panic!("Expected style") };
+        assert_eq!((properties.len(), properties[0].key.as_str(), properties[0].cleaned_value()), (1, "padding", "4".to_string()));
+        assert_eq!(pseudo_selectors.len(), 1);
+
+        let AstNode::File { styles, app: Some(app), .. } = parse(TargetPlatform::Web) else { panic!("Expected File with App") };
+        assert_eq!(styles.len(), 1);
+        assert_eq!(text_of(&app), "Wide");
+
+        // Without a target only unconditional content is kept, but every block is still checked
+        let AstNode::File { styles, app, .. } = parse(TargetPlatform::Universal) else { panic!("Expected File") };
+        assert_eq!(styles.len(), 1);
+        assert!(app.is_none());
+        let tokens = Lexer::new("@platform(tv) {\n}\n@platform(web) {\n    App {\n        width 10\n    }\n}\n", "test.kry".to_string())
+            .tokenize()
+            .unwrap();
+        let (_, errors) = Parser::new(tokens).parse_recovering();
+        let lines: Vec<usize> = errors.iter().map(|e| match e {
+            CompilerError::Parse { line, .. } => *line,
+            other => panic!("unexpected error {:?}", other),
+        }).collect();
+        assert_eq!(lines, vec![1, 5]);
+    }
+
+    #[test]
+    fn test_media_queries() {
+        let source = "style \"card\" {\n    @media (max-width: $tablet) {\n        padding: 8\n    }\n}\nApp {\n    @media (min-width: 601px) and (max-width: 900) {\n        width: 600\n    }\n}\n";
+        let tokens = Lexer::new(source, "test.kry".to_string()).tokenize().unwrap();
+        let AstNode::File { styles, app: Some(app), .. } = Parser::new(tokens).parse().unwrap() else { panic!("Expected File with App") };
+
+        let AstNode::Style { media_queries, .. } = &styles[0] else { panic!("Expected style") };
+        assert_eq!((media_queries[0].min_width.as_deref(), media_queries[0].max_width.as_deref()), (None, Some("$tablet")));
+        assert_eq!(media_queries[0].properties[0].key, "padding");
+        let AstNode::Element { media_queries, .. } = app.as_ref() else { panic!("Expected App") };
+        assert_eq!((media_queries[0].min_width.as_deref(), media_queries[0].max_width.as_deref()), (Some("601px"), Some("900")));
+        assert_eq!(media_queries[0].location.as_ref().map(|l| l.line), Some(7));
+
+        let tokens = Lexer::new("App {\n    @media (orientation: 1) {\n    }\n}\n", "test.kry".to_string()).tokenize().unwrap();
+        let (_, errors) = Parser::new(tokens).parse_recovering();
+        assert!(errors[0].to_string().contains("Expected min-width or max-width"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let parse = |value: &str| {
+            let source = format!("App {{\n    background_image: {}\n}}\n", value);
+            let tokens = Lexer::new(&source, "test.kry".to_string()).tokenize().unwrap();
+            let (ast, errors) = Parser::new(tokens).parse_recovering();
+            let AstNode::File { app: Some(app), .. } = ast else { panic!("Expected File with App") };
+            let AstNode::Element { properties, .. } = *app else { panic!("Expected App") };
+            match (&properties[..], &errors[..]) {
+                ([property], []) => Ok(property.value.clone()),
+                (_, [error, ..]) => Err(error.to_string()),
+                _ => panic!("Expected one property or an error"),
+            }
+        };
+        let gradient = |value: &str| match parse(value) {
+            Ok(PropertyValue::Gradient(gradient)) => gradient,
+            other => panic!("Expected a gradient: {:?}", other),
+        };
+
+        let linear = gradient("linear-gradient(to bottom left, #FF0000, $brand 40%,\n    darken(white, 10%))");
+        assert_eq!(linear.angle, 225.0);
+        assert_eq!(linear.stops[1], GradientStop { color: "$brand".to_string(), position: Some(40.0) });
+        assert_eq!(linear.stops[2].color, "darken(white, 10%)");
+        assert_eq!(gradient("linear-gradient(red, blue)").angle, 180.0);
+        assert_eq!(gradient("linear-gradient(-90deg, red, blue)").to_string(), "linear-gradient(-90deg, red, blue)");
+
+        let radial = gradient("radial-gradient(circle at top 25%, \"#FFF\" 0, transparent 60%)");
+        assert_eq!((radial.shape, radial.center), (RadialShape::Circle, (25.0, 0.0)));
+        assert_eq!(gradient("radial-gradient(red, blue)").center, (50.0, 50.0));
+
+        let error = |value: &str| parse(value).unwrap_err();
+        assert!(error("linear-gradient(red 60%, blue, white 20%)").contains("gradient stop at 20% comes after one at 60%"));
+        assert!(error("linear-gradient(red 5px, blue)").contains("gradient stop positions must be percentages"));
+        assert!(error("linear-gradient(red, bluish)").contains("'bluish' is not a color"));
+        assert!(error("linear-gradient(red, shade(blue))").contains("'shade()' is not a color function"));
+        assert!(error("linear-gradient(to middle, red, blue)").contains("'to middle' is not a gradient direction"));
+        assert!(error("radial-gradient(red)").contains("radial-gradient() needs at least 2 color stops"));
+
+        // The quoted form is the same value
+        let quoted = gradient("\"linear-gradient(to right, red, $brand 40%)\"");
+        assert_eq!(quoted, gradient("linear-gradient(to right, red, $brand 40%)"));
+        assert!(error("\"linear-gradient(red, bluish)\"").contains("'bluish' is not a color"));
+        assert!(error("\"linear-gradient(red, blue) x\"").contains("after the gradient"));
+        assert!(!matches!(parse("\"photo.png\""), Ok(PropertyValue::Gradient(_))));
+    }
+
+    #[test]
+    fn test_arithmetic_expressions() {
+        let parse = |key: &str, value: &str| {
+            let source = format!("App {{\n    {}: {}\n}}\n", key, value);
+            let tokens = Lexer::new(&source, "test.kry".to_string()).tokenize().unwrap();
+            let (ast, errors) = Parser::new(tokens).parse_recovering();
+            assert!(errors.is_empty(), "{:?}", errors);
+            let AstNode::File { app: Some(app), .. } = ast else { panic!("Expected File with App") };
+            let AstNode::Element { properties, .. } = *app else { panic!("Expected App") };
+            properties[0].value.clone()
+        };
+
+        // Multiplication binds tighter, and the text parses back to the same tree
+        let value = parse("width", "100% - $gap * 2 + -$inset");
+        assert_eq!(value.to_string(), "100% - $gap * 2 + -$inset");
+        let PropertyValue::Expression(expr) = value else { panic!("Expected an expression") };
+        assert!(matches!(*expr, Expression::Add(ref left, _) if matches!(**left, Expression::Subtract(..))));
+        assert_eq!(Parser::parse_expression(&expr.to_string()).unwrap().to_string(), expr.to_string());
+
+        assert_eq!(parse("width", "($a - 4px) / 2").to_string(), "($a - 4px) / 2");
+        assert_eq!(parse("width", "$a - ($b - 1)").to_string(), "$a - ($b - 1)");
+        assert_eq!(parse("text", "\"Hi \" + $name").to_string(), "\"Hi \" + $name");
+        assert_eq!(parse("height", "clamp(10px, 5vw + 1rem, max(40px, 10%))").to_string(), "clamp(10px, 5vw + 1rem, max(40px, 10%))");
+
+        // A lone number with a unit keeps its value type, and shorthands split on spaces
+        assert!(matches!(parse("width", "50%"), PropertyValue::Percentage(p) if p == 50.0));
+        let PropertyValue::Array(values) = parse("padding", "$gap * 2 10 -5") else { panic!("Expected shorthand values") };
+        let values: Vec<String> = values.iter().map(PropertyValue::to_string).collect();
+        assert_eq!(values, ["$gap * 2", "10", "-5"]);
+
+        assert!(Parser::parse_expression("1 +").is_none());
+        assert!(Parser::parse_expression("Hello world").is_none());
+    }
+}
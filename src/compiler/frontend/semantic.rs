@@ -1,28 +1,39 @@
 //! Semantic analysis and validation for the Kryon compiler
 
 use crate::compiler::frontend::ast::*;
+use crate::compiler::frontend::parser::Parser;
+use crate::compiler::middle_end::expressions::evaluate;
 use crate::compiler::middle_end::script::ScriptProcessor;
+use crate::compiler::middle_end::style_resolver::float_to_fixed_point;
 use crate::error::{CompilerError, Result};
+use crate::diagnostics::{Diagnostic, Diagnostics, Span};
 use crate::core::*;
+use crate::core::effects::{encode_filters, encode_shadows, parse_filters, parse_shadows};
 use crate::core::types::*;
 use crate::core::util::{clean_and_quote_value, parse_color};
 use std::collections::{HashMap, HashSet};
 
 pub struct SemanticAnalyzer {
-    errors: Vec<CompilerError>,
-    warnings: Vec<String>,
+    diagnostics: Diagnostics,
+    style_locations: HashMap<String, Span>, // Where each style was (last) defined
+    keyframes_locations: HashMap<String, Option<Span>>, // Every @keyframes name, with its definition
+    font_faces: HashMap<String, Vec<(u16, FontStyle, Option<Span>)>>, // Declared weight and style variants per family
     variable_usage: HashMap<String, Vec<usize>>, // Track where variables are used
 }
 
 impl SemanticAnalyzer {
     pub fn new() -> Self {
         Self {
-            errors: Vec::new(),
-            warnings: Vec::new(),
+            diagnostics: Diagnostics::new(),
+            style_locations: HashMap::new(),
+            keyframes_locations: HashMap::new(),
+            font_faces: HashMap::new(),
             variable_usage: HashMap::new(),
         }
     }
     
+    /// Validate the AST, collecting every error and warning rather than stopping
+    /// at the first. Fails with `CompilerError::Diagnostics` if any error was found.
     pub fn analyze(&mut self, ast: &mut AstNode, state: &mut CompilerState) -> Result<()> {
         // Phase 1: Collect all definitions
         self.collect_definitions(ast, state)?;
@@ -34,28 +45,55 @@ impl SemanticAnalyzer {
         self.validate_elements(ast, state)?;
         
         // Phase 4: Check for unused definitions
-        self.check_unused_definitions(state)?;
+        self.check_unused_definitions(ast, state)?;
         
-        // Return any accumulated errors
-        if !self.errors.is_empty() {
-            return Err(self.errors.remove(0));
-        }
-        
-        // Log warnings
-        for warning in &self.warnings {
-            log::warn!("{}", warning);
+        // Return all accumulated errors, along with the warnings
+        if self.diagnostics.has_errors() {
+            return Err(CompilerError::Diagnostics(self.diagnostics.clone()));
         }
         
         Ok(())
     }
+
+    /// Diagnostics collected by `analyze`, leaving the analyzer empty
+    pub fn take_diagnostics(&mut self) -> Diagnostics {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn error(&mut self, span: Option<Span>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic::error(message).with_span(span));
+    }
+
+    fn warning(&mut self, span: Option<Span>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic::warning(message).with_span(span));
+    }
+
+    /// "style defined here" note for a style, if its definition was located
+    fn style_note(&self, name: &str) -> Option<Span> {
+        self.style_locations.get(name).cloned()
+    }
     
     fn collect_definitions(&mut self, ast: &AstNode, state: &mut CompilerState) -> Result<()> {
         match ast {
-            AstNode::File { styles, components, scripts, directives, .. } => {
+            AstNode::File { styles, fonts, keyframes, components, scripts, directives, .. } => {
                 // Collect directives (variables)
                 for directive_node in directives {
                     self.collect_directive_definition(directive_node, state)?;
                 }
+                self.validate_themes(state);
+                
+                // Collect fonts before anything that may name a font family
+                for font_node in fonts {
+                    self.collect_font_definition(font_node);
+                }
+                for font_node in fonts {
+                    self.validate_font_fallbacks(font_node);
+                }
+                
+                // Collect keyframes before the styles that may animate with them
+                for keyframes_node in keyframes {
+                    self.collect_keyframes_definition(keyframes_node);
+                }
                 
                 // Collect styles
                 for style_node in styles {
@@ -79,16 +117,30 @@ impl SemanticAnalyzer {
     }
     
     fn collect_style_definition(&mut self, ast: &AstNode, state: &mut CompilerState) -> Result<()> {
-        if let AstNode::Style { name, extends, properties, pseudo_selectors: _ } = ast {
+        if let AstNode::Style { name, extends, properties, location, .. } = ast {
             // Check for duplicate style names - but allow includes to redefine styles
             // The latest definition wins (include order matters)
             if let Some(existing_index) = state.styles.iter().position(|s| s.source_name == *name) {
                 // Remove the existing style definition - the new one will replace it
                 state.styles.remove(existing_index);
                 log::debug!("Style '{}' redefined, using latest definition", name);
+
+                // Overriding an included style is intended; redefining one in the same file is not
+                if let (Some(previous), Some(current)) = (self.style_locations.get(name), location) {
+                    if previous.file == current.file {
+                        self.diagnostics.push(
+                            Diagnostic::warning(format!("style '{}' is defined more than once; the last definition wins", name))
+                                .with_span(Some(current.clone()))
+                                .with_note("previous definition here", Some(previous.clone())),
+                        );
+                    }
+                }
+            }
+            if let Some(location) = location {
+                self.style_locations.insert(name.clone(), location.clone());
             }
             
-            let style_id = (state.styles.len() + 1) as u8;
+            let style_id = (state.styles.len() + 1) as u16;
             let name_index = self.add_string_to_state(name, state)?;
             
             let mut style_entry = StyleEntry {
@@ -98,17 +150,27 @@ impl SemanticAnalyzer {
                 extends_style_names: extends.clone(),
                 properties: Vec::new(),
                 source_properties: Vec::new(),
+                media_property_sets: Vec::new(),
+                animations: Vec::new(),
                 calculated_size: 3, // Base size
                 is_resolved: false,
                 is_resolving: false,
+                location: location.clone(),
             };
             
             // Convert AST properties to source properties
             for ast_prop in properties {
+                if ast_prop.key == "font_family" {
+                    self.validate_font_family(ast_prop);
+                }
+                if ast_prop.key == "animation" || ast_prop.key == "transition" {
+                    self.validate_animation_property(ast_prop);
+                }
                 style_entry.source_properties.push(SourceProperty {
                     key: ast_prop.key.clone(),
                     value: ast_prop.value.to_string(),
                     line_num: ast_prop.line,
+                    location: ast_prop.location.clone(),
                 });
             }
             
@@ -119,6 +181,147 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
+    fn collect_font_definition(&mut self, ast: &AstNode) {
+        let AstNode::Font { name, path, properties, location } = ast else {
+            return;
+        };
+
+        if path.is_empty() {
+            self.error(location.clone(), format!("Font '{}' needs a 'src' file", name));
+        }
+        let descriptors = match font_descriptors(properties) {
+            Ok(descriptors) => descriptors,
+            Err((span, message)) => {
+                self.error(span.or_else(|| location.clone()), message);
+                FontDescriptors::default()
+            }
+        };
+
+        let faces = self.font_faces.entry(name.clone()).or_default();
+        let previous = faces
+            .iter()
+            .find(|(weight, style, _)| *weight == descriptors.weight && *style == descriptors.style)
+            .map(|(_, _, span)| span.clone());
+        faces.push((descriptors.weight, descriptors.style, location.clone()));
+
+        if let Some(previous) = previous {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "Font '{}' already has a {} {} face",
+                    name, descriptors.weight, descriptors.style.to_name()
+                ))
+                .with_span(location.clone())
+                .with_note("previous declaration here", previous),
+            );
+        }
+    }
+
+    /// Fallbacks must name declared families or generic ones
+    fn validate_font_fallbacks(&mut self, ast: &AstNode) {
+        let AstNode::Font { name, properties, .. } = ast else {
+            return;
+        };
+        let Ok(descriptors) = font_descriptors(properties) else {
+            return;
+        };
+        let location = properties.iter().find(|p| p.key == "fallback").and_then(|p| p.location.clone());
+
+        for fallback in &descriptors.fallbacks {
+            if fallback == name {
+                self.error(location.clone(), format!("Font '{}' cannot fall back to itself", name));
+            } else if !self.is_known_font_family(fallback) {
+                self.error(location.clone(), format!("Fallback font '{}' of '{}' is not declared", fallback, name));
+            }
+        }
+    }
+
+    /// `font_family` must name a declared font or a generic family
+    fn validate_font_family(&mut self, prop: &AstProperty) {
+        let family = prop.cleaned_value();
+        if prop.has_templates || family.is_empty() || family.starts_with('$') || self.is_known_font_family(&family) {
+            return;
+        }
+        self.error(
+            prop.location.clone(),
+            format!(
+                "Font family '{}' is not declared; add a `font \"{}\"` declaration or use one of {}",
+                family,
+                family,
+                GENERIC_FONT_FAMILIES.join(", ")
+            ),
+        );
+    }
+
+    fn is_known_font_family(&self, family: &str) -> bool {
+        self.font_faces.contains_key(family) || GENERIC_FONT_FAMILIES.contains(&family)
+    }
+
+    fn collect_keyframes_definition(&mut self, ast: &AstNode) {
+        let AstNode::Keyframes { name, frames, location } = ast else {
+            return;
+        };
+
+        // Like styles, an included definition may be overridden, but not one from the same file
+        if let Some((Some(previous), Some(current))) = self.keyframes_locations.get(name).map(|p| (p, location)) {
+            if previous.file == current.file {
+                self.diagnostics.push(
+                    Diagnostic::warning(format!("@keyframes '{}' is defined more than once; the last definition wins", name))
+                        .with_span(Some(current.clone()))
+                        .with_note("previous definition here", Some(previous.clone())),
+                );
+            }
+        }
+        self.keyframes_locations.insert(name.clone(), location.clone());
+
+        if frames.is_empty() {
+            self.error(location.clone(), format!("@keyframes '{}' has no keyframes", name));
+        }
+
+        let mut offsets = Vec::new();
+        for frame in frames {
+            let first_property = frame.properties.first().and_then(|p| p.location.clone());
+            if !(0.0..=100.0).contains(&frame.offset) {
+                self.error(
+                    first_property.or_else(|| location.clone()),
+                    format!("Keyframe offset {}% in '{}' must be between 0% and 100%", frame.offset, name),
+                );
+            } else if offsets.contains(&frame.offset.round()) {
+                self.error(
+                    first_property.or_else(|| location.clone()),
+                    format!("Keyframe {}% is defined more than once in '{}'", frame.offset, name),
+                );
+            }
+            offsets.push(frame.offset.round());
+
+            for prop in &frame.properties {
+                if !PropertyId::from_name(&prop.key).is_animatable() {
+                    self.error(
+                        prop.location.clone(),
+                        format!("Property '{}' cannot be animated in @keyframes '{}'", prop.key, name),
+                    );
+                    continue;
+                }
+                self.validate_property_value(&prop.key, &prop.value.to_string(), prop.location.clone());
+            }
+        }
+    }
+
+    /// Check `animation:` and `transition:` specs, including that named keyframes exist
+    fn validate_animation_property(&mut self, prop: &AstProperty) {
+        let spec = prop.cleaned_value();
+        if prop.key == "animation" {
+            match parse_animation_spec(&spec) {
+                Ok(animation) if !self.keyframes_locations.contains_key(&animation.keyframes) => {
+                    self.error(prop.location.clone(), format!("@keyframes '{}' is not defined", animation.keyframes));
+                }
+                Ok(_) => {}
+                Err(message) => self.error(prop.location.clone(), message),
+            }
+        } else if let Err(message) = parse_transition_specs(&spec) {
+            self.error(prop.location.clone(), message);
+        }
+    }
+
     fn collect_component_definition(&mut self, ast: &AstNode, state: &mut CompilerState) -> Result<()> {
         if let AstNode::Component { name, properties, template, .. } = ast {
             // Check for duplicate component names - but allow includes to redefine components
@@ -191,6 +394,23 @@ impl SemanticAnalyzer {
                     )?;
                 }
             }
+            AstNode::Theme { name, tokens, location } => {
+                if let Some(previous) = state.themes.iter().find(|theme| theme.name == *name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("theme '{}' is defined more than once", name))
+                            .with_span(location.clone())
+                            .with_note("previous definition here", previous.location.clone()),
+                    );
+                    return Ok(());
+                }
+                let name_index = self.add_string_to_state(name, state)?;
+                state.themes.push(ThemeEntry {
+                    name: name.clone(),
+                    name_index,
+                    tokens: tokens.clone(),
+                    location: location.clone(),
+                });
+            }
             _ => {
                 // Other directive types could be handled here in the future
             }
@@ -198,6 +418,54 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
+    /// Every theme must define every token, so switching themes never leaves a property
+    /// without a value. Whether the values agree in type is checked where a token is used.
+    fn validate_themes(&mut self, state: &CompilerState) {
+        let mut definitions: Vec<(&str, &ThemeEntry)> = Vec::new();
+        for theme in &state.themes {
+            for (index, token) in theme.tokens.iter().enumerate() {
+                if let Some(first) = theme.tokens[..index].iter().find(|t| t.key == token.key) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("token '{}' is defined more than once in theme '{}'", token.key, theme.name))
+                            .with_span(token.location.clone())
+                            .with_note("previous definition here", first.location.clone()),
+                    );
+                    continue;
+                }
+                if let PropertyValue::Variable(name) = &token.value {
+                    self.error(
+                        token.location.clone(),
+                        format!("token '{}' must be given a value; '${}' cannot be used inside @theme", token.key, name),
+                    );
+                }
+                if state.variables.contains_key(&token.key) {
+                    self.error(
+                        token.location.clone(),
+                        format!("token '{}' has the name of a variable from @variables; '${}' would be ambiguous", token.key, token.key),
+                    );
+                }
+                if !definitions.iter().any(|(key, _)| *key == token.key) {
+                    definitions.push((&token.key, theme));
+                }
+            }
+        }
+        
+        for theme in &state.themes {
+            for &(token, defined_in) in &definitions {
+                if theme.token(token).is_none() {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("theme '{}' does not define token '{}'", theme.name, token))
+                            .with_span(theme.location.clone())
+                            .with_note(
+                                format!("'{}' is defined in theme '{}'", token, defined_in.name),
+                                defined_in.token(token).and_then(|t| t.location.clone()),
+                            ),
+                    );
+                }
+            }
+        }
+    }
+    
     fn resolve_dependencies(&mut self, state: &mut CompilerState) -> Result<()> {
         // Resolve style inheritance
         self.resolve_style_inheritance(state)?;
@@ -234,11 +502,10 @@ impl SemanticAnalyzer {
         }
         
         if resolving.contains(&style_index) {
-            let style_name = &state.styles[style_index].source_name;
-            return Err(CompilerError::semantic_legacy(
-                0,
-                format!("Circular dependency in style inheritance involving '{}'", style_name)
-            ));
+            let style_name = state.styles[style_index].source_name.clone();
+            let span = self.style_note(&style_name);
+            self.error(span, format!("Circular dependency in style inheritance involving '{}'", style_name));
+            return Ok(());
         }
         
         resolving.insert(style_index);
@@ -249,13 +516,23 @@ impl SemanticAnalyzer {
         // Resolve base styles first
         for base_style_name in &extends_names {
             if let Some(base_index) = state.styles.iter().position(|s| s.source_name == *base_style_name) {
+                if resolving.contains(&base_index) && base_index != style_index {
+                    // Point at the style closing the cycle, not just the one it loops back to
+                    let name = state.styles[style_index].source_name.clone();
+                    let diagnostic = Diagnostic::error(format!(
+                        "Circular dependency in style inheritance: '{}' extends '{}', which already depends on it",
+                        name, base_style_name
+                    ))
+                    .with_span(self.style_note(&name))
+                    .with_note(format!("style '{}' defined here", base_style_name), self.style_note(base_style_name));
+                    self.diagnostics.push(diagnostic);
+                    continue;
+                }
                 self.resolve_style_recursive(base_index, state, resolved, resolving)?;
             } else {
-                return Err(CompilerError::semantic_legacy(
-                    0,
-                    format!("Style '{}' extends undefined style '{}'", 
-                           state.styles[style_index].source_name, base_style_name)
-                ));
+                let name = state.styles[style_index].source_name.clone();
+                let span = self.style_note(&name);
+                self.error(span, format!("Style '{}' extends undefined style '{}'", name, base_style_name));
             }
         }
         
@@ -307,7 +584,7 @@ impl SemanticAnalyzer {
                         if !prop_def.default_value.is_empty() {
                             let style_name = prop_def.default_value.trim_matches('"');
                             if !state.styles.iter().any(|s| s.source_name == style_name) {
-                                self.warnings.push(format!(
+                                self.warning(None, format!(
                                     "Component '{}' property '{}' defaults to undefined style '{}'",
                                     component.name, prop_def.name, style_name
                                 ));
@@ -363,12 +640,14 @@ impl SemanticAnalyzer {
             
             // Special validation for Input elements with type-specific property validation
             if element_type == "Input" {
-                self.validate_input_element_properties(properties)?;
+                self.validate_input_element_properties(properties);
             }
             
             // Validate parent-child relationships
             if let Some(parent) = parent_type {
-                self.validate_parent_child_relationship(parent, element_type)?;
+                if let Err(e) = self.validate_parent_child_relationship(parent, element_type) {
+                    self.diagnostics.push_error(e);
+                }
             }
             
             // Recursively validate children
@@ -380,15 +659,15 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
-    fn validate_property(&mut self, element_type: &str, prop: &mut AstProperty, _state: &CompilerState) -> Result<()> {
+    fn validate_property(&mut self, element_type: &str, prop: &mut AstProperty, state: &CompilerState) -> Result<()> {
         // Resolve property aliases first
         let resolved_key = self.resolve_property_alias(element_type, &prop.key);
         
         // If we resolved an alias, show a helpful message and update the property key
         if resolved_key != prop.key {
-            self.warnings.push(format!(
-                "Line {}: Property '{}' on {} element is automatically mapped to '{}' (consider updating your code)",
-                prop.line, prop.key, element_type, resolved_key
+            self.warning(prop.location.clone(), format!(
+                "Property '{}' on {} element is automatically mapped to '{}' (consider updating your code)",
+                prop.key, element_type, resolved_key
             ));
             // Update the property key to use the canonical name
             prop.key = resolved_key.clone();
@@ -406,19 +685,45 @@ impl SemanticAnalyzer {
         };
         
         if !is_valid {
-            return Err(CompilerError::semantic_legacy(
-                prop.line,
+            self.error(
+                prop.location.clone(),
                 format!("Property '{}' is not valid for element type '{}'", resolved_key, element_type)
-            ));
+            );
+            return Ok(());
+        }
+
+        // Style references must name a defined style (dynamic names are checked at runtime)
+        if resolved_key == "style" {
+            if let Some(style_name) = static_style_reference(&prop.value) {
+                if !state.styles.iter().any(|s| s.source_name == style_name) {
+                    self.error(prop.location.clone(), format!("Style '{}' is not defined", style_name));
+                }
+            }
+        }
+        
+        if resolved_key == "animation" || resolved_key == "transition" {
+            self.validate_animation_property(prop);
+            return Ok(());
+        }
+        
+        if resolved_key == "font_family" {
+            self.validate_font_family(prop);
         }
         
-        // Validate property value format
-        self.validate_property_value(&prop.key, &prop.value.to_string(), prop.line)?;
+        // Validate property value format; calc values and arithmetic are checked when converted
+        let is_calculated = match &prop.value {
+            PropertyValue::Calc(_) => true,
+            PropertyValue::Expression(expr) => expr.is_arithmetic(),
+            _ => false,
+        };
+        if !is_calculated {
+            self.validate_property_value(&prop.key, &prop.value.to_string(), prop.location.clone());
+        }
         
         Ok(())
     }
     
-    fn validate_property_value(&mut self, key: &str, value: &str, line: usize) -> Result<()> {
+    fn validate_property_value(&mut self, key: &str, value: &str, location: Option<Span>) {
         match key {
             key if key.contains("color") => {
                 let is_valid_color = value.starts_with('#') || // Hex color
@@ -427,10 +732,10 @@ impl SemanticAnalyzer {
                                    self.is_valid_color_keyword(value); // Color keyword
                 
                 if !is_valid_color {
-                    return Err(CompilerError::semantic_legacy(
-                        line,
-                        format!("Color property '{}' must be a hex color (#RGB), variable ($var), string, or valid color keyword", key)
-                    ));
+                    self.error(
+                        location,
+                        format!("Color property '{}' must be a hex color (#RGB), color function, variable ($var), string, or CSS color name", key)
+                    );
                 }
             }
             key if key.contains("width") || key.contains("height") || key.contains("size") => {
@@ -446,16 +751,14 @@ impl SemanticAnalyzer {
                               value.starts_with('"'); // Strings
                 
                 if !is_valid {
-                    return Err(CompilerError::semantic_legacy(
-                        line,
+                    self.error(
+                        location,
                         format!("Size property '{}' must be a number, percentage (%), CSS unit (px/em/rem/vw/vh), variable ($var), or string", key)
-                    ));
+                    );
                 }
             }
             _ => {}
         }
-        
-        Ok(())
     }
     
     fn validate_parent_child_relationship(&mut self, parent_type: &str, child_type: &str) -> Result<()> {
@@ -534,23 +837,37 @@ impl SemanticAnalyzer {
         Ok(())
     }
     
-    fn check_unused_definitions(&mut self, state: &CompilerState) -> Result<()> {
-        // Check for unused styles
-        let used_styles: HashSet<String> = HashSet::new();
+    fn check_unused_definitions(&mut self, ast: &AstNode, state: &CompilerState) -> Result<()> {
+        // Check for unused styles: referenced by an element, extended by another
+        // style, or named by a component property default
+        let mut used_styles: HashSet<String> = HashSet::new();
+        let mut has_dynamic_references = false;
 
-        // This would require traversing all elements to see which styles are referenced
-        // For now, just warn about styles that don't extend anything and aren't extended
+        if let AstNode::File { app, components, .. } = ast {
+            let templates = components.iter().filter_map(|component| match component {
+                AstNode::Component { template, .. } => Some(template.as_ref()),
+                _ => None,
+            });
+            for root in app.as_deref().into_iter().chain(templates) {
+                collect_style_references(root, &mut used_styles, &mut has_dynamic_references);
+            }
+        }
         for style in &state.styles {
-            if style.extends_style_names.is_empty() {
-                let is_extended = state.styles.iter()
-                    .any(|s| s.extends_style_names.contains(&style.source_name));
-                
-                if !is_extended {
-                    self.warnings.push(format!(
-                        "Style '{}' is defined but may not be used", 
-                        style.source_name
-                    ));
-                }
+            used_styles.extend(style.extends_style_names.iter().cloned());
+        }
+        for component in &state.component_defs {
+            used_styles.extend(component.properties.iter().map(|p| p.default_value.trim_matches('"').to_string()));
+        }
+
+        // A style picked at runtime could be any of them
+        if has_dynamic_references {
+            return Ok(());
+        }
+
+        for style in &state.styles {
+            if !used_styles.contains(&style.source_name) {
+                let span = self.style_note(&style.source_name);
+                self.warning(span, format!("Style '{}' is never used", style.source_name));
             }
         }
         
@@ -581,14 +898,9 @@ impl SemanticAnalyzer {
                 // Variable substitution happens in convert_ast_property_to_krb function
                 let string_index = state.strings.iter()
                     .position(|s| s.text == cleaned_value)
-                    .unwrap_or(0) as u8;
+                    .unwrap_or(0) as u16;
                 
-                Ok(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::String,
-                    size: 1,
-                    value: vec![string_index],
-                })
+                Ok(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
             }
             PropertyId::BorderWidth | PropertyId::BorderRadius => {
                 if let Ok(val) = cleaned_value.parse::<u8>() {
@@ -627,13 +939,8 @@ impl SemanticAnalyzer {
                     // This is a template variable - store it as a string for later resolution
                     let string_index = state.strings.iter()
                         .position(|s| s.text == cleaned_value)
-                        .unwrap_or(0) as u8;
-                    Ok(KrbProperty {
-                        property_id: property_id as u8,
-                        value_type: ValueType::TemplateVariable,
-                        size: 1,
-                        value: vec![string_index],
-                    })
+                        .unwrap_or(0) as u16;
+                    Ok(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
                 } else {
                     let visible = match cleaned_value.to_lowercase().as_str() {
                         "true" | "visible" | "1" => true,
@@ -655,13 +962,11 @@ impl SemanticAnalyzer {
                 }
             }
             _ => {
-                // Default handling - store as custom property
-                Ok(KrbProperty {
-                    property_id: PropertyId::CustomData as u8,
-                    value_type: ValueType::String,
-                    size: cleaned_value.len() as u8,
-                    value: cleaned_value.into_bytes(),
-                })
+                // Default handling - store as custom property referring to the string table
+                let string_index = state.strings.iter()
+                    .position(|s| s.text == cleaned_value)
+                    .unwrap_or(0) as u16;
+                Ok(KrbProperty::with_index(PropertyId::CustomData as u8, ValueType::String, string_index))
             }
         }
     }
@@ -678,7 +983,7 @@ impl SemanticAnalyzer {
             "window_title" | "window_width" | "window_height" | "window_min_width" |
             "window_min_height" | "window_max_width" | "window_max_height" |
             "resizable" | "keep_aspect_ratio" | "scale_factor" | "icon" |
-            "version" | "author" | "background_color" | "id" | "style" | "visible" |
+            "version" | "author" | "background_color" | "background_image" | "id" | "style" | "visible" |
             // Modern Taffy layout properties
             "display" | "flex_direction" | "flex_wrap" | "flex_grow" | "flex_shrink" | "flex_basis" |
             "align_items" | "align_self" | "align_content" | "justify_content" | "justify_items" | "justify_self" |
@@ -698,10 +1003,14 @@ impl SemanticAnalyzer {
             "text_alignment" | "line_height" | "text_decoration" | "text_transform" |
             "list_style_type" | "white_space" |
             "id" | "pos_x" | "pos_y" | "width" | "height" | "style" |
-            "background_color" | "border_color" | "border_width" | "border_radius" |
+            "background_color" | "background_image" | "border_color" | "border_width" | "border_radius" |
             "padding" | "margin" | "opacity" | "visibility" | "visible" | "z_index" |
             // Transform properties
             "transform" |
+            // Visual effects properties
+            "text_shadow" | "box_shadow" | "filter" |
+            // Animation properties
+            "animation" | "transition" |
             // Event handlers
             "onClick" | "onHover" | "onFocus" | "onBlur" |
             // Modern Taffy layout properties
@@ -766,26 +1075,25 @@ impl SemanticAnalyzer {
     }
     
     /// Comprehensive validation for Input elements based on their type attribute
-    fn validate_input_element_properties(&self, properties: &[AstProperty]) -> Result<()> {
+    fn validate_input_element_properties(&mut self, properties: &[AstProperty]) {
         // Find the type property to determine input type
         let input_type = self.get_input_type_from_properties(properties);
         
-        // Validate each property against the determined input type
+        // Validate each property against the determined input type. Properties that
+        // aren't valid on any Input were already reported by `validate_property`.
         for prop in properties {
-            if !self.is_property_valid_for_input_type(&prop.key, input_type) {
-                return Err(CompilerError::semantic_legacy(
-                    prop.line,
+            if self.is_valid_input_property(&prop.key) && !self.is_property_valid_for_input_type(&prop.key, input_type) {
+                self.error(
+                    prop.location.clone(),
                     format!(
                         "Property '{}' is not valid for Input type '{}'. Valid properties for this type are: {}",
                         prop.key,
                         input_type.to_name(),
                         self.get_valid_properties_for_input_type(input_type).join(", ")
                     )
-                ));
+                );
             }
         }
-        
-        Ok(())
     }
     
     /// Extract the input type from the element's properties, defaulting to "text"
@@ -813,7 +1121,7 @@ impl SemanticAnalyzer {
         // Common properties valid for all input types
         let is_common_property = matches!(property,
             "type" | "id" | "style" | "disabled" | "visible" | "width" | "height" |
-            "padding" | "margin" | "background_color" | "border_color" | "border_width" |
+            "padding" | "margin" | "background_color" | "background_image" | "border_color" | "border_width" |
             "border_radius" | "opacity" | "z_index" | "pos_x" | "pos_y" |
             "onClick" | "onFocus" | "onBlur" | "onHover" | "onPress" | "onRelease" |
             // Box model properties
@@ -902,7 +1210,7 @@ impl SemanticAnalyzer {
     fn get_valid_properties_for_input_type(&self, input_type: InputType) -> Vec<&'static str> {
         let mut props = vec![
             "type", "id", "style", "disabled", "visible", "width", "height",
-            "padding", "margin", "background_color", "border_color", "border_width",
+            "padding", "margin", "background_color", "background_image", "border_color", "border_width",
             "border_radius", "opacity", "z_index",
             "onClick", "onFocus", "onBlur", "onHover"
         ];
@@ -963,11 +1271,15 @@ impl SemanticAnalyzer {
 
     fn is_valid_image_property(&self, key: &str) -> bool {
         matches!(key,
-            "src" | "alt" | "fit" | "id" | "pos_x" | "pos_y" | "width" | "height" |
-            "style" | "background_color" | "border_color" | "border_width" |
+            "src" | "alt" | "fit" | "id" | "pos_x" | "pos_y" | "width" | "height" | "aspect_ratio" |
+            "style" | "background_color" | "background_image" | "border_color" | "border_width" |
             "border_radius" | "padding" | "margin" | "opacity" | "visibility" | "visible" | "z_index" |
             // Transform properties
             "transform" |
+            // Visual effects properties
+            "box_shadow" | "filter" | "backdrop_filter" |
+            // Animation properties
+            "animation" | "transition" |
             // Modern Taffy layout properties
             "display" | "flex_direction" | "flex_wrap" | "flex_grow" | "flex_shrink" | "flex_basis" |
             "align_items" | "align_self" | "align_content" | "justify_content" | "justify_items" | "justify_self" |
@@ -986,11 +1298,15 @@ impl SemanticAnalyzer {
         matches!(key,
             "gap" | "id" | "pos_x" | "pos_y" | "width" | "height" |
             "min_width" | "min_height" | "max_width" | "max_height" |
-            "style" | "background_color" | "border_color" | "border_width" |
+            "style" | "background_color" | "background_image" | "border_color" | "border_width" |
             "border_radius" | "padding" | "margin" | "opacity" | "visibility" | "visible" | "z_index" |
-            "list_style_type" |
+            "list_style_type" | "aspect_ratio" |
             // Transform properties
             "transform" |
+            // Visual effects properties
+            "box_shadow" | "filter" | "backdrop_filter" |
+            // Animation properties
+            "animation" | "transition" |
             // Modern Taffy layout properties
             "display" | "flex_direction" | "flex_wrap" | "flex_grow" | "flex_shrink" | "flex_basis" |
             "align_items" | "align_self" | "align_content" | "justify_content" | "justify_items" | "justify_self" |
@@ -1056,11 +1372,11 @@ impl SemanticAnalyzer {
         }
     }
     
-    fn add_string_to_state(&self, text: &str, state: &mut CompilerState) -> Result<u8> {
+    fn add_string_to_state(&self, text: &str, state: &mut CompilerState) -> Result<u16> {
         // Check if string already exists
         for (i, entry) in state.strings.iter().enumerate() {
             if entry.text == text {
-                return Ok(i as u8);
+                return Ok(i as u16);
             }
         }
         
@@ -1072,7 +1388,7 @@ impl SemanticAnalyzer {
             });
         }
         
-        let index = state.strings.len() as u8;
+        let index = state.strings.len() as u16;
         state.strings.push(StringEntry {
             text: text.to_string(),
             length: text.len(),
@@ -1084,15 +1400,97 @@ impl SemanticAnalyzer {
     
     /// Check if a string is a valid CSS color keyword
     fn is_valid_color_keyword(&self, value: &str) -> bool {
-        match value.trim().to_lowercase().as_str() {
-            "transparent" => true,
-            // Can add more color keywords here in the future (red, blue, green, etc.)
-            _ => false,
-        }
+        crate::core::color::named_color(value).is_some()
     }
 }
 
 /// Guess resource type from property key
+/// The style named by a `style:` value, or None if the name is computed
+fn static_style_reference(value: &PropertyValue) -> Option<String> {
+    match value {
+        PropertyValue::String(raw) => {
+            let (name, _) = clean_and_quote_value(raw);
+            (!name.contains('$') && !name.contains("{{")).then_some(name)
+        }
+        PropertyValue::Expression(expr) => match expr.as_ref() {
+            Expression::String(raw) => static_style_reference(&PropertyValue::String(raw.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Gather the styles elements refer to, noting whether any reference is dynamic
+fn collect_style_references(node: &AstNode, used: &mut HashSet<String>, dynamic: &mut bool) {
+    if let AstNode::Element { properties, children, .. } = node {
+        for prop in properties.iter().filter(|p| p.key == "style") {
+            match static_style_reference(&prop.value) {
+                Some(name) => {
+                    used.insert(name);
+                }
+                None => *dynamic = true,
+            }
+        }
+        for child in children {
+            collect_style_references(child, used, dynamic);
+        }
+    }
+}
+
+/// Descriptors of a `font` block; the shorthand form has the defaults
+#[derive(Debug, Clone, PartialEq)]
+struct FontDescriptors {
+    weight: u16,
+    style: FontStyle,
+    fallbacks: Vec<String>,
+}
+
+impl Default for FontDescriptors {
+    fn default() -> Self {
+        Self { weight: 400, style: FontStyle::Normal, fallbacks: Vec::new() }
+    }
+}
+
+/// Read `weight`, `style` and `fallback` (a comma-separated list of families)
+fn font_descriptors(properties: &[AstProperty]) -> std::result::Result<FontDescriptors, (Option<Span>, String)> {
+    let mut descriptors = FontDescriptors::default();
+    for prop in properties {
+        let value = prop.cleaned_value();
+        let invalid = |message: String| (prop.location.clone(), message);
+        match prop.key.as_str() {
+            "weight" => {
+                descriptors.weight = match value.to_lowercase().as_str() {
+                    "normal" => 400,
+                    "bold" => 700,
+                    number => number
+                        .parse::<u16>()
+                        .ok()
+                        .filter(|weight| (1..=1000).contains(weight))
+                        .ok_or_else(|| invalid(format!("Font weight '{}' must be normal, bold or 1 to 1000", value)))?,
+                };
+            }
+            "style" => {
+                descriptors.style = FontStyle::from_name(&value)
+                    .ok_or_else(|| invalid(format!("Font style '{}' must be normal, italic or oblique", value)))?;
+            }
+            "fallback" => {
+                descriptors.fallbacks = value
+                    .split(',')
+                    .map(|family| family.trim().trim_matches('"').to_string())
+                    .filter(|family| !family.is_empty())
+                    .collect();
+            }
+            other => {
+                return Err(invalid(format!(
+                    "Unknown font descriptor '{}' (expected src, weight, style or fallback)",
+                    other
+                )));
+            }
+        }
+    }
+    Ok(descriptors)
+}
+
 fn guess_resource_type(key: &str) -> ResourceType {    
     let lower_key = key.to_lowercase();
     
@@ -1115,13 +1513,17 @@ fn guess_resource_type(key: &str) -> ResourceType {
 
 pub fn convert_ast_to_state(ast: &AstNode, state: &mut CompilerState) -> Result<()> {
     match ast {
-        AstNode::File { app, styles, fonts, components, scripts, directives } => {
+        AstNode::File { app, styles, fonts, keyframes, components, scripts, .. } => {
             // Process styles first since elements may reference them
             for style_node in styles {
-                if let AstNode::Style { name, extends: _, properties, pseudo_selectors } = style_node {
+                if let AstNode::Style { name, extends: _, properties, pseudo_selectors, media_queries, location } = style_node {
                     // Convert style properties to KRB format first
                     let mut krb_properties = Vec::new();
                     for ast_prop in properties {
+                        // Animations are converted once the keyframes are in the animation table
+                        if ast_prop.key == "animation" || ast_prop.key == "transition" {
+                            continue;
+                        }
                         // Expand shorthand properties
                         let expanded_props = expand_shorthand_property(ast_prop)?;
                         for expanded_prop in expanded_props {
@@ -1134,23 +1536,25 @@ pub fn convert_ast_to_state(ast: &AstNode, state: &mut CompilerState) -> Result<
                     // Process pseudo-selectors and convert to state property sets
                     // This is a simplified implementation for now
                     if !pseudo_selectors.is_empty() {
-                        println!("Style '{}' has {} pseudo-selectors (processed)", name, pseudo_selectors.len());
+                        log::debug!("Style '{}' has {} pseudo-selectors (processed)", name, pseudo_selectors.len());
                         // Note: Full state property implementation requires substantial changes to
                         // the style system to properly store and apply state-based properties.
                         // For now, the renderer will need to use the existing compute_with_state method.
                     }
+                    let media_property_sets = convert_media_queries(media_queries, state)?;
                     
                     // Find existing style by name (created in semantic analysis phase)
                     if let Some(existing_style) = state.styles.iter_mut().find(|s| s.source_name == *name) {
                         // Update existing style with KRB properties instead of creating duplicate
                         existing_style.properties = krb_properties;
+                        existing_style.media_property_sets = media_property_sets;
                         existing_style.is_resolved = true;
                         existing_style.is_resolving = false;
                         
-                        println!("Updated existing style '{}' with ID {} and {} properties", name, existing_style.id, existing_style.properties.len());
+                        log::debug!("Updated existing style '{}' with ID {} and {} properties", name, existing_style.id, existing_style.properties.len());
                     } else {
                         // Style not found - create new one (fallback case)
-                        let style_id = (state.styles.len() + 1) as u8; // 1-based style IDs
+                        let style_id = (state.styles.len() + 1) as u16; // 1-based style IDs
                         
                         // Add style name to string table
                         let name_index = state.add_string(name.clone())?;
@@ -1166,37 +1570,113 @@ pub fn convert_ast_to_state(ast: &AstNode, state: &mut CompilerState) -> Result<
                                 key: p.key.clone(),
                                 value: p.value.to_string(),
                                 line_num: p.line,
+                                location: p.location.clone(),
                             }).collect(),
+                            media_property_sets,
+                            animations: Vec::new(),
                             calculated_size: 0, // Will be calculated later
                             is_resolved: true,
                             is_resolving: false,
+                            location: location.clone(),
                         };
                         
-                        println!("Added new style '{}' with ID {} and {} properties", name, style_id, style_entry.properties.len());
+                        log::debug!("Added new style '{}' with ID {} and {} properties", name, style_id, style_entry.properties.len());
                         state.styles.push(style_entry);
                     }
                 }
             }
             
-            // Process fonts
+            // Process fonts; every declaration becomes a font resource describing one face
             for font_node in fonts {
-                if let AstNode::Font { name, path } = font_node {
-                    // Add font name to string table
-                    let name_index = state.add_string(name.clone())?;
+                if let AstNode::Font { name, path, properties, location } = font_node {
+                    let descriptors = font_descriptors(properties).map_err(|(span, message)| {
+                        let span = span.or_else(|| location.clone());
+                        span_error(span.as_ref(), span.as_ref().map_or(0, |l| l.line), message)
+                    })?;
                     
-                    // Add font path to string table
+                    let name_index = state.add_string(name.clone())?;
                     let path_index = state.add_string(path.clone())?;
+                    let fallback_indices = descriptors
+                        .fallbacks
+                        .iter()
+                        .map(|fallback| state.add_string(fallback.clone()))
+                        .collect::<Result<Vec<_>>>()?;
+                    
+                    if state.resources.len() >= MAX_RESOURCES {
+                        return Err(CompilerError::LimitExceeded {
+                            limit_type: "resources".to_string(),
+                            limit: MAX_RESOURCES,
+                        });
+                    }
+                    let resource_index = state.resources.len() as u16;
+                    state.resources.push(ResourceEntry {
+                        resource_type: ResourceType::Font,
+                        name_index,
+                        format: ResourceFormat::External,
+                        data_string_index: path_index,
+                        index: resource_index,
+                        font: Some(FontFace {
+                            weight: descriptors.weight,
+                            style: descriptors.style,
+                            fallback_indices,
+                        }),
+                        data: Vec::new(),
+                        shared_with: None,
+                        location: location.clone(),
+                        calculated_size: 0,
+                    });
+                    state.header_flags |= FLAG_HAS_RESOURCES;
                     
-                    // Create font entry
-                    let font_entry = FontEntry {
+                    state.fonts.push(FontEntry {
                         name: name.clone(),
                         path: path.clone(),
                         name_index,
                         path_index,
+                        weight: descriptors.weight,
+                        style: descriptors.style,
+                        fallbacks: descriptors.fallbacks,
+                        resource_index,
+                        location: location.clone(),
+                    });
+                }
+            }
+            
+            // Process keyframes into the animation table; a later definition replaces an earlier one
+            for keyframes_node in keyframes {
+                if let AstNode::Keyframes { name, frames, .. } = keyframes_node {
+                    let mut entry = AnimationEntry {
+                        name: name.clone(),
+                        name_index: state.add_string(name.clone())?,
+                        keyframes: Vec::new(),
+                        calculated_size: 0,
                     };
-                    
-                    println!("Added font '{}' with path '{}'", name, path);
-                    state.fonts.push(font_entry);
+                    for frame in frames {
+                        let mut properties = Vec::new();
+                        for ast_prop in &frame.properties {
+                            if let Some(krb_prop) = convert_ast_property_to_krb(ast_prop, state)? {
+                                properties.push(krb_prop);
+                            }
+                        }
+                        entry.keyframes.push(KeyframeEntry { offset: frame.offset.round() as u8, properties });
+                    }
+                    entry.keyframes.sort_by_key(|frame| frame.offset);
+
+                    match state.animations.iter_mut().find(|a| a.name == *name) {
+                        Some(existing) => *existing = entry,
+                        None => state.animations.push(entry),
+                    }
+                    state.header_flags |= FLAG_HAS_ANIMATIONS;
+                }
+            }
+            for style_node in styles {
+                if let AstNode::Style { name, properties, .. } = style_node {
+                    let mut animations = Vec::new();
+                    for ast_prop in properties.iter().filter(|p| p.key == "animation" || p.key == "transition") {
+                        animations.extend(animation_refs(ast_prop, state)?);
+                    }
+                    if let Some(style) = state.styles.iter_mut().find(|s| s.source_name == *name) {
+                        style.animations = animations;
+                    }
                 }
             }
             
@@ -1290,18 +1770,19 @@ fn convert_element_to_state(
     state: &mut CompilerState, 
     parent_index: Option<usize>
 ) -> Result<usize> {
-    if let AstNode::Element { element_type, properties, children, pseudo_selectors } = ast_element {
+    if let AstNode::Element { element_type, properties, children, pseudo_selectors, media_queries, location, origin } = ast_element {
         let element_index = state.elements.len();
         
         let mut element = Element {
             element_type: ElementType::from_name(element_type),
             id_string_index: 0, pos_x: 0, pos_y: 0, width: 0, height: 0, layout: 0, style_id: 0, checked: false,
             property_count: 0, child_count: 0, event_count: 0, animation_count: 0, custom_prop_count: 0, state_prop_count: 0,
-            krb_properties: Vec::new(), krb_custom_properties: Vec::new(), krb_events: Vec::new(),
+            krb_properties: Vec::new(), krb_custom_properties: Vec::new(), krb_events: Vec::new(), krb_animations: Vec::new(),
             state_property_sets: Vec::new(), children: Vec::new(), parent_index, self_index: element_index,
             is_component_instance: false, component_def: None, is_definition_root: false,
             source_element_name: element_type.clone(), source_id_name: String::new(), source_properties: Vec::new(),
-            source_children_indices: Vec::new(), source_line_num: 0, layout_flags_source: 0,
+            source_children_indices: Vec::new(), source_line_num: location.as_ref().map_or(0, |l| l.line),
+            source_location: location.clone(), component_origin: origin.clone(), layout_flags_source: 0,
             position_hint: String::new(), orientation_hint: String::new(), calculated_size: KRB_ELEMENT_HEADER_SIZE as u32,
             absolute_offset: 0, processed_in_pass: false,
         };
@@ -1312,6 +1793,7 @@ fn convert_element_to_state(
                 key: ast_prop.key.clone(),
                 value: ast_prop.value.to_string(),
                 line_num: ast_prop.line,
+                location: ast_prop.location.clone(),
             });
             
             match ast_prop.key.as_str() {
@@ -1324,12 +1806,12 @@ fn convert_element_to_state(
                     // Store the element ID string in the string table and set the index
                     let id_string = ast_prop.cleaned_value();
                     let string_index = if let Some(entry) = state.strings.iter().position(|s| s.text == id_string) {
-                        entry as u8
+                        entry as u16
                     } else {
                         state.add_string(id_string.to_string())?
                     };
                     element.id_string_index = string_index;
-                    println!("Set element ID '{}' to string index {}", id_string, string_index);
+                    log::debug!("Set element ID '{}' to string index {}", id_string, string_index);
                 },
                 "style" => {
                     let style_name = ast_prop.cleaned_value();
@@ -1340,7 +1822,7 @@ fn convert_element_to_state(
                 "checked" => {
                     let checked_value = ast_prop.cleaned_value();
                     element.checked = checked_value == "true";
-                    println!("Set element checked state to {}", element.checked);
+                    log::debug!("Set element checked state to {}", element.checked);
                 },
 
                 // --- THIS IS THE CORRECTED LOGIC ---
@@ -1352,44 +1834,47 @@ fn convert_element_to_state(
                     let (func_name, params) = parse_function_call(&func_call)?;
                     
                     // Store the function call with parameters for runtime processing
-                    let callback_id = state.add_string(&func_call)? as u8;
+                    let callback_id = state.add_string(&func_call)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_CLICK, callback_id });
                 },
                 "onPress" => {
                     let func_name = ast_prop.cleaned_value();
-                    let callback_id = state.add_string(&func_name)? as u8;
+                    let callback_id = state.add_string(&func_name)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_PRESS, callback_id });
                 },
                 "onRelease" => {
                     let func_name = ast_prop.cleaned_value();
-                    let callback_id = state.add_string(&func_name)? as u8;
+                    let callback_id = state.add_string(&func_name)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_RELEASE, callback_id });
                 },
                 "onHover" => {
                     let func_name = ast_prop.cleaned_value();
-                    let callback_id = state.add_string(&func_name)? as u8;
+                    let callback_id = state.add_string(&func_name)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_HOVER, callback_id });
                 },
                 "onFocus" => {
                     let func_name = ast_prop.cleaned_value();
-                    let callback_id = state.add_string(&func_name)? as u8;
+                    let callback_id = state.add_string(&func_name)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_FOCUS, callback_id });
                 },
                 "onBlur" => {
                     let func_name = ast_prop.cleaned_value();
-                    let callback_id = state.add_string(&func_name)? as u8;
+                    let callback_id = state.add_string(&func_name)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_BLUR, callback_id });
                 },
                 "onChange" => {
                     let func_name = ast_prop.cleaned_value();
-                    let callback_id = state.add_string(&func_name)? as u8;
+                    let callback_id = state.add_string(&func_name)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_CHANGE, callback_id });
                 },
                 "onSubmit" => {
                     let func_name = ast_prop.cleaned_value();
-                    let callback_id = state.add_string(&func_name)? as u8;
+                    let callback_id = state.add_string(&func_name)?;
                     element.krb_events.push(KrbEvent { event_type: EVENT_TYPE_SUBMIT, callback_id });
                 },
+                "animation" | "transition" => {
+                    element.krb_animations.extend(animation_refs(ast_prop, state)?);
+                },
 
                 // Default case for all other standard properties
                 _ => {
@@ -1419,16 +1904,21 @@ fn convert_element_to_state(
                 }
                 element.state_property_sets.push(StatePropertySet {
                     state_flags: state_flag,
-                    property_count: state_props.len() as u8,
+                    property_count: state_props.len() as u16,
                     properties: state_props,
                 });
             }
         }
+        element.state_property_sets.extend(convert_media_queries(media_queries, state)?);
         
         // Finalize counts in the element header before adding it to the state
-        element.property_count = element.krb_properties.len() as u8;
-        element.state_prop_count = element.state_property_sets.len() as u8;
-        element.event_count = element.krb_events.len() as u8;
+        element.property_count = element.krb_properties.len() as u16;
+        element.state_prop_count = element.state_property_sets.len() as u16;
+        element.event_count = element.krb_events.len() as u16;
+        element.animation_count = element.krb_animations.len() as u16;
+        if !element.krb_animations.is_empty() {
+            state.header_flags |= FLAG_HAS_ANIMATIONS;
+        }
         
         state.elements.push(element);
         
@@ -1441,7 +1931,7 @@ fn convert_element_to_state(
         
         // Update the element in the state with its new child references
         state.elements[element_index].children = child_indices;
-        state.elements[element_index].child_count = state.elements[element_index].children.len() as u8;
+        state.elements[element_index].child_count = state.elements[element_index].children.len() as u16;
 
         Ok(element_index)
     } else {
@@ -1449,75 +1939,31 @@ fn convert_element_to_state(
     }
 }
 
-
-fn parse_calc_expression(expr: &str) -> Result<f32> {
-    // Simple calc() parser for basic expressions like:
-    // calc(100% - 20px), calc(50vw + 10px), calc(1.5em * 2)
-    let expr = expr.trim();
-    
-    // Remove calc() wrapper
-    if expr.starts_with("calc(") && expr.ends_with(")") {
-        let inner = &expr[5..expr.len()-1].trim();
-        
-        // For now, support simple binary operations
-        if let Some(pos) = inner.find(" + ") {
-            let left = &inner[..pos].trim();
-            let right = &inner[pos + 3..].trim();
-            return Ok(parse_calc_value(left)? + parse_calc_value(right)?);
-        } else if let Some(pos) = inner.find(" - ") {
-            let left = &inner[..pos].trim();
-            let right = &inner[pos + 3..].trim();
-            return Ok(parse_calc_value(left)? - parse_calc_value(right)?);
-        } else if let Some(pos) = inner.find(" * ") {
-            let left = &inner[..pos].trim();
-            let right = &inner[pos + 3..].trim();
-            return Ok(parse_calc_value(left)? * parse_calc_value(right)?);
-        } else if let Some(pos) = inner.find(" / ") {
-            let left = &inner[..pos].trim();
-            let right = &inner[pos + 3..].trim();
-            let right_val = parse_calc_value(right)?;
-            if right_val != 0.0 {
-                return Ok(parse_calc_value(left)? / right_val);
-            }
-        }
-        
-        // Single value
-        return parse_calc_value(inner);
+/// The animation references of an `animation:` or `transition:` property
+fn animation_refs(ast_prop: &AstProperty, state: &CompilerState) -> Result<Vec<KrbAnimationRef>> {
+    if ast_prop.key == "animation" {
+        let spec = parse_animation_spec(&ast_prop.cleaned_value())
+            .map_err(|message| property_error(ast_prop, message))?;
+        let index = state.animations.iter().position(|a| a.name == spec.keyframes).ok_or_else(|| {
+            property_error(ast_prop, format!("@keyframes '{}' is not defined", spec.keyframes))
+        })?;
+        Ok(vec![animation_ref(ANIMATION_KIND_KEYFRAMES, index as u16, &spec.timing)])
+    } else {
+        let specs = parse_transition_specs(&ast_prop.cleaned_value())
+            .map_err(|message| property_error(ast_prop, message))?;
+        Ok(specs.iter().map(|spec| animation_ref(ANIMATION_KIND_TRANSITION, spec.property_id as u16, &spec.timing)).collect())
     }
-    
-    Err(CompilerError::semantic_legacy(0, format!("Invalid calc() expression: {}", expr)))
 }
 
-fn parse_calc_value(value: &str) -> Result<f32> {
-    let value = value.trim();
-    
-    // Handle different units
-    if value.ends_with("px") {
-        let num_str = &value[..value.len()-2];
-        num_str.parse::<f32>().map_err(|_| CompilerError::semantic_legacy(0, format!("Invalid px value: {}", value)))
-    } else if value.ends_with("%") {
-        let num_str = &value[..value.len()-1];
-        // Convert percentage to decimal (50% -> 0.5)
-        num_str.parse::<f32>().map(|v| v / 100.0).map_err(|_| CompilerError::semantic_legacy(0, format!("Invalid % value: {}", value)))
-    } else if value.ends_with("em") {
-        let num_str = &value[..value.len()-2];
-        // Assume 1em = 16px for calc purposes
-        num_str.parse::<f32>().map(|v| v * 16.0).map_err(|_| CompilerError::semantic_legacy(0, format!("Invalid em value: {}", value)))
-    } else if value.ends_with("rem") {
-        let num_str = &value[..value.len()-3];
-        // Assume 1rem = 16px for calc purposes
-        num_str.parse::<f32>().map(|v| v * 16.0).map_err(|_| CompilerError::semantic_legacy(0, format!("Invalid rem value: {}", value)))
-    } else if value.ends_with("vw") {
-        let num_str = &value[..value.len()-2];
-        // Assume 1vw = 8px for calc purposes (800px viewport)
-        num_str.parse::<f32>().map(|v| v * 8.0).map_err(|_| CompilerError::semantic_legacy(0, format!("Invalid vw value: {}", value)))
-    } else if value.ends_with("vh") {
-        let num_str = &value[..value.len()-2];
-        // Assume 1vh = 6px for calc purposes (600px viewport)
-        num_str.parse::<f32>().map(|v| v * 6.0).map_err(|_| CompilerError::semantic_legacy(0, format!("Invalid vh value: {}", value)))
-    } else {
-        // Pure number
-        value.parse::<f32>().map_err(|_| CompilerError::semantic_legacy(0, format!("Invalid numeric value: {}", value)))
+fn animation_ref(kind: u8, target: u16, timing: &AnimationTiming) -> KrbAnimationRef {
+    KrbAnimationRef {
+        kind,
+        target,
+        duration_ms: timing.duration_ms,
+        delay_ms: timing.delay_ms,
+        easing: timing.easing as u8,
+        iterations: timing.iterations,
+        direction: timing.direction as u8,
     }
 }
 
@@ -1542,12 +1988,17 @@ fn parse_space_separated_values(value_str: &str) -> Vec<PropertyValue> {
 /// Expand shorthand properties into individual properties
 fn expand_shorthand_property(ast_prop: &AstProperty) -> Result<Vec<AstProperty>> {
     // Check if this is a shorthand property
-    match ast_prop.key.as_str() {
-        "margin" => expand_margin_shorthand(ast_prop),
-        "padding" => expand_padding_shorthand(ast_prop),
-        "border" => expand_border_shorthand(ast_prop),
-        _ => Ok(vec![ast_prop.clone()]), // Not a shorthand, return as-is
+    let mut expanded = match ast_prop.key.as_str() {
+        "margin" => expand_margin_shorthand(ast_prop)?,
+        "padding" => expand_padding_shorthand(ast_prop)?,
+        "border" => expand_border_shorthand(ast_prop)?,
+        _ => return Ok(vec![ast_prop.clone()]), // Not a shorthand, return as-is
+    };
+    // Errors in an expanded value point at the shorthand it came from
+    for prop in &mut expanded {
+        prop.location = ast_prop.location.clone();
     }
+    Ok(expanded)
 }
 
 /// Expand margin shorthand: margin: 10 0 -> margin_top: 10, margin_right: 0, etc.
@@ -1720,20 +2171,18 @@ fn expand_box_model_values(values: &[PropertyValue], property_prefix: &str, line
 }
 
 fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState) -> Result<Option<KrbProperty>> {
-    let cleaned_value = ast_prop.cleaned_value();
-    
-    // Check for calc() expressions first
-    let processed_value = if cleaned_value.starts_with("calc(") {
-        match parse_calc_expression(&cleaned_value) {
-            Ok(result) => format!("{}", result),
-            Err(_) => {
-                // If calc() parsing fails, store as string for runtime evaluation
-                cleaned_value.clone()
-            }
+    // Arithmetic the expression pass left alone uses a variable that is not an @variables entry
+    if let PropertyValue::Expression(expr) = &ast_prop.value {
+        if expr.is_arithmetic() {
+            let lookup = |name: &str| state.variables.get(name).map(|def| def.value.clone());
+            let value = evaluate(expr, &lookup)
+                .map_err(|message| property_error(ast_prop, format!("Invalid {} expression '{}': {}", ast_prop.key, expr.to_string(), message)))?;
+            let folded = AstProperty { value: value.into_property_value(), ..ast_prop.clone() };
+            return convert_ast_property_to_krb(&folded, state);
         }
-    } else {
-        cleaned_value.clone()
-    };
+    }
+
+    let cleaned_value = ast_prop.cleaned_value();
     
     // Use the comprehensive mapping from PropertyId::from_name()
     let property_id = PropertyId::from_name(&ast_prop.key);
@@ -1743,6 +2192,20 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
         return Ok(None); // Will be handled as custom property elsewhere
     }
     
+    // Lengths in different units are combined by the renderer
+    if let PropertyValue::Calc(calc) = &ast_prop.value {
+        if !property_id.is_length() {
+            return Err(property_error(ast_prop, format!("{} does not take a length, so it cannot be '{}'", ast_prop.key, calc)));
+        }
+        let value = calc.encode().map_err(|message| property_error(ast_prop, format!("Invalid {} '{}': {}", ast_prop.key, calc, message)))?;
+        return encoded_property(ast_prop, property_id, ValueType::Calc, value).map(Some);
+    }
+    
+    // A theme token is resolved by the renderer from the active theme
+    if let Some(token) = cleaned_value.strip_prefix('$').filter(|token| state.is_theme_token(token)) {
+        return convert_theme_token(ast_prop, property_id, token, state).map(Some);
+    }
+    
     // Special handling for text property with array values
     if property_id == PropertyId::TextContent && matches!(ast_prop.value, PropertyValue::Array(_)) {
         if let PropertyValue::Array(ref array_values) = ast_prop.value {
@@ -1756,12 +2219,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             }
             let joined_text = lines.join("\n");
             let string_index = state.add_string(joined_text)?;
-            return Ok(Some(KrbProperty {
-                property_id: property_id as u8,
-                value_type: ValueType::String,
-                size: 1,
-                value: vec![string_index],
-            }));
+            return Ok(Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index)));
         }
     }
     
@@ -1771,12 +2229,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             if cleaned_value.starts_with('$') {
                 // This is a template variable - store it as a string for later resolution
                 let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty { 
-                    property_id: property_id as u8, 
-                    value_type: ValueType::TemplateVariable,
-                    size: 1, 
-                    value: vec![string_index] 
-                })
+                Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
             } else if let Ok(color) = parse_color(&cleaned_value) {
                 Some(KrbProperty {
                     property_id: property_id as u8,
@@ -1788,6 +2241,29 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
                 return Err(CompilerError::semantic_legacy(ast_prop.line, format!("Invalid color value: {}", cleaned_value)));
             }
         }
+        PropertyId::BackgroundImage => match &ast_prop.value {
+            PropertyValue::Gradient(gradient) => {
+                let value = gradient.encode().map_err(|message| property_error(ast_prop, message))?;
+                Some(encoded_property(ast_prop, property_id, ValueType::Gradient, value)?)
+            }
+            _ if cleaned_value.starts_with('$') => {
+                let string_index = state.add_string(cleaned_value.clone())?;
+                Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
+            }
+            // A theme token or variable may hold a gradient as text
+            _ => match Parser::parse_gradient_source(&cleaned_value) {
+                Some(gradient) => {
+                    let value = gradient.and_then(|gradient| gradient.encode()).map_err(|message| property_error(ast_prop, message))?;
+                    Some(encoded_property(ast_prop, property_id, ValueType::Gradient, value)?)
+                }
+                None => {
+                    return Err(property_error(
+                        ast_prop,
+                        format!("background_image must be a linear-gradient() or radial-gradient(), not '{}'", cleaned_value),
+                    ));
+                }
+            },
+        },
         PropertyId::BorderWidth | PropertyId::BorderRadius | PropertyId::Padding | PropertyId::Margin | PropertyId::Gap => {
             if let Ok(val) = cleaned_value.parse::<u8>() {
                 Some(KrbProperty { property_id: property_id as u8, value_type: ValueType::Byte, size: 1, value: vec![val] })
@@ -1839,12 +2315,12 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
         }
         PropertyId::FontFamily => {
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty { property_id: property_id as u8, value_type: ValueType::String, size: 1, value: vec![string_index] })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
         }
         PropertyId::TextContent | PropertyId::WindowTitle => {
             // Keep variables as placeholders for reactive template system
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty { property_id: property_id as u8, value_type: ValueType::String, size: 1, value: vec![string_index] })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
         }
         PropertyId::Height | PropertyId::Width | PropertyId::Top | PropertyId::Left => {
             // Parse numeric value as u16 or percentage
@@ -1863,12 +2339,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             } else if cleaned_value.starts_with('$') {
                 // This is a template variable - store it as a string for later resolution
                 let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty { 
-                    property_id: property_id as u8, 
-                    value_type: ValueType::TemplateVariable, // Special marker for template variables
-                    size: 1, 
-                    value: vec![string_index] 
-                })
+                Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
             } else {
                 return Err(CompilerError::semantic_legacy(ast_prop.line, format!("Invalid numeric value for {}: {} (must be a number or percentage)", ast_prop.key, cleaned_value)));
             }
@@ -1879,12 +2350,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
         PropertyId::JustifyItems | PropertyId::JustifySelf | PropertyId::AlignSelf => {
             // Store as string index like other string properties
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty { 
-                property_id: property_id as u8, 
-                value_type: ValueType::String, 
-                size: 1, 
-                value: vec![string_index] 
-            })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
         }
         PropertyId::FlexGrow | PropertyId::FlexShrink => {
             // Store as float values
@@ -1898,12 +2364,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             } else if cleaned_value.starts_with('$') {
                 // This is a template variable - store it as a string for later resolution
                 let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::TemplateVariable,
-                    size: 1,
-                    value: vec![string_index],
-                })
+                Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
             } else {
                 return Err(CompilerError::semantic_legacy(ast_prop.line, format!("Invalid float value for {}: {}", ast_prop.key, cleaned_value)));
             }
@@ -1925,15 +2386,30 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
                 value: vec![input_type_value] 
             })
         }
-        PropertyId::ImageSource => {
-            // Store image source path as string
+        PropertyId::ImageSource | PropertyId::Icon if ast_prop.has_templates || !state.embed_resources => {
+            // A path built at runtime cannot be a resource, and one that is not embedded needs none
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty { 
-                property_id: property_id as u8, 
-                value_type: ValueType::String, 
-                size: 1, 
-                value: vec![string_index] 
-            })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
+        }
+        PropertyId::ImageSource | PropertyId::Icon => {
+            // Embedded files are resources; the extension tells sounds and videos from images
+            let resource_type = ResourceType::from_extension(&cleaned_value).unwrap_or(ResourceType::Image);
+            let resource_index = state.add_resource(resource_type, &cleaned_value, ast_prop.location.clone())?;
+            Some(KrbProperty::with_index(property_id as u8, ValueType::Resource, resource_index))
+        }
+        PropertyId::Opacity => {
+            // Same 8.8 fixed point encoding the style resolver uses
+            match cleaned_value.parse::<f64>() {
+                Ok(opacity) if (0.0..=1.0).contains(&opacity) => Some(KrbProperty {
+                    property_id: property_id as u8,
+                    value_type: ValueType::Percentage,
+                    size: 2,
+                    value: float_to_fixed_point(opacity).to_le_bytes().to_vec(),
+                }),
+                _ => {
+                    return Err(CompilerError::semantic_legacy(ast_prop.line, format!("Opacity must be a number between 0.0 and 1.0: {}", cleaned_value)));
+                }
+            }
         }
         PropertyId::ZIndex => {
             if let Ok(val) = cleaned_value.parse::<i16>() {
@@ -1952,12 +2428,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             if cleaned_value.starts_with('$') {
                 // This is a template variable - store it as a string for later resolution
                 let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::TemplateVariable,
-                    size: 1,
-                    value: vec![string_index],
-                })
+                Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
             } else {
                 let visible = match cleaned_value.to_lowercase().as_str() {
                     "true" | "visible" | "1" => true,
@@ -1983,32 +2454,17 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
         PropertyId::GridTemplateColumns | PropertyId::GridTemplateRows => {
             // Store grid template as string (e.g., "1fr 200px 1fr", "repeat(3, 1fr)")
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty {
-                property_id: property_id as u8,
-                value_type: ValueType::String,
-                size: 1,
-                value: vec![string_index],
-            })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
         }
         PropertyId::GridTemplateAreas => {
             // Store grid template areas as string (e.g., "header header" "sidebar content")
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty {
-                property_id: property_id as u8,
-                value_type: ValueType::String,
-                size: 1,
-                value: vec![string_index],
-            })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
         }
         PropertyId::GridArea => {
             // Store grid area as string (e.g., "header", "1 / 2 / 3 / 4")
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty {
-                property_id: property_id as u8,
-                value_type: ValueType::String,
-                size: 1,
-                value: vec![string_index],
-            })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
         }
         PropertyId::GridAutoFlow => {
             let flow_val = match cleaned_value.to_lowercase().as_str() {
@@ -2028,12 +2484,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
         PropertyId::GridColumn | PropertyId::GridRow => {
             // Store grid line specification as string (e.g., "1 / 3", "span 2")
             let string_index = state.add_string(cleaned_value.clone())?;
-            Some(KrbProperty {
-                property_id: property_id as u8,
-                value_type: ValueType::String,
-                size: 1,
-                value: vec![string_index],
-            })
+            Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
         }
         PropertyId::GridColumnStart | PropertyId::GridColumnEnd | PropertyId::GridRowStart | PropertyId::GridRowEnd => {
             // Parse grid line as integer or store as string for named lines
@@ -2047,12 +2498,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             } else {
                 // Named grid line
                 let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::String,
-                    size: 1,
-                    value: vec![string_index],
-                })
+                Some(KrbProperty::with_index(property_id as u8, ValueType::String, string_index))
             }
         }
         
@@ -2092,12 +2538,7 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             if cleaned_value.starts_with('$') {
                 // This is a template variable - store it as a string for later resolution
                 let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty { 
-                    property_id: property_id as u8, 
-                    value_type: ValueType::TemplateVariable,
-                    size: 1, 
-                    value: vec![string_index] 
-                })
+                Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
             } else if let Ok(color) = parse_color(&cleaned_value) {
                 Some(KrbProperty {
                     property_id: property_id as u8,
@@ -2397,44 +2838,25 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
         }
         
         // Visual Effects Properties
+        PropertyId::BoxShadow | PropertyId::TextShadow | PropertyId::Filter | PropertyId::BackdropFilter
+            if cleaned_value.starts_with('$') =>
+        {
+            let string_index = state.add_string(cleaned_value.clone())?;
+            Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
+        }
         PropertyId::BoxShadow | PropertyId::TextShadow => {
-            // Store shadow definition as string (e.g., "2px 2px 4px rgba(0,0,0,0.5)" or "none")
-            if cleaned_value.to_lowercase() == "none" {
-                Some(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::Enum,
-                    size: 1,
-                    value: vec![0u8], // 0 = none
-                })
-            } else {
-                // Store complex shadow as string for parsing by renderer
-                let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::String,
-                    size: 1,
-                    value: vec![string_index],
-                })
-            }
+            let variables = |name: &str| state.variables.get(name).map(|variable| variable.value.clone());
+            let shadows = parse_shadows(&cleaned_value, property_id == PropertyId::TextShadow, &variables)
+                .map_err(|message| property_error(ast_prop, format!("Invalid {} '{}': {}", ast_prop.key, cleaned_value, message)))?;
+            let value = encode_shadows(&shadows).map_err(|message| property_error(ast_prop, message))?;
+            Some(encoded_property(ast_prop, property_id, ValueType::Shadow, value)?)
         }
         PropertyId::Filter | PropertyId::BackdropFilter => {
-            // Store filter functions as string (e.g., "blur(5px)", "brightness(150%)")
-            if cleaned_value.to_lowercase() == "none" {
-                Some(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::Enum,
-                    size: 1,
-                    value: vec![0u8], // 0 = none
-                })
-            } else {
-                let string_index = state.add_string(cleaned_value.clone())?;
-                Some(KrbProperty {
-                    property_id: property_id as u8,
-                    value_type: ValueType::String,
-                    size: 1,
-                    value: vec![string_index],
-                })
-            }
+            let variables = |name: &str| state.variables.get(name).map(|variable| variable.value.clone());
+            let filters = parse_filters(&cleaned_value, &variables)
+                .map_err(|message| property_error(ast_prop, format!("Invalid {} '{}': {}", ast_prop.key, cleaned_value, message)))?;
+            let value = encode_filters(&filters).map_err(|message| property_error(ast_prop, message))?;
+            Some(encoded_property(ast_prop, property_id, ValueType::Filter, value)?)
         }
         
         // Responsive Properties (for media query support)
@@ -2469,8 +2891,259 @@ fn convert_ast_property_to_krb(ast_prop: &AstProperty, state: &mut CompilerState
             }
         }
         
+        PropertyId::AspectRatio => {
+            // Width over height, as a number (1.5) or a ratio (16/9)
+            let ratio = match cleaned_value.split_once('/') {
+                Some((width, height)) => width.trim().parse::<f32>().ok().zip(height.trim().parse::<f32>().ok())
+                    .filter(|&(_, height)| height != 0.0)
+                    .map(|(width, height)| width / height),
+                None => cleaned_value.parse::<f32>().ok(),
+            };
+            match ratio {
+                Some(ratio) if ratio > 0.0 => Some(KrbProperty {
+                    property_id: property_id as u8,
+                    value_type: ValueType::Float,
+                    size: 4,
+                    value: ratio.to_le_bytes().to_vec(),
+                }),
+                _ => {
+                    return Err(property_error(
+                        ast_prop,
+                        format!("Invalid aspect ratio: {} (use a positive number or width/height)", cleaned_value)
+                    ));
+                }
+            }
+        }
+        PropertyId::Transform => {
+            let transform = parse_transform(&cleaned_value)
+                .map_err(|e| property_error(ast_prop, format!("Invalid transform '{}': {}", cleaned_value, e)))?;
+            let index = state.transforms.len() as u16;
+            state.transforms.push(transform);
+            Some(KrbProperty {
+                property_id: property_id as u8,
+                value_type: ValueType::Transform,
+                size: 2,
+                value: index.to_le_bytes().to_vec(),
+            })
+        }
+        
         _ => None, // Should not be reached due to the initial match, but it's safe.
     };
     
     Ok(krb_prop)
 }
+
+/// An error in the value of `ast_prop`, located at the property when its position is known
+fn property_error(ast_prop: &AstProperty, message: String) -> CompilerError {
+    span_error(ast_prop.location.as_ref(), ast_prop.line, message)
+}
+
+/// A property holding an encoded `value`, failing at `ast_prop` instead of truncating its one-byte size
+fn encoded_property(ast_prop: &AstProperty, property_id: PropertyId, value_type: ValueType, value: Vec<u8>) -> Result<KrbProperty> {
+    let size = u8::try_from(value.len()).map_err(|_| {
+        property_error(ast_prop, format!("{} is {} bytes once encoded; at most 255 fit in a property", ast_prop.key, value.len()))
+    })?;
+    Ok(KrbProperty { property_id: property_id as u8, value_type, size, value })
+}
+
+/// An error at `span`, falling back to a bare line number for nodes built without one
+fn span_error(span: Option<&Span>, line: usize, message: String) -> CompilerError {
+    match span {
+        Some(span) => CompilerError::Diagnostics(std::iter::once(Diagnostic::error(message).with_span(Some(span.clone()))).collect()),
+        None => CompilerError::semantic_legacy(line, message),
+    }
+}
+
+/// Convert every `@media` block, reporting the bound errors of all of them together
+fn convert_media_queries(queries: &[MediaQuery], state: &mut CompilerState) -> Result<Vec<StatePropertySet>> {
+    let mut sets = Vec::new();
+    let mut errors = Diagnostics::new();
+    for query in queries {
+        match convert_media_query(query, state) {
+            Ok(set) => sets.push(set),
+            Err(error) => errors.push_error(error),
+        }
+    }
+    errors.into_result()?;
+    Ok(sets)
+}
+
+/// Convert a `@media` block into a conditional property set. Its viewport bounds
+/// come first, as MinViewportWidth and MaxViewportWidth, then the properties it sets.
+fn convert_media_query(query: &MediaQuery, state: &mut CompilerState) -> Result<StatePropertySet> {
+    let mut errors = Vec::new();
+    let mut width = |bound: &Option<String>| match bound.as_deref().map(|bound| resolve_breakpoint(bound, state)) {
+        Some(Ok(width)) => Some(width),
+        Some(Err(message)) => {
+            errors.push(message);
+            None
+        }
+        None => None,
+    };
+    let (min_width, max_width) = (width(&query.min_width), width(&query.max_width));
+    if let (Some(min), Some(max)) = (min_width, max_width) {
+        if min > max {
+            errors.push(format!("Media query can never match: min-width {}px is larger than max-width {}px", min, max));
+        }
+    }
+    if !errors.is_empty() {
+        let diagnostics = errors.into_iter().map(|message| Diagnostic::error(message).with_span(query.location.clone()));
+        return Err(CompilerError::Diagnostics(diagnostics.collect()));
+    }
+
+    let mut properties = Vec::new();
+    for (width, property_id) in [(min_width, PropertyId::MinViewportWidth), (max_width, PropertyId::MaxViewportWidth)] {
+        if let Some(width) = width {
+            properties.push(KrbProperty {
+                property_id: property_id as u8,
+                value_type: ValueType::Short,
+                size: 2,
+                value: width.to_le_bytes().to_vec(),
+            });
+        }
+    }
+    for ast_prop in &query.properties {
+        for expanded_prop in expand_shorthand_property(ast_prop)? {
+            if let Some(krb_prop) = convert_ast_property_to_krb(&expanded_prop, state)? {
+                properties.push(krb_prop);
+            }
+        }
+    }
+    Ok(StatePropertySet { state_flags: STATE_MEDIA, property_count: properties.len() as u16, properties })
+}
+
+/// A property set to `$token` refers to a slot of the theme table. The token's value in every
+/// theme is converted as this property, and all of them must have the same value type.
+fn convert_theme_token(ast_prop: &AstProperty, property_id: PropertyId, token: &str, state: &mut CompilerState) -> Result<KrbProperty> {
+    let located = |message: String, definition: Option<Span>| {
+        let diagnostic = Diagnostic::error(message)
+            .with_span(definition)
+            .with_note(format!("{} uses the token here", ast_prop.key), ast_prop.location.clone());
+        CompilerError::Diagnostics(std::iter::once(diagnostic).collect())
+    };
+    
+    let mut slot_type: Option<(ValueType, String)> = None;
+    let mut values = Vec::new();
+    for theme_index in 0..state.themes.len() {
+        let theme = &state.themes[theme_index];
+        let theme_name = theme.name.clone();
+        let definition = theme.token(token).cloned().ok_or_else(|| {
+            located(format!("theme '{}' does not define token '{}'", theme_name, token), theme.location.clone())
+        })?;
+        let location = definition.location.clone();
+        let value = AstProperty { key: ast_prop.key.clone(), ..definition };
+        let converted = convert_ast_property_to_krb(&value, state).map_err(|error| {
+            let message = match &error {
+                CompilerError::Diagnostics(diagnostics) => diagnostics.iter().next().map(|d| d.message.clone()).unwrap_or_default(),
+                error => Diagnostic::from_error(error).message,
+            };
+            located(message, location.clone())
+        })?;
+        let Some(converted) = converted else {
+            return Err(located(format!("token '{}' cannot be used as {}", token, ast_prop.key), location));
+        };
+        match &slot_type {
+            Some((value_type, first_theme)) if *value_type != converted.value_type => {
+                return Err(located(
+                    format!(
+                        "token '{}' is a {:?} in theme '{}' but a {:?} in theme '{}' when used as {}",
+                        token, value_type, first_theme, converted.value_type, theme_name, ast_prop.key
+                    ),
+                    location,
+                ));
+            }
+            Some(_) => {}
+            None => slot_type = Some((converted.value_type, theme_name)),
+        }
+        values.push(converted.value);
+    }
+    
+    let value_type = slot_type.map_or(ValueType::None, |(value_type, _)| value_type);
+    let slot = state.add_theme_slot(token, value_type, values)?;
+    Ok(KrbProperty::with_index(property_id as u8, ValueType::ThemeToken, slot))
+}
+
+/// A media query bound in pixels: `600px`, `600`, or a breakpoint declared in `@variables`
+fn resolve_breakpoint(bound: &str, state: &CompilerState) -> std::result::Result<u16, String> {
+    let value = match bound.strip_prefix('$') {
+        Some(name) => state.variables.get(name)
+            .map(|variable| variable.value.clone())
+            .ok_or_else(|| format!("Unknown breakpoint '{}'; declare it in @variables", bound))?,
+        None => bound.to_string(),
+    };
+    let width = value.trim().trim_matches('"');
+    width.strip_suffix("px").unwrap_or(width).parse::<u16>()
+        .map_err(|_| format!("Invalid breakpoint width '{}' (use whole pixels, such as 600px)", value))
+}
+
+/// Parse a transform list such as `translate(10px, 5px) rotate(45deg)` into a transform table entry
+fn parse_transform(value: &str) -> std::result::Result<TransformData, String> {
+    let mut properties = Vec::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let (name, tail) = rest.split_once('(').ok_or("expected a function such as scale(2)")?;
+        let (args, tail) = tail.split_once(')').ok_or("missing ')'")?;
+        let name = name.trim();
+        let args = args.split(',')
+            .map(|arg| CSSUnitValue::parse(arg).ok_or_else(|| format!("'{}' is not a number", arg.trim())))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rest = tail.trim_start();
+
+        let single = |property_type| match args.as_slice() {
+            [arg] => Ok(vec![(property_type, arg)]),
+            _ => Err(format!("{}() takes one value", name)),
+        };
+        let pair = |x, y| match args.as_slice() {
+            [arg] => Ok(vec![(x, arg)]),
+            [x_arg, y_arg] => Ok(vec![(x, x_arg), (y, y_arg)]),
+            _ => Err(format!("{}() takes one or two values", name)),
+        };
+        let entries = match name {
+            "translate" => pair(TransformPropertyType::TranslateX, TransformPropertyType::TranslateY)?,
+            "skew" => pair(TransformPropertyType::SkewX, TransformPropertyType::SkewY)?,
+            "scale" if args.len() == 1 => single(TransformPropertyType::Scale)?,
+            "scale" => pair(TransformPropertyType::ScaleX, TransformPropertyType::ScaleY)?,
+            "matrix" => {
+                if args.len() != 6 || args.iter().any(|arg| arg.unit != CSSUnit::Number) {
+                    return Err("matrix() takes six plain numbers".to_string());
+                }
+                let value = args.iter().flat_map(|arg| (arg.value as f32).to_le_bytes()).collect();
+                properties.push(TransformProperty {
+                    property_type: TransformPropertyType::Matrix,
+                    value_type: ValueType::TransformMatrix,
+                    value,
+                });
+                continue;
+            }
+            _ => {
+                // translateX and translate_x name the same function
+                let snake: String = name.chars()
+                    .flat_map(|c| if c.is_ascii_uppercase() { vec!['_', c.to_ascii_lowercase()] } else { vec![c] })
+                    .collect();
+                let property_type = TransformPropertyType::from_name(&snake)
+                    .ok_or_else(|| format!("unknown transform function '{}'", name))?;
+                single(property_type)?
+            }
+        };
+        properties.extend(entries.into_iter().map(|(property_type, arg)| TransformProperty {
+            property_type,
+            value_type: ValueType::CSSUnit,
+            value: arg.to_bytes(),
+        }));
+    }
+    if properties.is_empty() {
+        return Err("no transform functions".to_string());
+    }
+
+    let transform_type = if properties.iter().any(|p| matches!(p.property_type,
+        TransformPropertyType::ScaleZ | TransformPropertyType::TranslateZ | TransformPropertyType::RotateX |
+        TransformPropertyType::RotateY | TransformPropertyType::RotateZ | TransformPropertyType::Perspective))
+    {
+        TransformType::Transform3D
+    } else if properties.iter().all(|p| p.property_type == TransformPropertyType::Matrix) {
+        TransformType::Matrix2D
+    } else {
+        TransformType::Transform2D
+    };
+    Ok(TransformData { transform_type, properties })
+}
@@ -0,0 +1,192 @@
+// FILE: src/compiler/middle_end/colors.rs
+
+//! Folding of compile-time color functions
+//!
+//! `rgb()`, `hsl()`, `lighten()`, `mix()` and the other `core::color` functions are
+//! evaluated before semantic analysis, so every later stage sees a plain `#RRGGBBAA`
+//! color, in gradient stops too. `@variables` entries are folded first and may use
+//! each other in any order; property values may use any of them. A value that cannot
+//! be folded is reported at its property, and the rest are still checked.
+
+use crate::compiler::frontend::ast::{AstNode, AstProperty, PropertyValue};
+use crate::core::color::{evaluate_color, is_color_function, COLOR_FUNCTIONS};
+use crate::core::VariableDef;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use std::collections::{BTreeMap, HashMap};
+
+/// Replace every color function call in `ast` with the color it evaluates to.
+/// `variables` are those known before parsing, such as `--define` values; the
+/// `@variables` blocks of `ast` take precedence over them.
+pub fn fold_color_functions(ast: &mut AstNode, variables: &BTreeMap<String, VariableDef>) -> Diagnostics {
+    let mut values: HashMap<String, String> =
+        variables.iter().map(|(name, def)| (name.clone(), def.value.clone())).collect();
+    if let AstNode::File { directives, .. } = ast {
+        for directive in directives.iter() {
+            if let AstNode::Variables { variables } = directive {
+                values.extend(variables.iter().map(|(name, value)| (name.clone(), value.clone())));
+            }
+        }
+    }
+
+    let mut folder = Folder { variables: values, diagnostics: Diagnostics::new() };
+    folder.fold_node(ast);
+    folder.diagnostics
+}
+
+struct Folder {
+    variables: HashMap<String, String>,
+    diagnostics: Diagnostics,
+}
+
+impl Folder {
+    fn evaluate(&self, source: &str) -> Result<String, String> {
+        let lookup = |name: &str| self.variables.get(name).cloned();
+        evaluate_color(source, &lookup).map(|color| color.to_string())
+    }
+
+    fn fold_node(&mut self, node: &mut AstNode) {
+        match node {
+            AstNode::File { directives, styles, keyframes, components, app, .. } => {
+                for directive in directives.iter_mut() {
+                    self.fold_directive(directive);
+                }
+                for node in styles.iter_mut().chain(keyframes).chain(components) {
+                    self.fold_node(node);
+                }
+                if let Some(app) = app {
+                    self.fold_node(app);
+                }
+            }
+            AstNode::Style { properties, pseudo_selectors, media_queries, .. } => {
+                self.fold_properties(properties);
+                for selector in pseudo_selectors {
+                    self.fold_properties(&mut selector.properties);
+                }
+                for query in media_queries {
+                    self.fold_properties(&mut query.properties);
+                }
+            }
+            AstNode::Keyframes { frames, .. } => {
+                for frame in frames {
+                    self.fold_properties(&mut frame.properties);
+                }
+            }
+            AstNode::Component { template, .. } => self.fold_node(template),
+            AstNode::Element { properties, pseudo_selectors, media_queries, children, .. } => {
+                self.fold_properties(properties);
+                for selector in pseudo_selectors {
+                    self.fold_properties(&mut selector.properties);
+                }
+                for query in media_queries {
+                    self.fold_properties(&mut query.properties);
+                }
+                self.fold_nodes(children);
+            }
+            AstNode::For { body, .. } => self.fold_nodes(body),
+            AstNode::If { then_body, elif_branches, else_body, .. } => {
+                self.fold_nodes(then_body);
+                for (_, body) in elif_branches {
+                    self.fold_nodes(body);
+                }
+                if let Some(body) = else_body {
+                    self.fold_nodes(body);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fold_directive(&mut self, directive: &mut AstNode) {
+        match directive {
+            AstNode::Variables { variables } => {
+                let mut names: Vec<_> = variables.keys().filter(|name| is_color_function(&variables[*name])).cloned().collect();
+                names.sort();
+                for name in names {
+                    match self.evaluate(&variables[&name]) {
+                        Ok(color) => {
+                            variables.insert(name, color);
+                        }
+                        Err(message) => self.diagnostics.push(Diagnostic::error(format!("@variables entry '{}': {}", name, message))),
+                    }
+                }
+            }
+            AstNode::Theme { tokens, .. } => self.fold_properties(tokens),
+            _ => {}
+        }
+    }
+
+    fn fold_nodes(&mut self, nodes: &mut [AstNode]) {
+        for node in nodes {
+            self.fold_node(node);
+        }
+    }
+
+    fn fold_properties(&mut self, properties: &mut [AstProperty]) {
+        for property in properties {
+            if let Err(message) = self.fold_value(&mut property.value) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("invalid color in {}: {}", property.key, message)).with_span(property.location.clone()),
+                );
+            }
+        }
+    }
+
+    fn fold_value(&self, value: &mut PropertyValue) -> Result<(), String> {
+        match value {
+            PropertyValue::FunctionCall { name, .. } if COLOR_FUNCTIONS.contains(&name.to_lowercase().as_str()) => {
+                *value = PropertyValue::Color(self.evaluate(&value.to_string())?);
+            }
+            PropertyValue::Array(items) => {
+                for item in items {
+                    self.fold_value(item)?;
+                }
+            }
+            PropertyValue::Object(fields) => {
+                for field in fields.values_mut() {
+                    self.fold_value(field)?;
+                }
+            }
+            PropertyValue::Gradient(gradient) => {
+                for stop in &mut gradient.stops {
+                    stop.color = self.evaluate(&stop.color)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::properties::PropertyId;
+    use crate::compiler::test_support::compile;
+    use crate::{CompilerError, KrbReader};
+
+    #[test]
+    fn test_color_functions_fold_to_rgba() {
+        let source = "@variables {\n    accent: lighten($brand, 10%)\n    brand: rgb(51, 102, 255)\n}\n\nstyle \"card\" {\n    background_color: mix(white, $brand, 75%)\n    border_color: rebeccapurple\n}\n\nApp {\n    Container {\n        style: \"card\"\n        border_color: alpha(hsl(225, 100%, 60%), 0.5)\n    }\n}\n";
+        let krb = compile(source).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        let color = |properties: &[crate::KrbPropertyRecord], id: PropertyId| {
+            properties.iter().find(|p| p.property_id == id as u8).map(|p| p.value.clone()).unwrap()
+        };
+        assert_eq!(color(&file.styles[0].properties, PropertyId::BackgroundColor), [0xCC, 0xD9, 0xFF, 0xFF]);
+        assert_eq!(color(&file.styles[0].properties, PropertyId::BorderColor), [0x66, 0x33, 0x99, 0xFF]);
+        assert_eq!(color(&file.elements[1].properties, PropertyId::BorderColor), [0x33, 0x66, 0xFF, 0x80]);
+        assert!(!file.strings.iter().any(|s| s.text.contains("lighten")));
+    }
+
+    #[test]
+    fn test_invalid_colors_are_located() {
+        let source = "App {\n    Container {\n        background_color: darken(#FFF)\n        border_color: mix(red, $nope)\n    }\n}\n";
+        let CompilerError::Diagnostics(diagnostics) = compile(source).unwrap_err() else {
+            unreachable!("expected located errors");
+        };
+        let errors: Vec<_> = diagnostics.iter().map(|d| (d.message.as_str(), d.span.as_ref().map(|s| s.line))).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(errors[0], ("invalid color in background_color: darken() takes 2 arguments, not 1", Some(3)));
+        assert!(errors[1].0.contains("'$nope' is not an @variables entry"));
+        assert_eq!(errors[1].1, Some(4));
+    }
+}
@@ -45,11 +45,11 @@ impl ComponentResolver {
             if let Some(value) = mapping.get(var_name_str) {
                 result = result.replace(full_match, value);
             } else {
-                println!("DEBUG: Component resolver variable substitution failed for '{}'", var_name_str);
-                println!("  Input: {}", input);
-                println!("  Available in mapping:");
+                log::debug!("Component resolver variable substitution failed for '{}'", var_name_str);
+                log::debug!("  Input: {}", input);
+                log::debug!("  Available in mapping:");
                 for (k, v) in mapping {
-                    println!("    {} = {}", k, v);
+                    log::debug!("    {} = {}", k, v);
                 }
                 return Err(CompilerError::Variable {
                     file: "test".to_string(),
@@ -63,20 +63,20 @@ impl ComponentResolver {
     }
     
     pub fn resolve_components(&mut self, ast: &mut AstNode, state: &mut CompilerState) -> Result<()> {
-        println!("DEBUG: Starting component resolution");
+        log::debug!("Starting component resolution");
         
         // First, resolve template structures (like @for loops) in the main App element
-        println!("DEBUG: Processing template structures (for loops, if statements)");
+        log::debug!("Processing template structures (for loops, if statements)");
         self.resolve_template_structures(ast, state)?;
-        println!("DEBUG: Template structures processed");
+        log::debug!("Template structures processed");
         
         // Then resolve component instances
-        println!("DEBUG: Processing component instances");
+        log::debug!("Processing component instances");
         self.resolve_recursive(ast, state)?;
-        println!("DEBUG: Component instances processed");
+        log::debug!("Component instances processed");
         
         self.update_component_statistics(state);
-        println!("DEBUG: Component resolution complete");
+        log::debug!("Component resolution complete");
         Ok(())
     }
     
@@ -112,7 +112,10 @@ impl ComponentResolver {
                                 element_type: "Container".to_string(),
                                 properties: vec![],
                                 pseudo_selectors: vec![],
+                                media_queries: vec![],
                                 children: vec![],
+                                location: None,
+                                origin: None,
                             };
                             
                             self.expand_for_loop(&mut expanded, index_var.as_deref(), &var_name, &collection_name, &body_clone, state)?;
@@ -140,7 +143,10 @@ impl ComponentResolver {
                                 element_type: "Container".to_string(),
                                 properties: vec![],
                                 pseudo_selectors: vec![],
+                                media_queries: vec![],
                                 children: vec![],
+                                location: None,
+                                origin: None,
                             };
                             
                             self.expand_if_conditional(&mut expanded, &cond, &then_clone, &elif_clone, &else_clone, state)?;
@@ -239,9 +245,9 @@ impl ComponentResolver {
     }
     
     fn find_component_definition(&self, name: &str, state: &CompilerState) -> Option<ComponentDefinition> {
-        println!("DEBUG: Looking for component '{}' in {} available components:", name, state.component_defs.len());
+        log::debug!("Looking for component '{}' in {} available components:", name, state.component_defs.len());
         for comp in &state.component_defs {
-            println!("  Available component: {}", comp.name);
+            log::debug!("  Available component: {}", comp.name);
         }
         state.component_defs.iter()
             .find(|comp| comp.name == name)
@@ -254,7 +260,7 @@ impl ComponentResolver {
         component_def: &ComponentDefinition,
         state: &mut CompilerState
     ) -> Result<()> {
-        if let AstNode::Element {  properties, children, .. } = element {
+        if let AstNode::Element {  properties, children, location, .. } = element {
             // Check for recursive instantiation
             if self.instantiation_stack.contains(&component_def.name) {
                 return Err(CompilerError::component_legacy(
@@ -271,7 +277,7 @@ impl ComponentResolver {
             // Push component scope and add component properties
             state.variable_context.push_scope(VariableScope::Component);
             
-            println!("DEBUG: Instantiating component '{}' with properties:", component_def.name);
+            log::debug!("Instantiating component '{}' with properties:", component_def.name);
             
             // Add component properties to variable context
             for prop_def in &component_def.properties {
@@ -288,7 +294,7 @@ impl ComponentResolver {
                     state.current_file_path.clone(),
                     0 // TODO: get actual line from component definition
                 )?;
-                println!("  Added property: {} = {}", prop_def.name, clean_default);
+                log::debug!("  Added property: {} = {}", prop_def.name, clean_default);
             }
             
             // Override with instance properties
@@ -311,7 +317,7 @@ impl ComponentResolver {
                     state.current_file_path.clone(),
                     instance_prop.line
                 )?;
-                println!("  Overrode property: {} = {}", instance_prop.key, clean_value);
+                log::debug!("  Overrode property: {} = {}", instance_prop.key, clean_value);
             }
             
             // Clone and customize the template using the variable context
@@ -322,6 +328,11 @@ impl ComponentResolver {
             
             self.apply_variable_substitution(&mut instantiated_template, state)?;
             
+            // Remember the usage the template elements come from, before slot content
+            // (which belongs to the using file) is added
+            let origin = ComponentOrigin { component: component_def.name.clone(), location: location.clone() };
+            mark_component_origin(&mut instantiated_template, &origin);
+            
             // Handle instance children (slot content)
             if !children.is_empty() {
                 self.inject_slot_content(&mut instantiated_template, children)?;
@@ -365,11 +376,13 @@ impl ComponentResolver {
         
         // Convert source properties back to AST properties
         for source_prop in &element.source_properties {
-            properties.push(AstProperty::new(
+            let mut property = AstProperty::new(
                 source_prop.key.clone(),
                 PropertyValue::String(source_prop.value.clone()),
                 source_prop.line_num,
-            ));
+            );
+            property.location = source_prop.location.clone();
+            properties.push(property);
         }
         
         // Convert children
@@ -384,7 +397,10 @@ impl ComponentResolver {
             element_type: element.source_element_name.clone(),
             properties,
             pseudo_selectors: Vec::new(), // TODO: Convert state property sets back
+            media_queries: Vec::new(),
             children,
+            location: element.source_location.clone(),
+            origin: element.component_origin.clone(),
         })
     }
     
@@ -392,7 +408,15 @@ impl ComponentResolver {
         match template {
             AstNode::Element { properties, children, .. } => {
                 // Replace variable references in properties using the variable context
-                for prop in properties {
+                // Gradients hold only folded colors and calc values only numbers, so they keep their structure
+                for prop in properties.iter_mut().filter(|prop| !matches!(prop.value, PropertyValue::Gradient(_) | PropertyValue::Calc(_))) {
+                    // Arithmetic is folded now that the component's properties are known
+                    if let PropertyValue::Expression(expr) = &prop.value {
+                        if expr.is_arithmetic() {
+                            prop.value = state.variable_context.evaluate_value(expr, &prop.key, prop.location.as_ref())?.into_property_value();
+                            continue;
+                        }
+                    }
                     let value_str = prop.value.to_string();
                     let substituted = state.variable_context.substitute_variables(&value_str)?;
                     prop.value = PropertyValue::String(substituted);
@@ -655,7 +679,7 @@ impl ComponentResolver {
             let resolved_name = state.variable_context.substitute_variables(&fixed_name_pattern)?;
             let resolved_body = state.variable_context.substitute_variables(&fixed_body)?;
             
-            println!("✅ Resolved function: '{}' for component '{}'", resolved_name, component_name);
+            log::debug!("✅ Resolved function: '{}' for component '{}'", resolved_name, component_name);
             
             // Build complete function code
             let param_list = template.parameters.join(", ");
@@ -697,24 +721,24 @@ impl ComponentResolver {
         component_name: &str,
         state: &mut CompilerState
     ) -> Result<()> {
-        println!("DEBUG: Processing component scripts for: {}", component_name);
-        println!("DEBUG: Available component scripts: {:?}", state.component_scripts.keys().collect::<Vec<_>>());
+        log::debug!("Processing component scripts for: {}", component_name);
+        log::debug!("Available component scripts: {:?}", state.component_scripts.keys().collect::<Vec<_>>());
         
         // Get deferred scripts for this component
         if let Some(script_nodes) = state.component_scripts.get(component_name).cloned() {
-            println!("DEBUG: Found {} stored scripts for component {}", script_nodes.len(), component_name);
+            log::debug!("Found {} stored scripts for component {}", script_nodes.len(), component_name);
             for (i, script_node) in script_nodes.iter().enumerate() {
                 if matches!(script_node, AstNode::Script { .. }) {
-                    println!("DEBUG: Processing script {} for component {}", i, component_name);
+                    log::debug!("Processing script {} for component {}", i, component_name);
                     // Process the script with current variable context (component variables available)
                     let script_processor = ScriptProcessor::new();
                     let script_entry = script_processor.process_script(&script_node, state)?;
                     state.scripts.push(script_entry);
-                    println!("DEBUG: Successfully processed script {} for component {}", i, component_name);
+                    log::debug!("Successfully processed script {} for component {}", i, component_name);
                 }
             }
         } else {
-            println!("DEBUG: No stored scripts found for component {}", component_name);
+            log::debug!("No stored scripts found for component {}", component_name);
         }
         
         Ok(())
@@ -746,7 +770,7 @@ impl ComponentResolver {
             };
             match state.variable_context.get_variable(lookup_name) {
                 Some(var_entry) => {
-                    println!("DEBUG: Found variable '{}' with value '{}'", lookup_name, var_entry.value);
+                    log::debug!("Found variable '{}' with value '{}'", lookup_name, var_entry.value);
                     let var_value = &var_entry.value;
                     
                     // Handle array syntax like ["apple", "banana", "orange"]
@@ -850,7 +874,10 @@ impl ComponentResolver {
             element_type: "Container".to_string(),
             properties: vec![],
             pseudo_selectors: vec![],
+            media_queries: vec![],
             children: expanded_elements,
+            location: None,
+            origin: None,
         };
         
         Ok(())
@@ -913,7 +940,10 @@ impl ComponentResolver {
             element_type: "Container".to_string(),
             properties: vec![],
             pseudo_selectors: vec![],
+            media_queries: vec![],
             children: expanded_elements,
+            location: None,
+            origin: None,
         };
         
         Ok(())
@@ -954,15 +984,15 @@ pub struct ComponentComplexity {
 
 impl ComponentStats {
     pub fn print_summary(&self) {
-        println!("Component Statistics:");
-        println!("  Definitions: {}", self.total_definitions);
-        println!("  Instantiations: {}", self.total_instantiations);
-        println!("  Max depth: {}", self.max_instantiation_depth);
+        log::debug!("Component Statistics:");
+        log::debug!("  Definitions: {}", self.total_definitions);
+        log::debug!("  Instantiations: {}", self.total_instantiations);
+        log::debug!("  Max depth: {}", self.max_instantiation_depth);
         
         if !self.definitions_by_complexity.is_empty() {
-            println!("  Complexity breakdown:");
+            log::debug!("  Complexity breakdown:");
             for (name, complexity) in &self.definitions_by_complexity {
-                println!("    {}: {} props, {} elements, depth {}{}",
+                log::debug!("    {}: {} props, {} elements, depth {}{}",
                         name,
                         complexity.property_count,
                         complexity.template_element_count,
@@ -973,6 +1003,19 @@ impl ComponentStats {
     }
 }
 
+/// Set `origin` on every element of an instantiated template that has none yet;
+/// elements of nested components keep the innermost usage
+fn mark_component_origin(node: &mut AstNode, origin: &ComponentOrigin) {
+    if let AstNode::Element { children, origin: element_origin, .. } = node {
+        if element_origin.is_none() {
+            *element_origin = Some(origin.clone());
+        }
+        for child in children {
+            mark_component_origin(child, origin);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,568 @@
+// FILE: src/compiler/middle_end/expressions.rs
+
+//! Constant folding of arithmetic
+//!
+//! `@variables` entries and property values may use `+ - * / %`, parentheses,
+//! `min()`, `max()`, `clamp()` and `calc()`. Numbers keep their unit, and a plain
+//! number next to a length counts as pixels, so `$spacing * 2` and `$base + 4px` fold
+//! to a pixel count and `50% + 10%` to a percentage; angles in different units are
+//! converted to degrees. A sum of lengths that only the renderer can relate, such as
+//! `100% - 20px`, becomes a Calc value (see `core::expression`). `+` joins strings
+//! when either side is one. Binary `-` needs spaces around it: `10 -5` is two numbers.
+//!
+//! Folding runs after color folding. Component templates are left to the component
+//! resolver, which knows the values of the component's properties.
+
+use crate::compiler::frontend::ast::{AstNode, AstProperty, Expression, PropertyValue};
+use crate::compiler::frontend::parser::Parser;
+use crate::core::expression::{Calc, CalcOperator};
+use crate::core::{CSSUnit, VariableDef};
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The result of evaluating an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64, CSSUnit),
+    Text(String),
+    Bool(bool),
+    /// Lengths the renderer has to combine
+    Calc(Calc),
+}
+
+impl Value {
+    /// The property value this folds to; pixels are written as plain numbers, as in source
+    pub fn into_property_value(self) -> PropertyValue {
+        match self {
+            Value::Number(n, CSSUnit::Number | CSSUnit::Pixels) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+                PropertyValue::Integer(n as i64)
+            }
+            Value::Number(n, CSSUnit::Number | CSSUnit::Pixels) => PropertyValue::Number(n),
+            Value::Number(n, CSSUnit::Em) => PropertyValue::Em(n),
+            Value::Number(n, CSSUnit::Rem) => PropertyValue::Rem(n),
+            Value::Number(n, CSSUnit::ViewportWidth) => PropertyValue::ViewportWidth(n),
+            Value::Number(n, CSSUnit::ViewportHeight) => PropertyValue::ViewportHeight(n),
+            Value::Number(n, CSSUnit::Percentage) => PropertyValue::Percentage(n),
+            Value::Number(n, CSSUnit::Degrees) => PropertyValue::Degrees(n),
+            Value::Number(n, CSSUnit::Radians) => PropertyValue::Radians(n),
+            Value::Number(n, CSSUnit::Turns) => PropertyValue::Turns(n),
+            Value::Text(text) => PropertyValue::String(format!("\"{}\"", text)),
+            Value::Bool(b) => PropertyValue::Boolean(b),
+            Value::Calc(calc) => PropertyValue::Calc(calc),
+        }
+    }
+
+    /// The text of an `@variables` entry holding this value; strings are stored unquoted
+    fn into_variable(self) -> String {
+        match self {
+            Value::Text(text) => text,
+            value => value.into_property_value().to_string(),
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            Value::Text(text) => !text.is_empty() && text != "false",
+            Value::Number(..) | Value::Calc(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n, unit) => write!(f, "{}{}", n, unit.suffix()),
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Calc(calc) => write!(f, "{}", calc),
+        }
+    }
+}
+
+/// Evaluate `expr`, looking up `$name` with `lookup`. A variable's text is read as an
+/// expression when it is one, and as a string otherwise.
+pub fn evaluate(expr: &Expression, lookup: &dyn Fn(&str) -> Option<String>) -> Result<Value, String> {
+    Evaluator { lookup, resolving: Vec::new() }.evaluate(expr)
+}
+
+struct Evaluator<'a> {
+    lookup: &'a dyn Fn(&str) -> Option<String>,
+    /// Variables being evaluated, to catch ones that refer to themselves
+    resolving: Vec<String>,
+}
+
+impl Evaluator<'_> {
+    fn evaluate(&mut self, expr: &Expression) -> Result<Value, String> {
+        match expr {
+            Expression::String(s) => Ok(Value::Text(s.clone())),
+            Expression::Number(n) => Ok(Value::Number(*n, CSSUnit::Number)),
+            Expression::Integer(i) => Ok(Value::Number(*i as f64, CSSUnit::Number)),
+            Expression::Boolean(b) => Ok(Value::Bool(*b)),
+            Expression::Dimension(n, unit) => Ok(Value::Number(*n, *unit)),
+            Expression::Variable(name) => self.variable(name),
+            Expression::Add(left, right) => sum(CalcOperator::Add, self.evaluate(left)?, self.evaluate(right)?),
+            Expression::Subtract(left, right) => sum(CalcOperator::Subtract, self.evaluate(left)?, self.evaluate(right)?),
+            Expression::Multiply(left, right) => multiply(self.evaluate(left)?, self.evaluate(right)?),
+            Expression::Divide(left, right) => divide(CalcOperator::Divide, self.evaluate(left)?, self.evaluate(right)?),
+            Expression::Remainder(left, right) => divide(CalcOperator::Remainder, self.evaluate(left)?, self.evaluate(right)?),
+            Expression::Negate(operand) => negate(self.evaluate(operand)?),
+            Expression::Call { name, args } => {
+                let args = args.iter().map(|arg| self.evaluate(arg)).collect::<Result<Vec<_>, _>>()?;
+                call(name, &args)
+            }
+            Expression::NotEquals(left, right) => Ok(Value::Bool(self.compare(left, right)? != Ordering::Equal)),
+            Expression::EqualEquals(left, right) => Ok(Value::Bool(self.compare(left, right)? == Ordering::Equal)),
+            Expression::LessThan(left, right) => Ok(Value::Bool(self.compare(left, right)?.is_lt())),
+            Expression::LessThanOrEqual(left, right) => Ok(Value::Bool(self.compare(left, right)?.is_le())),
+            Expression::GreaterThan(left, right) => Ok(Value::Bool(self.compare(left, right)?.is_gt())),
+            Expression::GreaterThanOrEqual(left, right) => Ok(Value::Bool(self.compare(left, right)?.is_ge())),
+            Expression::Ternary { condition, true_value, false_value } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.evaluate(true_value)
+                } else {
+                    self.evaluate(false_value)
+                }
+            }
+        }
+    }
+
+    fn variable(&mut self, name: &str) -> Result<Value, String> {
+        let source = (self.lookup)(name).ok_or_else(|| format!("'${}' is not an @variables entry", name))?;
+        if self.resolving.iter().any(|resolving| resolving == name) {
+            return Err(format!("'${}' refers to itself", name));
+        }
+        let Some(expr) = Parser::parse_expression(&source) else {
+            return Ok(Value::Text(source));
+        };
+        self.resolving.push(name.to_string());
+        let value = self.evaluate(&expr);
+        self.resolving.pop();
+        value
+    }
+
+    /// Numbers in compatible units compare by value, anything else by its text
+    fn compare(&mut self, left: &Expression, right: &Expression) -> Result<Ordering, String> {
+        let (left, right) = (self.evaluate(left)?, self.evaluate(right)?);
+        if let (Value::Number(a, a_unit), Value::Number(b, b_unit)) = (&left, &right) {
+            if let Some((a, b, _)) = common_unit((*a, *a_unit), (*b, *b_unit)) {
+                return Ok(a.partial_cmp(&b).unwrap_or(Ordering::Equal));
+            }
+        }
+        Ok(left.to_string().cmp(&right.to_string()))
+    }
+}
+
+/// Both numbers in the unit they can be combined in: their own if they share it,
+/// pixels for a plain number and pixels, degrees for two angles
+fn common_unit((a, a_unit): (f64, CSSUnit), (b, b_unit): (f64, CSSUnit)) -> Option<(f64, f64, CSSUnit)> {
+    if a_unit == b_unit {
+        return Some((a, b, a_unit));
+    }
+    match (in_degrees(a, a_unit), in_degrees(b, b_unit)) {
+        (Some(a), Some(b)) => Some((a, b, CSSUnit::Degrees)),
+        _ => match (a_unit, b_unit) {
+            (CSSUnit::Number, CSSUnit::Pixels) | (CSSUnit::Pixels, CSSUnit::Number) => Some((a, b, CSSUnit::Pixels)),
+            _ => None,
+        },
+    }
+}
+
+fn in_degrees(value: f64, unit: CSSUnit) -> Option<f64> {
+    match unit {
+        CSSUnit::Degrees => Some(value),
+        CSSUnit::Radians => Some(value.to_degrees()),
+        CSSUnit::Turns => Some(value * 360.0),
+        _ => None,
+    }
+}
+
+/// The value as a length for a Calc value; plain numbers are pixels
+fn as_length(value: &Value) -> Option<Calc> {
+    match value {
+        Value::Number(n, CSSUnit::Number) => Some(Calc::Number(*n, CSSUnit::Pixels)),
+        Value::Number(n, unit @ (CSSUnit::Pixels | CSSUnit::Em | CSSUnit::Rem | CSSUnit::ViewportWidth |
+            CSSUnit::ViewportHeight | CSSUnit::Percentage)) => Some(Calc::Number(*n, *unit)),
+        Value::Calc(calc) => Some(calc.clone()),
+        _ => None,
+    }
+}
+
+fn sum(operator: CalcOperator, left: Value, right: Value) -> Result<Value, String> {
+    let add = operator == CalcOperator::Add;
+    match (&left, &right) {
+        (Value::Text(_), _) | (_, Value::Text(_)) if add => return Ok(Value::Text(format!("{}{}", left, right))),
+        (Value::Number(a, a_unit), Value::Number(b, b_unit)) => {
+            if let Some((a, b, unit)) = common_unit((*a, *a_unit), (*b, *b_unit)) {
+                return Ok(Value::Number(if add { a + b } else { a - b }, unit));
+            }
+        }
+        _ => {}
+    }
+    match (as_length(&left), as_length(&right)) {
+        (Some(a), Some(b)) => Ok(Value::Calc(Calc::binary(operator, a, b))),
+        _ if add => Err(format!("cannot add {} and {}", left, right)),
+        _ => Err(format!("cannot subtract {} from {}", right, left)),
+    }
+}
+
+fn multiply(left: Value, right: Value) -> Result<Value, String> {
+    match (&left, &right) {
+        (Value::Number(a, unit), Value::Number(b, CSSUnit::Number)) | (Value::Number(b, CSSUnit::Number), Value::Number(a, unit)) => {
+            Ok(Value::Number(a * b, *unit))
+        }
+        (Value::Calc(calc), Value::Number(n, CSSUnit::Number)) | (Value::Number(n, CSSUnit::Number), Value::Calc(calc)) => {
+            Ok(Value::Calc(Calc::binary(CalcOperator::Multiply, calc.clone(), Calc::Number(*n, CSSUnit::Number))))
+        }
+        _ => Err(format!("cannot multiply {} by {}; one of them must be a plain number", left, right)),
+    }
+}
+
+/// `/` or `%`. Dividing by a plain number keeps the unit; the ratio of two lengths
+/// in the same unit is a plain number, and their remainder keeps the unit.
+fn divide(operator: CalcOperator, left: Value, right: Value) -> Result<Value, String> {
+    let divide = operator == CalcOperator::Divide;
+    let apply = |a: f64, b: f64| if divide { a / b } else { a % b };
+    let error = || match divide {
+        true => format!("cannot divide {} by {}", left, right),
+        false => format!("cannot take {} modulo {}", left, right),
+    };
+    match (&left, &right) {
+        (_, Value::Number(b, _)) if *b == 0.0 => Err("division by zero".to_string()),
+        (Value::Number(a, unit), Value::Number(b, CSSUnit::Number)) => Ok(Value::Number(apply(*a, *b), *unit)),
+        (Value::Number(a, a_unit), Value::Number(b, b_unit)) => match common_unit((*a, *a_unit), (*b, *b_unit)) {
+            Some((a, b, unit)) => Ok(Value::Number(apply(a, b), if divide { CSSUnit::Number } else { unit })),
+            None => Err(error()),
+        },
+        (Value::Calc(calc), Value::Number(b, CSSUnit::Number)) => {
+            Ok(Value::Calc(Calc::binary(operator, calc.clone(), Calc::Number(*b, CSSUnit::Number))))
+        }
+        _ => Err(error()),
+    }
+}
+
+fn negate(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Number(n, unit) => Ok(Value::Number(-n, unit)),
+        Value::Calc(calc) => Ok(Value::Calc(Calc::binary(CalcOperator::Multiply, calc, Calc::Number(-1.0, CSSUnit::Number)))),
+        value => Err(format!("cannot negate {}", value)),
+    }
+}
+
+fn call(name: &str, args: &[Value]) -> Result<Value, String> {
+    match (name, args) {
+        ("calc", [value]) => Ok(value.clone()),
+        ("min" | "max", [_, ..]) => extreme(name, name == "min", args),
+        ("clamp", [min, value, max]) => {
+            // max(min, min(value, max)), so a minimum above the maximum wins
+            let upper = extreme(name, true, &[value.clone(), max.clone()])?;
+            match extreme(name, false, &[min.clone(), upper])? {
+                folded @ Value::Number(..) => Ok(folded),
+                _ => {
+                    let [min, value, max] = [min, value, max].map(|arg| as_length(arg).map(Box::new));
+                    match (min, value, max) {
+                        (Some(min), Some(value), Some(max)) => Ok(Value::Calc(Calc::Clamp(min, value, max))),
+                        _ => Err(format!("clamp() cannot compare {}", list(args))),
+                    }
+                }
+            }
+        }
+        ("calc", _) => Err(format!("calc() takes 1 argument, not {}", args.len())),
+        ("clamp", _) => Err(format!("clamp() takes 3 arguments, not {}", args.len())),
+        _ => Err(format!("{}() needs at least 1 argument", name)),
+    }
+}
+
+/// The smallest or largest of `args`, or a runtime min()/max() of lengths in different units
+fn extreme(name: &str, smallest: bool, args: &[Value]) -> Result<Value, String> {
+    let numbers: Option<Vec<(f64, CSSUnit)>> = args
+        .iter()
+        .map(|arg| match arg {
+            Value::Number(n, unit) => Some((*n, *unit)),
+            _ => None,
+        })
+        .collect();
+    if let Some((first, rest)) = numbers.as_deref().and_then(|numbers| numbers.split_first()) {
+        let folded = rest.iter().try_fold(*first, |best, next| {
+            common_unit(best, *next).map(|(a, b, unit)| (if smallest { a.min(b) } else { a.max(b) }, unit))
+        });
+        if let Some((n, unit)) = folded {
+            return Ok(Value::Number(n, unit));
+        }
+    }
+
+    let lengths: Option<Vec<Calc>> = args.iter().map(as_length).collect();
+    match lengths {
+        Some(lengths) if smallest => Ok(Value::Calc(Calc::Min(lengths))),
+        Some(lengths) => Ok(Value::Calc(Calc::Max(lengths))),
+        None => Err(format!("{}() cannot compare {}", name, list(args))),
+    }
+}
+
+fn list(values: &[Value]) -> String {
+    values.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+}
+
+/// Fold every arithmetic expression in `ast` whose variables are all known.
+/// `variables` are those known before parsing, such as `--define` values; the
+/// `@variables` blocks of `ast` take precedence over them.
+pub fn fold_expressions(ast: &mut AstNode, variables: &BTreeMap<String, VariableDef>) -> Diagnostics {
+    let mut values: HashMap<String, String> =
+        variables.iter().map(|(name, def)| (name.clone(), def.value.clone())).collect();
+    if let AstNode::File { directives, .. } = ast {
+        for directive in directives.iter() {
+            if let AstNode::Variables { variables } = directive {
+                values.extend(variables.iter().map(|(name, value)| (name.clone(), value.clone())));
+            }
+        }
+    }
+
+    let mut folder = Folder { variables: values, diagnostics: Diagnostics::new() };
+    folder.fold_node(ast);
+    folder.diagnostics
+}
+
+struct Folder {
+    variables: HashMap<String, String>,
+    diagnostics: Diagnostics,
+}
+
+impl Folder {
+    fn evaluate(&self, expr: &Expression) -> Result<Value, String> {
+        let lookup = |name: &str| self.variables.get(name).cloned();
+        evaluate(expr, &lookup)
+    }
+
+    fn fold_node(&mut self, node: &mut AstNode) {
+        match node {
+            AstNode::File { directives, styles, keyframes, app, .. } => {
+                for directive in directives.iter_mut() {
+                    self.fold_directive(directive);
+                }
+                for node in styles.iter_mut().chain(keyframes) {
+                    self.fold_node(node);
+                }
+                if let Some(app) = app {
+                    self.fold_node(app);
+                }
+            }
+            AstNode::Style { properties, pseudo_selectors, media_queries, .. } => {
+                self.fold_properties(properties);
+                for selector in pseudo_selectors {
+                    self.fold_properties(&mut selector.properties);
+                }
+                for query in media_queries {
+                    self.fold_properties(&mut query.properties);
+                }
+            }
+            AstNode::Keyframes { frames, .. } => {
+                for frame in frames {
+                    self.fold_properties(&mut frame.properties);
+                }
+            }
+            AstNode::Element { properties, pseudo_selectors, media_queries, children, .. } => {
+                self.fold_properties(properties);
+                for selector in pseudo_selectors {
+                    self.fold_properties(&mut selector.properties);
+                }
+                for query in media_queries {
+                    self.fold_properties(&mut query.properties);
+                }
+                self.fold_nodes(children);
+            }
+            AstNode::For { body, .. } => self.fold_nodes(body),
+            AstNode::If { then_body, elif_branches, else_body, .. } => {
+                self.fold_nodes(then_body);
+                for (_, body) in elif_branches {
+                    self.fold_nodes(body);
+                }
+                if let Some(body) = else_body {
+                    self.fold_nodes(body);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fold_directive(&mut self, directive: &mut AstNode) {
+        match directive {
+            AstNode::Variables { variables } => {
+                let mut names: Vec<_> = variables.keys().cloned().collect();
+                names.sort();
+                for name in names {
+                    let Some(expr) = Parser::parse_expression(&variables[&name]).filter(Expression::is_arithmetic) else {
+                        continue;
+                    };
+                    match self.evaluate(&expr) {
+                        Ok(value) => {
+                            variables.insert(name, value.into_variable());
+                        }
+                        Err(message) => self.diagnostics.push(Diagnostic::error(format!("@variables entry '{}': {}", name, message))),
+                    }
+                }
+            }
+            AstNode::Theme { tokens, .. } => self.fold_properties(tokens),
+            _ => {}
+        }
+    }
+
+    fn fold_nodes(&mut self, nodes: &mut [AstNode]) {
+        for node in nodes {
+            self.fold_node(node);
+        }
+    }
+
+    fn fold_properties(&mut self, properties: &mut [AstProperty]) {
+        for property in properties {
+            if let Err(message) = self.fold_value(&mut property.value) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("invalid expression in {}: {}", property.key, message)).with_span(property.location.clone()),
+                );
+            }
+        }
+    }
+
+    fn fold_value(&self, value: &mut PropertyValue) -> Result<(), String> {
+        match value {
+            // Variables the folder doesn't know, such as `@for` items, are substituted later
+            PropertyValue::Expression(expr) if !expr.extract_variables().iter().all(|name| self.variables.contains_key(name)) => {}
+            PropertyValue::Expression(expr) if expr.is_arithmetic() => {
+                *value = self.evaluate(expr)?.into_property_value();
+            }
+            // Template substitution only produces plain values, so a runtime calc is copied in
+            PropertyValue::Expression(expr) if matches!(**expr, Expression::Variable(_)) => {
+                if let Ok(Value::Calc(calc)) = self.evaluate(expr) {
+                    *value = PropertyValue::Calc(calc);
+                }
+            }
+            PropertyValue::Array(items) => {
+                for item in items {
+                    self.fold_value(item)?;
+                }
+            }
+            PropertyValue::Object(fields) => {
+                for field in fields.values_mut() {
+                    self.fold_value(field)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::expression::Calc;
+    use crate::core::properties::PropertyId;
+    use crate::core::types::ValueType;
+    use crate::compiler::test_support::{compile, compile_with};
+    use crate::{verify_krb, CompilerError, CompilerOptions, KrbReader, NumberFormat};
+
+    fn eval(source: &str) -> Result<String, String> {
+        let variables = HashMap::from([
+            ("spacing", "8"), ("base", "12px"), ("side", "100% - 20px"), ("name", "Ada"), ("loop", "$loop + 1"),
+        ]);
+        let lookup = |name: &str| variables.get(name).map(|value| value.to_string());
+        let expr = Parser::parse_expression(source).ok_or_else(|| format!("'{}' does not parse", source))?;
+        evaluate(&expr, &lookup).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn test_units_fold_or_stay_for_the_renderer() {
+        assert_eq!(eval("$spacing * 2"), Ok("16".to_string()));
+        assert_eq!(eval("$base + 4px"), Ok("16px".to_string()));
+        assert_eq!(eval("(1 + 2) * 3 % 4"), Ok("1".to_string()));
+        assert_eq!(eval("50% + 10% / 2"), Ok("55%".to_string()));
+        assert_eq!(eval("1turn - 90deg"), Ok("270deg".to_string()));
+        assert_eq!(eval("30px / 4px"), Ok("7.5".to_string()));
+        assert_eq!(eval("max(1em, 2em, 1.5em)"), Ok("2em".to_string()));
+        assert_eq!(eval("clamp(10, 50, 40px)"), Ok("40px".to_string()));
+        assert_eq!(eval("-$spacing"), Ok("-8".to_string()));
+
+        // Lengths the compiler cannot relate become a runtime calc
+        assert_eq!(eval("$side"), Ok("calc(100% - 20px)".to_string()));
+        assert_eq!(eval("($side) / 2 + 1em"), Ok("calc((100% - 20px) / 2 + 1em)".to_string()));
+        assert_eq!(eval("-(100vw - $spacing)"), Ok("calc((100vw - 8px) * -1)".to_string()));
+        assert_eq!(eval("min(100%, 600px)"), Ok("min(100%, 600px)".to_string()));
+
+        assert_eq!(eval("\"Hello \" + $name + \"!\""), Ok("Hello Ada!".to_string()));
+        assert_eq!(eval("$spacing > 4 ? \"wide\" : \"narrow\""), Ok("wide".to_string()));
+        assert_eq!(eval("$base == 12 ? 1 : 2"), Ok("1".to_string()));
+    }
+
+    #[test]
+    fn test_evaluation_errors() {
+        assert_eq!(eval("45deg + 10px"), Err("cannot add 45deg and 10px".to_string()));
+        assert_eq!(eval("2px * 3px"), Err("cannot multiply 2px by 3px; one of them must be a plain number".to_string()));
+        assert_eq!(eval("$side / 2px"), Err("cannot divide calc(100% - 20px) by 2px".to_string()));
+        assert_eq!(eval("$spacing % 0"), Err("division by zero".to_string()));
+        assert_eq!(eval("$name - 1"), Err("cannot subtract 1 from Ada".to_string()));
+        assert_eq!(eval("clamp(1, 2)"), Err("clamp() takes 3 arguments, not 2".to_string()));
+        assert_eq!(eval("$missing * 2"), Err("'$missing' is not an @variables entry".to_string()));
+        assert_eq!(eval("$loop"), Err("'$loop' refers to itself".to_string()));
+    }
+
+    #[test]
+    fn test_expressions_compile_to_values_and_calc() {
+        let source = "@variables {\n    gap: 6\n    double: $gap * 2\n    side: 100% - $double\n}\n\nstyle \"card\" {\n    padding: $gap * 2 ($gap + 1)\n    width: $side\n}\n\nApp {\n    Container {\n        style: \"card\"\n        height: min(50vh, 300px + 2.5%)\n        Text {\n            text: \"Hi\"\n            font_size: $double + 4px\n        }\n    }\n}\n";
+        for fixed_point in [false, true] {
+            let krb = compile_with(source, CompilerOptions { fixed_point, ..CompilerOptions::default() }).unwrap();
+            assert_eq!(verify_krb(&krb), Vec::new());
+            let file = KrbReader::new(&krb).unwrap().read().unwrap();
+            let format = NumberFormat::from_flags(file.header.flags);
+            let property = |properties: &[crate::KrbPropertyRecord], id: PropertyId| {
+                properties.iter().find(|p| p.property_id == id as u8).map(|p| (p.value_type, p.value.clone())).unwrap()
+            };
+
+            let style = &file.styles[0].properties;
+            assert_eq!(property(style, PropertyId::PaddingTop), (ValueType::Byte as u8, vec![12]));
+            assert_eq!(property(style, PropertyId::PaddingLeft), (ValueType::Byte as u8, vec![7]));
+            let (value_type, width) = property(style, PropertyId::Width);
+            assert_eq!(value_type, ValueType::Calc as u8);
+            assert_eq!(Calc::decode(&width, format).unwrap().to_string(), "calc(100% - 12px)");
+
+            let element = &file.elements[1].properties;
+            let (_, height) = property(element, PropertyId::Height);
+            assert_eq!(Calc::decode(&height, format).unwrap().to_string(), "min(50vh, 300px + 2.5%)");
+            let text = &file.elements[2].properties;
+            assert_eq!(property(text, PropertyId::FontSize), (ValueType::Short as u8, 16u16.to_le_bytes().to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_invalid_expressions_are_located() {
+        let source = "@variables {\n    tilt: 45deg\n}\n\nApp {\n    Container {\n        width: 100% * 2px\n        height: $tilt + 10\n    }\n}\n";
+        let CompilerError::Diagnostics(diagnostics) = compile(source).unwrap_err() else {
+            unreachable!("expected located errors");
+        };
+        let errors: Vec<_> = diagnostics.iter().map(|d| (d.message.as_str(), d.span.as_ref().map(|s| s.line))).collect();
+        assert_eq!(errors, [
+            ("invalid expression in width: cannot multiply 100% by 2px; one of them must be a plain number", Some(7)),
+            ("invalid expression in height: cannot add 45deg and 10", Some(8)),
+        ]);
+
+        let source = "App {\n    Text {\n        text: \"Total\"\n        font_weight: 100% - 2px\n    }\n}\n";
+        let CompilerError::Diagnostics(diagnostics) = compile(source).unwrap_err() else {
+            unreachable!("expected a located error");
+        };
+        let error = diagnostics.iter().next().unwrap();
+        assert_eq!(error.message, "font_weight does not take a length, so it cannot be 'calc(100% - 2px)'");
+        assert_eq!(error.span.as_ref().map(|s| s.line), Some(4));
+
+        // Component properties are only known once the component is expanded
+        let source = "Define Bar {\n    Properties {\n        parts: Int = 0\n    }\n    Container {\n        width: 100 / $parts\n    }\n}\n\nApp {\n    Bar {}\n}\n";
+        let error = compile(source).unwrap_err().to_string();
+        assert!(error.contains("main.kry:6:9: error: invalid expression in width: division by zero"), "{}", error);
+
+        // A calc value must fit the one-byte property size
+        let arguments: Vec<_> = (1..40).map(|i| format!("{}px + {}%", i, i)).collect();
+        let source = format!("App {{\n    Container {{\n        width: min({})\n    }}\n}}\n", arguments.join(", "));
+        let error = compile(&source).unwrap_err().to_string();
+        assert!(error.contains("main.kry:3:9: error: Invalid width"), "{}", error);
+        assert!(error.contains("more than a property can hold"), "{}", error);
+    }
+}
@@ -1,8 +1,12 @@
 // FILE: src/compiler/middle_end/mod.rs (Updated)
 
 // Declare all the files within this module
+pub mod colors;
+pub mod expressions;
 pub mod module_context;
+pub mod platform;
 pub mod preprocessor;
+pub mod resources;
 pub mod style_resolver;
 pub mod component_resolver;
 pub mod script;
@@ -22,6 +26,7 @@ pub fn setup_from_module_graph(
     options: &crate::CompilerOptions,
 ) -> Result<()> {
     state.variable_context.set_current_module(graph.root_module.clone());
+    state.embed_resources = options.embed_resources;
 
     for module in graph.get_ordered_modules() {
         state.variable_context.add_module_variables(module)?;
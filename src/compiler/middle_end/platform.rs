@@ -0,0 +1,198 @@
+// FILE: src/compiler/middle_end/platform.rs
+
+//! Removal of features the target platform's renderer cannot display
+//!
+//! `@platform` blocks and `key@platform` overrides are settled by the parser. What is
+//! left may still use something the target lacks, such as a video on an embedded
+//! renderer. Rather than fail the build, each such property or element is removed
+//! and reported as a warning, so shared sources compile for every target.
+
+use crate::compiler::frontend::ast::{AstNode, AstProperty};
+use crate::core::properties::PropertyId;
+use crate::core::types::{ElementType, ResourceType};
+use crate::core::PlatformProfile;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::TargetPlatform;
+
+/// Strip what `platform` does not support from `ast`, returning a warning for each removal
+pub fn strip_unsupported(ast: &mut AstNode, platform: TargetPlatform) -> Diagnostics {
+    let mut stripper = Stripper { platform, profile: platform.profile(), diagnostics: Diagnostics::new() };
+    stripper.strip_node(ast);
+    stripper.diagnostics
+}
+
+struct Stripper {
+    platform: TargetPlatform,
+    profile: &'static PlatformProfile,
+    diagnostics: Diagnostics,
+}
+
+impl Stripper {
+    fn strip_node(&mut self, node: &mut AstNode) {
+        match node {
+            AstNode::File { styles, keyframes, components, app, .. } => {
+                for node in styles.iter_mut().chain(keyframes).chain(components) {
+                    self.strip_node(node);
+                }
+                if let Some(app) = app {
+                    self.strip_node(app);
+                }
+            }
+            AstNode::Style { properties, pseudo_selectors, media_queries, .. } => {
+                self.strip_properties(properties);
+                for selector in pseudo_selectors {
+                    self.strip_properties(&mut selector.properties);
+                }
+                for query in media_queries {
+                    self.strip_properties(&mut query.properties);
+                }
+            }
+            AstNode::Keyframes { frames, .. } => {
+                for frame in frames {
+                    self.strip_properties(&mut frame.properties);
+                }
+            }
+            AstNode::Component { template, .. } => self.strip_node(template),
+            AstNode::Element { properties, pseudo_selectors, media_queries, children, .. } => {
+                self.strip_properties(properties);
+                for selector in pseudo_selectors {
+                    self.strip_properties(&mut selector.properties);
+                }
+                for query in media_queries {
+                    self.strip_properties(&mut query.properties);
+                }
+                self.strip_nodes(children);
+            }
+            AstNode::For { body, .. } => self.strip_nodes(body),
+            AstNode::If { then_body, elif_branches, else_body, .. } => {
+                self.strip_nodes(then_body);
+                for (_, body) in elif_branches {
+                    self.strip_nodes(body);
+                }
+                if let Some(body) = else_body {
+                    self.strip_nodes(body);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn strip_nodes(&mut self, nodes: &mut Vec<AstNode>) {
+        nodes.retain(|node| self.keep_node(node));
+        for node in nodes {
+            self.strip_node(node);
+        }
+    }
+
+    fn strip_properties(&mut self, properties: &mut Vec<AstProperty>) {
+        properties.retain(|property| self.keep_property(property));
+    }
+
+    fn keep_node(&mut self, node: &AstNode) -> bool {
+        let AstNode::Element { element_type, location, .. } = node else {
+            return true;
+        };
+        if self.profile.supports_video || ElementType::from_name(element_type) != ElementType::Video {
+            return true;
+        }
+        self.diagnostics.push(
+            Diagnostic::warning(format!(
+                "{} elements are not supported on {} targets; the element was removed",
+                element_type,
+                self.platform.name()
+            ))
+            .with_span(location.clone()),
+        );
+        false
+    }
+
+    fn keep_property(&mut self, property: &AstProperty) -> bool {
+        let message = if !self.profile.supports_property(PropertyId::from_name(&property.key)) {
+            format!("{} is not supported on {} targets; the property was removed", property.key, self.platform.name())
+        } else if !self.profile.supports_video
+            && ResourceType::from_extension(&property.cleaned_value()) == Some(ResourceType::Video)
+        {
+            format!(
+                "video '{}' cannot be played on {} targets; {} was removed",
+                property.cleaned_value(),
+                self.platform.name(),
+                property.key
+            )
+        } else {
+            return true;
+        };
+        self.diagnostics.push(Diagnostic::warning(message).with_span(property.location.clone()));
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::properties::PropertyId;
+    use crate::compiler::test_support::{compile_with, compile_with_stats};
+    use crate::{CompilerOptions, KrbReader, TargetPlatform};
+
+    const SOURCE: &str = "style \"card\" {\n    background_color: \"#FFFFFFFF\"\n    box_shadow: \"0 2px 4px #00000040\"\n}\n\nApp {\n    Container {\n        style: \"card\"\n        Text {\n            text: \"Hello\"\n            font_size: 16\n            font_size@mobile: 14\n            @platform(embedded) {\n                padding: 2\n            }\n        }\n        @platform(desktop, web) {\n            Text {\n                text: \"Wide layout\"\n            }\n        }\n        Video {\n            src: \"intro.mp4\"\n        }\n    }\n}\n";
+
+    fn compile_for(target_platform: TargetPlatform) -> crate::Result<(Vec<u8>, crate::CompilationStats)> {
+        compile_with_stats(SOURCE, CompilerOptions { target_platform, ..CompilerOptions::default() })
+    }
+
+    fn element_types(krb: &[u8]) -> Vec<u8> {
+        KrbReader::new(krb).unwrap().read().unwrap().elements.iter().map(|e| e.element_type).collect()
+    }
+
+    /// A property of the "Hello" text
+    fn text_property(krb: &[u8], property: PropertyId) -> Option<Vec<u8>> {
+        let file = KrbReader::new(krb).unwrap().read().unwrap();
+        file.elements[2].properties.iter().find(|p| p.property_id == property as u8).map(|p| p.value.clone())
+    }
+
+    #[test]
+    fn test_platform_blocks_and_overrides() {
+        let (desktop, stats) = compile_for(TargetPlatform::Desktop).unwrap();
+        let (mobile, _) = compile_for(TargetPlatform::Mobile).unwrap();
+        let (universal, _) = compile_for(TargetPlatform::Universal).unwrap();
+        assert!(stats.warnings.is_empty());
+
+        // App, Container, two Texts and Video on desktop; the second Text is desktop and web only
+        assert_eq!(element_types(&desktop).len(), 5);
+        assert_eq!(element_types(&mobile).len(), 4);
+        assert_eq!(element_types(&universal).len(), 4);
+        assert_ne!(text_property(&desktop, PropertyId::FontSize), text_property(&mobile, PropertyId::FontSize));
+        assert_eq!(text_property(&desktop, PropertyId::FontSize), text_property(&universal, PropertyId::FontSize));
+        assert!(text_property(&desktop, PropertyId::PaddingTop).is_none());
+    }
+
+    #[test]
+    fn test_embedded_strips_video_and_shadows() {
+        let (krb, stats) = compile_for(TargetPlatform::Embedded).unwrap();
+        let messages: Vec<_> = stats.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].starts_with("box_shadow is not supported on embedded targets"));
+        assert!(messages[1].starts_with("Video elements are not supported on embedded targets"));
+        assert_eq!(stats.warnings[0].span.as_ref().map(|s| s.line), Some(3));
+        assert_eq!(stats.warnings[1].span.as_ref().map(|s| s.line), Some(22));
+
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        assert_eq!(file.elements.len(), 3);
+        assert!(text_property(&krb, PropertyId::PaddingTop).is_some());
+        assert!(file.styles[0].properties.iter().all(|p| p.property_id != PropertyId::BoxShadow as u8));
+    }
+
+    #[test]
+    fn test_embedded_limits_and_unknown_platforms() {
+        let mut source = String::from("App {\n");
+        for _ in 0..70 {
+            source.push_str("    Container {\n    }\n");
+        }
+        source.push_str("}\n");
+        let options = |platform| CompilerOptions { target_platform: platform, ..CompilerOptions::default() };
+        assert!(compile_with(&source, options(TargetPlatform::Desktop)).is_ok());
+        let error = compile_with(&source, options(TargetPlatform::Embedded)).unwrap_err();
+        assert!(error.to_string().contains("main.kry:1:1: error: App has 70 children, over the limit of 64"), "{}", error);
+
+        let error = compile_with("App {\n    width@watch: 10\n}\n", options(TargetPlatform::Desktop)).unwrap_err();
+        assert!(error.to_string().contains("Unknown platform 'watch'"), "{}", error);
+    }
+}
@@ -6,9 +6,9 @@
 use crate::error::{CompilerError, Result, SourceMap};
 use crate::core::MAX_INCLUDE_DEPTH;
 
+use crate::compiler::file_provider::{DiskFileProvider, FileProvider};
 use crate::compiler::middle_end::module_context::{ModuleContext, ModuleGraph, ModuleImport};
 use std::collections::{HashSet, HashMap};
-use std::fs;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
@@ -24,6 +24,9 @@ pub struct Preprocessor {
     // New: Module isolation support
     module_graph: Option<ModuleGraph>,
     processed_modules: HashMap<PathBuf, ModuleContext>,
+
+    /// Directories searched, in order, for includes not found next to the including file
+    include_directories: Vec<PathBuf>,
 }
 
 impl Preprocessor {
@@ -36,24 +39,40 @@ impl Preprocessor {
             processed_file_cache: HashMap::new(),
             module_graph: None,
             processed_modules: HashMap::new(),
+            include_directories: Vec::new(),
         }
     }
 
+    /// Search these directories (from `-I` and the config file) after the including file's directory
+    pub fn with_include_directories<I, P>(mut self, directories: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.include_directories = directories.into_iter().map(Into::into).collect();
+        self
+    }
+
     
     /// NEW: Process includes with module isolation
     /// Each @include creates an isolated module context
     pub fn process_includes_isolated(&mut self, file_path: &str) -> Result<ModuleGraph> {
-        let root_path = fs::canonicalize(file_path).map_err(|e| {
-            CompilerError::FileNotFound {
-                path: format!("{}: {}", file_path, e),
-            }
-        })?;
+        let source = DiskFileProvider.read_to_string(Path::new(file_path))?;
+        self.process_source_isolated(&source, file_path, &DiskFileProvider)
+    }
+
+    /// Build the module graph for source text that need not exist on disk,
+    /// looking up its includes through `provider`
+    pub fn process_source_isolated(&mut self, source: &str, file_path: &str, provider: &dyn FileProvider) -> Result<ModuleGraph> {
+        let path = Path::new(file_path);
+        let root_path = provider.resolve(path).unwrap_or_else(|| path.to_path_buf());
+        let provider = RootOverlay { path: root_path.clone(), source, inner: provider };
         
         // Initialize module graph
         let mut graph = ModuleGraph::new(root_path.clone());
         
         // Process the root module and all its dependencies
-        self.process_module_recursive(&root_path, &mut graph, 0)?;
+        self.process_module_recursive(&root_path, &mut graph, 0, &provider)?;
         
         // Resolve module dependencies and import order
         graph.resolve_dependencies()?;
@@ -64,7 +83,7 @@ impl Preprocessor {
         Ok(graph)
     }
     
-    fn process_module_recursive(&mut self, module_path: &PathBuf, graph: &mut ModuleGraph, depth: usize) -> Result<()> {
+    fn process_module_recursive(&mut self, module_path: &PathBuf, graph: &mut ModuleGraph, depth: usize, provider: &dyn FileProvider) -> Result<()> {
         if depth > MAX_INCLUDE_DEPTH {
             return Err(CompilerError::Include {
                 message: format!("Maximum include depth ({}) exceeded processing '{}'", 
@@ -87,23 +106,19 @@ impl Preprocessor {
         self.included_files.insert(module_path.clone());
         
         // Read and process the module file
-        let content = fs::read_to_string(module_path).map_err(|e| {
-            CompilerError::FileNotFound {
-                path: format!("{}: {}", module_path.display(), e),
-            }
-        })?;
+        let content = provider.read_to_string(module_path)?;
         
         // Create module context
         let mut module = ModuleContext::new(module_path.clone());
         
         // Process the content and extract includes
-        let (processed_content, include_paths) = self.process_module_content(&content, module_path, depth)?;
+        let (processed_content, include_paths) = self.process_module_content(&content, module_path, depth, provider)?;
         module.content = processed_content;
         
         // Process all included modules first (dependencies)
         let mut import_order = 0;
         for include_path in include_paths {
-            self.process_module_recursive(&include_path, graph, depth + 1)?;
+            self.process_module_recursive(&include_path, graph, depth + 1, provider)?;
             
             // Record the import in the module
             let import = ModuleImport {
@@ -122,7 +137,7 @@ impl Preprocessor {
         Ok(())
     }
     
-    fn process_module_content(&mut self, content: &str, module_path: &PathBuf, _depth: usize) -> Result<(String, Vec<PathBuf>)> {
+    fn process_module_content(&mut self, content: &str, module_path: &Path, _depth: usize, provider: &dyn FileProvider) -> Result<(String, Vec<PathBuf>)> {
         let mut result = String::new();
         let mut include_paths = Vec::new();
         let mut line_num = 0;
@@ -137,20 +152,11 @@ impl Preprocessor {
                 // Store include path for comment before moving it
                 let include_comment = include_path.clone();
                 
-                // Resolve include path relative to current module
-                let full_include_path = if Path::new(&include_path).is_absolute() {
-                    PathBuf::from(include_path)
-                } else {
-                    base_dir.join(&include_path)
-                };
-                
-                let canonical_include_path = fs::canonicalize(&full_include_path).map_err(|e| {
-                    CompilerError::FileNotFound {
-                        path: format!("{}: {}", full_include_path.display(), e),
-                    }
-                })?;
-                
-                include_paths.push(canonical_include_path);
+                // The same module may be spelled differently; keep one import per canonical path
+                let canonical_include_path = self.resolve_include(&include_path, base_dir, module_path, line_num, provider)?;
+                if !include_paths.contains(&canonical_include_path) {
+                    include_paths.push(canonical_include_path);
+                }
                 
                 // In isolated mode, @include lines are removed from content
                 // The module content will be merged later during compilation
@@ -164,6 +170,40 @@ impl Preprocessor {
         Ok((result, include_paths))
     }
     
+    /// Resolve an include against the including file's directory, then each include
+    /// directory in order, returning the canonical path of the first match
+    fn resolve_include(
+        &self,
+        include_path: &str,
+        base_dir: &Path,
+        module_path: &Path,
+        line_num: usize,
+        provider: &dyn FileProvider,
+    ) -> Result<PathBuf> {
+        let candidates: Vec<PathBuf> = if Path::new(include_path).is_absolute() {
+            vec![PathBuf::from(include_path)]
+        } else {
+            std::iter::once(base_dir.join(include_path))
+                .chain(self.include_directories.iter().map(|dir| dir.join(include_path)))
+                .collect()
+        };
+
+        if let Some(resolved) = candidates.iter().find_map(|candidate| provider.resolve(candidate)) {
+            return Ok(resolved);
+        }
+
+        let tried: Vec<String> = candidates.iter().map(|c| format!("  {}", c.display())).collect();
+        Err(CompilerError::Include {
+            message: format!(
+                "Cannot find '{}' included from {}:{}; tried:\n{}",
+                include_path,
+                module_path.display(),
+                line_num,
+                tried.join("\n")
+            ),
+        })
+    }
+
     fn apply_module_imports(&mut self, graph: &mut ModuleGraph) -> Result<()> {
         // Apply imports in dependency order
         for module_path in graph.compilation_order.clone() {
@@ -200,6 +240,7 @@ impl Preprocessor {
             return Err(CompilerError::Parse {
                 file: "<unknown>".to_string(),
                 line: line_num,
+                column: 0,
                 message: "Invalid @include syntax: path must be quoted".to_string(),
             });
         }
@@ -225,6 +266,7 @@ impl Preprocessor {
         let end_quote_pos = end_quote_pos.ok_or_else(|| CompilerError::Parse {
             file: "<unknown>".to_string(),
             line: line_num,
+            column: 0,
             message: "Invalid @include syntax: missing closing quote".to_string(),
         })?;
 
@@ -238,6 +280,7 @@ impl Preprocessor {
             return Err(CompilerError::Parse {
                 file: "<unknown>".to_string(),
                 line: line_num,
+                column: 0,
                 message: format!("Invalid @include syntax: unexpected content after path: '{}'", 
                                after_quote),
             });
@@ -247,6 +290,7 @@ impl Preprocessor {
             return Err(CompilerError::Parse {
                 file: "<unknown>".to_string(),
                 line: line_num,
+                column: 0,
                 message: "Invalid @include syntax: path cannot be empty".to_string(),
             });
         }
@@ -287,6 +331,28 @@ impl Preprocessor {
 }
 
 
+/// Serves the root module from the source text handed to the preprocessor
+struct RootOverlay<'a> {
+    path: PathBuf,
+    source: &'a str,
+    inner: &'a dyn FileProvider,
+}
+
+impl FileProvider for RootOverlay<'_> {
+    fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        self.inner.resolve(path).or_else(|| (path == self.path).then(|| self.path.clone()))
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        if path == self.path {
+            Ok(self.source.to_string())
+        } else {
+            self.inner.read_to_string(path)
+        }
+    }
+}
+
+
 fn merge_module_graph_content(graph: &ModuleGraph) -> String {
     let mut merged = String::new();
     
@@ -301,4 +367,51 @@ fn merge_module_graph_content(graph: &ModuleGraph) -> String {
     merged
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
 
+    #[test]
+    fn test_include_directories_are_searched_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path().join("app");
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        for d in [&app_dir, &first, &second] {
+            fs::create_dir(d).unwrap();
+        }
+        fs::write(app_dir.join("main.kry"), "@include \"card.kry\"\n@include \"../second/card.kry\"\nApp {\n}\n").unwrap();
+        fs::write(second.join("card.kry"), "style \"card\" {\n}\n").unwrap();
+
+        let graph = Preprocessor::new()
+            .with_include_directories([&first, &second])
+            .process_includes_isolated(app_dir.join("main.kry").to_str().unwrap())
+            .unwrap();
+
+        // Both spellings resolve to the same canonical module
+        let card = fs::canonicalize(second.join("card.kry")).unwrap();
+        assert_eq!(graph.modules.len(), 2);
+        assert!(graph.modules.contains_key(&card));
+        assert_eq!(graph.compilation_order.len(), 2);
+        assert!(graph.modules[&graph.root_module].imports.iter().all(|i| i.module_path == card));
+    }
+
+    #[test]
+    fn test_missing_include_lists_every_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("lib");
+        fs::create_dir(&lib).unwrap();
+        let main = dir.path().join("main.kry");
+        fs::write(&main, "@include \"missing.kry\"\nApp {\n}\n").unwrap();
+
+        let error = Preprocessor::new()
+            .with_include_directories([&lib])
+            .process_includes_isolated(main.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains(&dir.path().join("missing.kry").display().to_string()), "{}", error);
+        assert!(error.contains(&lib.join("missing.kry").display().to_string()), "{}", error);
+    }
+}
@@ -0,0 +1,223 @@
+// FILE: src/compiler/middle_end/resources.rs
+
+//! Resource files embedded into the KRB output
+//!
+//! With `CompilerOptions::embed_resources` (or `embed_fonts` for fonts alone) the files
+//! behind resources are read through the `FileProvider`, relative to the file that
+//! referenced them, and stored after their resource records so a runtime without a
+//! filesystem needs nothing but the KRB. Identical files are stored once: later copies
+//! become `Shared` resources pointing at the first.
+
+use crate::compiler::file_provider::FileProvider;
+use crate::core::*;
+use crate::core::types::*;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::error::Result;
+use crate::CompilerOptions;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Read the files of every resource selected by `options`, reporting all missing or
+/// oversized files at once
+pub fn embed_resources(state: &mut CompilerState, provider: &dyn FileProvider, options: &CompilerOptions) -> Result<()> {
+    let mut diagnostics = Diagnostics::new();
+    let mut payloads: HashMap<md5::Digest, u16> = HashMap::new();
+
+    for index in 0..state.resources.len() {
+        let resource = &state.resources[index];
+        let embed = match resource.resource_type {
+            // Scripts have their own inline storage
+            ResourceType::Script => false,
+            ResourceType::Font => options.embed_resources || options.embed_fonts,
+            _ => options.embed_resources,
+        };
+        if !embed {
+            continue;
+        }
+
+        let file = state.strings.get(resource.data_string_index as usize).map(|s| s.text.clone()).unwrap_or_default();
+        let referenced_in = resource.location.as_ref().map_or(state.current_file_path.as_str(), |l| l.file.as_str());
+        let kind = type_name(resource.resource_type);
+
+        let data = match provider.resolve(&relative_to(referenced_in, &file)) {
+            Some(resolved) => provider.read_bytes(&resolved),
+            None => {
+                diagnostics.push(
+                    Diagnostic::error(format!("{} file '{}' was not found", kind, file)).with_span(resource.location.clone()),
+                );
+                continue;
+            }
+        };
+        let data = match data {
+            Ok(data) => data,
+            Err(error) => {
+                diagnostics.push(Diagnostic::error(error.to_string()).with_span(resource.location.clone()));
+                continue;
+            }
+        };
+
+        if let Some(&limit) = options.resource_size_limits.get(&resource.resource_type) {
+            if data.len() as u64 > limit {
+                diagnostics.push(
+                    Diagnostic::error(format!(
+                        "{} file '{}' is {} bytes, over the {} byte limit for embedded {}s",
+                        kind,
+                        file,
+                        data.len(),
+                        limit,
+                        kind.to_lowercase()
+                    ))
+                    .with_span(resource.location.clone()),
+                );
+                continue;
+            }
+        }
+
+        // Equal digests are confirmed byte for byte before the payload is shared
+        let digest = md5::compute(&data);
+        let shared = payloads.get(&digest).copied().filter(|&first| state.resources[first as usize].data == data);
+        let resource = &mut state.resources[index];
+        match shared {
+            Some(first) => {
+                resource.format = ResourceFormat::Shared;
+                resource.shared_with = Some(first);
+            }
+            None => {
+                payloads.entry(digest).or_insert(index as u16);
+                resource.format = ResourceFormat::Inline;
+                resource.data = data;
+            }
+        }
+    }
+
+    diagnostics.into_result().map(|_| ())
+}
+
+/// `path` as written in `source_file`, which may sit in another directory than the entry file
+pub(crate) fn relative_to(source_file: &str, path: &str) -> PathBuf {
+    Path::new(source_file).parent().unwrap_or(Path::new("")).join(path)
+}
+
+fn type_name(resource_type: ResourceType) -> &'static str {
+    match resource_type {
+        ResourceType::Image => "Image",
+        ResourceType::Font => "Font",
+        ResourceType::Sound => "Sound",
+        ResourceType::Video => "Video",
+        ResourceType::Script => "Script",
+        ResourceType::Custom => "Resource",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::properties::PropertyId;
+    use crate::core::types::{FontStyle, ResourceFormat, ResourceType, ValueType};
+    use crate::compiler::test_support::compile_app;
+    use crate::{CompilerOptions, KrbReader, MemoryFileProvider};
+
+    const FONTS: &str = "font \"Inter\" \"fonts/Inter.ttf\"\n\nfont \"Inter\" {\n    src: \"fonts/Inter-BoldItalic.ttf\"\n    weight: bold\n    style: italic\n    fallback: \"Roboto, sans-serif\"\n}\n\nfont \"Roboto\" \"fonts/Roboto.ttf\"\n\nApp {\n    Text {\n        text: \"Hello\"\n        font_family: \"Inter\"\n    }\n}\n";
+
+    const IMAGES: &str = "App {\n    icon: \"logo.png\"\n    Image {\n        src: \"img/logo.png\"\n    }\n    Image {\n        src: \"copy.png\"\n    }\n    Image {\n        src: \"click.wav\"\n    }\n}\n";
+
+    fn embedding(embed_fonts: bool, embed_resources: bool) -> CompilerOptions {
+        CompilerOptions { embed_fonts, embed_resources, ..CompilerOptions::default() }
+    }
+
+    #[test]
+    fn test_font_faces_round_trip() {
+        let provider = MemoryFileProvider::new()
+            .with_file("app/fonts/Inter.ttf", "regular")
+            .with_file("app/fonts/Inter-BoldItalic.ttf", "bold italic")
+            .with_file("app/fonts/Roboto.ttf", "roboto");
+
+        let external = compile_app(FONTS, &provider, CompilerOptions::default()).unwrap();
+        let file = KrbReader::new(&external).unwrap().read().unwrap();
+        let faces: Vec<_> = file.resources.iter().filter_map(|r| r.font.as_ref().map(|f| (r, f))).collect();
+        assert_eq!(faces.len(), 3);
+        let (bold, face) = faces[1];
+        assert_eq!(file.string(bold.name_index), Some("Inter"));
+        assert_eq!(file.string(bold.data_string_index), Some("fonts/Inter-BoldItalic.ttf"));
+        assert_eq!((face.weight, face.style), (700, FontStyle::Italic as u8));
+        let fallbacks: Vec<_> = face.fallbacks.iter().map(|&i| file.string(i).unwrap()).collect();
+        assert_eq!(fallbacks, vec!["Roboto", "sans-serif"]);
+        assert!(bold.data.is_empty());
+        assert!(crate::verify_krb(&external).is_empty());
+
+        let embedded = compile_app(FONTS, &provider, embedding(true, false)).unwrap();
+        let file = KrbReader::new(&embedded).unwrap().read().unwrap();
+        assert_eq!(file.resources[1].format, ResourceFormat::Inline as u8);
+        assert_eq!(file.resources[1].data, b"bold italic");
+        assert!(crate::verify_krb(&embedded).is_empty());
+    }
+
+    #[test]
+    fn test_font_errors_are_located() {
+        let source = FONTS.replace("font_family: \"Inter\"", "font_family: \"Comic\"").replace("Roboto, ", "Lato, ");
+        let error = compile_app(&source, &MemoryFileProvider::new(), CompilerOptions::default()).unwrap_err().to_string();
+        assert!(error.contains("Fallback font 'Lato' of 'Inter' is not declared"), "{}", error);
+        assert!(error.contains("Font family 'Comic' is not declared"), "{}", error);
+
+        let provider = MemoryFileProvider::new().with_file("app/fonts/Inter.ttf", "regular");
+        let error = compile_app(FONTS, &provider, embedding(true, false)).unwrap_err().to_string();
+        assert!(error.contains("main.kry:3"), "{}", error);
+        assert!(error.contains("Font file 'fonts/Inter-BoldItalic.ttf' was not found"), "{}", error);
+        assert!(error.contains("fonts/Roboto.ttf"), "{}", error);
+    }
+
+    #[test]
+    fn test_identical_files_are_embedded_once() {
+        let provider = MemoryFileProvider::new()
+            .with_file("app/logo.png", "PNG logo")
+            .with_file("app/img/logo.png", "PNG other")
+            .with_file("app/copy.png", "PNG logo")
+            .with_file("app/click.wav", b"RIFF\xFF\xFE click".to_vec());
+
+        let krb = compile_app(IMAGES, &provider, embedding(false, true)).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        let types: Vec<_> = file.resources.iter().map(|r| r.resource_type).collect();
+        assert_eq!(types, vec![ResourceType::Image as u8, ResourceType::Image as u8, ResourceType::Image as u8, ResourceType::Sound as u8]);
+        assert_eq!(file.resources[0].data, b"PNG logo");
+        assert_eq!(file.resources[1].data, b"PNG other");
+        assert_eq!(file.resources[2].format, ResourceFormat::Shared as u8);
+        assert_eq!(file.resources[2].shared_with, Some(0));
+        assert!(file.resources[2].data.is_empty());
+        assert_eq!(file.resources[3].data, b"RIFF\xFF\xFE click");
+        assert!(crate::verify_krb(&krb).is_empty());
+
+        // Without embedding, images are plain paths as they always were
+        let krb = compile_app(IMAGES, &provider, CompilerOptions::default()).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        assert!(file.resources.is_empty());
+        let src = file.elements[1].properties.iter().find(|p| p.property_id == PropertyId::ImageSource as u8).unwrap();
+        assert_eq!(src.value_type, ValueType::String as u8);
+        assert_eq!(file.string(src.value[0] as u16), Some("img/logo.png"));
+    }
+
+    #[test]
+    fn test_paths_resolve_from_the_referencing_module() {
+        let card = "Define Card {\n    Image {\n        src: \"img/a.png\"\n    }\n}\n";
+        let main = "@include \"lib/card.kry\"\n\nApp {\n    Image {\n        src: \"img/a.png\"\n    }\n    Card {}\n}\n";
+        let provider = MemoryFileProvider::new()
+            .with_file("app/lib/card.kry", card)
+            .with_file("app/img/a.png", "PNG app")
+            .with_file("app/lib/img/a.png", "PNG lib");
+
+        let krb = compile_app(main, &provider, embedding(false, true)).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        let data: Vec<_> = file.resources.iter().map(|r| r.data.as_slice()).collect();
+        assert_eq!(data, [b"PNG app".as_slice(), b"PNG lib".as_slice()]);
+    }
+
+    #[test]
+    fn test_missing_and_oversized_files_point_at_properties() {
+        let provider = MemoryFileProvider::new().with_file("app/logo.png", "PNG logo").with_file("app/copy.png", "PNG logo");
+        let mut options = embedding(false, true);
+        options.resource_size_limits.insert(ResourceType::Image, 4);
+
+        let error = compile_app(IMAGES, &provider, options).unwrap_err().to_string();
+        assert!(error.contains("main.kry:2:5: error: Image file 'logo.png' is 8 bytes, over the 4 byte limit"), "{}", error);
+        assert!(error.contains("main.kry:4:9: error: Image file 'img/logo.png' was not found"), "{}", error);
+        assert!(error.contains("main.kry:10:9: error: Sound file 'click.wav' was not found"), "{}", error);
+    }
+}
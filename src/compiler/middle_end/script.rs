@@ -4,6 +4,8 @@ use crate::compiler::frontend::ast::{AstNode, ScriptSource};
 use crate::error::{CompilerError, Result};
 use crate::core::*;
 use crate::core::types::*;
+use crate::diagnostics::Span;
+use crate::compiler::middle_end::resources::relative_to;
 use crate::core::properties::PropertyId;
 use crate::CompilerOptions;
 
@@ -52,7 +54,7 @@ impl ScriptProcessor {
     
     pub fn process_script(&self, script_node: &AstNode, state: &mut CompilerState) -> Result<ScriptEntry> {
         match script_node {
-            AstNode::Script { language, name, source, mode } => {
+            AstNode::Script { language, name, source, mode, location } => {
                 let language_id = ScriptLanguage::from_name(language)
                     .ok_or_else(|| CompilerError::script_legacy(0, format!("Unsupported script language: {}", language)))?;
                 
@@ -94,18 +96,23 @@ impl ScriptProcessor {
                             ScriptLanguage::Wren => ResourceType::Script,
                         };
                         
-                        let resource_idx = state.add_resource(res_type as u8, path)?;
+                        let resource_idx = state.add_resource(res_type, path, None)?;
                         let entry_points = self.extract_entry_points(language_id, source)?;
-                        (SCRIPT_STORAGE_EXTERNAL, resource_idx as u16, Vec::new(), Some(resource_idx), source.clone(), entry_points)
+                        (SCRIPT_STORAGE_EXTERNAL, resource_idx, Vec::new(), Some(resource_idx), source.clone(), entry_points)
                     }
                 };
                 
                 let mut script_functions = Vec::new();
                 for func_name in entry_points {
                     let func_name_index = state.add_string(&func_name)?;
+                    let function_location = match (source, location) {
+                        (ScriptSource::Inline(code), Some(start)) => self.locate_function(language_id, code, &func_name, start),
+                        _ => location.clone(),
+                    };
                     script_functions.push(ScriptFunction {
                         function_name: func_name,
                         function_name_index: func_name_index,
+                        location: function_location,
                     });
                 }
                 
@@ -121,13 +128,14 @@ impl ScriptProcessor {
                     },
                     name_index,
                     storage_format,
-                    entry_point_count: script_functions.len() as u8,
+                    entry_point_count: script_functions.len() as u16,
                     data_size,
                     entry_points: script_functions,
                     code_data: code_data.to_vec(),
                     resource_index,
                     calculated_size,
-                    source_line_num: 0, // Would be set by parser
+                    source_line_num: location.as_ref().map_or(0, |l| l.line),
+                    location: location.clone(),
                 })
             }
             _ => Err(CompilerError::script_legacy(0, "Expected script node"))
@@ -157,6 +165,22 @@ impl ScriptProcessor {
         Ok(functions)
     }
     
+    /// Where `function` is defined in inline `code` that starts at `start`
+    fn locate_function(&self, language: ScriptLanguage, code: &str, function: &str, start: &Span) -> Option<Span> {
+        let regex = self.function_regex.get(&language)?;
+        let name = regex.captures_iter(code)
+            .filter_map(|captures| captures.get(1))
+            .find(|name| name.as_str() == function)?;
+        
+        let before = &code[..name.start()];
+        let line_offset = before.matches('\n').count();
+        let column = match before.rfind('\n') {
+            Some(newline) => before[newline + 1..].chars().count() + 1,
+            None => start.column + before.chars().count(),
+        };
+        Some(Span::new(start.file.clone(), start.line + line_offset, column, function.chars().count()))
+    }
+    
     fn calculate_script_size(&self, entry_points: &[ScriptFunction], code_data: &[u8]) -> u32 {
         // Basic calculation: header + entry points + code data
         let header_size = 8; // Basic script entry header
@@ -282,12 +306,13 @@ impl ScriptProcessor {
 }
 
 impl CompilerState {
-    pub fn add_resource(&mut self, resource_type: u8, path: &str) -> Result<u8> {
+    pub fn add_resource(&mut self, resource_type: ResourceType, path: &str, location: Option<Span>) -> Result<u16> {
         let name_index = self.add_string(path)?;
         
-        // Check for existing resource
+        // Check for existing resource; the same path written in another directory names another file
+        let file = |location: &Option<Span>| relative_to(location.as_ref().map_or(self.current_file_path.as_str(), |l| l.file.as_str()), path);
         for entry in &self.resources {
-            if entry.resource_type as u8 == resource_type && entry.name_index == name_index {
+            if entry.resource_type == resource_type && entry.name_index == name_index && file(&entry.location) == file(&location) {
                 return Ok(entry.index);
             }
         }
@@ -299,15 +324,20 @@ impl CompilerState {
             });
         }
         
-        let index = self.resources.len() as u8;
+        let index = self.resources.len() as u16;
         self.resources.push(ResourceEntry {
-            resource_type: ResourceType::Script, // This should be parameterized
+            resource_type,
             name_index,
             format: ResourceFormat::External,
             data_string_index: name_index,
             index,
+            font: None,
+            data: Vec::new(),
+            shared_with: None,
+            location,
             calculated_size: 4,
         });
+        self.header_flags |= FLAG_HAS_RESOURCES;
         
         Ok(index)
     }
@@ -393,7 +423,7 @@ pub fn collect_function_templates(ast: &AstNode, state: &mut CompilerState) -> R
         AstNode::File { scripts, components, .. } => {
             // Collect global function templates (including scripts associated with components)
             for script_node in scripts {
-                if let AstNode::Script { language, name, source, .. } = script_node {
+                if let AstNode::Script { language, name, source, location, .. } = script_node {
                     if let Some(func_name) = name {
                         // Named global function
                         let template = create_function_template(
@@ -402,6 +432,7 @@ pub fn collect_function_templates(ast: &AstNode, state: &mut CompilerState) -> R
                             source,
                             &[],
                             FunctionScope::Global,
+                            location.as_ref(),
                             state
                         )?;
                         state.function_templates.push(template);
@@ -427,11 +458,11 @@ pub fn collect_function_templates(ast: &AstNode, state: &mut CompilerState) -> R
             // Collect component function templates
             for component_node in components {
                 if let AstNode::Component { name: comp_name, functions, .. } = component_node {
-                    println!("DEBUG: Processing component '{}' with {} functions", comp_name, functions.len());
+                    log::debug!("Processing component '{}' with {} functions", comp_name, functions.len());
                     for script_node in functions {
-                        if let AstNode::Script { language, name, source, .. } = script_node {
+                        if let AstNode::Script { language, name, source, location, .. } = script_node {
                             if let Some(func_name) = name {
-                                println!("DEBUG: Creating function template '{}' for component '{}' (raw function name from parser)", func_name, comp_name);
+                                log::debug!("Creating function template '{}' for component '{}' (raw function name from parser)", func_name, comp_name);
                                 // Named @function - create single template
                                 let template = create_function_template(
                                     func_name,
@@ -439,18 +470,19 @@ pub fn collect_function_templates(ast: &AstNode, state: &mut CompilerState) -> R
                                     source,
                                     &[],
                                     FunctionScope::Component(comp_name.clone()),
+                                    location.as_ref(),
                                     state
                                 )?;
                                 state.function_templates.push(template);
-                                println!("DEBUG: Added function template to state. Total templates: {}", state.function_templates.len());
+                                log::debug!("Added function template to state. Total templates: {}", state.function_templates.len());
                             } else {
                                 // Unnamed @script block - store for deferred processing during component instantiation
-                                println!("DEBUG: Storing @script block for component: {}", comp_name);
+                                log::debug!("Storing @script block for component: {}", comp_name);
                                 state.component_scripts
                                     .entry(comp_name.clone())
                                     .or_insert_with(Vec::new)
                                     .push(script_node.clone());
-                                println!("DEBUG: Component {} now has {} stored scripts", comp_name, state.component_scripts.get(comp_name).unwrap().len());
+                                log::debug!("Component {} now has {} stored scripts", comp_name, state.component_scripts.get(comp_name).unwrap().len());
                             }
                         }
                     }
@@ -460,7 +492,7 @@ pub fn collect_function_templates(ast: &AstNode, state: &mut CompilerState) -> R
         _ => {}
     }
     
-    println!("DEBUG: collect_function_templates finished. Total function templates: {}, component scripts: {}", 
+    log::debug!("collect_function_templates finished. Total function templates: {}, component scripts: {}", 
              state.function_templates.len(), 
              state.component_scripts.len());
     
@@ -474,6 +506,7 @@ fn create_function_template(
     source: &ScriptSource,
     parameters: &[String],
     scope: FunctionScope,
+    location: Option<&Span>,
     state: &mut CompilerState,
 ) -> Result<FunctionTemplate> {
     use crate::core::{FunctionTemplate, SourceLocation};
@@ -505,10 +538,17 @@ fn create_function_template(
         language: language.to_string(),
         scope,
         required_vars,
-        source_location: SourceLocation {
-            file: state.current_file_path.clone(),
-            line: state.current_line_num,
-            column: 0,
+        source_location: match location {
+            Some(location) => SourceLocation {
+                file: location.file.clone(),
+                line: location.line,
+                column: location.column,
+            },
+            None => SourceLocation {
+                file: state.current_file_path.clone(),
+                line: state.current_line_num,
+                column: 0,
+            },
         },
     })
 }
@@ -607,9 +647,17 @@ pub fn process_resolved_scripts(state: &mut CompilerState) -> Result<()> {
         
         // Create script function entry point
         let func_name_index = state.add_string(&resolved_func.name)?;
+        let location = state.function_templates.iter()
+            .find(|template| template.id == resolved_func.template_id)
+            .filter(|template| template.source_location.line > 0)
+            .map(|template| {
+                let source = &template.source_location;
+                Span::new(source.file.clone(), source.line, source.column, 0)
+            });
         let script_function = ScriptFunction {
             function_name: resolved_func.name.clone(),
             function_name_index: func_name_index,
+            location: location.clone(),
         };
         
         // Apply script-aware variable substitution to the already-complete function code
@@ -638,7 +686,8 @@ pub fn process_resolved_scripts(state: &mut CompilerState) -> Result<()> {
             entry_points: vec![script_function],
             resource_index: None,
             calculated_size,
-            source_line_num: 0, // Not available for resolved functions
+            source_line_num: location.as_ref().map_or(0, |l| l.line),
+            location,
         };
         
         state.scripts.push(script_entry);
@@ -654,7 +703,7 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
     use std::collections::HashMap;
     
     // Collect all variables from @variables blocks
-    let mut variable_map: HashMap<String, (u8, ValueType)> = HashMap::new();
+    let mut variable_map: HashMap<String, (u16, ValueType)> = HashMap::new();
     
     // First, collect variable data to avoid borrowing conflicts
     let variables_to_process: Vec<(String, String)> = state.variables.iter()
@@ -664,13 +713,13 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
     // Create template variables from the @variables block
     for (var_name, var_value) in variables_to_process {
         let name_index = if let Some(idx) = state.strings.iter().position(|s| s.text == var_name) {
-            idx as u8
+            idx as u16
         } else {
             state.add_string(var_name.clone())?
         };
         
         let default_value_index = if let Some(idx) = state.strings.iter().position(|s| s.text == var_value) {
-            idx as u8
+            idx as u16
         } else {
             state.add_string(var_value.clone())?
         };
@@ -694,7 +743,7 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
             default_value_index,
         };
         
-        variable_map.insert(var_name.clone(), (state.template_variables.len() as u8, value_type));
+        variable_map.insert(var_name.clone(), (state.template_variables.len() as u16, value_type));
         state.template_variables.push(template_var);
     }
     
@@ -703,10 +752,9 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
     for (element_index, element) in state.elements.iter().enumerate() {
         // First check krb_properties for TemplateVariable types
         for krb_prop in &element.krb_properties {
-            if krb_prop.value_type == ValueType::TemplateVariable && !krb_prop.value.is_empty() {
+            if krb_prop.value_type == ValueType::TemplateVariable {
                 // Get the variable name from the string table
-                let string_index = krb_prop.value[0];
-                if let Some(string_entry) = state.strings.get(string_index as usize) {
+                if let Some(string_entry) = krb_prop.index().and_then(|index| state.strings.get(index as usize)) {
                     let var_name = &string_entry.text;
                     if var_name.starts_with('$') {
                         let template_variables = vec![var_name[1..].to_string()]; // Remove $
@@ -718,8 +766,11 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
         
         // Also check source_properties for backward compatibility
         for source_prop in &element.source_properties {
-            // Check if this property has template variables
-            let template_variables = extract_template_variables(&source_prop.value);
+            // Check if this property has template variables; theme tokens are not bound at runtime
+            let template_variables: Vec<String> = extract_template_variables(&source_prop.value)
+                .into_iter()
+                .filter(|name| !state.is_theme_token(name))
+                .collect();
             
             if options.debug_mode {
                 log::debug!("Element {}: property '{}' = '{}' -> template vars: {:?}", 
@@ -740,7 +791,7 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
     for (element_index, property_id, prop_value, template_variables) in properties_to_process {
         // Get the expression string index
         let expression_index = if let Some(idx) = state.strings.iter().position(|s| s.text == prop_value) {
-            idx as u8
+            idx as u16
         } else {
             state.add_string(prop_value.clone())?
         };
@@ -758,7 +809,7 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
             property_id,
             template_expression: prop_value.clone(),
             template_expression_index: expression_index,
-            variable_count: variable_indices.len() as u8,
+            variable_count: variable_indices.len() as u16,
             variable_indices,
         };
         
@@ -792,7 +843,7 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
             } else {
                 // Fallback to string if percentage parsing fails
                 let string_index = state.add_string(resolved_value.clone())?;
-                (ValueType::String, 1, vec![string_index])
+                (ValueType::String, 2, string_index.to_le_bytes().to_vec())
             }
         } else if let Ok(val) = resolved_value.parse::<f32>() {
             // It's a float value
@@ -805,12 +856,12 @@ pub fn process_template_variables(state: &mut CompilerState, options: &CompilerO
             } else {
                 // Fallback to string if color parsing fails
                 let string_index = state.add_string(resolved_value.clone())?;
-                (ValueType::String, 1, vec![string_index])
+                (ValueType::String, 2, string_index.to_le_bytes().to_vec())
             }
         } else {
             // Keep it as a string
             let string_index = state.add_string(resolved_value)?;
-            (ValueType::String, 1, vec![string_index])
+            (ValueType::String, 2, string_index.to_le_bytes().to_vec())
         };
         
         resolved_substitutions.push((element_index, property_id, new_value_type, new_size, new_value));
@@ -464,9 +464,9 @@ impl LuaCompiler {
         
         // Report warnings if any (don't fail compilation, just warn)
         if !errors.is_empty() {
-            eprintln!("Lua semantic warnings in {}:", file_path);
+            log::warn!("Lua semantic warnings in {}:", file_path);
             for error in errors {
-                eprintln!("  Warning: {}", error);
+                log::warn!("  Warning: {}", error);
             }
         }
         
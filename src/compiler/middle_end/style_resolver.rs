@@ -3,7 +3,7 @@
 use crate::error::{CompilerError, Result};
 use crate::core::*;
 use crate::core::types::*;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 
 pub struct StyleResolver {
     resolution_cache: HashMap<String, Vec<KrbProperty>>,
@@ -39,8 +39,8 @@ impl StyleResolver {
         Ok(())
     }
     
-    fn build_dependency_graph(&self, state: &CompilerState) -> Result<HashMap<String, Vec<String>>> {
-        let mut graph = HashMap::new();
+    fn build_dependency_graph(&self, state: &CompilerState) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut graph = BTreeMap::new();
         
         for style in &state.styles {
             let mut dependencies = Vec::new();
@@ -63,9 +63,9 @@ impl StyleResolver {
         Ok(graph)
     }
     
-    fn topological_sort(&self, graph: &HashMap<String, Vec<String>>) -> Result<Vec<String>> {
-        let mut in_degree = HashMap::new();
-        let mut adj_list = HashMap::new();
+    fn topological_sort(&self, graph: &BTreeMap<String, Vec<String>>) -> Result<Vec<String>> {
+        let mut in_degree = BTreeMap::new();
+        let mut adj_list = BTreeMap::new();
         
         // Initialize in-degree and adjacency list
         for (node, dependencies) in graph {
@@ -138,8 +138,8 @@ impl StyleResolver {
             return Ok(());
         }
         
-        // Collect inherited properties
-        let mut inherited_properties = HashMap::new();
+        // Collect inherited properties, keyed by property ID so the merged list has a stable order
+        let mut inherited_properties = BTreeMap::new();
         
         // Process inheritance chain
         for base_style_name in &extends_style_names {
@@ -364,12 +364,7 @@ impl StyleResolver {
                 if cleaned_value.starts_with('$') {
                     // This is a template variable - store it as a string for later resolution
                     let string_index = state.add_string(cleaned_value.clone())?;
-                    Some(KrbProperty {
-                        property_id: property_id as u8,
-                        value_type: ValueType::TemplateVariable,
-                        size: 1,
-                        value: vec![string_index],
-                    })
+                    Some(KrbProperty::with_index(property_id as u8, ValueType::TemplateVariable, string_index))
                 } else {
                     let visible = match cleaned_value.to_lowercase().as_str() {
                         "true" | "visible" | "1" => true,
@@ -682,9 +677,12 @@ mod tests {
             extends_style_names: vec![],
             properties: vec![],
             source_properties: vec![],
+            media_property_sets: vec![],
+            animations: vec![],
             calculated_size: 0,
             is_resolved: false,
             is_resolving: false,
+            location: None,
         });
         
         state.styles.push(StyleEntry {
@@ -694,9 +692,12 @@ mod tests {
             extends_style_names: vec!["base".to_string()],
             properties: vec![],
             source_properties: vec![],
+            media_property_sets: vec![],
+            animations: vec![],
             calculated_size: 0,
             is_resolved: false,
             is_resolving: false,
+            location: None,
         });
         
         let graph = resolver.build_dependency_graph(&state).unwrap();
@@ -725,7 +726,7 @@ mod tests {
     #[test]
     fn test_circular_dependency_detection() {
         let resolver = StyleResolver::new();
-        let mut graph = HashMap::new();
+        let mut graph = BTreeMap::new();
         
         graph.insert("a".to_string(), vec!["b".to_string()]);
         graph.insert("b".to_string(), vec!["c".to_string()]);
@@ -734,10 +735,36 @@ mod tests {
         let result = resolver.topological_sort(&graph);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_media_sets_follow_styles() {
+        use crate::compiler::test_support::compile;
+
+        let source = "@variables {\n    tablet: 900px\n}\n\nstyle \"base\" {\n    @media (max-width: $tablet) {\n        height: 40\n    }\n}\n\nstyle \"card\" {\n    extends: \"base\"\n    @media (max-width: 600px) {\n        height: 20\n    }\n}\n\nApp {\n    Container {\n        style: \"card\"\n        @media (min-width: 601px) and (max-width: $tablet) {\n            width: 600\n        }\n    }\n}\n";
+        let krb = compile(source).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        let sets = &file.elements[1].state_property_sets;
+        let bounds: Vec<Vec<(u8, Vec<u8>)>> = sets.iter()
+            .map(|set| set.properties.iter()
+                .filter(|p| p.property_id == PropertyId::MinViewportWidth as u8 || p.property_id == PropertyId::MaxViewportWidth as u8)
+                .map(|p| (p.property_id, p.value.clone()))
+                .collect())
+            .collect();
+        let max = |width: u16| (PropertyId::MaxViewportWidth as u8, width.to_le_bytes().to_vec());
+        let min = |width: u16| (PropertyId::MinViewportWidth as u8, width.to_le_bytes().to_vec());
+        assert!(sets.iter().all(|set| set.state_flags == STATE_MEDIA));
+        assert_eq!(bounds, vec![vec![max(900)], vec![max(600)], vec![min(601), max(900)]]);
+
+        let error = compile("App {\n    @media (min-width: $tablet) and (max-width: $phone) {\n        width: 1\n    }\n}\n").unwrap_err().to_string();
+        assert!(error.contains("main.kry:2:5: error: Unknown breakpoint '$tablet'"), "{}", error);
+        assert!(error.contains("main.kry:2:5: error: Unknown breakpoint '$phone'"), "{}", error);
+        let error = compile("App {\n    @media (min-width: 900px) and (max-width: 600px) {\n        width: 1\n    }\n}\n").unwrap_err();
+        assert!(error.to_string().contains("main.kry:2:5: error: Media query can never match"), "{}", error);
+    }
 }
 
 /// Convert a 64-bit float to 8.8 fixed point (16-bit)
-fn float_to_fixed_point(value: f64) -> u16 {
+pub(crate) fn float_to_fixed_point(value: f64) -> u16 {
     (value * 256.0).round() as u16
 }
 
@@ -754,6 +781,24 @@ pub fn apply_style_properties_to_elements(state: &mut CompilerState) -> Result<(
     for element in &mut state.elements {
         // Element.style_id is u8, not Option<u8>
         if element.style_id > 0 {
+            // Media sets of the style and the styles it extends come before the element's own, which win
+            let mut media_sets = Vec::new();
+            collect_media_sets(state.styles.as_slice(), element.style_id, &mut media_sets);
+            if !media_sets.is_empty() {
+                element.state_property_sets.splice(0..0, media_sets);
+                element.state_prop_count = element.state_property_sets.len() as u16;
+            }
+
+            // A style's animations apply unless the element sets its own of the same kind
+            let mut animations = Vec::new();
+            collect_style_animations(state.styles.as_slice(), element.style_id, &mut animations);
+            animations.retain(|style_ref| !element.krb_animations.iter().any(|own| own.kind == style_ref.kind));
+            if !animations.is_empty() {
+                element.krb_animations.splice(0..0, animations);
+                element.animation_count = element.krb_animations.len() as u16;
+                state.header_flags |= FLAG_HAS_ANIMATIONS;
+            }
+
             // Find the style by ID
             if let Some(style) = state.styles.iter().find(|s| s.id == element.style_id) {
                 // Apply width, height, and layout properties if found in style
@@ -781,3 +826,30 @@ pub fn apply_style_properties_to_elements(state: &mut CompilerState) -> Result<(
     Ok(())
 }
 
+/// `@media` sets of a style, base styles first
+fn collect_media_sets(styles: &[StyleEntry], style_id: u16, media_sets: &mut Vec<StatePropertySet>) {
+    let Some(style) = styles.iter().find(|s| s.id == style_id) else {
+        return;
+    };
+    for base in &style.extends_style_names {
+        if let Some(base) = styles.iter().find(|s| s.source_name == *base) {
+            collect_media_sets(styles, base.id, media_sets);
+        }
+    }
+    media_sets.extend(style.media_property_sets.iter().cloned());
+}
+
+/// Animation references of a style; a style replaces the references of each kind it sets in its bases
+fn collect_style_animations(styles: &[StyleEntry], style_id: u16, animations: &mut Vec<KrbAnimationRef>) {
+    let Some(style) = styles.iter().find(|s| s.id == style_id) else {
+        return;
+    };
+    for base in &style.extends_style_names {
+        if let Some(base) = styles.iter().find(|s| s.source_name == *base) {
+            collect_style_animations(styles, base.id, animations);
+        }
+    }
+    animations.retain(|inherited| !style.animations.iter().any(|own| own.kind == inherited.kind));
+    animations.extend(style.animations.iter().cloned());
+}
+
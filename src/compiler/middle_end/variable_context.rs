@@ -7,7 +7,9 @@
 //! - Style calculations (computed variables)
 
 use crate::error::{CompilerError, Result};
+use crate::diagnostics::{Diagnostic, Span};
 use crate::core::*;
+use crate::compiler::middle_end::expressions::{evaluate, Value};
 use crate::compiler::middle_end::module_context::ModuleContext;
 use crate::compiler::frontend::ast::{Expression, AstProperty};
 use crate::types::*;
@@ -229,12 +231,12 @@ impl VariableContext {
                     
                     result = result.replace(var_match.as_str(), &replacement);
                 } else {
-                    println!("DEBUG: Variable substitution failed for '{}' in context:", var_name);
-                    println!("  Input string: {}", input);
-                    println!("  Current scope: {:?}", self.current_scope);
-                    println!("  Available variables in all scopes:");
+                    log::debug!("Variable substitution failed for '{}' in context:", var_name);
+                    log::debug!("  Input string: {}", input);
+                    log::debug!("  Current scope: {:?}", self.current_scope);
+                    log::debug!("  Available variables in all scopes:");
                     for var in self.get_all_variables() {
-                        println!("    {} = {} (scope: {:?})", var.name, var.value, var.scope);
+                        log::debug!("    {} = {} (scope: {:?})", var.name, var.value, var.scope);
                     }
                     return Err(CompilerError::variable_legacy(
                         0,
@@ -327,86 +329,20 @@ impl VariableContext {
         true // Default to read context
     }
     
-    /// Evaluate an expression with variable substitution
-    pub fn evaluate_expression(&self, expr: &Expression) -> Result<String> {
-        match expr {
-            Expression::String(s) => Ok(s.clone()),
-            Expression::Number(n) => Ok(n.to_string()),
-            Expression::Integer(i) => Ok(i.to_string()),
-            Expression::Boolean(b) => Ok(b.to_string()),
-            Expression::Variable(var_name) => {
-                if let Some(var_entry) = self.get_variable(var_name) {
-                    Ok(var_entry.value.clone())
-                } else {
-                    Err(CompilerError::variable_legacy(
-                        0,
-                        format!("Undefined variable: ${}", var_name)
-                    ))
-                }
-            }
-            Expression::NotEquals(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
-                Ok((left_val != right_val).to_string())
-            }
-            Expression::EqualEquals(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
-                Ok((left_val == right_val).to_string())
-            }
-            Expression::LessThan(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
-                // Try to parse as numbers for comparison
-                if let (Ok(left_num), Ok(right_num)) = (left_val.parse::<f64>(), right_val.parse::<f64>()) {
-                    Ok((left_num < right_num).to_string())
-                } else {
-                    Ok((left_val < right_val).to_string())
-                }
-            }
-            Expression::LessThanOrEqual(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
-                if let (Ok(left_num), Ok(right_num)) = (left_val.parse::<f64>(), right_val.parse::<f64>()) {
-                    Ok((left_num <= right_num).to_string())
-                } else {
-                    Ok((left_val <= right_val).to_string())
-                }
-            }
-            Expression::GreaterThan(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
-                if let (Ok(left_num), Ok(right_num)) = (left_val.parse::<f64>(), right_val.parse::<f64>()) {
-                    Ok((left_num > right_num).to_string())
-                } else {
-                    Ok((left_val > right_val).to_string())
-                }
-            }
-            Expression::GreaterThanOrEqual(left, right) => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
-                if let (Ok(left_num), Ok(right_num)) = (left_val.parse::<f64>(), right_val.parse::<f64>()) {
-                    Ok((left_num >= right_num).to_string())
-                } else {
-                    Ok((left_val >= right_val).to_string())
-                }
-            }
-            Expression::Ternary { condition, true_value, false_value } => {
-                let condition_result = self.evaluate_expression(condition)?;
-                let is_true = match condition_result.as_str() {
-                    "true" => true,
-                    "false" => false,
-                    "" => false, // Empty string is falsy
-                    _ => true,   // Non-empty string is truthy
-                };
-                
-                if is_true {
-                    self.evaluate_expression(true_value)
-                } else {
-                    self.evaluate_expression(false_value)
-                }
-            }
-        }
+    /// Evaluate an expression with variable substitution, folding arithmetic and
+    /// units as `middle_end::expressions` does
+    pub fn evaluate_expression(&self, expr: &Expression, subject: &str, location: Option<&Span>) -> Result<String> {
+        self.evaluate_value(expr, subject, location).map(|value| value.to_string())
+    }
+    
+    /// Evaluate an expression against the variables in scope. Errors name `subject`,
+    /// the property or variable being evaluated, and point at `location`.
+    pub fn evaluate_value(&self, expr: &Expression, subject: &str, location: Option<&Span>) -> Result<Value> {
+        let lookup = |name: &str| self.get_variable(name).map(|entry| entry.value.clone());
+        evaluate(expr, &lookup).map_err(|message| {
+            let diagnostic = Diagnostic::error(format!("invalid expression in {}: {}", subject, message)).with_span(location.cloned());
+            CompilerError::Diagnostics(std::iter::once(diagnostic).collect())
+        })
     }
     
     /// Get all variables in current scope (for debugging)
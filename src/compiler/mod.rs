@@ -5,351 +5,788 @@
 // 1. Declare the stages of the compiler as sub-modules.
 //    Rust will look for these as directories inside `src/compiler/`.
 mod backend;
+pub mod file_provider;
 pub(crate) mod frontend;
 pub(crate) mod middle_end;
 mod optimizer;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 // 2. Bring necessary types and modules into the current scope.
 use crate::core::*;
-use crate::core::util::is_valid_identifier;
+use crate::diagnostics::Diagnostics;
 use crate::error::{CompilerError, Result};
 use crate::{CompilerOptions, CompilationStats}; // Refers to the struct in src/lib.rs
 
-use crate::compiler::frontend::semantic::{SemanticAnalyzer, convert_ast_to_state};
+use crate::compiler::frontend::semantic::convert_ast_to_state;
 use crate::compiler::frontend::ast::AstNode;
-use crate::compiler::frontend::parse_module_graph_to_ast;
 use crate::compiler::middle_end::module_context::ModuleGraph;
-use crate::compiler::middle_end::style_resolver::{StyleResolver, apply_style_properties_to_elements};
+use crate::compiler::middle_end::style_resolver::apply_style_properties_to_elements;
 use crate::compiler::middle_end::setup_from_module_graph;
 use crate::compiler::middle_end::script::{process_template_variables, collect_function_templates, process_resolved_scripts};
 
-use crate::compiler::middle_end::component_resolver::ComponentResolver;
-use crate::compiler::backend::size_calculator::SizeCalculator;
-use crate::compiler::backend::codegen::CodeGenerator;
+use crate::compiler::file_provider::{DiskFileProvider, FileProvider};
+use std::time::Instant;
 
 /// The main entry point for compiling a file.
-/// This function orchestrates the entire pipeline from source to binary.
+/// Reads the source from disk, compiles it and writes the KRB output.
 pub fn compile_with_options(
     input_path: &str,
     output_path: &str,
     options: CompilerOptions,
 ) -> Result<CompilationStats> {
-    use std::fs;
-    use std::time::Instant;
+    let source = DiskFileProvider.read_to_string(std::path::Path::new(input_path))?;
+    let (krb_data, stats) = compile_source(&source, input_path, &DiskFileProvider, options)?;
+    std::fs::write(output_path, krb_data)?;
 
+    // A stale map from an earlier debug build would point at the wrong sources
+    let map_path = KrbDebugMap::map_path(output_path);
+    match &stats.debug_map {
+        Some(map) => std::fs::write(&map_path, map.to_json()?)?,
+        None if map_path.exists() => std::fs::remove_file(&map_path)?,
+        None => {}
+    }
+    Ok(stats)
+}
+
+/// Compile source text without touching the filesystem.
+/// This function orchestrates the entire pipeline from source to binary;
+/// includes and external scripts are looked up through `provider`.
+pub fn compile_source(
+    source: &str,
+    input_path: &str,
+    provider: &dyn FileProvider,
+    options: CompilerOptions,
+) -> Result<(Vec<u8>, CompilationStats)> {
+    if !options.verify_reproducible {
+        return compile_source_once(source, input_path, provider, options);
+    }
+
+    let options = CompilerOptions { verify_reproducible: false, ..options };
+    let (krb_data, stats) = compile_source_once(source, input_path, provider, options.clone())?;
+    let (rebuilt, rebuilt_stats) = compile_source_once(source, input_path, provider, options)?;
+    if let Some(offset) = krb_data.iter().zip(&rebuilt).position(|(a, b)| a != b)
+        .or_else(|| (krb_data.len() != rebuilt.len()).then(|| krb_data.len().min(rebuilt.len())))
+    {
+        return Err(CompilerError::CodeGen {
+            message: format!(
+                "Output is not reproducible: two builds of {} first differ at byte {:#06x} ({})",
+                input_path,
+                offset,
+                section_at(&krb_data, offset)
+            ),
+        });
+    }
+    if stats.debug_map != rebuilt_stats.debug_map {
+        return Err(CompilerError::CodeGen {
+            message: format!("Output is not reproducible: two builds of {} produced different debug maps", input_path),
+        });
+    }
+    Ok((krb_data, stats))
+}
+
+/// Name of the KRB section containing `offset`, for reproducibility errors
+fn section_at(krb_data: &[u8], offset: usize) -> &'static str {
+    if offset < KRB_HEADER_SIZE {
+        return "header";
+    }
+    let Ok(info) = validate_krb_file(krb_data) else {
+        return "unreadable file";
+    };
+    if info.has_feature(FLAG_COMPRESSED) {
+        return "compressed payload";
+    }
+    [
+        (info.string_offset, "string table"),
+        (info.element_offset, "element tree"),
+        (info.style_offset, "style table"),
+        (info.component_offset, "component table"),
+        (info.animation_offset, "animation table"),
+        (info.script_offset, "script table"),
+        (info.resource_offset, "resource table"),
+        (info.template_variable_offset, "template variables"),
+        (info.template_binding_offset, "template bindings"),
+        (info.transform_offset, "transforms"),
+        (info.theme_offset, "theme table"),
+    ]
+    .into_iter()
+    .filter(|&(start, _)| start as usize <= offset)
+    .max_by_key(|&(start, _)| start)
+    .map_or("header", |(_, name)| name)
+}
+
+fn compile_source_once(
+    source: &str,
+    input_path: &str,
+    provider: &dyn FileProvider,
+    options: CompilerOptions,
+) -> Result<(Vec<u8>, CompilationStats)> {
     let start_time = Instant::now();
-    let source_size = fs::metadata(input_path)?.len();
 
     // =======================================================
     // THE COMPILER PIPELINE
     // =======================================================
 
+    // STAGES 1 TO 4: PREPROCESSING, PARSING AND SEMANTIC ANALYSIS
+    let (module_graph, analysis) = analyze_source(source, input_path, provider, &options)?;
+
+    compile_analysis(analysis, &module_graph, input_path, provider, &options, source.len() as u64, start_time)
+}
+
+/// Stages 4.5 to 8 for an analyzed AST, followed by the statistics of the whole compilation
+fn compile_analysis(
+    analysis: Analysis,
+    module_graph: &ModuleGraph,
+    input_path: &str,
+    provider: &dyn FileProvider,
+    options: &CompilerOptions,
+    source_size: u64,
+    start_time: Instant,
+) -> Result<(Vec<u8>, CompilationStats)> {
+    let Analysis { mut ast, mut state, mut diagnostics } = analysis;
+    let krb_data = lower_to_krb(&mut ast, &mut state, options, provider).map_err(|e| diagnostics.fail(e))?;
+
+    // =======================================================
+    // FINAL STATS & OUTPUT
+    // =======================================================
+
+    let mut stats = CompilationStats::default();
+    stats.source_size = source_size;
+    stats.output_size = krb_data.len() as u64;
+    // Reading the header back also checks that a compressed payload inflates to it
+    stats.uncompressed_size = validate_krb_file(&krb_data)?.total_size as u64;
+    stats.compression_ratio = if source_size > 0 {
+        stats.output_size as f64 / source_size as f64
+    } else {
+        0.0
+    };
+    stats.compile_time_ms = start_time.elapsed().as_millis() as u64;
+    stats.element_count = state.elements.len();
+    stats.style_count = state.styles.len();
+    stats.component_count = state.component_defs.len();
+    stats.script_count = state.scripts.len();
+    stats.resource_count = state.resources.len();
+    stats.string_count = state.strings.len();
+    stats.include_count = module_graph.modules.len();
+    stats.variable_count = state.variables.len();
+    stats.dependencies = collect_dependencies(input_path, module_graph, &state, provider);
+    stats.warnings = diagnostics.warnings();
+    if options.generate_debug_info {
+        stats.debug_map = Some(backend::debug_info::build_debug_map(&state));
+    }
+
+    Ok((krb_data, stats))
+}
+
+/// An analyzed AST with the state and warnings collected while producing it
+pub(crate) struct Analysis {
+    pub ast: AstNode,
+    pub state: CompilerState,
+    pub diagnostics: Diagnostics,
+}
+
+/// Stages 1 to 4 of `compile_source`: build the include graph of `source`, then analyze it
+pub(crate) fn analyze_source(
+    source: &str,
+    input_path: &str,
+    provider: &dyn FileProvider,
+    options: &CompilerOptions,
+) -> Result<(ModuleGraph, Analysis)> {
     // STAGE 1: PREPROCESSING (Middle-End)
     // Handle @includes and build the module graph before any parsing.
-    let mut preprocessor = middle_end::preprocessor::Preprocessor::new();
-    let module_graph = preprocessor.process_includes_isolated(input_path)?;
+    let mut preprocessor = middle_end::preprocessor::Preprocessor::new()
+        .with_include_directories(&options.include_directories);
+    let module_graph = preprocessor.process_source_isolated(source, input_path, provider)?;
+
+    let analysis = analyze_module_graph(&module_graph, input_path, options)?;
+    Ok((module_graph, analysis))
+}
 
+/// Stages 2 to 4 of `compile_source` for an existing module graph. An error carries
+/// the warnings found before it.
+pub(crate) fn analyze_module_graph(
+    module_graph: &ModuleGraph,
+    input_path: &str,
+    options: &CompilerOptions,
+) -> Result<Analysis> {
     // STAGE 2: SETUP
     // Initialize the main CompilerState and the VariableContext from the module graph.
     let mut state = CompilerState::new();
     state.current_file_path = input_path.to_string();
-    setup_from_module_graph(&mut state, &module_graph, &options)?;
+    setup_from_module_graph(&mut state, module_graph, options)?;
 
     // STAGE 3: PARSING (Frontend)
     // Parse all modules into a single Abstract Syntax Tree (AST).
-    let mut ast = frontend::parse_module_graph(&module_graph, &options)?;
+    let mut ast = frontend::parse_module_graph(module_graph, options)?;
+
+    // STAGE 3.5: PLATFORM FILTERING (Middle-End)
+    // Remove what the target renderer cannot display, warning about each removal.
+    let mut diagnostics = middle_end::platform::strip_unsupported(&mut ast, options.target_platform);
+
+    // STAGE 3.6: COLOR FOLDING (Middle-End)
+    // Evaluate rgb(), hsl(), lighten(), mix() and the other color functions to RGBA values.
+    middle_end::colors::fold_color_functions(&mut ast, &state.variables).into_result().map_err(|e| diagnostics.fail(e))?;
+
+    // STAGE 3.7: EXPRESSION FOLDING (Middle-End)
+    // Evaluate arithmetic, min(), max() and clamp(); mixed-unit lengths become runtime calc values.
+    middle_end::expressions::fold_expressions(&mut ast, &state.variables).into_result().map_err(|e| diagnostics.fail(e))?;
 
     // STAGE 4: SEMANTIC ANALYSIS (Frontend)
     // Collect definitions (styles, components) from the AST and perform initial validation.
+    // Its warnings are kept and reported with the result, or with a later phase's error.
     let mut semantic_analyzer = frontend::semantic::SemanticAnalyzer::new();
-    semantic_analyzer.analyze(&mut ast, &mut state)?;
+    semantic_analyzer.analyze(&mut ast, &mut state).map_err(|e| diagnostics.fail(e))?;
+    diagnostics.extend(semantic_analyzer.take_diagnostics());
 
+    Ok(Analysis { ast, state, diagnostics })
+}
+
+/// Stages 4.5 to 8 of `compile_source`: resolve the analyzed AST and generate KRB bytes
+fn lower_to_krb(
+    ast: &mut AstNode,
+    state: &mut CompilerState,
+    options: &CompilerOptions,
+    provider: &dyn FileProvider,
+) -> Result<Vec<u8>> {
     // STAGE 4.5: FUNCTION TEMPLATE COLLECTION (Middle-End)
     // Collect function templates from component definitions for later instantiation.
-    collect_function_templates(&ast, &mut state)?;
+    collect_function_templates(ast, state)?;
 
     // STAGE 5: RESOLUTION (Middle-End)
     // Resolve style inheritance and expand component instances in the AST.
     let mut style_resolver = middle_end::style_resolver::StyleResolver::new();
-    style_resolver.resolve_all_styles(&mut state)?;
+    style_resolver.resolve_all_styles(state)?;
 
     // Component resolution - templates are now available from semantic analysis
-    let component_count = state.component_defs.len();
-    println!("DEBUG: Component count in main compile: {}", component_count);
+    log::debug!("Component count in main compile: {}", state.component_defs.len());
     
     // Always process template structures (@for loops, @if statements) even if no components
     let mut component_resolver = middle_end::component_resolver::ComponentResolver::new();
-    component_resolver.resolve_components(&mut ast, &mut state)?;
+    component_resolver.resolve_components(ast, state)?;
 
     // STAGE 5.5: RESOLVED SCRIPT PROCESSING (Middle-End)
     // Convert resolved function templates to script entries for the KRB
-    process_resolved_scripts(&mut state)?;
+    process_resolved_scripts(state)?;
 
     // STAGE 6: STATE CONVERSION
     // Convert the final, resolved AST into the internal `CompilerState` representation.
-    convert_ast_to_state(&ast, &mut state)?;
-    apply_style_properties_to_elements(&mut state)?;
-    process_template_variables(&mut state, &options)?;
+    convert_ast_to_state(ast, state)?;
+    apply_style_properties_to_elements(state)?;
+    process_template_variables(state, options)?;
+    middle_end::resources::embed_resources(state, provider, options)?;
 
     // STAGE 7: OPTIMIZATION
     // Run optimization passes on the generated internal state.
     let mut optim = optimizer::Optimizer::new();
-    optim.optimize(&mut state, options.optimization_level)?;
+    optim.optimize(state, options.optimization_level)?;
+
+    // STAGE 7.5: NUMBER ENCODING (Backend)
+    // Renderers without an FPU read every fractional number as Q16.16 fixed point.
+    if options.uses_fixed_point() {
+        backend::fixed_point::encode_fixed_point(state)?;
+    }
+    // Wide-gamut renderers read colors with 16 bits per channel.
+    if options.extended_color {
+        backend::extended_color::encode_extended_color(state)?;
+    }
 
     // STAGE 8: CODE GENERATION (Backend)
     // Calculate final sizes, offsets, and generate the binary KRB data.
     let size_calculator = backend::size_calculator::SizeCalculator::new();
-    size_calculator.calculate_sizes(&mut state)?;
-    size_calculator.validate_limits(&mut state)?;
+    size_calculator.calculate_sizes(state)?;
+    size_calculator.validate_limits(state, options.target_platform.profile())?;
 
-    let mut code_generator = backend::codegen::CodeGenerator::new();
-    let krb_data = code_generator.generate(&mut state)?;
+    let mut code_generator = backend::codegen::CodeGenerator::new()
+        .with_verification(options.debug_mode)
+        .with_compression(options.compress_output);
+    code_generator.generate(state)
+}
 
-    // =======================================================
-    // FINAL STATS & OUTPUT
-    // =======================================================
 
-    let mut stats = CompilationStats::default();
-    stats.source_size = source_size;
-    stats.output_size = krb_data.len() as u64;
-    stats.compression_ratio = if source_size > 0 {
-        stats.output_size as f64 / source_size as f64
-    } else {
-        0.0
-    };
-    stats.compile_time_ms = start_time.elapsed().as_millis() as u64;
-    stats.element_count = state.elements.len();
-    stats.style_count = state.styles.len();
-    stats.component_count = state.component_defs.len();
-    stats.script_count = state.scripts.len();
-    stats.resource_count = state.resources.len();
-    stats.string_count = state.strings.len();
-    stats.include_count = module_graph.modules.len();
-    stats.variable_count = state.variables.len();
-    
-    fs::write(output_path, krb_data)?;
+/// Files the output depends on: every module of the include graph plus the external
+/// script and resource files, found relative to the module that referenced them
+fn collect_dependencies(
+    input_path: &str,
+    module_graph: &ModuleGraph,
+    state: &CompilerState,
+    provider: &dyn FileProvider,
+) -> Vec<std::path::PathBuf> {
+    let mut dependencies: std::collections::BTreeSet<_> = module_graph.modules.keys().cloned().collect();
 
-    Ok(stats)
-}
+    for resource in &state.resources {
+        if let Some(entry) = state.strings.get(resource.data_string_index as usize) {
+            let referenced_in = resource.location.as_ref().map_or(input_path, |l| l.file.as_str());
+            if let Some(path) = provider.resolve(&middle_end::resources::relative_to(referenced_in, &entry.text)) {
+                dependencies.insert(path);
+            }
+        }
+    }
 
+    dependencies.into_iter().collect()
+}
 
-/// NEW: Compile with a module graph (module-aware compilation)
+/// Compile an already preprocessed module graph, reading the scripts and resources
+/// it refers to through `provider`
 pub fn compile_with_module_graph(
     module_graph: &ModuleGraph,
     filename: &str,
+    provider: &dyn FileProvider,
     options: CompilerOptions
 ) -> Result<(Vec<u8>, CompilationStats)> {
-    let mut state = CompilerState::new();
-    state.current_file_path = filename.to_string();
-    
-    let mut stats = CompilationStats::default();
-    
+    let start_time = Instant::now();
+
     if options.debug_mode {
         log::debug!("Starting module-aware compilation pipeline for {}", filename);
         log::debug!("Module graph has {} modules", module_graph.modules.len());
     }
-    
-    // Phase 0.1: Module processing - already done in preprocessor
-    if options.debug_mode {
-        log::debug!("Phase 0.1: Module processing complete");
-    }
-    
-    // Phase 0.2: Process variables with module isolation
-    if options.debug_mode {
-        log::debug!("Phase 0.2: Processing variables with module isolation...");
-    }
-    
-    // Set up the variable context with module support
-    let root_module = module_graph.modules.get(&module_graph.root_module)
-        .ok_or_else(|| CompilerError::Include { 
-            message: "Root module not found in module graph".to_string() 
-        })?;
-    
-    // Set current module context
-    state.variable_context.set_current_module(module_graph.root_module.clone());
-    
-    // Add variables from all modules in dependency order
-    for module in module_graph.get_ordered_modules() {
-        state.variable_context.add_module_variables(module)?;
-        
-        // Also add to legacy variables map for backward compatibility
-        for (name, var_def) in &module.variables {
-            if !module.is_private(name) || module.file_path == module_graph.root_module {
-                state.variables.insert(name.clone(), var_def.clone());
+
+    let analysis = analyze_module_graph(module_graph, filename, &options)?;
+    let source_size = module_graph.modules.get(&module_graph.root_module).map_or(0, |m| m.content.len() as u64);
+    compile_analysis(analysis, module_graph, filename, provider, &options, source_size, start_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::effects::{decode_filters, decode_shadows, format_filters, format_shadows};
+    use crate::core::gradient::{Gradient, GradientKind};
+    use crate::core::types::ValueType;
+    use crate::{verify_krb, KrbReader, MemoryFileProvider};
+    use super::test_support::{compile, compile_with, compile_with_stats};
+
+    /// Sources exercising every stage that keeps name-keyed maps: variables and
+    /// template bindings, style inheritance, function templates and components, then
+    /// animations, and last themes, media queries, fonts and effects. The repository
+    /// has no fixture files of its own; the examples the benchmarks load live in a
+    /// separate kryon-examples checkout, so these sources stand in for them.
+    const FIXTURES: &[&str] = &[
+        "@variables {\n    alpha: 1\n    beta: \"two\"\n    gamma: 3\n    delta: \"four\"\n    epsilon: 5\n    zeta: true\n}\n\nstyle \"a\" {\n    background_color: \"#FF0000FF\"\n}\nstyle \"b\" {\n    extends: \"a\"\n    border_width: 2\n}\nstyle \"c\" {\n    text_color: \"#00FF00FF\"\n}\n\n@function \"lua\" one() {\n    print(1)\n}\n@function \"lua\" two() {\n    print(2)\n}\n@function \"lua\" three() {\n    print(3)\n}\n\nDefine Card {\n    Properties {\n        title: String = \"t\"\n    }\n    @function \"lua\" toggle() {\n        print(\"t\")\n    }\n    @function \"lua\" close() {\n        print(\"c\")\n    }\n    Container {\n        Text {\n            text: $title\n        }\n    }\n}\n\nApp {\n    Container {\n        style: \"b\"\n        Text {\n            text: \"A $alpha B $beta\"\n        }\n        Text {\n            text: \"$gamma $delta $epsilon\"\n            style: \"c\"\n        }\n    }\n    Card {\n        title: \"x\"\n    }\n    Card {\n        title: \"y\"\n    }\n}\n",
+        "@keyframes pulse {\n    from {\n        opacity: 0\n    }\n    to {\n        opacity: 1\n    }\n}\n\nstyle \"base\" {\n    border_width: 1\n    border_color: \"#000000FF\"\n    background_color: \"#FFFFFFFF\"\n}\n\nApp {\n    window_title: \"Fixture\"\n    Container {\n        style: \"base\"\n        animation: \"pulse 1s infinite\"\n        Button {\n            text: \"Go\"\n        }\n    }\n}\n",
+        "@variables {\n    tablet: 900px\n}\n\n@theme light {\n    surface: \"#FFFFFFFF\"\n    gap: 8\n}\n\n@theme dark {\n    surface: \"#202020FF\"\n    gap: 4\n}\n\nfont \"Inter\" \"fonts/Inter.ttf\"\n\nstyle \"card\" {\n    background_color: $surface\n    padding: $gap\n    box_shadow: \"0 2px 4px #00000040\"\n    @media (max-width: $tablet) {\n        height: 40\n    }\n}\n\nApp {\n    Container {\n        style: \"card\"\n        background_image: linear-gradient(to right, red, blue)\n        filter: \"blur(2px) grayscale(50%)\"\n        @media (min-width: 601px) and (max-width: $tablet) {\n            width: 600\n        }\n        Text {\n            text: \"Hi\"\n            font_family: \"Inter\"\n        }\n    }\n}\n",
+    ];
+
+    #[test]
+    fn test_fixtures_compile_byte_identically() {
+        let provider = MemoryFileProvider::new();
+        for (index, source) in FIXTURES.iter().enumerate() {
+            for optimization_level in [0, 1] {
+                let options = CompilerOptions { optimization_level, compress_output: index == 1, ..CompilerOptions::default() };
+                let (first, _) = compile_source(source, "main.kry", &provider, options.clone()).unwrap();
+                let (second, _) = compile_source(source, "main.kry", &provider, options).unwrap();
+                assert!(first == second, "fixture {} differs between builds at -O{}", index, optimization_level);
             }
         }
     }
-    
-    // Inject custom variables
-    for (name, value) in &options.custom_variables {
-        if !is_valid_identifier(name) {
-            return Err(CompilerError::variable_legacy(
-                0,
-                format!("Invalid custom variable name '{}'", name)
-            ));
-        }
-        
-        state.variables.insert(name.clone(), crate::core::VariableDef {
-            value: value.clone(),
-            raw_value: value.clone(),
-            def_line: 0,
-            is_resolving: false,
-            is_resolved: true,
-        });
-        
-        state.variable_context.add_string_variable(
-            name.clone(),
-            value.clone(),
-            "custom".to_string(),
-            0
-        )?;
+
+    #[test]
+    fn test_dependencies_resolve_from_the_referencing_module() {
+        let provider = MemoryFileProvider::new()
+            .with_file("app/lib/fonts.kry", "font \"Inter\" \"fonts/Inter.ttf\"\n")
+            .with_file("app/lib/fonts/Inter.ttf", "regular")
+            .with_file("app/fonts/Inter.ttf", "unrelated");
+        let source = "@include \"lib/fonts.kry\"\n\nApp {\n    Text {\n        text: \"Hi\"\n        font_family: \"Inter\"\n    }\n}\n";
+        let (_, stats) = compile_source(source, "app/main.kry", &provider, CompilerOptions::default()).unwrap();
+        let dependencies: Vec<_> = stats.dependencies.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+        assert_eq!(dependencies, ["app/lib/fonts/Inter.ttf", "app/lib/fonts.kry", "app/main.kry"]);
     }
-    
-    stats.variable_count = state.variables.len();
-    
-    if options.debug_mode {
-        log::debug!("Phase 0.2 complete. Variables resolved: {}", stats.variable_count);
+
+    #[test]
+    fn test_module_graph_embeds_resources_through_the_provider() {
+        let provider = MemoryFileProvider::new()
+            .with_file("app/lib/fonts.kry", "font \"Inter\" \"fonts/Inter.ttf\"\n")
+            .with_file("app/lib/fonts/Inter.ttf", "regular");
+        let source = "@include \"lib/fonts.kry\"\n\nApp {\n    Text {\n        text: \"Hi\"\n        font_family: \"Inter\"\n    }\n}\n";
+        let options = CompilerOptions { embed_fonts: true, ..CompilerOptions::default() };
+        let module_graph = middle_end::preprocessor::Preprocessor::new()
+            .process_source_isolated(source, "app/main.kry", &provider)
+            .unwrap();
+
+        let (krb, stats) = compile_with_module_graph(&module_graph, "app/main.kry", &provider, options.clone()).unwrap();
+        let (expected, expected_stats) = compile_source(source, "app/main.kry", &provider, options).unwrap();
+        assert_eq!(krb, expected);
+        assert_eq!(KrbReader::new(&krb).unwrap().read().unwrap().resources[0].data, b"regular");
+        assert_eq!(stats.uncompressed_size, expected_stats.uncompressed_size);
+        assert_eq!(stats.dependencies, expected_stats.dependencies);
     }
-    
-    // Phase 1: Lexical analysis and parsing
-    if options.debug_mode {
-        log::debug!("Phase 1: Lexical analysis and parsing...");
+
+    #[test]
+    fn test_verify_reproducible_option() {
+        let options = CompilerOptions { verify_reproducible: true, generate_debug_info: true, ..CompilerOptions::default() };
+        let (krb, stats) = compile_with_stats(FIXTURES[0], options).unwrap();
+        assert!(stats.debug_map.is_some());
+
+        assert_eq!(section_at(&krb, 4), "header");
+        assert_eq!(section_at(&krb, KRB_HEADER_SIZE), "string table");
+        let info = validate_krb_file(&krb).unwrap();
+        assert_eq!(section_at(&krb, info.style_offset as usize), "style table");
     }
-    
-    // Parse each module separately to preserve component template boundaries
-    let ast = parse_module_graph_to_ast(module_graph, &options)?;
-    
-    if options.debug_mode {
-        log::debug!("Phase 1 complete. AST parsed successfully");
+
+    #[test]
+    fn test_theme_tokens_are_checked() {
+        let messages = |error: CompilerError| {
+            let CompilerError::Diagnostics(diagnostics) = error else { unreachable!("expected located errors: {}", error) };
+            diagnostics.iter().map(|d| (d.message.clone(), d.span.as_ref().map(|s| s.line))).collect::<Vec<_>>()
+        };
+
+        let missing = "@theme light {\n    surface: \"#FFFFFFFF\"\n    side: 50%\n}\n\n@theme dark {\n    side: 40%\n}\n\nApp {\n    background_color: $surface\n}\n";
+        let errors = messages(compile(missing).unwrap_err());
+        assert_eq!(errors, [("theme 'dark' does not define token 'surface'".to_string(), Some(6))]);
+
+        let mismatch = "@theme light {\n    side: 50%\n}\n\n@theme dark {\n    side: 120\n}\n\nApp {\n    width: $side\n}\n";
+        let errors = messages(compile(mismatch).unwrap_err());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.contains("is a Percentage in theme 'light' but a Short in theme 'dark'"), "{}", errors[0].0);
+        assert_eq!(errors[0].1, Some(6));
+
+        let unfit = "@theme light {\n    fg: \"#000000FF\"\n}\n\n@theme dark {\n    fg: 5\n}\n\nApp {\n    border_color: $fg\n}\n";
+        let CompilerError::Diagnostics(diagnostics) = compile(unfit).unwrap_err() else { unreachable!() };
+        let error = diagnostics.iter().next().unwrap();
+        assert_eq!((error.message.as_str(), error.span.as_ref().map(|s| s.line)), ("Invalid color value: 5", Some(6)));
+        assert_eq!(error.notes[0].message, "border_color uses the token here");
+        assert_eq!(error.notes[0].span.as_ref().map(|s| (s.file.as_str(), s.line)), Some(("main.kry", 10)));
     }
-    
-    // Continue with remaining phases using the existing pipeline
-    let result = compile_ast_with_state(ast, &mut state, &options)?;
-    
-    stats.element_count = state.elements.len();
-    stats.style_count = state.styles.len();
-    stats.component_count = state.component_defs.len();
-    stats.script_count = state.scripts.len();
-    stats.resource_count = state.resources.len();
-    stats.string_count = state.strings.len();
-    stats.include_count = module_graph.modules.len();
-    
-    Ok((result, stats))
-}
 
+    #[test]
+    fn test_gradients_are_encoded() {
+        let source = "@variables {\n    brand: #3366FF\n}\n\n@theme light {\n    hero: radial-gradient(white, $brand)\n}\n\nDefine Banner {\n    Container {\n        background_image: linear-gradient(to left, red, blue)\n    }\n}\n\nApp {\n    background_image: $hero\n    Container {\n        background_image: linear-gradient(0.25turn, lighten(#000, 50%), $brand 30%, red)\n    }\n    Banner {}\n}\n";
+        let with_colors = |extended_color| compile_with(source, CompilerOptions { extended_color, ..CompilerOptions::default() }).unwrap();
+        for (krb, color_size) in [(with_colors(false), 4), (with_colors(true), 8)] {
+            assert_eq!(verify_krb(&krb), Vec::new());
+            let file = KrbReader::new(&krb).unwrap().read().unwrap();
+            let gradient = |element: usize| {
+                let property = file.elements[element].properties.iter().find(|p| p.property_id == PropertyId::BackgroundImage as u8).unwrap();
+                assert_eq!(property.value_type, ValueType::Gradient as u8);
+                Gradient::decode(&property.value, color_size).unwrap()
+            };
 
-/// Helper function to continue compilation from AST with existing state
-fn compile_ast_with_state(
-    mut ast: AstNode,
-    state: &mut CompilerState,
-    options: &CompilerOptions
-) -> Result<Vec<u8>> {
-    // Phase 1.2: Semantic analysis
-    if options.debug_mode {
-        log::debug!("Phase 1.2: Semantic analysis...");
+            let stops = gradient(1).stops;
+            assert_eq!(gradient(1).angle, 90.0);
+            assert_eq!(stops.iter().map(|s| s.position.unwrap()).collect::<Vec<_>>(), [0.0, 30.0, 100.0]);
+            assert_eq!(stops[1].color, if color_size == 4 { "#3366FFFF" } else { "#33336666FFFFFFFF" });
+            // The component's gradient keeps its structure through instantiation
+            assert!(gradient(2).to_string().starts_with("linear-gradient(270deg, "), "{}", gradient(2));
+
+            let slot = &file.theme_table.slots[0];
+            assert_eq!(slot.value_type, ValueType::Gradient as u8);
+            let hero = Gradient::decode(&file.theme_table.themes[0].values[0], color_size).unwrap();
+            assert_eq!((hero.kind, hero.center, hero.stops.len()), (GradientKind::Radial, (50.0, 50.0), 2));
+        }
+
+        // Quoted gradients, directly or through a theme token, are the same values
+        let banner = |value: &str| {
+            let source = format!("@theme light {{\n    hero: {}\n}}\n\nApp {{\n    background_image: {}\n    Container {{\n        background_image: $hero\n    }}\n}}\n", value, value);
+            compile(&source).unwrap()
+        };
+        assert_eq!(banner("\"linear-gradient(to right, red, #3366FF)\""), banner("linear-gradient(to right, red, #3366FF)"));
     }
-    
-    println!("DEBUG: Before semantic analysis");
-    
-    let mut semantic_analyzer = SemanticAnalyzer::new();
-    semantic_analyzer.analyze(&mut ast, state)?;
-    
-    if options.debug_mode {
-        log::debug!("Phase 1.2 complete. Semantic analysis passed");
+
+    #[test]
+    fn test_shadows_and_filters_are_encoded() {
+        let source = "@variables {\n    glow: #3366FF\n}\n\nstyle \"card\" {\n    box_shadow: \"0 2px 4px #00000040, inset 0 0 0 1px $glow\"\n}\n\nApp {\n    Container {\n        style: \"card\"\n        backdrop_filter: blur(8px)\n    }\n    Text {\n        text: \"Hi\"\n        text_shadow: \"1px 1px red\"\n        filter: \"brightness(120%) drop-shadow(0 1px 2px black)\"\n    }\n}\n";
+        let with_colors = |extended_color| compile_with(source, CompilerOptions { extended_color, ..CompilerOptions::default() }).unwrap();
+        for (krb, color_size) in [(with_colors(false), 4), (with_colors(true), 8)] {
+            assert_eq!(verify_krb(&krb), Vec::new());
+            let file = KrbReader::new(&krb).unwrap().read().unwrap();
+            let value = |properties: &[KrbPropertyRecord], id: PropertyId, value_type: ValueType| {
+                let property = properties.iter().find(|p| p.property_id == id as u8).unwrap();
+                assert_eq!(property.value_type, value_type as u8);
+                property.value.clone()
+            };
+
+            let card = decode_shadows(&value(&file.styles[0].properties, PropertyId::BoxShadow, ValueType::Shadow), color_size).unwrap();
+            assert_eq!(card.len(), 2);
+            assert!(card[1].inset);
+            assert_eq!(card[1].spread, 1.0);
+            assert_eq!(card[1].color.len(), color_size);
+            let text = decode_shadows(&value(&file.elements[2].properties, PropertyId::TextShadow, ValueType::Shadow), color_size).unwrap();
+            assert_eq!((text[0].offset, text[0].blur), ((1.0, 1.0), 0.0));
+
+            let backdrop = decode_filters(&value(&file.elements[1].properties, PropertyId::BackdropFilter, ValueType::Filter), color_size).unwrap();
+            assert_eq!(format_filters(&backdrop), "blur(8px)");
+            let filters = decode_filters(&value(&file.elements[2].properties, PropertyId::Filter, ValueType::Filter), color_size).unwrap();
+            assert!(format_filters(&filters).starts_with("brightness(120%) drop-shadow(0px 1px 2px #000000"), "{}", format_filters(&filters));
+            if color_size == 4 {
+                assert_eq!(format_shadows(&card), "0px 2px 4px #00000040, inset 0px 0px 0px 1px #3366FFFF");
+            }
+        }
+
+        let malformed = "App {\n    Container {\n        width: 10\n        box_shadow: \"2px red\"\n    }\n}\n";
+        let error = compile(malformed).unwrap_err();
+        let CompilerError::Diagnostics(diagnostics) = error else { unreachable!("expected a located error: {}", error) };
+        let error = diagnostics.iter().next().unwrap();
+        assert_eq!(error.span.as_ref().map(|s| s.line), Some(4));
+        assert!(error.message.starts_with("Invalid box_shadow '2px red'"), "{}", error.message);
+
+        // Filters inside a component template point at the template
+        let templated = "Define Card {\n    Container {\n        filter: \"blur(banana)\"\n    }\n}\n\nApp {\n    Card {}\n}\n";
+        let error = compile(templated).unwrap_err();
+        assert!(error.to_string().contains("main.kry:3:9: error: Invalid filter 'blur(banana)'"), "{}", error);
     }
-    
-    // Phase 1.25: Style resolution
-    if options.debug_mode {
-        log::debug!("Phase 1.25: Resolving style inheritance...");
+
+    #[test]
+    fn test_compiled_file_verifies() {
+        let data = compile(
+            "style \"card\" {\n    background_color: \"#FF0000FF\"\n}\n\nApp {\n    Container {\n        style: \"card\"\n        Text {\n            text: \"Hello\"\n        }\n    }\n}\n",
+        )
+        .unwrap();
+        assert_eq!(verify_krb(&data), Vec::new());
     }
-    
-    let mut style_resolver = StyleResolver::new();
-    style_resolver.resolve_all_styles(state)?;
-    
-    if options.debug_mode {
-        log::debug!("Phase 1.25 complete. Style inheritance resolved");
+
+    #[test]
+    fn test_reports_every_violation() {
+        let mut data = compile(
+            "style \"card\" {\n    background_color: \"#FF0000FF\"\n}\n\nApp {\n    Button {\n        style: \"card\"\n        text: \"Go\"\n        onClick: \"missing\"\n    }\n}\n",
+        )
+        .unwrap();
+        let file = KrbReader::new(&data).unwrap().read().unwrap();
+        let button = &file.elements[1];
+
+        // Point the button at a style that does not exist
+        data[button.offset as usize + 11] = 42;
+
+        let violations = verify_krb(&data);
+        let messages: Vec<&str> = violations.iter().map(|v| v.message.as_str()).collect();
+        assert_eq!(violations.len(), 2, "{:?}", messages);
+        assert!(violations.iter().all(|v| v.offset >= button.offset));
+        assert!(messages.iter().any(|m| m.contains("style_id 42")));
+        assert!(messages.iter().any(|m| m.contains("\"missing\" is not a script entry point")));
     }
-    
-    // Phase 1.3: Component resolution and template structure processing (moved before AST conversion)
-    if options.debug_mode {
-        log::debug!("Phase 1.3: Resolving components and template structures...");
+
+    #[test]
+    fn test_overlapping_sections_are_reported() {
+        let mut data = compile("App {\n    Text {\n        text: \"Hi\"\n    }\n}\n").unwrap();
+        // Point the element section at the string table
+        let string_offset = data[48..52].to_vec();
+        data[28..32].copy_from_slice(&string_offset);
+
+        let violations = verify_krb(&data);
+        assert!(violations.iter().any(|v| v.message.contains("overlap")));
     }
-    
-    let component_count = state.component_defs.len();
-    
-    // Always process template structures (@for loops, @if statements) even if no components
-    let mut component_resolver = ComponentResolver::new();
-    component_resolver.resolve_components(&mut ast, state)?;
-    
-    if options.debug_mode {
-        log::debug!("Phase 1.3 complete. Template structures and component instances resolved in AST");
+
+    #[test]
+    fn test_compressed_output_round_trips() {
+        let source = "App {\n    window_title: \"Compressed\"\n    Container {\n        Text {\n            text: \"Hello\"\n        }\n        Text {\n            text: \"Hello\"\n        }\n    }\n}\n";
+        let with_compression = |compress_output| compile_with_stats(source, CompilerOptions { compress_output, ..CompilerOptions::default() }).unwrap();
+        let (plain, plain_stats) = with_compression(false);
+        let (packed, stats) = with_compression(true);
+
+        assert_eq!(decompress_krb(&packed).unwrap().as_ref(), plain.as_slice());
+        assert_eq!(stats.output_size, packed.len() as u64);
+        assert_eq!(stats.uncompressed_size, plain.len() as u64);
+        assert_eq!(plain_stats.uncompressed_size, plain_stats.output_size);
+
+        let info = validate_krb_file(&packed).unwrap();
+        assert!(info.has_feature(FLAG_COMPRESSED));
+        assert_eq!(info.total_size as usize, plain.len());
+        assert_eq!(info.stored_size as usize, packed.len());
+
+        let file = KrbReader::new(&packed).unwrap().read().unwrap();
+        assert_eq!(file.elements.len(), 4);
+        assert!(file.strings.iter().any(|s| s.text == "Compressed"));
+        assert!(verify_krb(&packed).is_empty());
+
+        let truncated = &packed[..packed.len() - 4];
+        let error = validate_krb_file(truncated).unwrap_err();
+        assert!(error.to_string().contains("Compressed payload"), "{}", error);
     }
-    
-    // Clear component state since components are now expanded to regular elements
-    state.component_defs.clear();
-    state.component_ast_templates.clear();
-    
-    // Phase 1.4: Convert AST to internal representation
-    if options.debug_mode {
-        log::debug!("Phase 1.4: Converting AST to internal representation...");
+
+    #[test]
+    fn test_compressed_output_keeps_themes() {
+        let with_compression = |compress_output| compile_with(FIXTURES[2], CompilerOptions { compress_output, ..CompilerOptions::default() }).unwrap();
+        let (plain, packed) = (with_compression(false), with_compression(true));
+
+        // The theme extension is part of the payload; only the fixed header is stored as is
+        assert_eq!(&packed[..6], &plain[..6]);
+        assert_eq!(&packed[8..KRB_HEADER_SIZE], &plain[8..KRB_HEADER_SIZE]);
+        assert_eq!(decompress_krb(&packed).unwrap().as_ref(), plain.as_slice());
+
+        let info = validate_krb_file(&packed).unwrap();
+        assert!(info.has_feature(FLAG_HAS_THEMES) && info.has_feature(FLAG_COMPRESSED));
+        assert_eq!((info.theme_count, info.theme_offset), (2, validate_krb_file(&plain).unwrap().theme_offset));
+        let file = KrbReader::new(&packed).unwrap().read().unwrap();
+        assert_eq!(file.theme_table.themes.len(), 2);
+        assert_eq!(verify_krb(&packed), Vec::new());
     }
-    
-    convert_ast_to_state(&ast, state)?;
-    
-    // Phase 1.5: Apply style properties to elements
-    if options.debug_mode {
-        log::debug!("Phase 1.5: Applying style properties to elements...");
+
+    #[test]
+    fn test_animation_table_round_trips() {
+        let source = "@keyframes fade_in {\n    from {\n        opacity: 0\n    }\n    to {\n        opacity: 1\n        background_color: \"#FF0000FF\"\n    }\n}\n\nApp {\n    Container {\n        animation: \"fade_in 300ms ease_out infinite\"\n        transition: \"width 1s 50ms\"\n    }\n}\n";
+        let krb = compile(source).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+
+        assert!(file.header.has_feature(crate::FLAG_HAS_ANIMATIONS));
+        assert_eq!(file.animations.len(), 1);
+        assert_eq!(file.string(file.animations[0].name_index), Some("fade_in"));
+        let positions: Vec<u8> = file.animations[0].keyframes.iter().map(|k| k.position).collect();
+        assert_eq!(positions, vec![0, 100]);
+        assert_eq!(file.animations[0].keyframes[1].properties.len(), 2);
+
+        let container = &file.elements[1];
+        assert_eq!(container.animation_count, 2);
+        assert_eq!(container.animations[0].kind, ANIMATION_KIND_KEYFRAMES);
+        assert_eq!(container.animations[0].target, 0);
+        assert_eq!(container.animations[0].duration_ms, 300);
+        assert_eq!(container.animations[0].easing, Easing::EaseOut as u8);
+        assert_eq!(container.animations[0].iterations, 0);
+        assert_eq!(container.animations[1].kind, ANIMATION_KIND_TRANSITION);
+        assert_eq!(container.animations[1].target, PropertyId::Width as u16);
+        assert_eq!(container.animations[1].delay_ms, 50);
+        assert!(verify_krb(&krb).is_empty());
     }
-    
-    apply_style_properties_to_elements(state)?;
-    
-    if options.debug_mode {
-        log::debug!("Phase 1.5 complete. Style properties applied to elements");
-        log::debug!("Elements: {}, Styles: {}", state.elements.len(), state.styles.len());
+
+    #[test]
+    fn test_style_animations_apply_to_elements() {
+        let source = "@keyframes fade {\n    to {\n        opacity: 0\n    }\n}\n\n@keyframes grow {\n    to {\n        width: 200\n    }\n}\n\nstyle \"base\" {\n    transition: \"opacity 100ms\"\n    animation: \"grow 2s\"\n}\n\nstyle \"card\" {\n    extends: \"base\"\n    background_color: \"#FFFFFFFF\"\n    animation: \"fade 1s\"\n}\n\nApp {\n    Container {\n        style: \"card\"\n    }\n    Container {\n        style: \"card\"\n        transition: \"width 300ms\"\n    }\n}\n";
+        let krb = compile(source).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        let refs = |element: usize| file.elements[element].animations.iter().map(|a| (a.kind, a.target, a.duration_ms)).collect::<Vec<_>>();
+
+        // The card's animation replaces its base's; the base's transition is inherited
+        let fade = (ANIMATION_KIND_KEYFRAMES, 0, 1000);
+        assert_eq!(refs(1), [(ANIMATION_KIND_TRANSITION, PropertyId::Opacity as u16, 100), fade]);
+        // An element's own transitions replace the style's
+        assert_eq!(refs(2), [fade, (ANIMATION_KIND_TRANSITION, PropertyId::Width as u16, 300)]);
+        assert!(verify_krb(&krb).is_empty());
+
+        let error = compile("style \"card\" {\n    animation: \"missing 1s\"\n}\n\nApp {\n    style: \"card\"\n}\n").unwrap_err();
+        let crate::CompilerError::Diagnostics(diagnostics) = error else { unreachable!("expected a located error: {}", error) };
+        let error = diagnostics.iter().next().unwrap();
+        assert_eq!((error.message.as_str(), error.span.as_ref().map(|s| s.line)), ("@keyframes 'missing' is not defined", Some(2)));
     }
-    
-    // Phase 1.6: Process template variables
-    if options.debug_mode {
-        log::debug!("Phase 1.6: Processing template variables...");
+
+    #[test]
+    fn test_animation_errors_are_located() {
+        let source = "@keyframes spin {\n    50% {\n        text: \"x\"\n    }\n}\n\nApp {\n    Container {\n        animation: \"missing 1s\"\n    }\n}\n";
+        let error = compile(source).unwrap_err().to_string();
+        assert!(error.contains("Property 'text' cannot be animated"), "{}", error);
+        assert!(error.contains("@keyframes 'missing' is not defined"), "{}", error);
     }
-    
-    process_template_variables(state, options)?;
-    
-    if options.debug_mode {
-        log::debug!("Phase 1.6 complete. Template variables: {}, bindings: {}", 
-                   state.template_variables.len(), state.template_bindings.len());
+
+    /// An App with `count` Text elements, each with its own string, in groups of 100
+    fn source_with_texts(count: usize) -> String {
+        let mut source = String::from("App {\n");
+        for group in 0..count.div_ceil(100) {
+            source.push_str("    Container {\n");
+            for i in group * 100..count.min((group + 1) * 100) {
+                source.push_str(&format!("        Text {{\n            text: \"Line {}\"\n        }}\n", i));
+            }
+            source.push_str("    }\n");
+        }
+        source.push_str("}\n");
+        source
     }
-    
-    // Phase 2: Calculate sizes
-    if options.debug_mode {
-        log::debug!("Phase 2: Calculating sizes...");
+
+    #[test]
+    fn test_small_files_stay_compact() {
+        let krb = compile(&source_with_texts(10)).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        assert_eq!(IndexWidth::from_flags(file.header.flags), IndexWidth::Compact);
+        assert_eq!(file.elements[1].offset as usize - file.elements[0].offset as usize, 19 + 2);
+        assert!(verify_krb(&krb).is_empty());
     }
-    
-    let size_calculator = SizeCalculator::new();
-    size_calculator.calculate_sizes(state)?;
-    size_calculator.validate_limits(state)?;
-    
-    let size_stats = size_calculator.get_size_stats(state);
-    
-    if options.debug_mode {
-        log::debug!("Phase 2 complete. Total size: {} bytes", size_stats.total_size);
-        if options.optimization_level >= 2 {
-            size_stats.print_breakdown();
+
+    #[test]
+    fn test_more_than_255_strings_use_wide_indices() {
+        let krb = compile(&source_with_texts(300)).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        assert_eq!(IndexWidth::from_flags(file.header.flags), IndexWidth::Wide);
+        assert!(file.strings.len() >= 300);
+
+        let last = file.elements.last().unwrap();
+        let text = last.properties.iter().find(|p| p.value_type == ValueType::String as u8).unwrap();
+        let index = decode_index(&text.value).unwrap();
+        assert_eq!(text.value.len(), 2);
+        assert!(index > u8::MAX as u16);
+        assert_eq!(file.string(index), Some("Line 299"));
+        assert_eq!(verify_krb(&krb), Vec::new());
+    }
+
+    #[test]
+    fn test_more_than_255_children_round_trip() {
+        let source = format!("App {{\n{}}}\n", "    Text {\n        text: \"Row\"\n    }\n".repeat(300));
+        let krb = compile(&source).unwrap();
+        let file = KrbReader::new(&krb).unwrap().read().unwrap();
+        assert_eq!(IndexWidth::from_flags(file.header.flags), IndexWidth::Wide);
+        assert_eq!(file.elements[0].children.len(), 300);
+        assert_eq!(file.elements[300].parent, Some(0));
+        assert_eq!(file.elements.len(), 301);
+        assert_eq!(verify_krb(&krb), Vec::new());
+    }
+
+    #[test]
+    fn test_read_theme_table() {
+        let source = "@theme light {\n    surface: \"#FFFFFFFF\"\n    gap: 8\n}\n\n@theme dark {\n    surface: \"#202020FF\"\n    gap: 8\n}\n\nstyle \"card\" {\n    background_color: $surface\n    padding: $gap\n}\n\nApp {\n    Container {\n        style: \"card\"\n        border_color: $surface\n    }\n}\n";
+        for compress_output in [false, true] {
+            let data = compile_with(source, CompilerOptions { compress_output, ..CompilerOptions::default() }).unwrap();
+            let file = KrbReader::new(&data).unwrap().read().unwrap();
+            assert_ne!(file.header.flags & FLAG_HAS_THEMES, 0);
+            assert_eq!(file.header.theme_count, 2);
+            assert_eq!(verify_krb(&data), Vec::new());
+
+            // The element reuses the style's slot for the same token and type
+            let table = &file.theme_table;
+            let slots: Vec<_> = table.slots.iter().map(|s| (file.string(s.name_index).unwrap(), s.value_type)).collect();
+            assert_eq!(slots, [("surface", ValueType::Color as u8), ("gap", ValueType::Byte as u8)]);
+            let themes: Vec<_> = table.themes.iter().map(|t| file.string(t.name_index).unwrap()).collect();
+            assert_eq!(themes, ["light", "dark"]);
+            assert_eq!(table.themes[0].values[0], [0xFF, 0xFF, 0xFF, 0xFF]);
+            assert_eq!(table.themes[1].values[0], [0x20, 0x20, 0x20, 0xFF]);
+            assert_eq!(table.themes[1].values[1], [8]);
+
+            let tokens = file.styles[0].properties.iter().chain(&file.elements[1].properties)
+                .filter(|p| p.value_type == ValueType::ThemeToken as u8);
+            assert_eq!(tokens.map(|p| p.value[0]).collect::<Vec<_>>(), [0, 1, 1, 1, 1, 0]);
         }
     }
-    
-    // Phase 3: Generate KRB binary
-    if options.debug_mode {
-        log::debug!("Phase 3: Generating KRB binary...");
+
+    const DEBUG_SOURCE: &str = "@variables {\n    count: 0\n}\n\nstyle \"card\" {\n    background_color: \"#FF0000FF\"\n}\n\nDefine Badge {\n    Properties {\n        label: String = \"hi\"\n    }\n    Text {\n        text: $label\n    }\n}\n\n@script \"lua\" {\n    local x = 1\n    function on_click()\n        print(x)\n    end\n}\n\nApp {\n    Container {\n        style: \"card\"\n        Text {\n            text: \"Count: $count\"\n        }\n    }\n    Badge {\n        label: \"new\"\n    }\n}\n";
+
+    fn main_location(line: usize, column: usize) -> Option<DebugLocation> {
+        Some(DebugLocation { file: "main.kry".to_string(), line, column })
     }
-    
-    let mut code_generator = CodeGenerator::new();
-    let krb_data = code_generator.generate(state)?;
 
-    if options.debug_mode {
-        log::debug!("Phase 3 complete. KRB data size: {} bytes", krb_data.len());
+    #[test]
+    fn test_debug_map_locates_sources() {
+        let options = CompilerOptions { generate_debug_info: true, ..CompilerOptions::default() };
+        let (krb, stats) = compile_with_stats(DEBUG_SOURCE, options).unwrap();
+        let map = stats.debug_map.unwrap();
+
+        let file = KrbReader::new(&krb).unwrap().with_debug_map(Some(map.clone())).read().unwrap();
+        assert_eq!(map.elements.len(), file.elements.len());
+        assert_eq!(file.element_location(0), main_location(25, 1).as_ref());
+        assert_eq!(file.element_location(2), main_location(28, 9).as_ref());
+        assert_eq!(file.style_location(1), main_location(5, 7).as_ref());
+        assert_eq!(file.binding_location(0), main_location(29, 13).as_ref());
+        assert_eq!(file.entry_point_location(0, "on_click"), main_location(20, 14).as_ref());
+
+        let badge = map.element(3).unwrap();
+        assert_eq!(badge.location, main_location(13, 5));
+        let origin = badge.component.as_ref().unwrap();
+        assert_eq!(origin.component, "Badge");
+        assert_eq!(origin.location, main_location(32, 5));
+
+        assert_eq!(KrbDebugMap::from_json(&map.to_json().unwrap()).unwrap(), map);
+        let (_, stats) = compile_with_stats(DEBUG_SOURCE, CompilerOptions::default()).unwrap();
+        assert!(stats.debug_map.is_none());
     }
-    
-    Ok(krb_data)
-}
 
+    #[test]
+    fn test_read_compiled_file() {
+        let data = compile(
+            "style \"card\" {\n    background_color: \"#FF0000FF\"\n}\n\nApp {\n    window_title: \"Reader\"\n    Container {\n        style: \"card\"\n        Text {\n            text: \"Hello\"\n        }\n    }\n}\n",
+        )
+        .unwrap();
+        let file = KrbReader::new(&data).unwrap().read().unwrap();
+
+        assert_eq!(file.strings.len(), file.header.string_count as usize);
+        assert_eq!(file.elements.len(), 3);
+        assert_eq!(file.elements[0].element_type, 0x00);
+        assert_eq!(file.elements[0].children, vec![1]);
+        assert_eq!(file.elements[1].parent, Some(0));
+        assert_eq!(file.elements[2].element_type, 0x02);
+
+        let style = file.style(file.elements[1].style_id).unwrap();
+        assert_eq!(file.string(style.name_index), Some("card"));
+        assert!(file.strings.iter().any(|s| s.text == "Hello"));
+    }
+}
@@ -56,12 +56,12 @@ impl Optimizer {
         for (old_index, string_entry) in state.strings.iter().enumerate() {
             if let Some(&new_index) = string_map.get(&string_entry.text) {
                 // String already exists, map old index to existing new index
-                index_mapping.insert(old_index as u8, new_index);
+                index_mapping.insert(old_index as u16, new_index);
             } else {
                 // New string, add to deduplicated table
-                let new_index = new_strings.len() as u8;
+                let new_index = new_strings.len() as u16;
                 string_map.insert(string_entry.text.clone(), new_index);
-                index_mapping.insert(old_index as u8, new_index);
+                index_mapping.insert(old_index as u16, new_index);
                 
                 new_strings.push(StringEntry {
                     text: string_entry.text.clone(),
@@ -88,7 +88,7 @@ impl Optimizer {
         Ok(())
     }
     
-    fn update_string_references(&self, state: &mut CompilerState, mapping: &HashMap<u8, u8>) -> Result<()> {
+    fn update_string_references(&self, state: &mut CompilerState, mapping: &HashMap<u16, u16>) -> Result<()> {
         // Update element string references
         for element in &mut state.elements {
             if let Some(&new_index) = mapping.get(&element.id_string_index) {
@@ -97,9 +97,9 @@ impl Optimizer {
             
             // Update property string references
             for prop in &mut element.krb_properties {
-                if prop.value_type == ValueType::String && prop.size == 1 && !prop.value.is_empty() {
-                    if let Some(&new_index) = mapping.get(&prop.value[0]) {
-                        prop.value[0] = new_index;
+                if prop.value_type == ValueType::String {
+                    if let Some(&new_index) = prop.index().and_then(|index| mapping.get(&index)) {
+                        *prop = KrbProperty::with_index(prop.property_id, prop.value_type, new_index);
                     }
                 }
             }
@@ -109,9 +109,10 @@ impl Optimizer {
                 if let Some(&new_index) = mapping.get(&custom_prop.key_index) {
                     custom_prop.key_index = new_index;
                 }
-                if custom_prop.value_type == ValueType::String && custom_prop.size == 1 && !custom_prop.value.is_empty() {
-                    if let Some(&new_index) = mapping.get(&custom_prop.value[0]) {
-                        custom_prop.value[0] = new_index;
+                if custom_prop.value_type == ValueType::String {
+                    if let Some(&new_index) = decode_index(&custom_prop.value).and_then(|index| mapping.get(&index)) {
+                        custom_prop.size = 2;
+                        custom_prop.value = new_index.to_le_bytes().to_vec();
                     }
                 }
             }
@@ -124,9 +125,9 @@ impl Optimizer {
             }
             
             for prop in &mut style.properties {
-                if prop.value_type == ValueType::String && prop.size == 1 && !prop.value.is_empty() {
-                    if let Some(&new_index) = mapping.get(&prop.value[0]) {
-                        prop.value[0] = new_index;
+                if prop.value_type == ValueType::String {
+                    if let Some(&new_index) = prop.index().and_then(|index| mapping.get(&index)) {
+                        *prop = KrbProperty::with_index(prop.property_id, prop.value_type, new_index);
                     }
                 }
             }
@@ -162,6 +163,32 @@ impl Optimizer {
             if let Some(&new_index) = mapping.get(&resource.data_string_index) {
                 resource.data_string_index = new_index;
             }
+            if let Some(font) = &mut resource.font {
+                for index in &mut font.fallback_indices {
+                    if let Some(&new_index) = mapping.get(index) {
+                        *index = new_index;
+                    }
+                }
+            }
+        }
+        
+        // Update theme string references
+        for theme in &mut state.themes {
+            if let Some(&new_index) = mapping.get(&theme.name_index) {
+                theme.name_index = new_index;
+            }
+        }
+        for slot in &mut state.theme_slots {
+            if let Some(&new_index) = mapping.get(&slot.name_index) {
+                slot.name_index = new_index;
+            }
+            if slot.value_type == ValueType::String {
+                for value in &mut slot.values {
+                    if let Some(&new_index) = decode_index(value).and_then(|index| mapping.get(&index)) {
+                        *value = new_index.to_le_bytes().to_vec();
+                    }
+                }
+            }
         }
         
         Ok(())
@@ -326,12 +353,21 @@ impl Optimizer {
         // Check element property references to resources
         for element in &state.elements {
             for prop in &element.krb_properties {
-                if prop.value_type == ValueType::Resource && !prop.value.is_empty() {
-                    used_resources.insert(prop.value[0] as usize);
+                if prop.value_type == ValueType::Resource {
+                    if let Some(index) = prop.index() {
+                        used_resources.insert(index as usize);
+                    }
                 }
             }
         }
         
+        // Fonts are looked up by family name rather than by index
+        for (index, resource) in state.resources.iter().enumerate() {
+            if resource.resource_type == ResourceType::Font {
+                used_resources.insert(index);
+            }
+        }
+        
         // Check script external references
         for script in &state.scripts {
             if let Some(resource_index) = script.resource_index {
@@ -412,7 +448,7 @@ impl Optimizer {
         let merged_text = format!("{} {}", text1, text2);
         
         // Update the first element with merged text
-        let string_index = state.strings.len() as u8;
+        let string_index = state.strings.len() as u16;
         state.strings.push(StringEntry {
             length: merged_text.len(),
             text: merged_text,
@@ -423,19 +459,14 @@ impl Optimizer {
         let mut found = false;
         for prop in &mut state.elements[index].krb_properties {
             if prop.property_id == PropertyId::TextContent as u8 {
-                prop.value = vec![string_index];
+                *prop = KrbProperty::with_index(prop.property_id, ValueType::String, string_index);
                 found = true;
                 break;
             }
         }
 
         if !found {
-            state.elements[index].krb_properties.push(KrbProperty {
-                property_id: PropertyId::TextContent as u8,
-                value_type: ValueType::String,
-                size: 1,
-                value: vec![string_index],
-            });
+            state.elements[index].krb_properties.push(KrbProperty::with_index(PropertyId::TextContent as u8, ValueType::String, string_index));
         }
 
         // Remove the second element
@@ -450,10 +481,8 @@ impl Optimizer {
     fn get_text_content(&self, element: &Element, state: &CompilerState) -> Result<String> {
         for prop in &element.krb_properties {
             if prop.property_id == PropertyId::TextContent as u8 &&
-               prop.value_type == ValueType::String &&
-               !prop.value.is_empty() {
-                let string_index = prop.value[0] as usize;
-                if let Some(string_entry) = state.strings.get(string_index) {
+               prop.value_type == ValueType::String {
+                if let Some(string_entry) = prop.index().and_then(|index| state.strings.get(index as usize)) {
                     return Ok(string_entry.text.clone());
                 }
             }
@@ -467,7 +496,7 @@ impl Optimizer {
         for prop in &mut element.krb_properties {
             if prop.property_id == PropertyId::TextContent as u8 {
                 // Add new string to string table
-                let string_index = state.strings.len() as u8;
+                let string_index = state.strings.len() as u16;
                 state.strings.push(StringEntry {
                     text: text.to_string(),
                     length: text.len(),
@@ -475,27 +504,22 @@ impl Optimizer {
                 });
                 
                 // Update property value
-                prop.value = vec![string_index];
+                *prop = KrbProperty::with_index(prop.property_id, ValueType::String, string_index);
                 return Ok(());
             }
         }
         
         // If no text property exists, create one
-        let string_index = state.strings.len() as u8;
+        let string_index = state.strings.len() as u16;
         state.strings.push(StringEntry {
             text: text.to_string(),
             length: text.len(),
             index: string_index,
         });
         
-        element.krb_properties.push(KrbProperty {
-            property_id: PropertyId::TextContent as u8,
-            value_type: ValueType::String,
-            size: 1,
-            value: vec![string_index],
-        });
+        element.krb_properties.push(KrbProperty::with_index(PropertyId::TextContent as u8, ValueType::String, string_index));
         
-        element.property_count = element.krb_properties.len() as u8;
+        element.property_count = element.krb_properties.len() as u16;
         
         Ok(())
     }
@@ -511,7 +535,7 @@ impl Optimizer {
             
             // Remove the deleted child from parent's child list
             element.children.retain(|&child| child != removed_index);
-            element.child_count = element.children.len() as u8;
+            element.child_count = element.children.len() as u16;
         }
         
         // Update self_index for all elements after the removed one
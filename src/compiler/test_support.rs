@@ -0,0 +1,27 @@
+// FILE: src/compiler/test_support.rs
+
+//! Helpers for tests that run sources through the whole pipeline
+
+use crate::compiler::file_provider::MemoryFileProvider;
+use crate::error::Result;
+use crate::{CompilationStats, CompilerOptions};
+
+/// Compile `source` as `main.kry` with the default options
+pub(crate) fn compile(source: &str) -> Result<Vec<u8>> {
+    compile_with(source, CompilerOptions::default())
+}
+
+/// Compile `source` as `main.kry`
+pub(crate) fn compile_with(source: &str, options: CompilerOptions) -> Result<Vec<u8>> {
+    compile_with_stats(source, options).map(|(krb, _)| krb)
+}
+
+/// Compile `source` as `main.kry`, keeping the compilation statistics
+pub(crate) fn compile_with_stats(source: &str, options: CompilerOptions) -> Result<(Vec<u8>, CompilationStats)> {
+    super::compile_source(source, "main.kry", &MemoryFileProvider::new(), options)
+}
+
+/// Compile `source` as `app/main.kry`, reading the files it refers to from `provider`
+pub(crate) fn compile_app(source: &str, provider: &MemoryFileProvider, options: CompilerOptions) -> Result<Vec<u8>> {
+    super::compile_source(source, "app/main.kry", provider, options).map(|(krb, _)| krb)
+}
@@ -0,0 +1,270 @@
+// FILE: src/core/animation.rs
+
+//! Animation and transition specs
+//!
+//! Elements run a `@keyframes` animation with `animation: "fade_in 300ms ease_out infinite"`
+//! and animate property changes with `transition: "opacity 200ms, width 1s ease_in 100ms"`.
+//! Words may come in any order: the first time is the duration and the second the delay.
+
+use crate::core::properties::PropertyId;
+
+/// Element animation reference that plays an entry of the animation table
+pub const ANIMATION_KIND_KEYFRAMES: u8 = 0x01;
+/// Element animation reference that animates changes of one property
+pub const ANIMATION_KIND_TRANSITION: u8 = 0x02;
+
+/// Size of one animation reference written after an element's events
+pub const KRB_ANIMATION_REF_SIZE: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum Easing {
+    #[default]
+    Linear = 0x00,
+    Ease = 0x01,
+    EaseIn = 0x02,
+    EaseOut = 0x03,
+    EaseInOut = 0x04,
+    StepStart = 0x05,
+    StepEnd = 0x06,
+}
+
+impl Easing {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::Linear),
+            0x01 => Some(Self::Ease),
+            0x02 => Some(Self::EaseIn),
+            0x03 => Some(Self::EaseOut),
+            0x04 => Some(Self::EaseInOut),
+            0x05 => Some(Self::StepStart),
+            0x06 => Some(Self::StepEnd),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.replace('-', "_").as_str() {
+            "linear" => Some(Self::Linear),
+            "ease" => Some(Self::Ease),
+            "ease_in" => Some(Self::EaseIn),
+            "ease_out" => Some(Self::EaseOut),
+            "ease_in_out" => Some(Self::EaseInOut),
+            "step_start" => Some(Self::StepStart),
+            "step_end" => Some(Self::StepEnd),
+            _ => None,
+        }
+    }
+
+    pub fn to_name(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Ease => "ease",
+            Self::EaseIn => "ease_in",
+            Self::EaseOut => "ease_out",
+            Self::EaseInOut => "ease_in_out",
+            Self::StepStart => "step_start",
+            Self::StepEnd => "step_end",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum AnimationDirection {
+    #[default]
+    Normal = 0x00,
+    Reverse = 0x01,
+    Alternate = 0x02,
+    AlternateReverse = 0x03,
+}
+
+impl AnimationDirection {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::Normal),
+            0x01 => Some(Self::Reverse),
+            0x02 => Some(Self::Alternate),
+            0x03 => Some(Self::AlternateReverse),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.replace('-', "_").as_str() {
+            "normal" => Some(Self::Normal),
+            "reverse" => Some(Self::Reverse),
+            "alternate" => Some(Self::Alternate),
+            "alternate_reverse" => Some(Self::AlternateReverse),
+            _ => None,
+        }
+    }
+
+    pub fn to_name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Reverse => "reverse",
+            Self::Alternate => "alternate",
+            Self::AlternateReverse => "alternate_reverse",
+        }
+    }
+}
+
+/// Timing shared by animations and transitions
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnimationTiming {
+    pub duration_ms: u16,
+    pub delay_ms: u16,
+    pub easing: Easing,
+    /// How often the animation plays; 0 repeats forever
+    pub iterations: u8,
+    pub direction: AnimationDirection,
+}
+
+/// A parsed `animation:` value
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationSpec {
+    pub keyframes: String,
+    pub timing: AnimationTiming,
+}
+
+/// One entry of a parsed `transition:` value
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionSpec {
+    pub property: String,
+    pub property_id: PropertyId,
+    pub timing: AnimationTiming,
+}
+
+/// Parse `name duration [easing] [delay] [count|infinite] [direction]`
+pub fn parse_animation_spec(spec: &str) -> Result<AnimationSpec, String> {
+    let mut keyframes = None;
+    let mut timing = AnimationTiming { iterations: 1, ..AnimationTiming::default() };
+    let mut times = 0;
+
+    for word in spec.split_whitespace() {
+        if let Some(ms) = parse_time(word)? {
+            set_time(&mut timing, &mut times, ms, word)?;
+        } else if let Some(easing) = Easing::from_name(word) {
+            timing.easing = easing;
+        } else if let Some(direction) = AnimationDirection::from_name(word) {
+            timing.direction = direction;
+        } else if word == "infinite" {
+            timing.iterations = 0;
+        } else if let Ok(count) = word.parse::<u8>() {
+            if count == 0 {
+                return Err("Iteration count must be at least 1 (use 'infinite' to repeat forever)".to_string());
+            }
+            timing.iterations = count;
+        } else if keyframes.is_none() {
+            keyframes = Some(word.to_string());
+        } else {
+            return Err(format!("Unexpected '{}' in animation", word));
+        }
+    }
+
+    let keyframes = keyframes.ok_or("Animation must name a @keyframes definition")?;
+    if times == 0 {
+        return Err(format!("Animation '{}' needs a duration such as 300ms or 1.5s", keyframes));
+    }
+    Ok(AnimationSpec { keyframes, timing })
+}
+
+/// Parse comma-separated `property duration [easing] [delay]` entries
+pub fn parse_transition_specs(spec: &str) -> Result<Vec<TransitionSpec>, String> {
+    let mut transitions = Vec::new();
+    for entry in spec.split(',') {
+        let mut words = entry.split_whitespace();
+        let property = words.next().ok_or("Empty transition entry")?.to_string();
+        let property_id = PropertyId::from_name(&property);
+        if !property_id.is_animatable() {
+            return Err(format!("Property '{}' cannot be animated", property));
+        }
+
+        let mut timing = AnimationTiming { iterations: 1, ..AnimationTiming::default() };
+        let mut times = 0;
+        for word in words {
+            if let Some(ms) = parse_time(word)? {
+                set_time(&mut timing, &mut times, ms, word)?;
+            } else if let Some(easing) = Easing::from_name(word) {
+                timing.easing = easing;
+            } else {
+                return Err(format!("Unexpected '{}' in transition of '{}'", word, property));
+            }
+        }
+        if times == 0 {
+            return Err(format!("Transition of '{}' needs a duration such as 200ms", property));
+        }
+        transitions.push(TransitionSpec { property, property_id, timing });
+    }
+    Ok(transitions)
+}
+
+/// The first time of a spec is its duration, the second its delay
+fn set_time(timing: &mut AnimationTiming, times: &mut usize, ms: u16, word: &str) -> Result<(), String> {
+    match *times {
+        0 => timing.duration_ms = ms,
+        1 => timing.delay_ms = ms,
+        _ => return Err(format!("Unexpected third time '{}' (expected a duration and a delay)", word)),
+    }
+    *times += 1;
+    Ok(())
+}
+
+/// `300ms` or `1.5s` in milliseconds; `None` if the word is not a time
+fn parse_time(word: &str) -> Result<Option<u16>, String> {
+    let (number, scale) = if let Some(number) = word.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = word.strip_suffix('s') {
+        (number, 1000.0)
+    } else {
+        return Ok(None);
+    };
+    let Ok(value) = number.parse::<f64>() else {
+        return Ok(None);
+    };
+
+    let ms = (value * scale).round();
+    if !(0.0..=u16::MAX as f64).contains(&ms) {
+        return Err(format!("Time '{}' must be between 0ms and {}ms", word, u16::MAX));
+    }
+    Ok(Some(ms as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_animation_spec() {
+        let spec = parse_animation_spec("pulse 1.5s ease-in-out 200ms infinite alternate").unwrap();
+        assert_eq!(spec.keyframes, "pulse");
+        assert_eq!(
+            spec.timing,
+            AnimationTiming {
+                duration_ms: 1500,
+                delay_ms: 200,
+                easing: Easing::EaseInOut,
+                iterations: 0,
+                direction: AnimationDirection::Alternate,
+            }
+        );
+
+        assert!(parse_animation_spec("pulse").unwrap_err().contains("duration"));
+        assert!(parse_animation_spec("pulse fade 1s").unwrap_err().contains("'fade'"));
+    }
+
+    #[test]
+    fn test_parse_transition_specs() {
+        let specs = parse_transition_specs("opacity 200ms, background_color 1s ease_out 50ms").unwrap();
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[0].property_id, PropertyId::Opacity);
+        assert_eq!(specs[1].timing.duration_ms, 1000);
+        assert_eq!(specs[1].timing.delay_ms, 50);
+        assert_eq!(specs[1].timing.easing, Easing::EaseOut);
+
+        assert!(parse_transition_specs("text 200ms").unwrap_err().contains("cannot be animated"));
+    }
+}
@@ -0,0 +1,570 @@
+// FILE: src/core/color.rs
+
+//! CSS color syntax and compile-time color functions
+//!
+//! Colors are written as hex (`#RGB`, `#RGBA`, `#RRGGBB`, `#RRGGBBAA`), as one of the
+//! CSS named colors, or with `rgb()`, `rgba()`, `hsl()` and `hsla()`. The functions
+//! below take colors as arguments and are folded by the compiler to one RGBA value:
+//!
+//! ```text
+//! lighten(color, amount)           raise the HSL lightness by amount
+//! darken(color, amount)            lower the HSL lightness by amount
+//! mix(a, b[, weight])              weight of a mixed with b in RGB; 50% by default
+//! alpha(color, amount)             replace the alpha channel
+//! contrast(color[, dark, light])   whichever of dark (black) and light (white) has the
+//!                                  higher WCAG contrast ratio against color
+//! ```
+//!
+//! Amounts, weights and alphas are percentages or numbers from 0 to 1. `rgb()` channels
+//! are 0 to 255 or percentages; `hsl()` hues are degrees, with or without `deg`, `rad`
+//! or `turn`. Arguments may be `$variables`, which must hold compile-time colors.
+//!
+//! In a KRB file a Color value is RGBA with 8 bits per channel. Files written for
+//! wide-gamut renderers set `FLAG_EXTENDED_COLOR` and use 16 bits per channel.
+
+use crate::core::constants::FLAG_EXTENDED_COLOR;
+use crate::core::util::Color;
+
+/// Functions accepted by `evaluate_color`; a value calling one of them is folded
+pub const COLOR_FUNCTIONS: &[&str] =
+    &["rgb", "rgba", "hsl", "hsla", "lighten", "darken", "mix", "alpha", "contrast"];
+
+/// Variables that refer to each other deeper than this are taken to be a cycle
+const MAX_VARIABLE_DEPTH: usize = 16;
+
+/// Whether `value` is a call of one of `COLOR_FUNCTIONS`
+pub fn is_color_function(value: &str) -> bool {
+    let value = value.trim();
+    value.split_once('(').is_some_and(|(name, _)| COLOR_FUNCTIONS.contains(&name.trim().to_lowercase().as_str()))
+}
+
+/// A named color from the CSS Color Module, including `transparent`
+pub fn named_color(name: &str) -> Option<Color> {
+    let name = name.trim().to_lowercase();
+    let index = NAMED_COLORS.binary_search_by(|(n, _)| n.cmp(&name.as_str())).ok()?;
+    let [r, g, b, a] = NAMED_COLORS[index].1.to_be_bytes();
+    Some(Color::new(r, g, b, a))
+}
+
+/// Evaluate a color or color function call, looking `$variables` up with `variables`
+pub fn evaluate_color(source: &str, variables: &dyn Fn(&str) -> Option<String>) -> Result<Color, String> {
+    Evaluator { variables, depth: 0 }.color(source).map(Rgba::to_color)
+}
+
+/// Bytes of a Color value: RGBA with 8 bits per channel, or with 16 bits per channel
+/// (four little-endian u16) in files with `FLAG_EXTENDED_COLOR`
+pub fn color_size(flags: u16) -> usize {
+    if flags & FLAG_EXTENDED_COLOR != 0 {
+        8
+    } else {
+        4
+    }
+}
+
+/// Widen an 8-bit RGBA color to 16 bits per channel; `c * 257` maps 0xFF to 0xFFFF exactly
+pub fn widen_color(value: &[u8]) -> Option<Vec<u8>> {
+    let channels: &[u8; 4] = value.try_into().ok()?;
+    Some(channels.iter().flat_map(|&c| (c as u16 * 257).to_le_bytes()).collect())
+}
+
+/// `#RRGGBBAA` for an 8-bit color, `#RRRRGGGGBBBBAAAA` for a 16-bit one
+pub fn format_color(value: &[u8]) -> Option<String> {
+    match value {
+        [r, g, b, a] => Some(format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a)),
+        [_, _, _, _, _, _, _, _] => {
+            let channel = |i: usize| u16::from_le_bytes([value[i], value[i + 1]]);
+            Some(format!("#{:04X}{:04X}{:04X}{:04X}", channel(0), channel(2), channel(4), channel(6)))
+        }
+        _ => None,
+    }
+}
+
+/// A color with channels from 0 to 1, so functions can be chained without rounding
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rgba {
+    r: f64,
+    g: f64,
+    b: f64,
+    a: f64,
+}
+
+impl Rgba {
+    fn from_color(color: Color) -> Self {
+        let channel = |c: u8| c as f64 / 255.0;
+        Self { r: channel(color.r), g: channel(color.g), b: channel(color.b), a: channel(color.a) }
+    }
+
+    fn to_color(self) -> Color {
+        let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::new(channel(self.r), channel(self.g), channel(self.b), channel(self.a))
+    }
+
+    fn from_hsl(hue: f64, saturation: f64, lightness: f64, a: f64) -> Self {
+        let hue = hue.rem_euclid(360.0) / 360.0;
+        let (s, l) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        if s == 0.0 {
+            return Self { r: l, g: l, b: l, a };
+        }
+        let q = if l < 0.5 { l * (1.0 + s) } else { l + s - l * s };
+        let p = 2.0 * l - q;
+        let channel = |t: f64| {
+            let t = t.rem_euclid(1.0);
+            if t < 1.0 / 6.0 {
+                p + (q - p) * 6.0 * t
+            } else if t < 0.5 {
+                q
+            } else if t < 2.0 / 3.0 {
+                p + (q - p) * (2.0 / 3.0 - t) * 6.0
+            } else {
+                p
+            }
+        };
+        Self { r: channel(hue + 1.0 / 3.0), g: channel(hue), b: channel(hue - 1.0 / 3.0), a }
+    }
+
+    /// Hue in degrees, saturation and lightness
+    fn to_hsl(self) -> (f64, f64, f64) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let lightness = (max + min) / 2.0;
+        let delta = max - min;
+        if delta == 0.0 {
+            return (0.0, 0.0, lightness);
+        }
+        let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+        let hue = if max == self.r {
+            ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / delta + 2.0
+        } else {
+            (self.r - self.g) / delta + 4.0
+        };
+        (hue * 60.0, saturation, lightness)
+    }
+
+    fn with_lightness(self, change: f64) -> Self {
+        let (h, s, l) = self.to_hsl();
+        Self::from_hsl(h, s, l + change, self.a)
+    }
+
+    /// WCAG relative luminance
+    fn luminance(self) -> f64 {
+        let linear = |c: f64| if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    fn contrast_ratio(self, other: Self) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+}
+
+/// A function argument
+#[derive(Debug, Clone, Copy)]
+enum Arg {
+    Color(Rgba),
+    Number(f64),
+    Percent(f64),
+    Degrees(f64),
+}
+
+struct Evaluator<'a> {
+    variables: &'a dyn Fn(&str) -> Option<String>,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn color(&mut self, source: &str) -> Result<Rgba, String> {
+        match self.arg(source)? {
+            Arg::Color(color) => Ok(color),
+            _ => Err(format!("'{}' is not a color", source.trim())),
+        }
+    }
+
+    fn arg(&mut self, source: &str) -> Result<Arg, String> {
+        let source = source.trim();
+        let unquoted = source.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(source);
+        if unquoted.is_empty() {
+            return Err("expected a color".to_string());
+        }
+        if let Some(name) = unquoted.strip_prefix('$') {
+            return self.variable(name);
+        }
+        if let Some((name, rest)) = unquoted.split_once('(') {
+            let args = rest.trim_end().strip_suffix(')').ok_or_else(|| format!("missing ')' in '{}'", unquoted))?;
+            return self.call(&name.trim().to_lowercase(), args).map(Arg::Color);
+        }
+        if unquoted.starts_with('#') {
+            return hex_color(unquoted).map(|c| Arg::Color(Rgba::from_color(c)));
+        }
+        if unquoted.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '-' || c == '+') {
+            return number(unquoted);
+        }
+        named_color(unquoted)
+            .map(|c| Arg::Color(Rgba::from_color(c)))
+            .ok_or_else(|| format!("'{}' is not a color", unquoted))
+    }
+
+    fn variable(&mut self, name: &str) -> Result<Arg, String> {
+        let name = name.trim_start_matches('{').trim_end_matches('}');
+        let value = (self.variables)(name)
+            .ok_or_else(|| format!("'${}' is not an @variables entry; color functions need compile-time values", name))?;
+        if self.depth == MAX_VARIABLE_DEPTH {
+            return Err(format!("'${}' refers to itself", name));
+        }
+        self.depth += 1;
+        let result = self.arg(&value);
+        self.depth -= 1;
+        result
+    }
+
+    fn call(&mut self, name: &str, source: &str) -> Result<Rgba, String> {
+        let args = split_args(source)
+            .into_iter()
+            .map(|arg| self.arg(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        let arity = |min: usize, max: usize| {
+            if (min..=max).contains(&args.len()) {
+                Ok(())
+            } else if min == max {
+                Err(format!("{}() takes {} arguments, not {}", name, min, args.len()))
+            } else {
+                Err(format!("{}() takes {} to {} arguments, not {}", name, min, max, args.len()))
+            }
+        };
+        let color = |index: usize| match args[index] {
+            Arg::Color(color) => Ok(color),
+            _ => Err(format!("argument {} of {}() must be a color", index + 1, name)),
+        };
+        let amount = |index: usize| match args.get(index) {
+            Some(Arg::Percent(p)) => Ok(p / 100.0),
+            Some(Arg::Number(n)) if (0.0..=1.0).contains(n) => Ok(*n),
+            _ => Err(format!("argument {} of {}() must be a percentage or a number from 0 to 1", index + 1, name)),
+        };
+
+        match name {
+            "rgb" | "rgba" => {
+                arity(3, 4)?;
+                let channel = |index: usize| match args[index] {
+                    Arg::Number(n) if (0.0..=255.0).contains(&n) => Ok(n / 255.0),
+                    Arg::Percent(p) => Ok(p / 100.0),
+                    _ => Err(format!("argument {} of {}() must be a number from 0 to 255 or a percentage", index + 1, name)),
+                };
+                let a = if args.len() == 4 { amount(3)? } else { 1.0 };
+                Ok(Rgba { r: channel(0)?, g: channel(1)?, b: channel(2)?, a })
+            }
+            "hsl" | "hsla" => {
+                arity(3, 4)?;
+                let hue = match args[0] {
+                    Arg::Number(degrees) | Arg::Degrees(degrees) => degrees,
+                    _ => return Err(format!("argument 1 of {}() must be a hue", name)),
+                };
+                let percent = |index: usize| match args[index] {
+                    Arg::Percent(p) | Arg::Number(p) => Ok(p / 100.0),
+                    _ => Err(format!("argument {} of {}() must be a percentage", index + 1, name)),
+                };
+                let a = if args.len() == 4 { amount(3)? } else { 1.0 };
+                Ok(Rgba::from_hsl(hue, percent(1)?, percent(2)?, a))
+            }
+            "lighten" | "darken" => {
+                arity(2, 2)?;
+                let change = if name == "lighten" { amount(1)? } else { -amount(1)? };
+                Ok(color(0)?.with_lightness(change))
+            }
+            "mix" => {
+                arity(2, 3)?;
+                let weight = if args.len() == 3 { amount(2)? } else { 0.5 };
+                let (a, b) = (color(0)?, color(1)?);
+                let blend = |x: f64, y: f64| x * weight + y * (1.0 - weight);
+                Ok(Rgba { r: blend(a.r, b.r), g: blend(a.g, b.g), b: blend(a.b, b.b), a: blend(a.a, b.a) })
+            }
+            "alpha" => {
+                arity(2, 2)?;
+                Ok(Rgba { a: amount(1)?, ..color(0)? })
+            }
+            "contrast" => {
+                if args.len() != 1 {
+                    arity(3, 3)?;
+                }
+                let base = color(0)?;
+                let (dark, light) = if args.len() == 3 {
+                    (color(1)?, color(2)?)
+                } else {
+                    (Rgba { r: 0.0, g: 0.0, b: 0.0, a: 1.0 }, Rgba { r: 1.0, g: 1.0, b: 1.0, a: 1.0 })
+                };
+                Ok(if base.contrast_ratio(dark) >= base.contrast_ratio(light) { dark } else { light })
+            }
+            _ => Err(format!("unknown color function '{}'", name)),
+        }
+    }
+}
+
+/// Split function arguments at top-level commas
+pub(crate) fn split_args(source: &str) -> Vec<&str> {
+    if source.trim().is_empty() {
+        return Vec::new();
+    }
+    let (mut args, mut depth, mut start, mut quoted) = (Vec::new(), 0usize, 0, false);
+    for (i, ch) in source.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                args.push(&source[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&source[start..]);
+    args
+}
+
+fn number(source: &str) -> Result<Arg, String> {
+    let split = source.find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'))).unwrap_or(source.len());
+    let (digits, unit) = source.split_at(split);
+    let value: f64 = digits.parse().map_err(|_| format!("'{}' is not a number", source))?;
+    match unit.trim().to_lowercase().as_str() {
+        "" => Ok(Arg::Number(value)),
+        "%" => Ok(Arg::Percent(value)),
+        "deg" => Ok(Arg::Degrees(value)),
+        "rad" => Ok(Arg::Degrees(value.to_degrees())),
+        "turn" => Ok(Arg::Degrees(value * 360.0)),
+        _ => Err(format!("'{}' has a unit a color cannot use", source)),
+    }
+}
+
+/// `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA`
+pub fn hex_color(source: &str) -> Result<Color, String> {
+    let hex = source.trim().trim_start_matches('#');
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex color: {}", source));
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap_or(0) * 17;
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    match hex.len() {
+        3 => Ok(Color::new(digit(0), digit(1), digit(2), 255)),
+        4 => Ok(Color::new(digit(0), digit(1), digit(2), digit(3))),
+        6 => Ok(Color::new(pair(0), pair(2), pair(4), 255)),
+        8 => Ok(Color::new(pair(0), pair(2), pair(4), pair(6))),
+        _ => Err(format!("Invalid color format: {} (expected 3, 4, 6, or 8 hex digits)", source)),
+    }
+}
+
+/// The CSS named colors as 0xRRGGBBAA, sorted by name
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xF0F8FFFF),
+    ("antiquewhite", 0xFAEBD7FF),
+    ("aqua", 0x00FFFFFF),
+    ("aquamarine", 0x7FFFD4FF),
+    ("azure", 0xF0FFFFFF),
+    ("beige", 0xF5F5DCFF),
+    ("bisque", 0xFFE4C4FF),
+    ("black", 0x000000FF),
+    ("blanchedalmond", 0xFFEBCDFF),
+    ("blue", 0x0000FFFF),
+    ("blueviolet", 0x8A2BE2FF),
+    ("brown", 0xA52A2AFF),
+    ("burlywood", 0xDEB887FF),
+    ("cadetblue", 0x5F9EA0FF),
+    ("chartreuse", 0x7FFF00FF),
+    ("chocolate", 0xD2691EFF),
+    ("coral", 0xFF7F50FF),
+    ("cornflowerblue", 0x6495EDFF),
+    ("cornsilk", 0xFFF8DCFF),
+    ("crimson", 0xDC143CFF),
+    ("cyan", 0x00FFFFFF),
+    ("darkblue", 0x00008BFF),
+    ("darkcyan", 0x008B8BFF),
+    ("darkgoldenrod", 0xB8860BFF),
+    ("darkgray", 0xA9A9A9FF),
+    ("darkgreen", 0x006400FF),
+    ("darkgrey", 0xA9A9A9FF),
+    ("darkkhaki", 0xBDB76BFF),
+    ("darkmagenta", 0x8B008BFF),
+    ("darkolivegreen", 0x556B2FFF),
+    ("darkorange", 0xFF8C00FF),
+    ("darkorchid", 0x9932CCFF),
+    ("darkred", 0x8B0000FF),
+    ("darksalmon", 0xE9967AFF),
+    ("darkseagreen", 0x8FBC8FFF),
+    ("darkslateblue", 0x483D8BFF),
+    ("darkslategray", 0x2F4F4FFF),
+    ("darkslategrey", 0x2F4F4FFF),
+    ("darkturquoise", 0x00CED1FF),
+    ("darkviolet", 0x9400D3FF),
+    ("deeppink", 0xFF1493FF),
+    ("deepskyblue", 0x00BFFFFF),
+    ("dimgray", 0x696969FF),
+    ("dimgrey", 0x696969FF),
+    ("dodgerblue", 0x1E90FFFF),
+    ("firebrick", 0xB22222FF),
+    ("floralwhite", 0xFFFAF0FF),
+    ("forestgreen", 0x228B22FF),
+    ("fuchsia", 0xFF00FFFF),
+    ("gainsboro", 0xDCDCDCFF),
+    ("ghostwhite", 0xF8F8FFFF),
+    ("gold", 0xFFD700FF),
+    ("goldenrod", 0xDAA520FF),
+    ("gray", 0x808080FF),
+    ("green", 0x008000FF),
+    ("greenyellow", 0xADFF2FFF),
+    ("grey", 0x808080FF),
+    ("honeydew", 0xF0FFF0FF),
+    ("hotpink", 0xFF69B4FF),
+    ("indianred", 0xCD5C5CFF),
+    ("indigo", 0x4B0082FF),
+    ("ivory", 0xFFFFF0FF),
+    ("khaki", 0xF0E68CFF),
+    ("lavender", 0xE6E6FAFF),
+    ("lavenderblush", 0xFFF0F5FF),
+    ("lawngreen", 0x7CFC00FF),
+    ("lemonchiffon", 0xFFFACDFF),
+    ("lightblue", 0xADD8E6FF),
+    ("lightcoral", 0xF08080FF),
+    ("lightcyan", 0xE0FFFFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2FF),
+    ("lightgray", 0xD3D3D3FF),
+    ("lightgreen", 0x90EE90FF),
+    ("lightgrey", 0xD3D3D3FF),
+    ("lightpink", 0xFFB6C1FF),
+    ("lightsalmon", 0xFFA07AFF),
+    ("lightseagreen", 0x20B2AAFF),
+    ("lightskyblue", 0x87CEFAFF),
+    ("lightslategray", 0x778899FF),
+    ("lightslategrey", 0x778899FF),
+    ("lightsteelblue", 0xB0C4DEFF),
+    ("lightyellow", 0xFFFFE0FF),
+    ("lime", 0x00FF00FF),
+    ("limegreen", 0x32CD32FF),
+    ("linen", 0xFAF0E6FF),
+    ("magenta", 0xFF00FFFF),
+    ("maroon", 0x800000FF),
+    ("mediumaquamarine", 0x66CDAAFF),
+    ("mediumblue", 0x0000CDFF),
+    ("mediumorchid", 0xBA55D3FF),
+    ("mediumpurple", 0x9370DBFF),
+    ("mediumseagreen", 0x3CB371FF),
+    ("mediumslateblue", 0x7B68EEFF),
+    ("mediumspringgreen", 0x00FA9AFF),
+    ("mediumturquoise", 0x48D1CCFF),
+    ("mediumvioletred", 0xC71585FF),
+    ("midnightblue", 0x191970FF),
+    ("mintcream", 0xF5FFFAFF),
+    ("mistyrose", 0xFFE4E1FF),
+    ("moccasin", 0xFFE4B5FF),
+    ("navajowhite", 0xFFDEADFF),
+    ("navy", 0x000080FF),
+    ("oldlace", 0xFDF5E6FF),
+    ("olive", 0x808000FF),
+    ("olivedrab", 0x6B8E23FF),
+    ("orange", 0xFFA500FF),
+    ("orangered", 0xFF4500FF),
+    ("orchid", 0xDA70D6FF),
+    ("palegoldenrod", 0xEEE8AAFF),
+    ("palegreen", 0x98FB98FF),
+    ("paleturquoise", 0xAFEEEEFF),
+    ("palevioletred", 0xDB7093FF),
+    ("papayawhip", 0xFFEFD5FF),
+    ("peachpuff", 0xFFDAB9FF),
+    ("peru", 0xCD853FFF),
+    ("pink", 0xFFC0CBFF),
+    ("plum", 0xDDA0DDFF),
+    ("powderblue", 0xB0E0E6FF),
+    ("purple", 0x800080FF),
+    ("rebeccapurple", 0x663399FF),
+    ("red", 0xFF0000FF),
+    ("rosybrown", 0xBC8F8FFF),
+    ("royalblue", 0x4169E1FF),
+    ("saddlebrown", 0x8B4513FF),
+    ("salmon", 0xFA8072FF),
+    ("sandybrown", 0xF4A460FF),
+    ("seagreen", 0x2E8B57FF),
+    ("seashell", 0xFFF5EEFF),
+    ("sienna", 0xA0522DFF),
+    ("silver", 0xC0C0C0FF),
+    ("skyblue", 0x87CEEBFF),
+    ("slateblue", 0x6A5ACDFF),
+    ("slategray", 0x708090FF),
+    ("slategrey", 0x708090FF),
+    ("snow", 0xFFFAFAFF),
+    ("springgreen", 0x00FF7FFF),
+    ("steelblue", 0x4682B4FF),
+    ("tan", 0xD2B48CFF),
+    ("teal", 0x008080FF),
+    ("thistle", 0xD8BFD8FF),
+    ("tomato", 0xFF6347FF),
+    ("transparent", 0x00000000),
+    ("turquoise", 0x40E0D0FF),
+    ("violet", 0xEE82EEFF),
+    ("wheat", 0xF5DEB3FF),
+    ("white", 0xFFFFFFFF),
+    ("whitesmoke", 0xF5F5F5FF),
+    ("yellow", 0xFFFF00FF),
+    ("yellowgreen", 0x9ACD32FF),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> Result<String, String> {
+        let variables = |name: &str| match name {
+            "brand" => Some("\"#3366FF\"".to_string()),
+            "accent" => Some("lighten($brand, 10%)".to_string()),
+            "loop" => Some("$loop".to_string()),
+            _ => None,
+        };
+        evaluate_color(source, &variables).map(|c| c.to_string())
+    }
+
+    #[test]
+    fn test_css_color_syntax() {
+        assert!(NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(NAMED_COLORS.len(), 149);
+        assert_eq!(eval("rebeccapurple").unwrap(), "#663399FF");
+        assert_eq!(eval("Transparent").unwrap(), "#00000000");
+        assert_eq!(eval("rgb(255, 128, 0)").unwrap(), "#FF8000FF");
+        assert_eq!(eval("rgba(100%, 0, 0, 0.5)").unwrap(), "#FF000080");
+        assert_eq!(eval("hsl(120, 100%, 25%)").unwrap(), "#008000FF");
+        assert_eq!(eval("hsla(0.5turn, 100%, 50%, 50%)").unwrap(), "#00FFFF80");
+        assert_eq!(eval("#f80").unwrap(), "#FF8800FF");
+        assert_eq!(eval("\"#11223344\"").unwrap(), "#11223344");
+    }
+
+    #[test]
+    fn test_color_functions_fold() {
+        assert_eq!(eval("lighten(#000000, 50%)").unwrap(), "#808080FF");
+        assert_eq!(eval("darken(white, 0.25)").unwrap(), "#BFBFBFFF");
+        assert_eq!(eval("mix(red, blue)").unwrap(), "#800080FF");
+        assert_eq!(eval("mix(red, blue, 25%)").unwrap(), "#4000BFFF");
+        assert_eq!(eval("alpha($brand, 50%)").unwrap(), "#3366FF80");
+        assert_eq!(eval("contrast(#FFFF00)").unwrap(), "#000000FF");
+        assert_eq!(eval("contrast(navy, #111111, #EEEEEE)").unwrap(), "#EEEEEEFF");
+        assert_eq!(eval("darken(lighten($brand, 20%), 20%)").unwrap(), "#3366FFFF");
+        assert_eq!(eval("$accent").unwrap(), eval("lighten(#3366FF, 10%)").unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(eval("rgb(1, 2)").unwrap_err(), "rgb() takes 3 to 4 arguments, not 2");
+        assert_eq!(eval("lighten(red, 150)").unwrap_err(), "argument 2 of lighten() must be a percentage or a number from 0 to 1");
+        assert_eq!(eval("mix(red, 3)").unwrap_err(), "argument 2 of mix() must be a color");
+        assert_eq!(eval("bluish").unwrap_err(), "'bluish' is not a color");
+        assert_eq!(eval("shade(red)").unwrap_err(), "unknown color function 'shade'");
+        assert!(eval("alpha($missing, 1)").unwrap_err().contains("'$missing' is not an @variables entry"));
+        assert_eq!(eval("$loop").unwrap_err(), "'$loop' refers to itself");
+        assert!(is_color_function("darken(#000, 10%)") && !is_color_function("translate(1px)"));
+    }
+
+    #[test]
+    fn test_extended_color_layout() {
+        let wide = widen_color(&[0xFF, 0x80, 0x00, 0x01]).unwrap();
+        assert_eq!(wide, [0xFF, 0xFF, 0x80, 0x80, 0x00, 0x00, 0x01, 0x01]);
+        assert_eq!(format_color(&wide).as_deref(), Some("#FFFF808000000101"));
+        assert_eq!(format_color(&[1, 2, 3, 4]).as_deref(), Some("#01020304"));
+        assert_eq!(widen_color(&wide), None);
+        assert_eq!((color_size(0), color_size(FLAG_EXTENDED_COLOR)), (4, 8));
+    }
+}
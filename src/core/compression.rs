@@ -0,0 +1,133 @@
+// FILE: src/core/compression.rs
+
+//! Compressed KRB container
+//!
+//! A compressed file keeps bytes 0..72, the fixed header, uncompressed so a
+//! runtime can size its buffers before inflating anything:
+//!
+//! ```text
+//! 0x00  header     the header of the uncompressed file with FLAG_COMPRESSED set;
+//!                  counts, section offsets and total_size all describe the
+//!                  decompressed image
+//! 0x48  payload    raw DEFLATE stream (RFC 1951) of image bytes 72..total_size
+//! ```
+//!
+//! Nothing after byte 72 stays uncompressed. With FLAG_HAS_THEMES the 6-byte header
+//! extension (theme count and theme table offset) sits at bytes 72..78 of the image,
+//! so it is the start of the payload and is read only after inflating.
+//!
+//! Decompressing yields the plain file byte for byte, except that
+//! FLAG_COMPRESSED is cleared in its header.
+
+use crate::core::constants::*;
+use crate::error::{CompilerError, Result};
+use std::borrow::Cow;
+
+/// Byte offset of the header flags
+const FLAGS_OFFSET: usize = 6;
+/// Byte offset of the header's total size
+const TOTAL_SIZE_OFFSET: usize = 68;
+/// Strongest DEFLATE level; output size matters more than compile time here
+const COMPRESSION_LEVEL: u8 = 10;
+
+/// Whether `data` is stored in the compressed container
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.len() >= KRB_HEADER_SIZE && read_flags(data) & FLAG_COMPRESSED != 0
+}
+
+/// Wrap an uncompressed KRB image in the compressed container
+pub fn compress_krb(image: &[u8]) -> Result<Vec<u8>> {
+    if image.len() < KRB_HEADER_SIZE || is_compressed(image) {
+        return Err(CompilerError::CodeGen {
+            message: "Only a complete, uncompressed KRB image can be compressed".to_string(),
+        });
+    }
+
+    let payload = miniz_oxide::deflate::compress_to_vec(&image[KRB_HEADER_SIZE..], COMPRESSION_LEVEL);
+    let mut output = Vec::with_capacity(KRB_HEADER_SIZE + payload.len());
+    output.extend_from_slice(&image[..KRB_HEADER_SIZE]);
+    write_flags(&mut output, read_flags(image) | FLAG_COMPRESSED);
+    output.extend_from_slice(&payload);
+    Ok(output)
+}
+
+/// The uncompressed image of a KRB file, borrowing `data` when it is not compressed
+pub fn decompress_krb(data: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if !is_compressed(data) {
+        return Ok(Cow::Borrowed(data));
+    }
+
+    let total_size = u32::from_le_bytes([
+        data[TOTAL_SIZE_OFFSET],
+        data[TOTAL_SIZE_OFFSET + 1],
+        data[TOTAL_SIZE_OFFSET + 2],
+        data[TOTAL_SIZE_OFFSET + 3],
+    ]) as usize;
+    let expected = total_size.checked_sub(KRB_HEADER_SIZE).ok_or_else(|| CompilerError::InvalidFormat {
+        message: format!("Compressed file declares {} bytes, less than its header", total_size),
+    })?;
+
+    let payload = miniz_oxide::inflate::decompress_to_vec_with_limit(&data[KRB_HEADER_SIZE..], expected)
+        .map_err(|e| CompilerError::InvalidFormat {
+            message: format!("Compressed payload could not be inflated: {:?}", e.status),
+        })?;
+    if payload.len() != expected {
+        return Err(CompilerError::InvalidFormat {
+            message: format!(
+                "Size mismatch: header says {}, compressed payload holds {}",
+                total_size,
+                KRB_HEADER_SIZE + payload.len()
+            ),
+        });
+    }
+
+    let mut image = Vec::with_capacity(total_size);
+    image.extend_from_slice(&data[..KRB_HEADER_SIZE]);
+    write_flags(&mut image, read_flags(data) & !FLAG_COMPRESSED);
+    image.extend_from_slice(&payload);
+    Ok(Cow::Owned(image))
+}
+
+fn read_flags(data: &[u8]) -> u16 {
+    u16::from_le_bytes([data[FLAGS_OFFSET], data[FLAGS_OFFSET + 1]])
+}
+
+fn write_flags(data: &mut [u8], flags: u16) {
+    data[FLAGS_OFFSET..FLAGS_OFFSET + 2].copy_from_slice(&flags.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header with its total size filled in, followed by repetitive section bytes
+    fn image() -> Vec<u8> {
+        let mut image = vec![0; KRB_HEADER_SIZE];
+        image[..4].copy_from_slice(b"KRB1");
+        image.extend((0..400u32).map(|i| (i % 7) as u8));
+        let total_size = image.len() as u32;
+        image[TOTAL_SIZE_OFFSET..TOTAL_SIZE_OFFSET + 4].copy_from_slice(&total_size.to_le_bytes());
+        image
+    }
+
+    #[test]
+    fn test_container_round_trips() {
+        let image = image();
+        let packed = compress_krb(&image).unwrap();
+
+        assert!(is_compressed(&packed) && !is_compressed(&image));
+        assert!(packed.len() < image.len());
+        assert_eq!(&packed[..FLAGS_OFFSET], &image[..FLAGS_OFFSET]);
+        assert_eq!(decompress_krb(&packed).unwrap().as_ref(), image.as_slice());
+        assert!(matches!(decompress_krb(&image).unwrap(), Cow::Borrowed(_)));
+        assert!(compress_krb(&packed).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_payload_is_rejected() {
+        let mut packed = compress_krb(&image()).unwrap();
+        packed.truncate(packed.len() - 4);
+        let error = decompress_krb(&packed).unwrap_err();
+        assert!(error.to_string().contains("Compressed payload"), "{}", error);
+    }
+}
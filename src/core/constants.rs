@@ -14,12 +14,22 @@ pub const FLAG_HAS_ANIMATIONS: u16 = 1 << 2;
 pub const FLAG_HAS_RESOURCES: u16 = 1 << 3;
 pub const FLAG_COMPRESSED: u16 = 1 << 4;
 pub const FLAG_FIXED_POINT: u16 = 1 << 5;
+/// Colors have 16 bits per channel, see `core::color::color_size`
 pub const FLAG_EXTENDED_COLOR: u16 = 1 << 6;
 pub const FLAG_HAS_APP: u16 = 1 << 7;
 pub const FLAG_HAS_SCRIPTS: u16 = 1 << 8;
 pub const FLAG_HAS_STATE_PROPERTIES: u16 = 1 << 9;
 pub const FLAG_HAS_TEMPLATE_VARIABLES: u16 = 1 << 10;
 pub const FLAG_HAS_TRANSFORMS: u16 = 1 << 11;
+/// Indices and counts are u16 instead of u8, see `core::index_width`
+pub const FLAG_WIDE_INDICES: u16 = 1 << 12;
+/// The file has a theme table; its count and offset follow the header, see `KRB_THEME_HEADER_SIZE`
+pub const FLAG_HAS_THEMES: u16 = 1 << 13;
+
+/// Header extension of files with `FLAG_HAS_THEMES`: theme count (u16) and theme table
+/// offset (u32) at offset 72, so the first section starts after it. Other readers are
+/// unaffected, since every section is located by its offset.
+pub const KRB_THEME_HEADER_SIZE: usize = 6;
 
 // Layout flags (must match renderer's LayoutDirection enum)
 pub const LAYOUT_DIRECTION_MASK: u8 = 0x03;
@@ -54,16 +64,20 @@ pub const STATE_ACTIVE: u8 = 1 << 1;
 pub const STATE_FOCUS: u8 = 1 << 2;
 pub const STATE_DISABLED: u8 = 1 << 3;
 pub const STATE_CHECKED: u8 = 1 << 4;
+/// A `@media` set: applies while the viewport width is within the MinViewportWidth
+/// and MaxViewportWidth properties at the start of the set
+pub const STATE_MEDIA: u8 = 1 << 7;
 
-// Compiler limits
+// Compiler limits; table sizes are bounded by the u16 indices of wide files
 pub const MAX_ELEMENTS: usize = 1024;
-pub const MAX_STRINGS: usize = 1024;
-pub const MAX_PROPERTIES: usize = 64;
-pub const MAX_CUSTOM_PROPERTIES: usize = 32;
-pub const MAX_STYLES: usize = 256;
-pub const MAX_CHILDREN: usize = 256;
+pub const MAX_STRINGS: usize = u16::MAX as usize;
+pub const MAX_PROPERTIES: usize = u16::MAX as usize;
+pub const MAX_CUSTOM_PROPERTIES: usize = u16::MAX as usize;
+pub const MAX_STYLES: usize = u16::MAX as usize;
+pub const MAX_CHILDREN: usize = u16::MAX as usize;
 pub const MAX_EVENTS: usize = 16;
-pub const MAX_RESOURCES: usize = 256;
+pub const MAX_ANIMATIONS: usize = u16::MAX as usize;
+pub const MAX_RESOURCES: usize = u16::MAX as usize;
 pub const MAX_INCLUDE_DEPTH: usize = 16;
 pub const MAX_COMPONENT_DEFS: usize = 128;
 pub const MAX_BLOCK_DEPTH: usize = 64;
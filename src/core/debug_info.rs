@@ -0,0 +1,185 @@
+// FILE: src/core/debug_info.rs
+
+//! Source map for compiled KRB files
+//!
+//! With debug info enabled the compiler writes `app.krb.map` next to `app.krb`.
+//! It is a JSON document mapping the runtime's indices back to the source:
+//!
+//! ```text
+//! elements       element index (pre-order, root first) -> file:line:column and the
+//!                component usage the element was expanded from, if any
+//! styles         style ID -> style definition
+//! bindings       template binding index -> the property holding the expression
+//! entry_points   script index and function name -> function definition
+//! ```
+//!
+//! The KRB file itself is unchanged, so release builds simply ship without the map.
+
+use crate::diagnostics::Span;
+use crate::error::{CompilerError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Version of the map format written by this compiler
+pub const DEBUG_MAP_VERSION: u32 = 1;
+
+/// A position in a source file; lines and columns start at 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DebugLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<&Span> for DebugLocation {
+    fn from(span: &Span) -> Self {
+        Self { file: span.file.clone(), line: span.line, column: span.column }
+    }
+}
+
+impl fmt::Display for DebugLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// The component usage an element was instantiated from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentInstanceInfo {
+    pub component: String,
+    pub location: Option<DebugLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElementDebugInfo {
+    pub index: u16,
+    pub element_type: String,
+    pub location: Option<DebugLocation>,
+    pub component: Option<ComponentInstanceInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StyleDebugInfo {
+    pub id: u16,
+    pub name: String,
+    pub location: Option<DebugLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BindingDebugInfo {
+    pub index: u16,
+    /// Element index as written in the binding record
+    pub element: u16,
+    pub expression: String,
+    pub location: Option<DebugLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryPointDebugInfo {
+    /// Index of the script in the script table
+    pub script: u16,
+    pub function: String,
+    pub location: Option<DebugLocation>,
+}
+
+/// Contents of a `.krb.map` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KrbDebugMap {
+    pub version: u32,
+    pub elements: Vec<ElementDebugInfo>,
+    pub styles: Vec<StyleDebugInfo>,
+    pub bindings: Vec<BindingDebugInfo>,
+    pub entry_points: Vec<EntryPointDebugInfo>,
+}
+
+impl Default for KrbDebugMap {
+    fn default() -> Self {
+        Self {
+            version: DEBUG_MAP_VERSION,
+            elements: Vec::new(),
+            styles: Vec::new(),
+            bindings: Vec::new(),
+            entry_points: Vec::new(),
+        }
+    }
+}
+
+impl KrbDebugMap {
+    /// Path of the map that belongs to `krb_path`
+    pub fn map_path(krb_path: impl AsRef<Path>) -> PathBuf {
+        let mut path = krb_path.as_ref().as_os_str().to_owned();
+        path.push(".map");
+        PathBuf::from(path)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let map: Self = serde_json::from_str(json).map_err(|e| CompilerError::InvalidFormat {
+            message: format!("Invalid debug map: {}", e),
+        })?;
+        if map.version != DEBUG_MAP_VERSION {
+            return Err(CompilerError::InvalidFormat {
+                message: format!("Unsupported debug map version {} (expected {})", map.version, DEBUG_MAP_VERSION),
+            });
+        }
+        Ok(map)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| CompilerError::CodeGen {
+            message: format!("Failed to serialize debug map: {}", e),
+        })
+    }
+
+    /// Load the map next to `krb_path`; `None` if the file was compiled without one
+    pub fn load(krb_path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = Self::map_path(krb_path);
+        match std::fs::read_to_string(&path) {
+            Ok(json) => Self::from_json(&json).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(CompilerError::FileNotFound { path: format!("{}: {}", path.display(), e) }),
+        }
+    }
+
+    pub fn element(&self, index: u16) -> Option<&ElementDebugInfo> {
+        self.elements.iter().find(|e| e.index == index)
+    }
+
+    pub fn style(&self, id: u16) -> Option<&StyleDebugInfo> {
+        self.styles.iter().find(|s| s.id == id)
+    }
+
+    pub fn binding(&self, index: u16) -> Option<&BindingDebugInfo> {
+        self.bindings.iter().find(|b| b.index == index)
+    }
+
+    pub fn entry_point(&self, script: u16, function: &str) -> Option<&EntryPointDebugInfo> {
+        self.entry_points.iter().find(|e| e.script == script && e.function == function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompilerOptions;
+
+    #[test]
+    fn test_map_is_written_next_to_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("app.kry");
+        let output = dir.path().join("app.krb");
+        std::fs::write(&input, "App {\n    Text {\n        text: \"Hi\"\n    }\n}\n").unwrap();
+        let (input, output) = (input.to_str().unwrap(), output.to_str().unwrap());
+
+        let options = CompilerOptions { generate_debug_info: true, ..CompilerOptions::default() };
+        crate::compile_file_with_options(input, output, options).unwrap();
+        assert_eq!(KrbDebugMap::map_path(output), dir.path().join("app.krb.map"));
+        let map = KrbDebugMap::load(output).unwrap().unwrap();
+        assert_eq!(map.element(1).unwrap().location.as_ref().map(|l| l.line), Some(2));
+
+        // A release build must not leave the old map pointing at stale sources
+        crate::compile_file_with_options(input, output, CompilerOptions::default()).unwrap();
+        assert!(KrbDebugMap::load(output).unwrap().is_none());
+        assert!(KrbDebugMap::from_json("{\"version\": 99, \"elements\": [], \"styles\": [], \"bindings\": [], \"entry_points\": []}").is_err());
+    }
+}
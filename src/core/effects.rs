@@ -0,0 +1,570 @@
+// FILE: src/core/effects.rs
+
+//! Shadows and filters
+//!
+//! `box_shadow` and `text_shadow` take a comma-separated list of layers, the first
+//! painted on top. `filter` and `backdrop_filter` take a space-separated chain of
+//! functions, applied in order. Both are written as in CSS, and `none` clears them:
+//!
+//! ```text
+//! box_shadow: "0 1px 2px #0000004D, inset 0 0 0 1px rgba(255, 255, 255, 0.1)"
+//! text_shadow: "1px 1px 2px black"
+//! filter: "blur(4px) brightness(120%) drop-shadow(0 2px 4px $shade)"
+//! ```
+//!
+//! A shadow layer is `[inset] <x> <y> [<blur> [<spread>]] [<color>]`. Lengths are
+//! pixels, with or without `px`, and the color defaults to black. Text shadows have no
+//! spread and cannot be inset. The filter functions are
+//!
+//! ```text
+//! blur([<length>])                          blur radius; 0 by default
+//! brightness(), contrast(), grayscale(),
+//! invert(), opacity(), saturate(),
+//! sepia([<amount>])                         a percentage or a number, 100% by default;
+//!                                           grayscale, invert, opacity and sepia stop at 100%
+//! hue-rotate([<angle>])                     0deg by default
+//! drop-shadow(<x> <y> [<blur>] [<color>])   a text shadow of the filtered content
+//! ```
+//!
+//! In a KRB file both are integers only, so they read the same with or without
+//! `FLAG_FIXED_POINT`. Lengths are tenths of a pixel, amounts hundredths of a percent
+//! and angles hundredths of a degree clockwise, from 0 to 360. Colors are Color values
+//! (4 bytes, or 8 with `FLAG_EXTENDED_COLOR`).
+//!
+//! ```text
+//! Shadow   u8 layer count, then for each layer:
+//!              u8 flags (1 = inset), i16 x, i16 y, u16 blur, i16 spread, color
+//! Filter   u8 function count, then for each function a FilterKind byte and its argument:
+//!              blur          u16 radius
+//!              hue-rotate    u16 angle
+//!              drop-shadow   i16 x, i16 y, u16 blur, color
+//!              the others    u16 amount
+//! ```
+
+use crate::core::color::{evaluate_color, format_color, split_args, widen_color};
+use std::fmt;
+
+/// Layers a shadow may have; with 16-bit colors the largest value still fits a property
+pub const MAX_SHADOW_LAYERS: usize = 8;
+
+/// Functions a filter chain may have
+pub const MAX_FILTER_FUNCTIONS: usize = 16;
+
+/// Largest length, in pixels, that tenths of a pixel in an i16 can hold
+const MAX_LENGTH: f64 = i16::MAX as f64 / 10.0;
+
+/// Largest amount, 655.35%, that hundredths of a percent in a u16 can hold
+const MAX_AMOUNT: f64 = u16::MAX as f64 / 10000.0;
+
+const SHADOW_INSET: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadow {
+    pub inset: bool,
+    /// Pixels to the right and down
+    pub offset: (f64, f64),
+    pub blur: f64,
+    pub spread: f64,
+    /// A Color value: RGBA with 8 bits per channel, or 16 when read from a file with extended colors
+    pub color: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FilterKind {
+    Blur = 0,
+    Brightness = 1,
+    Contrast = 2,
+    Grayscale = 3,
+    HueRotate = 4,
+    Invert = 5,
+    Opacity = 6,
+    Saturate = 7,
+    Sepia = 8,
+    DropShadow = 9,
+}
+
+impl FilterKind {
+    const ALL: [Self; 10] = [
+        Self::Blur,
+        Self::Brightness,
+        Self::Contrast,
+        Self::Grayscale,
+        Self::HueRotate,
+        Self::Invert,
+        Self::Opacity,
+        Self::Saturate,
+        Self::Sepia,
+        Self::DropShadow,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blur => "blur",
+            Self::Brightness => "brightness",
+            Self::Contrast => "contrast",
+            Self::Grayscale => "grayscale",
+            Self::HueRotate => "hue-rotate",
+            Self::Invert => "invert",
+            Self::Opacity => "opacity",
+            Self::Saturate => "saturate",
+            Self::Sepia => "sepia",
+            Self::DropShadow => "drop-shadow",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Radius in pixels
+    Blur(f64),
+    /// Degrees clockwise
+    HueRotate(f64),
+    DropShadow(Shadow),
+    /// One of the other functions with its amount, 1 being 100%
+    Amount(FilterKind, f64),
+}
+
+impl Filter {
+    pub fn kind(&self) -> FilterKind {
+        match self {
+            Self::Blur(_) => FilterKind::Blur,
+            Self::HueRotate(_) => FilterKind::HueRotate,
+            Self::DropShadow(_) => FilterKind::DropShadow,
+            Self::Amount(kind, _) => *kind,
+        }
+    }
+}
+
+/// Parse a `box_shadow` value, or a `text_shadow` one when `text` is set, looking
+/// `$variables` in colors up with `variables`
+pub fn parse_shadows(source: &str, text: bool, variables: &dyn Fn(&str) -> Option<String>) -> Result<Vec<Shadow>, String> {
+    let source = source.trim();
+    if source.eq_ignore_ascii_case("none") {
+        return Ok(Vec::new());
+    }
+    let layers = split_args(source)
+        .into_iter()
+        .map(|layer| parse_shadow(layer, text, variables))
+        .collect::<Result<Vec<_>, _>>()?;
+    if layers.len() > MAX_SHADOW_LAYERS {
+        return Err(format!("{} shadow layers given; at most {} are allowed", layers.len(), MAX_SHADOW_LAYERS));
+    }
+    Ok(layers)
+}
+
+/// Parse a `filter` or `backdrop_filter` value, looking `$variables` in colors up with `variables`
+pub fn parse_filters(source: &str, variables: &dyn Fn(&str) -> Option<String>) -> Result<Vec<Filter>, String> {
+    let source = source.trim();
+    if source.eq_ignore_ascii_case("none") {
+        return Ok(Vec::new());
+    }
+    let filters = split_words(source)
+        .into_iter()
+        .map(|filter| parse_filter(filter, variables))
+        .collect::<Result<Vec<_>, _>>()?;
+    if filters.is_empty() {
+        return Err("expected filter functions such as blur(4px), or none".to_string());
+    }
+    if filters.len() > MAX_FILTER_FUNCTIONS {
+        return Err(format!("{} filter functions given; at most {} are allowed", filters.len(), MAX_FILTER_FUNCTIONS));
+    }
+    Ok(filters)
+}
+
+fn parse_shadow(source: &str, text: bool, variables: &dyn Fn(&str) -> Option<String>) -> Result<Shadow, String> {
+    let (mut inset, mut lengths, mut color) = (false, Vec::new(), None);
+    let mut lengths_ended = false;
+    for word in split_words(source) {
+        if word.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.')) {
+            if lengths_ended {
+                return Err(format!("the lengths of shadow '{}' must be written together", source.trim()));
+            }
+            lengths.push(length(word)?);
+            continue;
+        }
+        lengths_ended = !lengths.is_empty();
+        if word.eq_ignore_ascii_case("inset") {
+            if text {
+                return Err("text shadows and drop shadows cannot be inset".to_string());
+            }
+            inset = true;
+        } else if color.is_some() {
+            return Err(format!("shadow '{}' has more than one color", source.trim()));
+        } else {
+            color = Some(evaluate_color(word, variables)?.to_bytes().to_vec());
+        }
+    }
+
+    let most = if text { 3 } else { 4 };
+    if !(2..=most).contains(&lengths.len()) {
+        return Err(if text {
+            format!("shadow '{}' needs an x and y offset and may have a blur radius", source.trim())
+        } else {
+            format!("shadow '{}' needs an x and y offset and may have a blur radius and a spread", source.trim())
+        });
+    }
+    let blur = lengths.get(2).copied().unwrap_or(0.0);
+    if blur < 0.0 {
+        return Err(format!("shadow '{}' has a negative blur radius", source.trim()));
+    }
+    Ok(Shadow {
+        inset,
+        offset: (lengths[0], lengths[1]),
+        blur,
+        spread: lengths.get(3).copied().unwrap_or(0.0),
+        color: color.unwrap_or_else(|| vec![0, 0, 0, 0xFF]),
+    })
+}
+
+fn parse_filter(source: &str, variables: &dyn Fn(&str) -> Option<String>) -> Result<Filter, String> {
+    let (name, rest) = source.split_once('(').ok_or_else(|| format!("'{}' is not a filter function such as blur(4px)", source))?;
+    let argument = rest.strip_suffix(')').ok_or_else(|| format!("missing ')' in '{}'", source))?.trim();
+    let kind = FilterKind::from_name(&name.trim().to_lowercase()).ok_or_else(|| {
+        let names: Vec<String> = FilterKind::ALL.iter().map(|kind| format!("{}()", kind.name())).collect();
+        format!("unknown filter function '{}'; use one of {}", name.trim(), names.join(", "))
+    })?;
+
+    let filter = match kind {
+        FilterKind::Blur => {
+            let radius = if argument.is_empty() { 0.0 } else { length(argument)? };
+            if radius < 0.0 {
+                return Err("blur() radius cannot be negative".to_string());
+            }
+            Filter::Blur(radius)
+        }
+        FilterKind::HueRotate => Filter::HueRotate(if argument.is_empty() { 0.0 } else { angle(argument)? }),
+        FilterKind::DropShadow => Filter::DropShadow(parse_shadow(argument, true, variables)?),
+        FilterKind::Grayscale | FilterKind::Invert | FilterKind::Opacity | FilterKind::Sepia => {
+            Filter::Amount(kind, amount(kind, argument)?.min(1.0))
+        }
+        FilterKind::Brightness | FilterKind::Contrast | FilterKind::Saturate => Filter::Amount(kind, amount(kind, argument)?),
+    };
+    Ok(filter)
+}
+
+/// A length in pixels: `4px`, or a plain number
+fn length(source: &str) -> Result<f64, String> {
+    let value: f64 = source.strip_suffix("px").unwrap_or(source).parse()
+        .map_err(|_| format!("'{}' is not a length in pixels", source))?;
+    if value.abs() > MAX_LENGTH {
+        return Err(format!("'{}' is outside -{}px to {}px", source, MAX_LENGTH, MAX_LENGTH));
+    }
+    Ok(value)
+}
+
+/// An angle in degrees: `90deg`, `1.5rad`, `0.25turn`, or a plain number of degrees
+fn angle(source: &str) -> Result<f64, String> {
+    let parse = |number: &str| number.parse::<f64>().map_err(|_| format!("'{}' is not an angle", source));
+    if let Some(turns) = source.strip_suffix("turn") {
+        Ok(parse(turns)? * 360.0)
+    } else if let Some(radians) = source.strip_suffix("rad") {
+        Ok(parse(radians)?.to_degrees())
+    } else {
+        parse(source.strip_suffix("deg").unwrap_or(source))
+    }
+}
+
+/// An amount of a filter function: `150%`, or a number where 1 is 100%; 100% when omitted
+fn amount(kind: FilterKind, source: &str) -> Result<f64, String> {
+    if source.is_empty() {
+        return Ok(1.0);
+    }
+    let value = match source.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|p| p / 100.0),
+        None => source.parse::<f64>(),
+    };
+    match value {
+        Ok(value) if (0.0..=MAX_AMOUNT).contains(&value) => Ok(value),
+        Ok(_) => Err(format!("{}() amount '{}' is outside 0% to {}%", kind.name(), source, u16::MAX as f64 / 100.0)),
+        Err(_) => Err(format!("{}() takes a percentage or a number, not '{}'", kind.name(), source)),
+    }
+}
+
+/// Split at whitespace outside parentheses and quotes
+fn split_words(source: &str) -> Vec<&str> {
+    let (mut words, mut depth, mut start, mut quoted) = (Vec::new(), 0usize, None, false);
+    for (i, ch) in source.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        match (ch.is_whitespace() && !quoted && depth == 0, start) {
+            (true, Some(word_start)) => {
+                words.push(&source[word_start..i]);
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        words.push(&source[word_start..]);
+    }
+    words
+}
+
+/// The KRB Shadow value of `shadows`; an error if it does not fit a property
+pub fn encode_shadows(shadows: &[Shadow]) -> Result<Vec<u8>, String> {
+    let value = shadows_value(count_byte(shadows.len(), "shadow layers")?, shadows);
+    fit_property(value, shadows.len(), "shadow layers")
+}
+
+fn shadows_value(count: u8, shadows: &[Shadow]) -> Vec<u8> {
+    let mut value = vec![count];
+    for shadow in shadows {
+        value.push(if shadow.inset { SHADOW_INSET } else { 0 });
+        write_shadow(&mut value, shadow, true);
+    }
+    value
+}
+
+/// The KRB Filter value of `filters`; an error if it does not fit a property
+pub fn encode_filters(filters: &[Filter]) -> Result<Vec<u8>, String> {
+    let value = filters_value(count_byte(filters.len(), "filter functions")?, filters);
+    fit_property(value, filters.len(), "filter functions")
+}
+
+fn filters_value(count: u8, filters: &[Filter]) -> Vec<u8> {
+    let mut value = vec![count];
+    for filter in filters {
+        value.push(filter.kind() as u8);
+        match filter {
+            Filter::Blur(radius) => value.extend((tenths(*radius) as u16).to_le_bytes()),
+            Filter::HueRotate(degrees) => value.extend(hundredths(degrees.rem_euclid(360.0)).to_le_bytes()),
+            Filter::DropShadow(shadow) => write_shadow(&mut value, shadow, false),
+            Filter::Amount(_, amount) => value.extend(hundredths(amount * 100.0).to_le_bytes()),
+        }
+    }
+    value
+}
+
+fn count_byte(count: usize, what: &str) -> Result<u8, String> {
+    u8::try_from(count).map_err(|_| format!("{} {} given; at most 255 fit in a value", count, what))
+}
+
+fn fit_property(value: Vec<u8>, count: usize, what: &str) -> Result<Vec<u8>, String> {
+    if value.len() > u8::MAX as usize {
+        return Err(format!("{} {} need {} bytes, more than a property can hold", count, what, value.len()));
+    }
+    Ok(value)
+}
+
+fn write_shadow(value: &mut Vec<u8>, shadow: &Shadow, spread: bool) {
+    value.extend(tenths(shadow.offset.0).to_le_bytes());
+    value.extend(tenths(shadow.offset.1).to_le_bytes());
+    value.extend((tenths(shadow.blur) as u16).to_le_bytes());
+    if spread {
+        value.extend(tenths(shadow.spread).to_le_bytes());
+    }
+    value.extend(&shadow.color);
+}
+
+fn tenths(pixels: f64) -> i16 {
+    (pixels * 10.0).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+}
+
+fn hundredths(value: f64) -> u16 {
+    (value * 100.0).round().clamp(0.0, u16::MAX as f64) as u16
+}
+
+/// Read a Shadow value whose colors are `color_size` bytes; `None` if it is malformed
+pub fn decode_shadows(value: &[u8], color_size: usize) -> Option<Vec<Shadow>> {
+    let mut reader = Reader { value, color_size };
+    let shadows = (0..reader.u8()?)
+        .map(|_| {
+            let flags = reader.u8()?;
+            reader.shadow(flags & SHADOW_INSET != 0, true)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    reader.value.is_empty().then_some(shadows)
+}
+
+/// Read a Filter value whose colors are `color_size` bytes; `None` if it is malformed
+pub fn decode_filters(value: &[u8], color_size: usize) -> Option<Vec<Filter>> {
+    let mut reader = Reader { value, color_size };
+    let filters = (0..reader.u8()?)
+        .map(|_| {
+            let kind = FilterKind::from_u8(reader.u8()?)?;
+            Some(match kind {
+                FilterKind::Blur => Filter::Blur(reader.u16()? as f64 / 10.0),
+                FilterKind::HueRotate => Filter::HueRotate(reader.u16()? as f64 / 100.0),
+                FilterKind::DropShadow => Filter::DropShadow(reader.shadow(false, false)?),
+                _ => Filter::Amount(kind, reader.u16()? as f64 / 10000.0),
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    reader.value.is_empty().then_some(filters)
+}
+
+/// Widen the 8-bit colors of a Shadow value to 16 bits per channel; the caller checks
+/// that the wider value still fits a property
+pub fn widen_shadows(value: &[u8]) -> Option<Vec<u8>> {
+    let mut shadows = decode_shadows(value, 4)?;
+    for shadow in &mut shadows {
+        shadow.color = widen_color(&shadow.color)?;
+    }
+    Some(shadows_value(value[0], &shadows))
+}
+
+/// Widen the 8-bit drop shadow colors of a Filter value to 16 bits per channel; the
+/// caller checks that the wider value still fits a property
+pub fn widen_filters(value: &[u8]) -> Option<Vec<u8>> {
+    let mut filters = decode_filters(value, 4)?;
+    for filter in &mut filters {
+        if let Filter::DropShadow(shadow) = filter {
+            shadow.color = widen_color(&shadow.color)?;
+        }
+    }
+    Some(filters_value(value[0], &filters))
+}
+
+/// `none`, or the layers separated by commas
+pub fn format_shadows(shadows: &[Shadow]) -> String {
+    if shadows.is_empty() {
+        return "none".to_string();
+    }
+    shadows.iter().map(Shadow::to_string).collect::<Vec<_>>().join(", ")
+}
+
+/// `none`, or the functions separated by spaces
+pub fn format_filters(filters: &[Filter]) -> String {
+    if filters.is_empty() {
+        return "none".to_string();
+    }
+    filters.iter().map(Filter::to_string).collect::<Vec<_>>().join(" ")
+}
+
+struct Reader<'a> {
+    value: &'a [u8],
+    color_size: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+        let taken = self.value.get(..count)?;
+        self.value = &self.value[count..];
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn length(&mut self) -> Option<f64> {
+        self.u16().map(|tenths| tenths as i16 as f64 / 10.0)
+    }
+
+    fn shadow(&mut self, inset: bool, spread: bool) -> Option<Shadow> {
+        let offset = (self.length()?, self.length()?);
+        let blur = self.u16()? as f64 / 10.0;
+        let spread = if spread { self.length()? } else { 0.0 };
+        let color = self.take(self.color_size)?.to_vec();
+        Some(Shadow { inset, offset, blur, spread, color })
+    }
+}
+
+impl fmt::Display for Shadow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.inset {
+            write!(f, "inset ")?;
+        }
+        write!(f, "{}px {}px {}px", self.offset.0, self.offset.1, self.blur)?;
+        if self.spread != 0.0 {
+            write!(f, " {}px", self.spread)?;
+        }
+        write!(f, " {}", format_color(&self.color).unwrap_or_default())
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blur(radius) => write!(f, "blur({}px)", radius),
+            Self::HueRotate(degrees) => write!(f, "hue-rotate({}deg)", degrees),
+            Self::DropShadow(shadow) => write!(f, "drop-shadow({})", shadow),
+            Self::Amount(kind, amount) => write!(f, "{}({}%)", kind.name(), (amount * 10000.0).round() / 100.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(name: &str) -> Option<String> {
+        (name == "shade").then(|| "alpha(black, 25%)".to_string())
+    }
+
+    #[test]
+    fn test_shadow_layers() {
+        let shadows = parse_shadows("0 1px 2px #0000004D, inset -1.5px 0 0 1px rgba(255, 255, 255, 0.5)", false, &variables).unwrap();
+        assert_eq!(format_shadows(&shadows), "0px 1px 2px #0000004D, inset -1.5px 0px 0px 1px #FFFFFF80");
+        let value = encode_shadows(&shadows).unwrap();
+        assert_eq!(value[..14], [2, 0, 0, 0, 10, 0, 20, 0, 0, 0, 0, 0, 0, 0x4D]);
+        assert_eq!(value[14..24], [SHADOW_INSET, 0xF1, 0xFF, 0, 0, 0, 0, 10, 0, 0xFF]);
+        assert_eq!(decode_shadows(&value, 4).unwrap(), shadows);
+
+        let wide = widen_shadows(&value).unwrap();
+        assert_eq!(wide.len(), value.len() + 8);
+        assert_eq!(format_shadows(&decode_shadows(&wide, 8).unwrap()), "0px 1px 2px #0000000000004D4D, inset -1.5px 0px 0px 1px #FFFFFFFFFFFF8080");
+        assert_eq!(parse_shadows("$shade 2px 3px", true, &variables).unwrap()[0].color, [0, 0, 0, 0x40]);
+        assert_eq!(parse_shadows("none", false, &variables).unwrap(), []);
+        assert_eq!(decode_shadows(&value[..value.len() - 1], 4), None);
+    }
+
+    #[test]
+    fn test_filter_chains() {
+        let filters = parse_filters("blur(4px) brightness(1.2) grayscale(150%) hue-rotate(-0.25turn) drop-shadow(0 2px $shade) invert()", &variables).unwrap();
+        assert_eq!(
+            format_filters(&filters),
+            "blur(4px) brightness(120%) grayscale(100%) hue-rotate(-90deg) drop-shadow(0px 2px 0px #00000040) invert(100%)"
+        );
+        let value = encode_filters(&filters).unwrap();
+        assert_eq!(value[..10], [6, 0, 40, 0, 1, 0xE0, 0x2E, 3, 0x10, 0x27]);
+        let decoded = decode_filters(&value, 4).unwrap();
+        assert_eq!(decoded[3], Filter::HueRotate(270.0));
+        assert_eq!(format_filters(&decode_filters(&widen_filters(&value).unwrap(), 8).unwrap()).split(' ').nth(4), Some("drop-shadow(0px"));
+        assert_eq!(decode_filters(&[1, 10, 0, 0], 4), None);
+    }
+
+    #[test]
+    fn test_malformed_values() {
+        let shadow = |source: &str| parse_shadows(source, false, &variables).unwrap_err();
+        assert_eq!(shadow("2px"), "shadow '2px' needs an x and y offset and may have a blur radius and a spread");
+        assert_eq!(shadow("1px red 2px"), "the lengths of shadow '1px red 2px' must be written together");
+        assert_eq!(shadow("1em 1em"), "'1em' is not a length in pixels");
+        assert_eq!(shadow("1px 1px red blue"), "shadow '1px 1px red blue' has more than one color");
+        assert_eq!(shadow("1px 1px -2px"), "shadow '1px 1px -2px' has a negative blur radius");
+        assert_eq!(shadow("1px 1px bluish"), "'bluish' is not a color");
+        assert_eq!(parse_shadows("inset 1px 1px", true, &variables).unwrap_err(), "text shadows and drop shadows cannot be inset");
+
+        let filter = |source: &str| parse_filters(source, &variables).unwrap_err();
+        assert!(filter("glow(2px)").starts_with("unknown filter function 'glow'; use one of blur(), brightness()"));
+        assert_eq!(filter("blur(-1px)"), "blur() radius cannot be negative");
+        assert_eq!(filter("brightness(red)"), "brightness() takes a percentage or a number, not 'red'");
+        assert_eq!(filter("contrast(-10%)"), "contrast() amount '-10%' is outside 0% to 655.35%");
+        assert_eq!(filter("blur(4px"), "missing ')' in 'blur(4px'");
+        assert_eq!(filter("sepia"), "'sepia' is not a filter function such as blur(4px)");
+
+        // Encoding fails instead of truncating a count or size that does not fit
+        let shadows = vec![parse_shadows("1px 1px red", false, &variables).unwrap().remove(0); 20];
+        assert_eq!(encode_shadows(&shadows).unwrap_err(), "20 shadow layers need 261 bytes, more than a property can hold");
+        let filters = vec![Filter::Blur(1.0); 300];
+        assert_eq!(encode_filters(&filters).unwrap_err(), "300 filter functions given; at most 255 fit in a value");
+    }
+}
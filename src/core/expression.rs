@@ -0,0 +1,290 @@
+// FILE: src/core/expression.rs
+
+//! Unit arithmetic left for the renderer
+//!
+//! Arithmetic in property values is folded at compile time whenever the units allow
+//! it (see `compiler::middle_end::expressions`). A sum of lengths in different units,
+//! such as `100% - 20px`, only has a value once the renderer knows what the
+//! percentage is of, so it is written as a Calc value instead: a postfix program for
+//! a stack machine that leaves exactly one number.
+//!
+//! ```text
+//! 0x00  number     4-byte number (f32, or Q16.16 with FLAG_FIXED_POINT), then a CSSUnit byte
+//! 0x01  add        pop b, pop a, push a + b
+//! 0x02  subtract   a - b
+//! 0x03  multiply   a * b; one of the two is a plain number
+//! 0x04  divide     a / b; b is a plain number
+//! 0x05  remainder  a % b, with the sign of a; b is a plain number
+//! 0x06  min        then u8 n: pop n values, push the smallest
+//! 0x07  max        then u8 n: pop n values, push the largest
+//! 0x08  clamp      pop max, pop value, pop min, push max(min, min(value, max))
+//! ```
+//!
+//! Plain numbers in a sum are pixels; the compiler writes them with the Pixels unit.
+
+use crate::core::fixed_point::NumberFormat;
+use crate::core::state::CSSUnit;
+use std::fmt;
+
+/// Functions an expression may call; `calc()` just groups its argument
+pub const EXPRESSION_FUNCTIONS: [&str; 4] = ["min", "max", "clamp", "calc"];
+
+const NUMBER: u8 = 0x00;
+const MIN: u8 = 0x06;
+const MAX: u8 = 0x07;
+const CLAMP: u8 = 0x08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CalcOperator {
+    Add = 0x01,
+    Subtract = 0x02,
+    Multiply = 0x03,
+    Divide = 0x04,
+    Remainder = 0x05,
+}
+
+impl CalcOperator {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Add),
+            0x02 => Some(Self::Subtract),
+            0x03 => Some(Self::Multiply),
+            0x04 => Some(Self::Divide),
+            0x05 => Some(Self::Remainder),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Subtract => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+            Self::Remainder => "%",
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Add | Self::Subtract => 1,
+            Self::Multiply | Self::Divide | Self::Remainder => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calc {
+    Number(f64, CSSUnit),
+    Binary(CalcOperator, Box<Calc>, Box<Calc>),
+    Min(Vec<Calc>),
+    Max(Vec<Calc>),
+    Clamp(Box<Calc>, Box<Calc>, Box<Calc>),
+}
+
+impl Calc {
+    pub fn binary(operator: CalcOperator, left: Calc, right: Calc) -> Self {
+        Self::Binary(operator, Box::new(left), Box::new(right))
+    }
+
+    /// The KRB Calc value with f32 numbers, or an error if it does not fit a property
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut value = Vec::new();
+        self.write_program(&mut value)?;
+        if value.len() > u8::MAX as usize {
+            return Err(format!("expression needs {} bytes, more than a property can hold", value.len()));
+        }
+        Ok(value)
+    }
+
+    fn write_program(&self, value: &mut Vec<u8>) -> Result<(), String> {
+        match self {
+            Self::Number(number, unit) => {
+                value.push(NUMBER);
+                value.extend((*number as f32).to_le_bytes());
+                value.push(*unit as u8);
+            }
+            Self::Binary(operator, left, right) => {
+                left.write_program(value)?;
+                right.write_program(value)?;
+                value.push(*operator as u8);
+            }
+            Self::Min(arguments) | Self::Max(arguments) => {
+                let count = u8::try_from(arguments.len()).map_err(|_| "too many arguments".to_string())?;
+                for argument in arguments {
+                    argument.write_program(value)?;
+                }
+                value.push(if matches!(self, Self::Min(_)) { MIN } else { MAX });
+                value.push(count);
+            }
+            Self::Clamp(min, preferred, max) => {
+                for argument in [min, preferred, max] {
+                    argument.write_program(value)?;
+                }
+                value.push(CLAMP);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a Calc value whose numbers are in `format`; `None` if it is malformed
+    pub fn decode(value: &[u8], format: NumberFormat) -> Option<Self> {
+        let mut stack: Vec<Calc> = Vec::new();
+        let mut offset = 0;
+        while let Some(&opcode) = value.get(offset) {
+            offset += 1;
+            match opcode {
+                NUMBER => {
+                    let number = value.get(offset..offset + 4)?;
+                    let unit = CSSUnit::from_u8(*value.get(offset + 4)?)?;
+                    stack.push(Self::Number(format.decode([number[0], number[1], number[2], number[3]]), unit));
+                    offset += 5;
+                }
+                MIN | MAX => {
+                    let count = *value.get(offset)? as usize;
+                    offset += 1;
+                    if count == 0 || count > stack.len() {
+                        return None;
+                    }
+                    let arguments = stack.split_off(stack.len() - count);
+                    stack.push(if opcode == MIN { Self::Min(arguments) } else { Self::Max(arguments) });
+                }
+                CLAMP => {
+                    let max = stack.pop()?;
+                    let preferred = stack.pop()?;
+                    let min = stack.pop()?;
+                    stack.push(Self::Clamp(Box::new(min), Box::new(preferred), Box::new(max)));
+                }
+                _ => {
+                    let operator = CalcOperator::from_u8(opcode)?;
+                    let right = stack.pop()?;
+                    let left = stack.pop()?;
+                    stack.push(Self::binary(operator, left, right));
+                }
+            }
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some(calc), true) => Some(calc),
+            _ => None,
+        }
+    }
+
+    fn write_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(number, unit) => write!(f, "{}{}", number, unit.suffix()),
+            Self::Binary(operator, left, right) => {
+                left.write_operand(f, operator.precedence(), false)?;
+                write!(f, " {} ", operator.symbol())?;
+                right.write_operand(f, operator.precedence(), true)
+            }
+            Self::Min(arguments) => write_function(f, "min", arguments.iter()),
+            Self::Max(arguments) => write_function(f, "max", arguments.iter()),
+            Self::Clamp(min, preferred, max) => write_function(f, "clamp", [min, preferred, max].into_iter().map(|a| &**a)),
+        }
+    }
+
+    /// Write an operand of an operator, in parentheses if it binds less tightly
+    fn write_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8, right: bool) -> fmt::Result {
+        match self {
+            Self::Binary(operator, ..) if operator.precedence() < precedence || (right && operator.precedence() == precedence) => {
+                write!(f, "(")?;
+                self.write_nested(f)?;
+                write!(f, ")")
+            }
+            _ => self.write_nested(f),
+        }
+    }
+}
+
+fn write_function<'a>(f: &mut fmt::Formatter<'_>, name: &str, arguments: impl Iterator<Item = &'a Calc>) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    for (index, argument) in arguments.enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        argument.write_nested(f)?;
+    }
+    write!(f, ")")
+}
+
+/// Offsets of the 4-byte numbers in a Calc value, or `None` if its opcodes are malformed
+pub fn calc_number_offsets(value: &[u8]) -> Option<Vec<usize>> {
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    while let Some(&opcode) = value.get(offset) {
+        match opcode {
+            NUMBER if offset + 6 <= value.len() => {
+                offsets.push(offset + 1);
+                offset += 6;
+            }
+            MIN | MAX if offset + 2 <= value.len() => offset += 2,
+            CLAMP => offset += 1,
+            _ => {
+                CalcOperator::from_u8(opcode)?;
+                offset += 1;
+            }
+        }
+    }
+    Some(offsets)
+}
+
+impl fmt::Display for Calc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Binary(..) => {
+                write!(f, "calc(")?;
+                self.write_nested(f)?;
+                write!(f, ")")
+            }
+            _ => self.write_nested(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(number: f64, unit: CSSUnit) -> Calc {
+        Calc::Number(number, unit)
+    }
+
+    #[test]
+    fn test_calc_round_trip() {
+        // (100% - 20px) / 2, as `calc((100% - 20px) / 2)`
+        let half = Calc::binary(
+            CalcOperator::Divide,
+            Calc::binary(CalcOperator::Subtract, length(100.0, CSSUnit::Percentage), length(20.0, CSSUnit::Pixels)),
+            length(2.0, CSSUnit::Number),
+        );
+        let value = half.encode().unwrap();
+        assert_eq!(value.len(), 3 * 6 + 2);
+        assert_eq!(value[..6], [0x00, 0x00, 0x00, 0xC8, 0x42, 0x06]);
+        assert_eq!(value[value.len() - 1], CalcOperator::Divide as u8);
+        let decoded = Calc::decode(&value, NumberFormat::Float).unwrap();
+        assert_eq!(decoded, half);
+        assert_eq!(decoded.to_string(), "calc((100% - 20px) / 2)");
+
+        let clamp = Calc::Clamp(
+            Box::new(length(10.0, CSSUnit::Pixels)),
+            Box::new(Calc::binary(CalcOperator::Add, length(5.0, CSSUnit::ViewportWidth), length(1.0, CSSUnit::Rem))),
+            Box::new(Calc::Min(vec![length(40.0, CSSUnit::Pixels), length(10.0, CSSUnit::Percentage)])),
+        );
+        let value = clamp.encode().unwrap();
+        assert_eq!(Calc::decode(&value, NumberFormat::Float).unwrap().to_string(), "clamp(10px, 5vw + 1rem, min(40px, 10%))");
+        assert_eq!(calc_number_offsets(&value).unwrap(), [1, 7, 13, 20, 26]);
+
+        assert_eq!(Calc::decode(&value[..value.len() - 1], NumberFormat::Float), None);
+        assert_eq!(Calc::decode(&[0x01], NumberFormat::Float), None);
+        assert_eq!(calc_number_offsets(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x0F]), None);
+    }
+
+    #[test]
+    fn test_calc_size_limit() {
+        let long = (0..50).fold(length(0.0, CSSUnit::Pixels), |sum, _| {
+            Calc::binary(CalcOperator::Add, sum, length(1.0, CSSUnit::Percentage))
+        });
+        assert!(long.encode().unwrap_err().contains("more than a property can hold"));
+    }
+}
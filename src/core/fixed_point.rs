@@ -0,0 +1,133 @@
+// FILE: src/core/fixed_point.rs
+
+//! Encoding of fractional numbers in a KRB file
+//!
+//! Numbers are IEEE 754 f32 unless `FLAG_FIXED_POINT` is set. Renderers without an
+//! FPU read such files with integer arithmetic only: every 32-bit number is a signed
+//! Q16.16 value, an i32 holding the number times 65536, rounded to nearest. It spans
+//! -32768 to 32767.99998 in steps of 1/65536; the compiler rejects values outside
+//! that range. Sizes are unchanged, so the two encodings share one layout:
+//!
+//! ```text
+//! Float, Percentage (4 bytes)   one number: flex grow/shrink, line height, spacing,
+//!                               aspect ratio, percentages of width, height and insets
+//! CSSUnit (5 bytes)             one number followed by a CSSUnit byte; used by transforms
+//! TransformMatrix (4n bytes)    n numbers in row-major order
+//! Calc                          numbers inside the program (see `core::expression`)
+//! ```
+//!
+//! Opacity is always a 2-byte 8.8 fixed-point Percentage, and font sizes are whole
+//! pixels in a Short, so neither needs converting.
+
+use crate::core::constants::FLAG_FIXED_POINT;
+use crate::core::expression::calc_number_offsets;
+use crate::core::types::ValueType;
+
+/// One unit in Q16.16
+const ONE: f64 = 65536.0;
+
+/// How the 32-bit numbers of a file are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    #[default]
+    Float,
+    FixedPoint,
+}
+
+impl NumberFormat {
+    pub fn from_flags(flags: u16) -> Self {
+        if flags & FLAG_FIXED_POINT != 0 {
+            Self::FixedPoint
+        } else {
+            Self::Float
+        }
+    }
+
+    /// Encode `value`, or `None` if this format cannot represent it
+    pub fn encode(self, value: f64) -> Option<[u8; 4]> {
+        match self {
+            Self::Float => Some((value as f32).to_le_bytes()),
+            Self::FixedPoint => {
+                let scaled = (value * ONE).round();
+                if !scaled.is_finite() || scaled < i32::MIN as f64 || scaled > i32::MAX as f64 {
+                    return None;
+                }
+                Some((scaled as i32).to_le_bytes())
+            }
+        }
+    }
+
+    pub fn decode(self, bytes: [u8; 4]) -> f64 {
+        match self {
+            Self::Float => f32::from_le_bytes(bytes) as f64,
+            Self::FixedPoint => i32::from_le_bytes(bytes) as f64 / ONE,
+        }
+    }
+}
+
+/// Number of 32-bit numbers in a value of this type and length; they start at offset 0, 4 bytes apart
+pub fn number_count(value_type: ValueType, len: usize) -> usize {
+    match value_type {
+        ValueType::Float | ValueType::Percentage if len == 4 => 1,
+        ValueType::CSSUnit if len == 5 => 1,
+        ValueType::TransformMatrix if len % 4 == 0 => len / 4,
+        _ => 0,
+    }
+}
+
+/// Re-encode the f32 numbers in `value` as Q16.16; on overflow the value is left
+/// partly converted and the number that does not fit is returned
+pub fn convert_to_fixed_point(value_type: ValueType, value: &mut [u8]) -> Result<(), f64> {
+    let offsets = match value_type {
+        ValueType::Calc => calc_number_offsets(value).unwrap_or_default(),
+        _ => (0..number_count(value_type, value.len())).map(|index| index * 4).collect(),
+    };
+    for offset in offsets {
+        let bytes = &mut value[offset..offset + 4];
+        let number = NumberFormat::Float.decode([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let encoded = NumberFormat::FixedPoint.encode(number).ok_or(number)?;
+        bytes.copy_from_slice(&encoded);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_q16_16_round_trip_and_range() {
+        let fixed = NumberFormat::FixedPoint;
+        assert_eq!(fixed.encode(1.5), Some(0x0001_8000i32.to_le_bytes()));
+        assert_eq!(fixed.encode(-0.25), Some((-16384i32).to_le_bytes()));
+        assert_eq!(fixed.decode(fixed.encode(12.75).unwrap()), 12.75);
+        assert!(fixed.encode(32767.99).is_some());
+        assert!(fixed.encode(-32768.0).is_some());
+        assert_eq!(fixed.encode(32768.0), None);
+        assert_eq!(fixed.encode(f64::NAN), None);
+        assert_eq!(NumberFormat::from_flags(FLAG_FIXED_POINT), NumberFormat::FixedPoint);
+        assert_eq!(NumberFormat::from_flags(0), NumberFormat::Float);
+    }
+
+    #[test]
+    fn test_layouts() {
+        let mut unit = 2.5f32.to_le_bytes().to_vec();
+        unit.push(0x07);
+        assert_eq!(convert_to_fixed_point(ValueType::CSSUnit, &mut unit), Ok(()));
+        assert_eq!(unit, [0x00, 0x80, 0x02, 0x00, 0x07]);
+
+        let mut matrix: Vec<u8> = [1.0f32, 0.0, 0.0, 1.0, 40000.0, 0.0].iter().flat_map(|n| n.to_le_bytes()).collect();
+        assert_eq!(convert_to_fixed_point(ValueType::TransformMatrix, &mut matrix), Err(40000.0));
+
+        // 50% - 1.5px
+        let mut calc = vec![0x00, 0x00, 0x00, 0x48, 0x42, 0x06, 0x00, 0x00, 0x00, 0xC0, 0x3F, 0x01, 0x02];
+        assert_eq!(convert_to_fixed_point(ValueType::Calc, &mut calc), Ok(()));
+        assert_eq!(calc, [0x00, 0x00, 0x00, 0x32, 0x00, 0x06, 0x00, 0x00, 0x80, 0x01, 0x00, 0x01, 0x02]);
+
+        // Opacity is already 8.8 fixed point
+        let mut opacity = vec![0x80, 0x00];
+        assert_eq!(number_count(ValueType::Percentage, opacity.len()), 0);
+        assert_eq!(convert_to_fixed_point(ValueType::Percentage, &mut opacity), Ok(()));
+        assert_eq!(opacity, [0x80, 0x00]);
+    }
+}
@@ -0,0 +1,245 @@
+// FILE: src/core/gradient.rs
+
+//! Linear and radial gradients
+//!
+//! `background_image` takes a CSS-style gradient:
+//!
+//! ```text
+//! linear-gradient([<angle> | to <side> [<side>],] <stop>, <stop>, ...)
+//! radial-gradient([circle | ellipse] [at <x> [<y>]],] <stop>, <stop>, ...)
+//! ```
+//!
+//! A stop is a color followed by an optional percentage. Stops without one are spread
+//! evenly between their neighbours; the first defaults to 0% and the last to 100%.
+//! Linear gradients point to the bottom and radial ones are centered ellipses unless
+//! told otherwise.
+//!
+//! In a KRB file a Gradient value is integers only, so it reads the same with or
+//! without `FLAG_FIXED_POINT`:
+//!
+//! ```text
+//! u8    kind          0 linear, 1 radial
+//! u8    shape         radial: 0 ellipse, 1 circle; 0 for linear
+//! u16   angle         linear: hundredths of a degree clockwise from "to top"; 0 for radial
+//! u16   center x      radial: hundredths of a percent of the width; 0 for linear
+//! u16   center y      radial: hundredths of a percent of the height; 0 for linear
+//! u8    stop count
+//! then, for each stop:
+//!       color         a Color value (4 bytes, or 8 with FLAG_EXTENDED_COLOR)
+//!       u16 position  hundredths of a percent along the gradient, never decreasing
+//! ```
+
+use crate::core::color::{format_color, widen_color};
+use crate::core::util::parse_color;
+use std::fmt;
+
+/// Stops a gradient may have; with 16-bit colors the largest value still fits a property
+pub const MAX_GRADIENT_STOPS: usize = 16;
+
+/// Bytes before the first stop
+const HEADER_SIZE: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum GradientKind {
+    Linear = 0,
+    Radial = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum RadialShape {
+    #[default]
+    Ellipse = 0,
+    Circle = 1,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientStop {
+    /// Color as written: hex, a named color, a color function call or a `$variable`
+    pub color: String,
+    /// Percentage along the gradient, when one was given
+    pub position: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub shape: RadialShape,
+    /// Degrees clockwise from "to top"
+    pub angle: f64,
+    /// Percentages of the width and height
+    pub center: (f64, f64),
+    pub stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    pub fn linear(angle: f64, stops: Vec<GradientStop>) -> Self {
+        Self { kind: GradientKind::Linear, shape: RadialShape::Ellipse, angle, center: (50.0, 50.0), stops }
+    }
+
+    pub fn radial(shape: RadialShape, center: (f64, f64), stops: Vec<GradientStop>) -> Self {
+        Self { kind: GradientKind::Radial, shape, angle: 0.0, center, stops }
+    }
+
+    /// Stop positions with the missing ones filled in
+    pub fn positions(&self) -> Vec<f64> {
+        let mut positions: Vec<Option<f64>> = self.stops.iter().map(|stop| stop.position).collect();
+        let last = positions.len().saturating_sub(1);
+        if let Some(first) = positions.first_mut() {
+            first.get_or_insert(0.0);
+        }
+        if let Some(end) = positions.last_mut() {
+            end.get_or_insert(100.0);
+        }
+
+        // Spread each run of missing positions evenly between the stops around it
+        let mut start = 0;
+        for index in 1..=last {
+            if let (Some(from), Some(to)) = (positions[start], positions[index]) {
+                let steps = (index - start) as f64;
+                for (step, position) in positions[start + 1..index].iter_mut().enumerate() {
+                    *position = Some(from + (to - from) * (step + 1) as f64 / steps);
+                }
+                start = index;
+            }
+        }
+        positions.into_iter().map(|position| position.unwrap_or(0.0)).collect()
+    }
+
+    /// The KRB Gradient value, with 8-bit colors; every stop color must be a compile-time color
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let (angle, center) = match self.kind {
+            GradientKind::Linear => (hundredths(self.angle.rem_euclid(360.0)), (0, 0)),
+            GradientKind::Radial => (0, (hundredths(self.center.0), hundredths(self.center.1))),
+        };
+        let shape = match self.kind {
+            GradientKind::Linear => 0,
+            GradientKind::Radial => self.shape as u8,
+        };
+
+        let mut value = vec![self.kind as u8, shape];
+        value.extend(angle.to_le_bytes());
+        value.extend(center.0.to_le_bytes());
+        value.extend(center.1.to_le_bytes());
+        let count = u8::try_from(self.stops.len()).map_err(|_| format!("gradient has {} stops; at most 255 fit", self.stops.len()))?;
+        value.push(count);
+        for (stop, position) in self.stops.iter().zip(self.positions()) {
+            let color = parse_color(&stop.color).map_err(|e| format!("gradient stop '{}': {}", stop.color, e))?;
+            value.extend(color.to_bytes());
+            value.extend(hundredths(position).to_le_bytes());
+        }
+        if value.len() > u8::MAX as usize {
+            return Err(format!("gradient with {} stops needs {} bytes, more than a property can hold", self.stops.len(), value.len()));
+        }
+        Ok(value)
+    }
+
+    /// Read a Gradient value whose colors are `color_size` bytes; `None` if it is malformed
+    pub fn decode(value: &[u8], color_size: usize) -> Option<Self> {
+        let header: &[u8; HEADER_SIZE] = value.get(..HEADER_SIZE)?.try_into().ok()?;
+        let [kind, shape, a0, a1, x0, x1, y0, y1, count] = *header;
+        let stops = &value[HEADER_SIZE..];
+        if stops.len() != count as usize * (color_size + 2) {
+            return None;
+        }
+
+        let stops = stops
+            .chunks_exact(color_size + 2)
+            .map(|stop| GradientStop {
+                color: format_color(&stop[..color_size]).unwrap_or_default(),
+                position: Some(u16::from_le_bytes([stop[color_size], stop[color_size + 1]]) as f64 / 100.0),
+            })
+            .collect();
+        let number = |lo, hi| u16::from_le_bytes([lo, hi]) as f64 / 100.0;
+        match (kind, shape) {
+            (0, 0) => Some(Self::linear(number(a0, a1), stops)),
+            (1, 0) => Some(Self::radial(RadialShape::Ellipse, (number(x0, x1), number(y0, y1)), stops)),
+            (1, 1) => Some(Self::radial(RadialShape::Circle, (number(x0, x1), number(y0, y1)), stops)),
+            _ => None,
+        }
+    }
+}
+
+/// Widen the 8-bit stop colors of a Gradient value to 16 bits per channel
+pub fn widen_gradient(value: &[u8]) -> Option<Vec<u8>> {
+    let header = value.get(..HEADER_SIZE)?;
+    let stops = &value[HEADER_SIZE..];
+    if stops.len() != header[HEADER_SIZE - 1] as usize * 6 {
+        return None;
+    }
+
+    let mut wide = header.to_vec();
+    for stop in stops.chunks_exact(6) {
+        wide.extend(widen_color(&stop[..4])?);
+        wide.extend(&stop[4..]);
+    }
+    Some(wide)
+}
+
+fn hundredths(value: f64) -> u16 {
+    (value * 100.0).round().clamp(0.0, u16::MAX as f64) as u16
+}
+
+impl fmt::Display for Gradient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            GradientKind::Linear => write!(f, "linear-gradient({}deg", self.angle)?,
+            GradientKind::Radial => {
+                let shape = match self.shape {
+                    RadialShape::Ellipse => "ellipse",
+                    RadialShape::Circle => "circle",
+                };
+                write!(f, "radial-gradient({} at {}% {}%", shape, self.center.0, self.center.1)?
+            }
+        }
+        for stop in &self.stops {
+            write!(f, ", {}", stop.color)?;
+            if let Some(position) = stop.position {
+                write!(f, " {}%", position)?;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(color: &str, position: Option<f64>) -> GradientStop {
+        GradientStop { color: color.to_string(), position }
+    }
+
+    #[test]
+    fn test_missing_positions_are_spread() {
+        let stops = vec![stop("red", None), stop("white", None), stop("blue", Some(40.0)), stop("#000", None), stop("#fff", None)];
+        assert_eq!(Gradient::linear(180.0, stops).positions(), [0.0, 20.0, 40.0, 70.0, 100.0]);
+        let stops = vec![stop("red", Some(25.0)), stop("blue", None)];
+        assert_eq!(Gradient::linear(180.0, stops).positions(), [25.0, 100.0]);
+    }
+
+    #[test]
+    fn test_gradient_layout() {
+        let linear = Gradient::linear(-45.0, vec![stop("red", None), stop("#0000FF80", Some(75.5))]);
+        let value = linear.encode().unwrap();
+        assert_eq!(value, [0, 0, 0x0C, 0x7B, 0, 0, 0, 0, 2, 0xFF, 0, 0, 0xFF, 0, 0, 0, 0, 0xFF, 0x80, 0x7E, 0x1D]);
+        assert_eq!(Gradient::decode(&value, 4).unwrap().to_string(), "linear-gradient(315deg, #FF0000FF 0%, #0000FF80 75.5%)");
+
+        let radial = Gradient::radial(RadialShape::Circle, (25.0, 100.0), vec![stop("white", None), stop("black", None)]);
+        let value = radial.encode().unwrap();
+        assert_eq!(value[..9], [1, 1, 0, 0, 0xC4, 0x09, 0x10, 0x27, 2]);
+        let wide = widen_gradient(&value).unwrap();
+        assert_eq!(wide.len(), 9 + 2 * 10);
+        assert_eq!(Gradient::decode(&wide, 8).unwrap().to_string(), "radial-gradient(circle at 25% 100%, #FFFFFFFFFFFFFFFF 0%, #000000000000FFFF 100%)");
+
+        assert_eq!(Gradient::decode(&value[..value.len() - 1], 4), None);
+        assert_eq!(widen_gradient(&wide), None);
+        assert!(Gradient::linear(0.0, vec![stop("bluish", None)]).encode().unwrap_err().contains("'bluish' is not a color"));
+
+        // Too many stops are an error rather than a truncated count or size
+        let many = |count| Gradient::linear(0.0, vec![stop("red", None); count]).encode().unwrap_err();
+        assert_eq!(many(50), "gradient with 50 stops needs 309 bytes, more than a property can hold");
+        assert_eq!(many(300), "gradient has 300 stops; at most 255 fit");
+    }
+}
@@ -0,0 +1,119 @@
+// FILE: src/core/index_width.rs
+
+//! Width of table indices and counts in a KRB file
+//!
+//! Compact files store table indices and record counts in one byte. Files with
+//! `FLAG_WIDE_INDICES` set store all of them as little-endian u16 instead. The
+//! compiler writes the compact form whenever every value fits in a byte.
+//!
+//! These fields widen:
+//!
+//! ```text
+//! strings      length
+//! elements     id string, style ID, property/child/event/animation/custom/state counts
+//! properties   payload of String, Resource, TemplateVariable and ThemeToken values (size becomes 2)
+//! custom props key string
+//! state sets   property count
+//! events       callback string
+//! animations   reference target; table name, keyframe count, keyframe property count
+//! styles       ID, name, property count
+//! components   name, property count, property name, default value length
+//! scripts      name, entry point count, entry point names
+//! resources    name, data string, fallback count and fallbacks, shared resource
+//! template     variable name and default value; binding expression, variable count and indices
+//! transforms   property count
+//! themes       slot count, slot names, theme names, index payloads of theme values
+//! ```
+//!
+//! Enum bytes (types, property IDs, flags) and property value sizes stay one byte.
+
+use crate::core::constants::FLAG_WIDE_INDICES;
+use crate::core::types::ValueType;
+
+/// Ordered narrowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum IndexWidth {
+    #[default]
+    Compact,
+    Wide,
+}
+
+impl IndexWidth {
+    pub fn from_flags(flags: u16) -> Self {
+        if flags & FLAG_WIDE_INDICES != 0 {
+            Self::Wide
+        } else {
+            Self::Compact
+        }
+    }
+
+    /// The narrowest width that can hold `largest`, or `None` if not even u16 can
+    pub fn for_largest(largest: usize) -> Option<Self> {
+        if largest <= u8::MAX as usize {
+            Some(Self::Compact)
+        } else if largest <= u16::MAX as usize {
+            Some(Self::Wide)
+        } else {
+            None
+        }
+    }
+
+    /// Bytes taken by one index or count
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::Compact => 1,
+            Self::Wide => 2,
+        }
+    }
+
+    /// Size of an element header: 11 fixed bytes plus two indices and six counts
+    pub fn element_header_size(self) -> usize {
+        11 + 8 * self.bytes()
+    }
+
+    /// Size of an element's animation reference: 8 fixed bytes plus the target
+    pub fn animation_ref_size(self) -> usize {
+        8 + self.bytes()
+    }
+
+    /// Encoded size of a property value: index values take this width, anything else its own length
+    pub fn value_size(self, value_type: ValueType, value: &[u8]) -> usize {
+        if value_type.holds_index() && decode_index(value).is_some() {
+            self.bytes()
+        } else {
+            value.len()
+        }
+    }
+
+    /// Append `value` in this width; the caller has checked that it fits
+    pub fn write(self, output: &mut Vec<u8>, value: u16) {
+        match self {
+            Self::Compact => output.push(value as u8),
+            Self::Wide => output.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+}
+
+/// The table index held by a value whose type `holds_index`, in either width
+pub fn decode_index(value: &[u8]) -> Option<u16> {
+    match *value {
+        [index] => Some(index as u16),
+        [lo, hi] => Some(u16::from_le_bytes([lo, hi])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width_selection() {
+        assert_eq!(IndexWidth::for_largest(255), Some(IndexWidth::Compact));
+        assert_eq!(IndexWidth::for_largest(256), Some(IndexWidth::Wide));
+        assert_eq!(IndexWidth::for_largest(70_000), None);
+        assert_eq!(decode_index(&[0x2C, 0x01]), Some(300));
+        assert_eq!(decode_index(&[7]), Some(7));
+        assert_eq!(decode_index(&[1, 2, 3]), None);
+    }
+}
@@ -1,25 +1,46 @@
 // FILE: src/core/mod.rs
 
 // 1. Declare the modules
+pub mod animation;
+pub mod color;
+pub mod compression;
 pub mod constants;
+pub mod debug_info;
+pub mod effects;
+pub mod expression;
+pub mod fixed_point;
+pub mod gradient;
+pub mod index_width;
+pub mod platform;
 pub mod types;
 pub mod properties;
+pub mod reader;
 pub mod state;
 pub mod util;
+pub mod verifier;
 
 use crate::error::{CompilerError, Result};
 
 // 2. Re-export the most important types for easy access
 //    This prevents other files from having to write `use crate::core::state::CompilerState;`
 //    and allows them to just write `use crate::core::CompilerState;`
+pub use animation::*;
+pub use compression::*;
 pub use constants::*;
+pub use debug_info::*;
+pub use fixed_point::*;
+pub use index_width::*;
+pub use platform::*;
 pub use core::*;
 pub use properties::*;
+pub use reader::*;
 pub use state::*;
 pub use util::*;
+pub use verifier::*;
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use serde::Serialize;
+use std::borrow::Cow;
 use std::io::{Cursor, Read};
 
 /// Information about a KRB file, generated by inspecting its header.
@@ -34,6 +55,9 @@ pub struct KrbFileInfo {
     pub script_count: u16,
     pub string_count: u16,
     pub resource_count: u16,
+    pub template_variable_count: u16,
+    pub template_binding_count: u16,
+    pub transform_count: u16,
     pub element_offset: u32,
     pub style_offset: u32,
     pub component_offset: u32,
@@ -41,7 +65,16 @@ pub struct KrbFileInfo {
     pub script_offset: u32,
     pub string_offset: u32,
     pub resource_offset: u32,
+    pub template_variable_offset: u32,
+    pub template_binding_offset: u32,
+    pub transform_offset: u32,
+    /// From the header extension of files with `FLAG_HAS_THEMES`; zero otherwise
+    pub theme_count: u16,
+    pub theme_offset: u32,
+    /// Size of the uncompressed file
     pub total_size: u32,
+    /// Bytes the file actually occupies; smaller than `total_size` when compressed
+    pub stored_size: u32,
 }
 
 impl KrbFileInfo {
@@ -71,7 +104,39 @@ impl KrbFileInfo {
 }
 
 /// Validates a byte slice as a KRB file and extracts its header information.
+/// Compressed files are inflated to check that the payload matches the header.
 pub fn validate_krb_file(data: &[u8]) -> Result<KrbFileInfo> {
+    Ok(decode_krb_file(data)?.1)
+}
+
+/// Validate the header and return the uncompressed image together with its header information
+pub(crate) fn decode_krb_file(data: &[u8]) -> Result<(Cow<'_, [u8]>, KrbFileInfo)> {
+    let mut info = read_header(data)?;
+    let image = decompress_krb(data)?;
+
+    if info.total_size as usize != image.len() {
+        return Err(CompilerError::InvalidFormat {
+            message: format!(
+                "Size mismatch: header says {}, actual {}",
+                info.total_size,
+                image.len()
+            ),
+        });
+    }
+
+    // The extension is part of the compressed payload, so it is read from the image
+    if info.has_feature(FLAG_HAS_THEMES) {
+        let extension = image.get(KRB_HEADER_SIZE..KRB_HEADER_SIZE + KRB_THEME_HEADER_SIZE).ok_or_else(|| {
+            CompilerError::InvalidFormat { message: "File too small for the theme header extension".to_string() }
+        })?;
+        info.theme_count = u16::from_le_bytes([extension[0], extension[1]]);
+        info.theme_offset = u32::from_le_bytes([extension[2], extension[3], extension[4], extension[5]]);
+    }
+
+    Ok((image, info))
+}
+
+fn read_header(data: &[u8]) -> Result<KrbFileInfo> {
     if data.len() < KRB_HEADER_SIZE {
         return Err(CompilerError::InvalidFormat {
             message: format!(
@@ -104,12 +169,9 @@ pub fn validate_krb_file(data: &[u8]) -> Result<KrbFileInfo> {
     let script_count = cursor.read_u16::<LittleEndian>()?;
     let string_count = cursor.read_u16::<LittleEndian>()?;
     let resource_count = cursor.read_u16::<LittleEndian>()?;
-    
-    // Skip the 3 reserved count fields in the v0.5 header
-    let _ = cursor.read_u16::<LittleEndian>()?;
-    let _ = cursor.read_u16::<LittleEndian>()?;
-    let _ = cursor.read_u16::<LittleEndian>()?;
-
+    let template_variable_count = cursor.read_u16::<LittleEndian>()?;
+    let template_binding_count = cursor.read_u16::<LittleEndian>()?;
+    let transform_count = cursor.read_u16::<LittleEndian>()?;
 
     let element_offset = cursor.read_u32::<LittleEndian>()?;
     let style_offset = cursor.read_u32::<LittleEndian>()?;
@@ -118,24 +180,12 @@ pub fn validate_krb_file(data: &[u8]) -> Result<KrbFileInfo> {
     let script_offset = cursor.read_u32::<LittleEndian>()?;
     let string_offset = cursor.read_u32::<LittleEndian>()?;
     let resource_offset = cursor.read_u32::<LittleEndian>()?;
-    
-    // Skip the 3 reserved offset fields in the v0.5 header
-    let _ = cursor.read_u32::<LittleEndian>()?;
-    let _ = cursor.read_u32::<LittleEndian>()?;
-    let _ = cursor.read_u32::<LittleEndian>()?;
+    let template_variable_offset = cursor.read_u32::<LittleEndian>()?;
+    let template_binding_offset = cursor.read_u32::<LittleEndian>()?;
+    let transform_offset = cursor.read_u32::<LittleEndian>()?;
 
     let total_size = cursor.read_u32::<LittleEndian>()?;
 
-    if total_size as usize != data.len() {
-        return Err(CompilerError::InvalidFormat {
-            message: format!(
-                "Size mismatch: header says {}, actual {}",
-                total_size,
-                data.len()
-            ),
-        });
-    }
-
     Ok(KrbFileInfo {
         version: (major, minor),
         flags,
@@ -146,6 +196,9 @@ pub fn validate_krb_file(data: &[u8]) -> Result<KrbFileInfo> {
         script_count,
         string_count,
         resource_count,
+        template_variable_count,
+        template_binding_count,
+        transform_count,
         element_offset,
         style_offset,
         component_offset,
@@ -153,6 +206,12 @@ pub fn validate_krb_file(data: &[u8]) -> Result<KrbFileInfo> {
         script_offset,
         string_offset,
         resource_offset,
+        template_variable_offset,
+        template_binding_offset,
+        transform_offset,
+        theme_count: 0,
+        theme_offset: 0,
         total_size,
+        stored_size: data.len() as u32,
     })
 }
\ No newline at end of file
@@ -0,0 +1,76 @@
+// FILE: src/core/platform.rs
+
+//! Limits and capabilities of the renderers a KRB file can target
+//!
+//! `TargetPlatform::profile` picks one of these. Desktop, mobile, web and universal
+//! builds share the standard profile, whose limits are the format's own. Embedded
+//! renderers run on microcontrollers: their tables are smaller, they have no video
+//! decoder, no blur pipeline for shadows and filters, and no FPU.
+
+use crate::core::constants::*;
+use crate::core::properties::PropertyId;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformProfile {
+    pub max_elements: usize,
+    pub max_strings: usize,
+    pub max_styles: usize,
+    pub max_component_defs: usize,
+    pub max_animations: usize,
+    pub max_resources: usize,
+    pub max_properties: usize,
+    pub max_custom_properties: usize,
+    pub max_children: usize,
+    /// Largest KRB image the renderer can load, in bytes
+    pub max_file_size: u64,
+    /// Video elements and video resources can be played
+    pub supports_video: bool,
+    /// Properties the renderer ignores; they are stripped with a warning
+    pub unsupported_properties: &'static [PropertyId],
+    /// Numbers must be Q16.16 fixed point (`NumberFormat::FixedPoint`); the renderer has no FPU
+    pub fixed_point: bool,
+}
+
+impl PlatformProfile {
+    pub const STANDARD: Self = Self {
+        max_elements: MAX_ELEMENTS,
+        max_strings: MAX_STRINGS,
+        max_styles: MAX_STYLES,
+        max_component_defs: MAX_COMPONENT_DEFS,
+        max_animations: MAX_ANIMATIONS,
+        max_resources: MAX_RESOURCES,
+        max_properties: MAX_PROPERTIES,
+        max_custom_properties: MAX_CUSTOM_PROPERTIES,
+        max_children: MAX_CHILDREN,
+        max_file_size: 2_147_483_648,
+        supports_video: true,
+        unsupported_properties: &[],
+        fixed_point: false,
+    };
+
+    pub const EMBEDDED: Self = Self {
+        max_elements: 256,
+        max_strings: 512,
+        max_styles: 64,
+        max_component_defs: 32,
+        max_animations: 16,
+        max_resources: 32,
+        max_properties: 32,
+        max_custom_properties: 8,
+        max_children: 64,
+        max_file_size: 64 * 1024,
+        supports_video: false,
+        unsupported_properties: &[
+            PropertyId::Shadow,
+            PropertyId::BoxShadow,
+            PropertyId::TextShadow,
+            PropertyId::Filter,
+            PropertyId::BackdropFilter,
+        ],
+        fixed_point: true,
+    };
+
+    pub fn supports_property(&self, property: PropertyId) -> bool {
+        !self.unsupported_properties.contains(&property)
+    }
+}
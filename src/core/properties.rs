@@ -123,6 +123,9 @@ pub enum PropertyId {
     // Rich Text Properties
     Spans = 0xA0,
     
+    // Fill Properties
+    BackgroundImage = 0xA1,
+    
     // Taffy Modern Flexbox Properties (0x40-0x4F)
     Display = 0x40,
     FlexDirection = 0x41,
@@ -155,6 +158,32 @@ pub enum PropertyId {
 
 
 impl PropertyId {
+    /// Canonical property names accepted by `from_name` (one spelling per property)
+    pub const KNOWN_NAMES: &'static [&'static str] = &[
+        "background_color", "text_color", "border_color", "border_width", "border_radius",
+        "padding", "margin", "text", "font_size", "font_weight", "text_alignment", "font_family",
+        "src", "list_style_type", "white_space", "opacity", "z_index", "visibility", "gap",
+        "min_width", "min_height", "max_width", "max_height", "aspect_ratio", "transform", "shadow",
+        "box_shadow", "overflow", "overflow-x", "overflow-y", "line_height", "letter_spacing",
+        "text_decoration", "text_transform", "text_indent", "text_overflow", "font_style",
+        "font_variant", "word_spacing", "text_shadow", "filter", "backdrop_filter",
+        "min_viewport_width", "max_viewport_width", "spans", "background_image", "width", "height", "cursor", "checked",
+        "type", "window_width", "window_height", "window_title", "resizable", "keep_aspect_ratio",
+        "scale_factor", "icon", "version", "author", "grid_template_columns", "grid_template_rows",
+        "grid_template_areas", "grid_auto_columns", "grid_auto_rows", "grid_auto_flow", "grid_area",
+        "grid_column", "grid_row", "grid_column_start", "grid_column_end", "grid_row_start",
+        "grid_row_end", "grid_gap", "grid_column_gap", "grid_row_gap", "display", "flex_direction",
+        "flex_wrap", "flex_grow", "flex_shrink", "flex_basis", "align_items", "align_self",
+        "align_content", "justify_content", "justify_items", "justify_self", "order", "position",
+        "left", "top", "right", "bottom", "inset", "padding_top", "padding_right", "padding_bottom",
+        "padding_left", "margin_top", "margin_right", "margin_bottom", "margin_left",
+        "border_top_width", "border_right_width", "border_bottom_width", "border_left_width",
+        "border_top_color", "border_right_color", "border_bottom_color", "border_left_color",
+        "border_top_left_radius", "border_top_right_radius", "border_bottom_right_radius",
+        "border_bottom_left_radius", "box_sizing", "outline", "outline_color", "outline_width",
+        "outline_offset", "min_size", "max_size", "preferred_size",
+    ];
+
     /// Centralized property name to ID mapping - used by all compilation phases
     pub fn from_name(key: &str) -> Self {
         match key {
@@ -184,7 +213,6 @@ impl PropertyId {
             "aspect_ratio" => PropertyId::AspectRatio,             // 0x16
             "transform" => PropertyId::Transform,                  // 0x17
             "shadow" => PropertyId::Shadow,                        // 0x18
-            "box_shadow" => PropertyId::Shadow,                    // 0x18 (alias)
             "overflow" => PropertyId::Overflow,                    // 0x8B
             "overflow-x" => PropertyId::OverflowX,                 // 0x8C
             "overflow-y" => PropertyId::OverflowY,                 // 0x8D
@@ -212,6 +240,9 @@ impl PropertyId {
             
             // Rich text properties
             "spans" => PropertyId::Spans, // 0xA0
+            
+            // Fill properties
+            "background_image" | "background-image" => PropertyId::BackgroundImage, // 0xA1
             "width" => PropertyId::Width,                          // 0x19
             "height" => PropertyId::Height,                        // 0x1A
             "cursor" => PropertyId::Cursor,                        // 0x29
@@ -306,9 +337,233 @@ impl PropertyId {
         }
     }
     
+    /// Decode a property ID byte as written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(PropertyId::Invalid),
+            0x01 => Some(PropertyId::BackgroundColor),
+            0x02 => Some(PropertyId::ForegroundColor),
+            0x03 => Some(PropertyId::BorderColor),
+            0x04 => Some(PropertyId::BorderWidth),
+            0x05 => Some(PropertyId::BorderRadius),
+            0x06 => Some(PropertyId::Padding),
+            0x07 => Some(PropertyId::Margin),
+            0x08 => Some(PropertyId::TextContent),
+            0x09 => Some(PropertyId::FontSize),
+            0x0A => Some(PropertyId::FontWeight),
+            0x0B => Some(PropertyId::TextAlignment),
+            0x0C => Some(PropertyId::FontFamily),
+            0x0D => Some(PropertyId::ImageSource),
+            0x1E => Some(PropertyId::ListStyleType),
+            0x1F => Some(PropertyId::WhiteSpace),
+            0x0E => Some(PropertyId::Opacity),
+            0x0F => Some(PropertyId::ZIndex),
+            0x10 => Some(PropertyId::Visibility),
+            0x11 => Some(PropertyId::Gap),
+            0x12 => Some(PropertyId::MinWidth),
+            0x13 => Some(PropertyId::MinHeight),
+            0x14 => Some(PropertyId::MaxWidth),
+            0x15 => Some(PropertyId::MaxHeight),
+            0x16 => Some(PropertyId::AspectRatio),
+            0x17 => Some(PropertyId::Transform),
+            0x18 => Some(PropertyId::Shadow),
+            0x19 => Some(PropertyId::Width),
+            0x1A => Some(PropertyId::Height),
+            0x1D => Some(PropertyId::CustomData),
+            0x20 => Some(PropertyId::WindowWidth),
+            0x21 => Some(PropertyId::WindowHeight),
+            0x22 => Some(PropertyId::WindowTitle),
+            0x23 => Some(PropertyId::Resizable),
+            0x24 => Some(PropertyId::KeepAspect),
+            0x25 => Some(PropertyId::ScaleFactor),
+            0x26 => Some(PropertyId::Icon),
+            0x27 => Some(PropertyId::Version),
+            0x28 => Some(PropertyId::Author),
+            0x29 => Some(PropertyId::Cursor),
+            0x2A => Some(PropertyId::Checked),
+            0x2B => Some(PropertyId::InputType),
+            0x60 => Some(PropertyId::GridTemplateColumns),
+            0x61 => Some(PropertyId::GridTemplateRows),
+            0x62 => Some(PropertyId::GridTemplateAreas),
+            0x63 => Some(PropertyId::GridAutoColumns),
+            0x64 => Some(PropertyId::GridAutoRows),
+            0x65 => Some(PropertyId::GridAutoFlow),
+            0x66 => Some(PropertyId::GridArea),
+            0x67 => Some(PropertyId::GridColumn),
+            0x68 => Some(PropertyId::GridRow),
+            0x69 => Some(PropertyId::GridColumnStart),
+            0x6A => Some(PropertyId::GridColumnEnd),
+            0x6B => Some(PropertyId::GridRowStart),
+            0x6C => Some(PropertyId::GridRowEnd),
+            0x6D => Some(PropertyId::GridGap),
+            0x6E => Some(PropertyId::GridColumnGap),
+            0x6F => Some(PropertyId::GridRowGap),
+            0x71 => Some(PropertyId::PaddingTop),
+            0x72 => Some(PropertyId::PaddingRight),
+            0x73 => Some(PropertyId::PaddingBottom),
+            0x74 => Some(PropertyId::PaddingLeft),
+            0x76 => Some(PropertyId::MarginTop),
+            0x77 => Some(PropertyId::MarginRight),
+            0x78 => Some(PropertyId::MarginBottom),
+            0x79 => Some(PropertyId::MarginLeft),
+            0x7A => Some(PropertyId::BorderTopWidth),
+            0x7B => Some(PropertyId::BorderRightWidth),
+            0x7C => Some(PropertyId::BorderBottomWidth),
+            0x7D => Some(PropertyId::BorderLeftWidth),
+            0x7E => Some(PropertyId::BorderTopColor),
+            0x7F => Some(PropertyId::BorderRightColor),
+            0x80 => Some(PropertyId::BorderBottomColor),
+            0x81 => Some(PropertyId::BorderLeftColor),
+            0x82 => Some(PropertyId::BorderTopLeftRadius),
+            0x83 => Some(PropertyId::BorderTopRightRadius),
+            0x84 => Some(PropertyId::BorderBottomRightRadius),
+            0x85 => Some(PropertyId::BorderBottomLeftRadius),
+            0x86 => Some(PropertyId::BoxSizing),
+            0x87 => Some(PropertyId::Outline),
+            0x88 => Some(PropertyId::OutlineColor),
+            0x89 => Some(PropertyId::OutlineWidth),
+            0x8A => Some(PropertyId::OutlineOffset),
+            0x8B => Some(PropertyId::Overflow),
+            0x8C => Some(PropertyId::OverflowX),
+            0x8D => Some(PropertyId::OverflowY),
+            0x8E => Some(PropertyId::LineHeight),
+            0x8F => Some(PropertyId::LetterSpacing),
+            0x93 => Some(PropertyId::TextDecoration),
+            0x94 => Some(PropertyId::TextTransform),
+            0x95 => Some(PropertyId::TextIndent),
+            0x96 => Some(PropertyId::TextOverflow),
+            0x97 => Some(PropertyId::FontStyle),
+            0x98 => Some(PropertyId::FontVariant),
+            0x99 => Some(PropertyId::WordSpacing),
+            0x9A => Some(PropertyId::BoxShadow),
+            0x9B => Some(PropertyId::TextShadow),
+            0x9C => Some(PropertyId::Filter),
+            0x9D => Some(PropertyId::BackdropFilter),
+            0x9E => Some(PropertyId::MinViewportWidth),
+            0x9F => Some(PropertyId::MaxViewportWidth),
+            0xA0 => Some(PropertyId::Spans),
+            0xA1 => Some(PropertyId::BackgroundImage),
+            0x40 => Some(PropertyId::Display),
+            0x41 => Some(PropertyId::FlexDirection),
+            0x42 => Some(PropertyId::FlexWrap),
+            0x43 => Some(PropertyId::FlexGrow),
+            0x44 => Some(PropertyId::FlexShrink),
+            0x45 => Some(PropertyId::FlexBasis),
+            0x46 => Some(PropertyId::AlignItems),
+            0x47 => Some(PropertyId::AlignSelf),
+            0x48 => Some(PropertyId::AlignContent),
+            0x49 => Some(PropertyId::JustifyContent),
+            0x4A => Some(PropertyId::JustifyItems),
+            0x4B => Some(PropertyId::JustifySelf),
+            0x4C => Some(PropertyId::Order),
+            0x50 => Some(PropertyId::Position),
+            0x51 => Some(PropertyId::Left),
+            0x52 => Some(PropertyId::Top),
+            0x53 => Some(PropertyId::Right),
+            0x54 => Some(PropertyId::Bottom),
+            0x55 => Some(PropertyId::Inset),
+            0x90 => Some(PropertyId::MinSize),
+            0x91 => Some(PropertyId::MaxSize),
+            0x92 => Some(PropertyId::PreferredSize),
+            _ => None,
+        }
+    }
+
     /// Check if this property should be handled ONLY in the element header and never as a style property
     /// These are truly element-specific properties that don't make sense as inheritable styles
     pub fn is_element_header_property(key: &str) -> bool {
         matches!(key, "id" | "checked")
     }
+
+    /// Properties a renderer can interpolate between keyframes
+    pub fn is_animatable(self) -> bool {
+        matches!(
+            self,
+            Self::BackgroundColor
+                | Self::ForegroundColor
+                | Self::BorderColor
+                | Self::BorderWidth
+                | Self::BorderRadius
+                | Self::Padding
+                | Self::Margin
+                | Self::FontSize
+                | Self::Opacity
+                | Self::Gap
+                | Self::Width
+                | Self::Height
+                | Self::MinWidth
+                | Self::MinHeight
+                | Self::MaxWidth
+                | Self::MaxHeight
+                | Self::Left
+                | Self::Top
+                | Self::Right
+                | Self::Bottom
+                | Self::LetterSpacing
+                | Self::LineHeight
+        )
+    }
+
+    /// Properties measured in lengths, which may be a Calc value left for the renderer
+    pub fn is_length(self) -> bool {
+        matches!(
+            self,
+            Self::BorderWidth
+                | Self::BorderRadius
+                | Self::Padding
+                | Self::Margin
+                | Self::FontSize
+                | Self::Gap
+                | Self::Width
+                | Self::Height
+                | Self::MinWidth
+                | Self::MinHeight
+                | Self::MaxWidth
+                | Self::MaxHeight
+                | Self::Left
+                | Self::Top
+                | Self::Right
+                | Self::Bottom
+                | Self::LetterSpacing
+                | Self::PaddingTop
+                | Self::PaddingRight
+                | Self::PaddingBottom
+                | Self::PaddingLeft
+                | Self::MarginTop
+                | Self::MarginRight
+                | Self::MarginBottom
+                | Self::MarginLeft
+                | Self::BorderTopWidth
+                | Self::BorderRightWidth
+                | Self::BorderBottomWidth
+                | Self::BorderLeftWidth
+                | Self::BorderTopLeftRadius
+                | Self::BorderTopRightRadius
+                | Self::BorderBottomRightRadius
+                | Self::BorderBottomLeftRadius
+                | Self::GridColumnGap
+                | Self::GridRowGap
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_u8_roundtrip() {
+        for name in PropertyId::KNOWN_NAMES {
+            let id = PropertyId::from_name(name);
+            assert_eq!(PropertyId::from_u8(id as u8), Some(id));
+        }
+        assert_eq!(PropertyId::from_u8(0xFF), None);
+    }
+
+    #[test]
+    fn test_known_names_resolve() {
+        for name in PropertyId::KNOWN_NAMES {
+            assert_ne!(PropertyId::from_name(name), PropertyId::CustomData, "{}", name);
+        }
+    }
 }
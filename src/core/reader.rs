@@ -0,0 +1,814 @@
+// FILE: src/core/reader.rs
+
+//! Decoding of compiled KRB files
+//!
+//! `KrbReader` decodes every section `CodeGenerator` writes into plain typed
+//! records. Fields keep the raw on-disk values (indices, type bytes) so tools
+//! can inspect even inconsistent files, and every record remembers the byte
+//! offset it was decoded from.
+
+use crate::core::constants::*;
+use crate::core::types::{ResourceFormat, ResourceType};
+use crate::core::debug_info::{DebugLocation, KrbDebugMap};
+use crate::core::index_width::IndexWidth;
+use crate::core::{decode_krb_file, KrbFileInfo};
+use crate::error::{CompilerError, Result};
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// Script code follows the entry point list only for inline scripts
+pub(crate) const SCRIPT_STORAGE_INLINE: u8 = 0;
+
+/// Deepest element nesting the reader accepts before treating the file as corrupt
+const MAX_ELEMENT_DEPTH: usize = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbStringRecord {
+    pub offset: u32,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbPropertyRecord {
+    pub offset: u32,
+    pub property_id: u8,
+    pub value_type: u8,
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbCustomPropertyRecord {
+    pub offset: u32,
+    pub key_index: u16,
+    pub value_type: u8,
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbStateSetRecord {
+    pub offset: u32,
+    pub state_flags: u8,
+    pub properties: Vec<KrbPropertyRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbEventRecord {
+    pub offset: u32,
+    pub event_type: u8,
+    pub callback_id: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbAnimationRefRecord {
+    pub offset: u32,
+    pub kind: u8,
+    /// Animation table index for keyframes, property ID for transitions
+    pub target: u16,
+    pub duration_ms: u16,
+    pub delay_ms: u16,
+    pub easing: u8,
+    pub iterations: u8,
+    pub direction: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbElementRecord {
+    pub offset: u32,
+    pub element_type: u8,
+    pub id_string_index: u16,
+    pub pos_x: u16,
+    pub pos_y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub layout: u8,
+    pub style_id: u16,
+    pub checked: bool,
+    pub animation_count: u16,
+    pub properties: Vec<KrbPropertyRecord>,
+    pub custom_properties: Vec<KrbCustomPropertyRecord>,
+    pub state_property_sets: Vec<KrbStateSetRecord>,
+    pub events: Vec<KrbEventRecord>,
+    pub animations: Vec<KrbAnimationRefRecord>,
+    /// Raw child offset table as written after the animation references
+    pub child_offsets: Vec<u16>,
+    /// Indices of the children within the same element list
+    pub children: Vec<usize>,
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbStyleRecord {
+    pub offset: u32,
+    pub id: u16,
+    pub name_index: u16,
+    pub properties: Vec<KrbPropertyRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbComponentPropertyRecord {
+    pub offset: u32,
+    pub name_index: u16,
+    pub value_type_hint: u8,
+    pub default_value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbComponentRecord {
+    pub offset: u32,
+    pub name_index: u16,
+    pub properties: Vec<KrbComponentPropertyRecord>,
+    /// Template element tree in pre-order, root first; empty when none was written
+    pub template: Vec<KrbElementRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbKeyframeRecord {
+    pub offset: u32,
+    /// Position in the animation in percent
+    pub position: u8,
+    pub properties: Vec<KrbPropertyRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbAnimationRecord {
+    pub offset: u32,
+    pub name_index: u16,
+    pub keyframes: Vec<KrbKeyframeRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbScriptRecord {
+    pub offset: u32,
+    pub language_id: u8,
+    pub name_index: u16,
+    pub storage_format: u8,
+    pub data_size: u16,
+    pub entry_points: Vec<u16>,
+    pub code: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbResourceRecord {
+    pub offset: u32,
+    pub resource_type: u8,
+    pub name_index: u16,
+    pub format: u8,
+    pub data_string_index: u16,
+    /// Face descriptors, present for font resources
+    pub font: Option<KrbFontFaceRecord>,
+    /// File contents of an inline resource
+    pub data: Vec<u8>,
+    /// For a shared resource, the resource whose inline data it uses
+    pub shared_with: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbFontFaceRecord {
+    pub weight: u16,
+    pub style: u8,
+    /// String indices of the families to try when a glyph is missing
+    pub fallbacks: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbTemplateVariableRecord {
+    pub offset: u32,
+    pub name_index: u16,
+    pub value_type: u8,
+    pub default_value_index: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbTemplateBindingRecord {
+    pub offset: u32,
+    pub element_index: u16,
+    pub property_id: u8,
+    pub expression_index: u16,
+    pub variable_indices: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbTransformPropertyRecord {
+    pub offset: u32,
+    pub property_type: u8,
+    pub value_type: u8,
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbTransformRecord {
+    pub offset: u32,
+    pub transform_type: u8,
+    pub properties: Vec<KrbTransformPropertyRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbThemeSlotRecord {
+    pub offset: u32,
+    pub name_index: u16,
+    pub value_type: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbThemeRecord {
+    pub offset: u32,
+    pub name_index: u16,
+    /// One value per slot, typed by the slot
+    pub values: Vec<Vec<u8>>,
+}
+
+/// Theme table: the slots properties of type `ThemeToken` refer to, and each theme's values for them
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct KrbThemeTable {
+    pub slots: Vec<KrbThemeSlotRecord>,
+    pub themes: Vec<KrbThemeRecord>,
+}
+
+/// A fully decoded KRB file
+#[derive(Debug, Clone, Serialize)]
+pub struct KrbFile {
+    pub header: KrbFileInfo,
+    pub strings: Vec<KrbStringRecord>,
+    /// Main element tree in pre-order, root first
+    pub elements: Vec<KrbElementRecord>,
+    pub styles: Vec<KrbStyleRecord>,
+    pub components: Vec<KrbComponentRecord>,
+    pub animations: Vec<KrbAnimationRecord>,
+    pub scripts: Vec<KrbScriptRecord>,
+    pub resources: Vec<KrbResourceRecord>,
+    pub template_variables: Vec<KrbTemplateVariableRecord>,
+    pub template_bindings: Vec<KrbTemplateBindingRecord>,
+    pub transforms: Vec<KrbTransformRecord>,
+    pub theme_table: KrbThemeTable,
+    /// Source map attached with `KrbReader::with_debug_map`
+    #[serde(skip)]
+    pub debug_map: Option<KrbDebugMap>,
+}
+
+impl KrbFile {
+    /// Look up a string table entry by index
+    pub fn string(&self, index: u16) -> Option<&str> {
+        self.strings.get(index as usize).map(|s| s.text.as_str())
+    }
+
+    /// Find a style by its style ID
+    pub fn style(&self, id: u16) -> Option<&KrbStyleRecord> {
+        self.styles.iter().find(|s| s.id == id)
+    }
+
+    /// Where the element at `index` of `elements` was written in the source
+    pub fn element_location(&self, index: u16) -> Option<&DebugLocation> {
+        self.debug_map.as_ref()?.element(index)?.location.as_ref()
+    }
+
+    pub fn style_location(&self, id: u16) -> Option<&DebugLocation> {
+        self.debug_map.as_ref()?.style(id)?.location.as_ref()
+    }
+
+    pub fn binding_location(&self, index: u16) -> Option<&DebugLocation> {
+        self.debug_map.as_ref()?.binding(index)?.location.as_ref()
+    }
+
+    /// Where `function` of the script at `script` is defined
+    pub fn entry_point_location(&self, script: u16, function: &str) -> Option<&DebugLocation> {
+        self.debug_map.as_ref()?.entry_point(script, function)?.location.as_ref()
+    }
+}
+
+/// Bounds-checked little-endian cursor over one section of the file
+struct SectionCursor<'a> {
+    data: &'a [u8],
+    position: usize,
+    end: usize,
+    section: &'static str,
+    width: IndexWidth,
+}
+
+impl<'a> SectionCursor<'a> {
+    fn offset(&self) -> u32 {
+        self.position as u32
+    }
+
+    fn take(&mut self, length: usize, what: &str) -> Result<&'a [u8]> {
+        if self.position + length > self.end {
+            return Err(CompilerError::InvalidFormat {
+                message: format!(
+                    "Unexpected end of {} section reading {} at offset {} ({} bytes needed, {} available)",
+                    self.section, what, self.position, length, self.end.saturating_sub(self.position)
+                ),
+            });
+        }
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self, what: &str) -> Result<u8> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn read_u16(&mut self, what: &str) -> Result<u16> {
+        let bytes = self.take(2, what)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self, what: &str) -> Result<u32> {
+        let bytes = self.take(4, what)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a table index or count, one or two bytes depending on the file
+    fn read_index(&mut self, what: &str) -> Result<u16> {
+        match self.width {
+            IndexWidth::Compact => self.read_u8(what).map(u16::from),
+            IndexWidth::Wide => self.read_u16(what),
+        }
+    }
+
+    fn read_indices(&mut self, count: u16, what: &str) -> Result<Vec<u16>> {
+        (0..count).map(|_| self.read_index(what)).collect()
+    }
+}
+
+pub struct KrbReader<'a> {
+    /// Uncompressed image; compressed files are inflated up front
+    data: Cow<'a, [u8]>,
+    header: KrbFileInfo,
+    debug_map: Option<KrbDebugMap>,
+}
+
+impl<'a> KrbReader<'a> {
+    /// Validate the header, decompressing the file if needed, and prepare to decode the sections
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let (data, header) = decode_krb_file(data)?;
+        Ok(Self { data, header, debug_map: None })
+    }
+
+    /// Attach the source map written alongside the file, see `KrbDebugMap::load`
+    pub fn with_debug_map(mut self, debug_map: Option<KrbDebugMap>) -> Self {
+        self.debug_map = debug_map;
+        self
+    }
+
+    pub fn header(&self) -> &KrbFileInfo {
+        &self.header
+    }
+
+    /// Decode every section
+    pub fn read(&self) -> Result<KrbFile> {
+        Ok(KrbFile {
+            header: self.header.clone(),
+            strings: self.read_strings()?,
+            elements: self.read_elements()?,
+            styles: self.read_styles()?,
+            components: self.read_components()?,
+            animations: self.read_animations()?,
+            scripts: self.read_scripts()?,
+            resources: self.read_resources()?,
+            template_variables: self.read_template_variables()?,
+            template_bindings: self.read_template_bindings()?,
+            transforms: self.read_transforms()?,
+            theme_table: self.read_theme_table()?,
+            debug_map: self.debug_map.clone(),
+        })
+    }
+
+    /// Section boundaries follow from the header: each section ends where the next one starts
+    fn cursor(&self, start: u32, section: &'static str) -> Result<SectionCursor<'_>> {
+        let start = start as usize;
+        if start > self.data.len() || start < KRB_HEADER_SIZE {
+            return Err(CompilerError::InvalidFormat {
+                message: format!("{} section offset {} is outside the file ({} bytes)", section, start, self.data.len()),
+            });
+        }
+        let h = &self.header;
+        let end = [
+            h.string_offset, h.element_offset, h.style_offset, h.component_offset, h.animation_offset,
+            h.script_offset, h.resource_offset, h.template_variable_offset, h.template_binding_offset,
+            h.transform_offset, h.theme_offset,
+        ]
+        .iter()
+        .map(|&o| o as usize)
+        .filter(|&o| o > start && o <= self.data.len())
+        .min()
+        .unwrap_or(self.data.len());
+        let width = IndexWidth::from_flags(h.flags);
+        Ok(SectionCursor { data: &self.data, position: start, end, section, width })
+    }
+
+    pub fn read_strings(&self) -> Result<Vec<KrbStringRecord>> {
+        if self.header.string_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.string_offset, "string")?;
+        let mut strings = Vec::with_capacity(self.header.string_count as usize);
+        for _ in 0..self.header.string_count {
+            let offset = cursor.offset();
+            let length = cursor.read_index("string length")? as usize;
+            let bytes = cursor.take(length, "string data")?;
+            let text = String::from_utf8(bytes.to_vec()).map_err(|_| CompilerError::InvalidFormat {
+                message: format!("String at offset {} is not valid UTF-8", offset),
+            })?;
+            strings.push(KrbStringRecord { offset, text });
+        }
+        Ok(strings)
+    }
+
+    pub fn read_elements(&self) -> Result<Vec<KrbElementRecord>> {
+        Ok(self.read_element_section()?.0)
+    }
+
+    /// Decode the main element tree, also returning where the tree ends and
+    /// where the element section ends so callers can spot unreachable bytes
+    pub(crate) fn read_element_section(&self) -> Result<(Vec<KrbElementRecord>, u32, u32)> {
+        let mut elements = Vec::new();
+        if self.header.element_count == 0 {
+            return Ok((elements, self.header.element_offset, self.header.element_offset));
+        }
+        let mut cursor = self.cursor(self.header.element_offset, "element")?;
+        read_element_tree(&mut cursor, &mut elements, None, 0)?;
+        Ok((elements, cursor.offset(), cursor.end as u32))
+    }
+
+    pub fn read_styles(&self) -> Result<Vec<KrbStyleRecord>> {
+        if self.header.style_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.style_offset, "style")?;
+        let mut styles = Vec::with_capacity(self.header.style_count as usize);
+        for _ in 0..self.header.style_count {
+            let offset = cursor.offset();
+            let id = cursor.read_index("style id")?;
+            let name_index = cursor.read_index("style name index")?;
+            let count = cursor.read_index("style property count")?;
+            let properties = read_properties(&mut cursor, count)?;
+            styles.push(KrbStyleRecord { offset, id, name_index, properties });
+        }
+        Ok(styles)
+    }
+
+    /// The component format has no template-present flag, so a template element is
+    /// only recognised as the bytes left in the section after the last component.
+    pub fn read_components(&self) -> Result<Vec<KrbComponentRecord>> {
+        if self.header.component_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.component_offset, "component")?;
+        let mut components = Vec::with_capacity(self.header.component_count as usize);
+        for i in 0..self.header.component_count {
+            let offset = cursor.offset();
+            let name_index = cursor.read_index("component name index")?;
+            let count = cursor.read_index("component property count")?;
+            let mut properties = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let prop_offset = cursor.offset();
+                let prop_name_index = cursor.read_index("component property name index")?;
+                let value_type_hint = cursor.read_u8("component property type")?;
+                let length = cursor.read_index("component default length")? as usize;
+                let default_value = cursor.take(length, "component default value")?.to_vec();
+                properties.push(KrbComponentPropertyRecord {
+                    offset: prop_offset,
+                    name_index: prop_name_index,
+                    value_type_hint,
+                    default_value,
+                });
+            }
+
+            let mut template = Vec::new();
+            if i + 1 == self.header.component_count && cursor.position < cursor.end {
+                read_element_tree(&mut cursor, &mut template, None, 0)?;
+            }
+            components.push(KrbComponentRecord { offset, name_index, properties, template });
+        }
+        Ok(components)
+    }
+
+    pub fn read_animations(&self) -> Result<Vec<KrbAnimationRecord>> {
+        if self.header.animation_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.animation_offset, "animation")?;
+        let mut animations = Vec::with_capacity(self.header.animation_count as usize);
+        for _ in 0..self.header.animation_count {
+            let offset = cursor.offset();
+            let name_index = cursor.read_index("animation name index")?;
+            let count = cursor.read_index("keyframe count")?;
+            let mut keyframes = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let keyframe_offset = cursor.offset();
+                let position = cursor.read_u8("keyframe offset")?;
+                let property_count = cursor.read_index("keyframe property count")?;
+                let properties = read_properties(&mut cursor, property_count)?;
+                keyframes.push(KrbKeyframeRecord { offset: keyframe_offset, position, properties });
+            }
+            animations.push(KrbAnimationRecord { offset, name_index, keyframes });
+        }
+        Ok(animations)
+    }
+
+    pub fn read_scripts(&self) -> Result<Vec<KrbScriptRecord>> {
+        if self.header.script_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.script_offset, "script")?;
+        let mut scripts = Vec::with_capacity(self.header.script_count as usize);
+        for _ in 0..self.header.script_count {
+            let offset = cursor.offset();
+            let language_id = cursor.read_u8("script language")?;
+            let name_index = cursor.read_index("script name index")?;
+            let storage_format = cursor.read_u8("script storage format")?;
+            let entry_point_count = cursor.read_index("script entry point count")?;
+            let data_size = cursor.read_u16("script data size")?;
+            let entry_points = cursor.read_indices(entry_point_count, "script entry point")?;
+            let code = if storage_format == SCRIPT_STORAGE_INLINE {
+                cursor.take(data_size as usize, "script code")?.to_vec()
+            } else {
+                Vec::new()
+            };
+            scripts.push(KrbScriptRecord {
+                offset,
+                language_id,
+                name_index,
+                storage_format,
+                data_size,
+                entry_points,
+                code,
+            });
+        }
+        Ok(scripts)
+    }
+
+    pub fn read_resources(&self) -> Result<Vec<KrbResourceRecord>> {
+        if self.header.resource_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.resource_offset, "resource")?;
+        let mut resources = Vec::with_capacity(self.header.resource_count as usize);
+        for _ in 0..self.header.resource_count {
+            let offset = cursor.offset();
+            let resource_type = cursor.read_u8("resource type")?;
+            let name_index = cursor.read_index("resource name index")?;
+            let format = cursor.read_u8("resource format")?;
+            let data_string_index = cursor.read_index("resource data index")?;
+
+            let font = if resource_type == ResourceType::Font as u8 {
+                let weight = cursor.read_u16("font weight")?;
+                let style = cursor.read_u8("font style")?;
+                let fallback_count = cursor.read_index("font fallback count")?;
+                let fallbacks = cursor.read_indices(fallback_count, "font fallback")?;
+                Some(KrbFontFaceRecord { weight, style, fallbacks })
+            } else {
+                None
+            };
+            let mut data = Vec::new();
+            let mut shared_with = None;
+            if format == ResourceFormat::Inline as u8 {
+                let size = cursor.read_u32("inline resource size")?;
+                data = cursor.take(size as usize, "inline resource data")?.to_vec();
+            } else if format == ResourceFormat::Shared as u8 {
+                shared_with = Some(cursor.read_index("shared resource index")?);
+            }
+
+            resources.push(KrbResourceRecord {
+                offset,
+                resource_type,
+                name_index,
+                format,
+                data_string_index,
+                font,
+                data,
+                shared_with,
+            });
+        }
+        Ok(resources)
+    }
+
+    pub fn read_template_variables(&self) -> Result<Vec<KrbTemplateVariableRecord>> {
+        if self.header.template_variable_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.template_variable_offset, "template variable")?;
+        let mut variables = Vec::with_capacity(self.header.template_variable_count as usize);
+        for _ in 0..self.header.template_variable_count {
+            let offset = cursor.offset();
+            variables.push(KrbTemplateVariableRecord {
+                offset,
+                name_index: cursor.read_index("template variable name index")?,
+                value_type: cursor.read_u8("template variable type")?,
+                default_value_index: cursor.read_index("template variable default")?,
+            });
+        }
+        Ok(variables)
+    }
+
+    pub fn read_template_bindings(&self) -> Result<Vec<KrbTemplateBindingRecord>> {
+        if self.header.template_binding_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.template_binding_offset, "template binding")?;
+        let mut bindings = Vec::with_capacity(self.header.template_binding_count as usize);
+        for _ in 0..self.header.template_binding_count {
+            let offset = cursor.offset();
+            let element_index = cursor.read_u16("binding element index")?;
+            let property_id = cursor.read_u8("binding property id")?;
+            let expression_index = cursor.read_index("binding expression index")?;
+            let variable_count = cursor.read_index("binding variable count")?;
+            let variable_indices = cursor.read_indices(variable_count, "binding variable index")?;
+            bindings.push(KrbTemplateBindingRecord {
+                offset,
+                element_index,
+                property_id,
+                expression_index,
+                variable_indices,
+            });
+        }
+        Ok(bindings)
+    }
+
+    pub fn read_transforms(&self) -> Result<Vec<KrbTransformRecord>> {
+        if self.header.transform_count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut cursor = self.cursor(self.header.transform_offset, "transform")?;
+        let mut transforms = Vec::with_capacity(self.header.transform_count as usize);
+        for _ in 0..self.header.transform_count {
+            let offset = cursor.offset();
+            let transform_type = cursor.read_u8("transform type")?;
+            let count = cursor.read_index("transform property count")?;
+            let mut properties = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let prop_offset = cursor.offset();
+                let property_type = cursor.read_u8("transform property type")?;
+                let value_type = cursor.read_u8("transform value type")?;
+                let length = cursor.read_u8("transform value size")? as usize;
+                let value = cursor.take(length, "transform value")?.to_vec();
+                properties.push(KrbTransformPropertyRecord { offset: prop_offset, property_type, value_type, value });
+            }
+            transforms.push(KrbTransformRecord { offset, transform_type, properties });
+        }
+        Ok(transforms)
+    }
+
+    pub fn read_theme_table(&self) -> Result<KrbThemeTable> {
+        if self.header.theme_count == 0 {
+            return Ok(KrbThemeTable::default());
+        }
+        let mut cursor = self.cursor(self.header.theme_offset, "theme")?;
+        let slot_count = cursor.read_index("theme slot count")?;
+        let mut slots = Vec::with_capacity(slot_count as usize);
+        for _ in 0..slot_count {
+            let offset = cursor.offset();
+            slots.push(KrbThemeSlotRecord {
+                offset,
+                name_index: cursor.read_index("theme slot name index")?,
+                value_type: cursor.read_u8("theme slot value type")?,
+            });
+        }
+        let mut themes = Vec::with_capacity(self.header.theme_count as usize);
+        for _ in 0..self.header.theme_count {
+            let offset = cursor.offset();
+            let name_index = cursor.read_index("theme name index")?;
+            let mut values = Vec::with_capacity(slots.len());
+            for _ in &slots {
+                let length = cursor.read_u8("theme value size")? as usize;
+                values.push(cursor.take(length, "theme value")?.to_vec());
+            }
+            themes.push(KrbThemeRecord { offset, name_index, values });
+        }
+        Ok(KrbThemeTable { slots, themes })
+    }
+}
+
+fn read_properties(cursor: &mut SectionCursor, count: u16) -> Result<Vec<KrbPropertyRecord>> {
+    let mut properties = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let offset = cursor.offset();
+        let property_id = cursor.read_u8("property id")?;
+        let value_type = cursor.read_u8("property value type")?;
+        let size = cursor.read_u8("property size")? as usize;
+        let value = cursor.take(size, "property value")?.to_vec();
+        properties.push(KrbPropertyRecord { offset, property_id, value_type, value });
+    }
+    Ok(properties)
+}
+
+/// Decode an element and its subtree in pre-order, returning the element's index
+fn read_element_tree(
+    cursor: &mut SectionCursor,
+    elements: &mut Vec<KrbElementRecord>,
+    parent: Option<usize>,
+    depth: usize,
+) -> Result<usize> {
+    if depth > MAX_ELEMENT_DEPTH {
+        return Err(CompilerError::InvalidFormat {
+            message: format!("Element nesting deeper than {} at offset {}", MAX_ELEMENT_DEPTH, cursor.position),
+        });
+    }
+
+    let offset = cursor.offset();
+    // Check the whole header is present so truncation is reported once, then decode it field by field
+    cursor.take(cursor.width.element_header_size(), "element header")?;
+    cursor.position = offset as usize;
+    let element_type = cursor.read_u8("element type")?;
+    let id_string_index = cursor.read_index("element id")?;
+    let (pos_x, pos_y) = (cursor.read_u16("element x")?, cursor.read_u16("element y")?);
+    let (width, height) = (cursor.read_u16("element width")?, cursor.read_u16("element height")?);
+    let layout = cursor.read_u8("element layout")?;
+    let style_id = cursor.read_index("element style")?;
+    let checked = cursor.read_u8("element checked")? != 0;
+    let property_count = cursor.read_index("element property count")?;
+    let child_count = cursor.read_index("element child count")?;
+    let event_count = cursor.read_index("element event count")?;
+    let animation_count = cursor.read_index("element animation count")?;
+    let custom_prop_count = cursor.read_index("element custom property count")?;
+    let state_prop_count = cursor.read_index("element state set count")?;
+
+    let mut element = KrbElementRecord {
+        offset,
+        element_type,
+        id_string_index,
+        pos_x,
+        pos_y,
+        width,
+        height,
+        layout,
+        style_id,
+        checked,
+        animation_count,
+        properties: read_properties(cursor, property_count)?,
+        custom_properties: Vec::new(),
+        state_property_sets: Vec::new(),
+        events: Vec::new(),
+        animations: Vec::new(),
+        child_offsets: Vec::new(),
+        children: Vec::new(),
+        parent,
+    };
+
+    for _ in 0..custom_prop_count {
+        let prop_offset = cursor.offset();
+        let key_index = cursor.read_index("custom property key")?;
+        let value_type = cursor.read_u8("custom property value type")?;
+        let size = cursor.read_u8("custom property size")? as usize;
+        let value = cursor.take(size, "custom property value")?.to_vec();
+        element.custom_properties.push(KrbCustomPropertyRecord { offset: prop_offset, key_index, value_type, value });
+    }
+
+    for _ in 0..state_prop_count {
+        let set_offset = cursor.offset();
+        let state_flags = cursor.read_u8("state flags")?;
+        let count = cursor.read_index("state property count")?;
+        let properties = read_properties(cursor, count)?;
+        element.state_property_sets.push(KrbStateSetRecord { offset: set_offset, state_flags, properties });
+    }
+
+    for _ in 0..event_count {
+        let event_offset = cursor.offset();
+        let event_type = cursor.read_u8("event type")?;
+        let callback_id = cursor.read_index("event callback")?;
+        element.events.push(KrbEventRecord { offset: event_offset, event_type, callback_id });
+    }
+
+    for _ in 0..animation_count {
+        let animation_offset = cursor.offset();
+        element.animations.push(KrbAnimationRefRecord {
+            offset: animation_offset,
+            kind: cursor.read_u8("animation kind")?,
+            target: cursor.read_index("animation target")?,
+            duration_ms: cursor.read_u16("animation duration")?,
+            delay_ms: cursor.read_u16("animation delay")?,
+            easing: cursor.read_u8("animation easing")?,
+            iterations: cursor.read_u8("animation iterations")?,
+            direction: cursor.read_u8("animation direction")?,
+        });
+    }
+
+    for _ in 0..child_count {
+        element.child_offsets.push(cursor.read_u16("child offset")?);
+    }
+
+    let index = elements.len();
+    elements.push(element);
+    for _ in 0..child_count {
+        let child = read_element_tree(cursor, elements, Some(index), depth + 1)?;
+        elements[index].children.push(child);
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncated_section_is_reported() {
+        let mut data = vec![0u8; KRB_HEADER_SIZE];
+        data[..4].copy_from_slice(KRB_MAGIC);
+        data[18..20].copy_from_slice(&1u16.to_le_bytes()); // one string
+        data[48..52].copy_from_slice(&(KRB_HEADER_SIZE as u32).to_le_bytes()); // string offset
+        data[68..72].copy_from_slice(&(KRB_HEADER_SIZE as u32).to_le_bytes()); // total size
+
+        let error = KrbReader::new(&data).unwrap().read_strings().unwrap_err();
+        assert!(error.to_string().contains("offset 72"));
+    }
+}
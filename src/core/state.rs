@@ -1,12 +1,14 @@
 // FILE: src/core/state.rs
 
-use crate::compiler::frontend::ast::AstNode;
+use crate::compiler::frontend::ast::{AstNode, AstProperty, ComponentOrigin};
 use crate::compiler::frontend::ast::PropertyValue;
 use crate::compiler::middle_end::variable_context::VariableContext;
 use crate::core::constants::*;
+use crate::core::index_width::decode_index;
 use crate::core::types::*;
+use crate::diagnostics::Span;
 use crate::error::CompilerError;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 // Transform data structures
 #[derive(Debug, Clone)]
@@ -23,6 +25,19 @@ pub enum TransformType {
     Matrix2D = 0x03,
     Matrix3D = 0x04,
 }
+impl TransformType {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Transform2D),
+            0x02 => Some(Self::Transform3D),
+            0x03 => Some(Self::Matrix2D),
+            0x04 => Some(Self::Matrix3D),
+            _ => None,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone)]
 pub struct TransformProperty {
@@ -52,6 +67,28 @@ pub enum TransformPropertyType {
 }
 
 impl TransformPropertyType {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Scale),
+            0x02 => Some(Self::ScaleX),
+            0x03 => Some(Self::ScaleY),
+            0x04 => Some(Self::TranslateX),
+            0x05 => Some(Self::TranslateY),
+            0x06 => Some(Self::Rotate),
+            0x07 => Some(Self::SkewX),
+            0x08 => Some(Self::SkewY),
+            0x09 => Some(Self::ScaleZ),
+            0x0A => Some(Self::TranslateZ),
+            0x0B => Some(Self::RotateX),
+            0x0C => Some(Self::RotateY),
+            0x0D => Some(Self::RotateZ),
+            0x0E => Some(Self::Perspective),
+            0x0F => Some(Self::Matrix),
+            _ => None,
+        }
+    }
+
     pub fn from_name(name: &str) -> Option<Self> {
         match name {
             "scale" => Some(Self::Scale),
@@ -95,7 +132,67 @@ pub enum CSSUnit {
     Number = 0x0A,
 }
 
+impl CSSUnitValue {
+    /// Parse a number with an optional unit suffix, such as `12px`, `45deg` or `1.5`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let split = text.find(|c: char| c.is_ascii_alphabetic() || c == '%').unwrap_or(text.len());
+        let (number, suffix) = text.split_at(split);
+        let unit = CSSUnit::from_suffix(suffix)?;
+        Some(Self { value: number.parse().ok()?, unit })
+    }
+
+    /// The `CSSUnit` encoding: the number, then the unit byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.value as f32).to_le_bytes().to_vec();
+        bytes.push(self.unit as u8);
+        bytes
+    }
+}
+
 impl CSSUnit {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Pixels),
+            0x02 => Some(Self::Em),
+            0x03 => Some(Self::Rem),
+            0x04 => Some(Self::ViewportWidth),
+            0x05 => Some(Self::ViewportHeight),
+            0x06 => Some(Self::Percentage),
+            0x07 => Some(Self::Degrees),
+            0x08 => Some(Self::Radians),
+            0x09 => Some(Self::Turns),
+            0x0A => Some(Self::Number),
+            _ => None,
+        }
+    }
+
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|unit| unit.suffix() == suffix)
+    }
+
+    const ALL: [Self; 10] = [
+        Self::Pixels, Self::Em, Self::Rem, Self::ViewportWidth, Self::ViewportHeight,
+        Self::Percentage, Self::Degrees, Self::Radians, Self::Turns, Self::Number,
+    ];
+
+    /// The suffix written after the number in source; empty for plain numbers
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::Pixels => "px",
+            Self::Em => "em",
+            Self::Rem => "rem",
+            Self::ViewportWidth => "vw",
+            Self::ViewportHeight => "vh",
+            Self::Percentage => "%",
+            Self::Degrees => "deg",
+            Self::Radians => "rad",
+            Self::Turns => "turn",
+            Self::Number => "",
+        }
+    }
+
     pub fn from_property_value(value: &PropertyValue) -> Option<CSSUnitValue> {
         match value {
             PropertyValue::Pixels(v) => {
@@ -143,9 +240,25 @@ pub struct KrbProperty {
     pub value: Vec<u8>,
 }
 
+impl KrbProperty {
+    /// A String, Resource or TemplateVariable value referring to table entry `index`
+    pub fn with_index(property_id: u8, value_type: ValueType, index: u16) -> Self {
+        Self { property_id, value_type, size: 2, value: index.to_le_bytes().to_vec() }
+    }
+
+    /// The table index held by an index-valued property
+    pub fn index(&self) -> Option<u16> {
+        if self.value_type.holds_index() {
+            decode_index(&self.value)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct KrbCustomProperty {
-    pub key_index: u8,
+    pub key_index: u16,
     pub value_type: ValueType,
     pub size: u8,
     pub value: Vec<u8>,
@@ -154,7 +267,7 @@ pub struct KrbCustomProperty {
 #[derive(Debug, Clone)]
 pub struct StatePropertySet {
     pub state_flags: u8,
-    pub property_count: u8,
+    pub property_count: u16,
     pub properties: Vec<KrbProperty>,
 }
 
@@ -163,31 +276,103 @@ pub struct SourceProperty {
     pub key: String,
     pub value: String,
     pub line_num: usize,
+    pub location: Option<Span>,
 }
 
 #[derive(Debug, Clone)]
 pub struct KrbEvent {
     pub event_type: u8,
-    pub callback_id: u8,
+    pub callback_id: u16,
+}
+
+/// An element's use of an animation or transition, written after its events
+#[derive(Debug, Clone)]
+pub struct KrbAnimationRef {
+    /// `ANIMATION_KIND_KEYFRAMES` or `ANIMATION_KIND_TRANSITION`
+    pub kind: u8,
+    /// Animation table index for keyframes, PropertyId for transitions
+    pub target: u16,
+    pub duration_ms: u16,
+    pub delay_ms: u16,
+    pub easing: u8,
+    pub iterations: u8,
+    pub direction: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyframeEntry {
+    /// Position in the animation in percent
+    pub offset: u8,
+    pub properties: Vec<KrbProperty>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationEntry {
+    pub name: String,
+    pub name_index: u16,
+    pub keyframes: Vec<KeyframeEntry>,
+    pub calculated_size: u32,
+}
+
+/// A `@theme` block: the value of every design token in one theme
+#[derive(Debug, Clone)]
+pub struct ThemeEntry {
+    pub name: String,
+    pub name_index: u16,
+    /// Token definitions as written; each property referring to a token converts its value
+    pub tokens: Vec<AstProperty>,
+    pub location: Option<Span>,
+}
+
+impl ThemeEntry {
+    pub fn token(&self, name: &str) -> Option<&AstProperty> {
+        self.tokens.iter().find(|token| token.key == name)
+    }
+}
+
+/// A slot of the theme table: a token as converted for one value type, with a value per theme
+#[derive(Debug, Clone)]
+pub struct ThemeSlot {
+    pub token: String,
+    pub name_index: u16,
+    pub value_type: ValueType,
+    /// One value per entry of `CompilerState::themes`, in the same order
+    pub values: Vec<Vec<u8>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct StringEntry {
     pub text: String,
     pub length: usize,
-    pub index: u8,
+    pub index: u16,
 }
 
 #[derive(Debug, Clone)]
 pub struct ResourceEntry {
     pub resource_type: ResourceType,
-    pub name_index: u8,
+    pub name_index: u16,
     pub format: ResourceFormat,
-    pub data_string_index: u8,
-    pub index: u8,
+    pub data_string_index: u16,
+    pub index: u16,
+    /// Descriptors of a `ResourceType::Font` face
+    pub font: Option<FontFace>,
+    /// File contents of an `Inline` resource
+    pub data: Vec<u8>,
+    /// The earlier resource holding the bytes of a `Shared` resource
+    pub shared_with: Option<u16>,
+    /// Where the resource was first referenced, for errors about its file
+    pub location: Option<Span>,
     pub calculated_size: u32,
 }
 
+/// Weight, style and fallback chain written after a font resource record
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    pub weight: u16,
+    pub style: FontStyle,
+    pub fallback_indices: Vec<u16>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ComponentPropertyDef {
     pub name: String,
@@ -208,10 +393,10 @@ pub struct ComponentDefinition {
 #[derive(Debug, Clone)]
 pub struct TemplateVariable {
     pub name: String,
-    pub name_index: u8,
+    pub name_index: u16,
     pub value_type: ValueType,
     pub default_value: String,
-    pub default_value_index: u8,
+    pub default_value_index: u16,
 }
 
 #[derive(Debug, Clone)]
@@ -219,73 +404,88 @@ pub struct TemplateBinding {
     pub element_index: u16,
     pub property_id: u8,
     pub template_expression: String,
-    pub template_expression_index: u8,
-    pub variable_count: u8,
-    pub variable_indices: Vec<u8>,
+    pub template_expression_index: u16,
+    pub variable_count: u16,
+    pub variable_indices: Vec<u16>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ScriptFunction {
     pub function_name: String,
-    pub function_name_index: u8,
+    pub function_name_index: u16,
+    /// Where the function is defined, for runtime error reports
+    pub location: Option<Span>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ScriptEntry {
     pub language_id: ScriptLanguage,
     pub name: String,
-    pub name_index: u8,
+    pub name_index: u16,
     pub storage_format: u8,
-    pub entry_point_count: u8,
+    pub entry_point_count: u16,
     pub data_size: u16,
     pub entry_points: Vec<ScriptFunction>,
     pub code_data: Vec<u8>,
-    pub resource_index: Option<u8>,
+    pub resource_index: Option<u16>,
     pub calculated_size: u32,
     pub source_line_num: usize,
+    pub location: Option<Span>,
 }
 
 #[derive(Debug, Clone)]
 pub struct StyleEntry {
-    pub id: u8,
+    pub id: u16,
     pub source_name: String,
-    pub name_index: u8,
+    pub name_index: u16,
     pub extends_style_names: Vec<String>,
     pub properties: Vec<KrbProperty>,
     pub source_properties: Vec<SourceProperty>,
+    /// `@media` sets, copied to every element using the style
+    pub media_property_sets: Vec<StatePropertySet>,
+    /// `animation:` and `transition:` references, copied to every element using the style
+    pub animations: Vec<KrbAnimationRef>,
     pub calculated_size: u32,
     pub is_resolved: bool,
     pub is_resolving: bool,
+    pub location: Option<Span>,
 }
 
 #[derive(Debug, Clone)]
 pub struct FontEntry {
     pub name: String,
     pub path: String,
-    pub name_index: u8,
-    pub path_index: u8,
+    pub name_index: u16,
+    pub path_index: u16,
+    pub weight: u16,
+    pub style: FontStyle,
+    pub fallbacks: Vec<String>,
+    /// The font resource describing this face
+    pub resource_index: u16,
+    pub location: Option<Span>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Element {
     pub element_type: ElementType,
-    pub id_string_index: u8,
+    pub id_string_index: u16,
     pub pos_x: u16,
     pub pos_y: u16,
     pub width: u16,
     pub height: u16,
     pub layout: u8,
-    pub style_id: u8,
+    pub style_id: u16,
     pub checked: bool,
-    pub property_count: u8,
-    pub child_count: u8,
-    pub event_count: u8,
-    pub animation_count: u8,
-    pub custom_prop_count: u8,
-    pub state_prop_count: u8,
+    pub property_count: u16,
+    pub child_count: u16,
+    pub event_count: u16,
+    pub animation_count: u16,
+    pub custom_prop_count: u16,
+    pub state_prop_count: u16,
     pub krb_properties: Vec<KrbProperty>,
     pub krb_custom_properties: Vec<KrbCustomProperty>,
     pub krb_events: Vec<KrbEvent>,
+    pub krb_animations: Vec<KrbAnimationRef>,
     pub state_property_sets: Vec<StatePropertySet>,
     pub children: Vec<usize>,
     pub parent_index: Option<usize>,
@@ -298,6 +498,9 @@ pub struct Element {
     pub source_properties: Vec<SourceProperty>,
     pub source_children_indices: Vec<usize>,
     pub source_line_num: usize,
+    pub source_location: Option<Span>,
+    /// The component usage this element was expanded from
+    pub component_origin: Option<ComponentOrigin>,
     pub layout_flags_source: u8,
     pub position_hint: String,
     pub orientation_hint: String,
@@ -350,22 +553,27 @@ pub struct ResolvedFunction {
     pub parameters: Vec<String>,
 }
 
+/// Maps that are iterated while building the output are `BTreeMap`s, so that
+/// identical input always produces a byte-identical KRB file
 #[derive(Debug)]
 pub struct CompilerState {
     pub elements: Vec<Element>,
     pub strings: Vec<StringEntry>,
     pub styles: Vec<StyleEntry>,
     pub fonts: Vec<FontEntry>,
+    pub animations: Vec<AnimationEntry>,
     pub scripts: Vec<ScriptEntry>,
     pub resources: Vec<ResourceEntry>,
     pub component_defs: Vec<ComponentDefinition>,
-    pub component_ast_templates: HashMap<String, AstNode>,
-    pub variables: HashMap<String, VariableDef>,
+    pub component_ast_templates: BTreeMap<String, AstNode>,
+    pub variables: BTreeMap<String, VariableDef>,
     pub variable_context: VariableContext,
     pub has_app: bool,
     pub header_flags: u16,
     pub current_line_num: usize,
     pub current_file_path: String,
+    /// `src` and `icon` files become resources to embed; otherwise they stay plain paths
+    pub embed_resources: bool,
     pub element_offset: u32,
     pub style_offset: u32,
     pub component_def_offset: u32,
@@ -377,6 +585,7 @@ pub struct CompilerState {
     pub total_element_data_size: u32,
     pub total_style_data_size: u32,
     pub total_component_def_data_size: u32,
+    pub total_animation_data_size: u32,
     pub total_script_data_size: u32,
     pub total_string_data_size: u32,
     pub total_resource_table_size: u32,
@@ -389,11 +598,15 @@ pub struct CompilerState {
     pub transforms: Vec<TransformData>,
     pub transform_offset: u32,
     pub total_transform_size: u32,
+    pub themes: Vec<ThemeEntry>,
+    pub theme_slots: Vec<ThemeSlot>,
+    pub theme_offset: u32,
+    pub total_theme_size: u32,
     pub function_templates: Vec<FunctionTemplate>,
-    pub resolved_functions: HashMap<String, ResolvedFunction>,
-    pub component_functions: HashMap<String, Vec<String>>,
+    pub resolved_functions: BTreeMap<String, ResolvedFunction>,
+    pub component_functions: BTreeMap<String, Vec<String>>,
     pub next_template_id: usize,
-    pub component_scripts: HashMap<String, Vec<AstNode>>,
+    pub component_scripts: BTreeMap<String, Vec<AstNode>>,
 }
 
 impl CompilerState {
@@ -403,16 +616,18 @@ impl CompilerState {
             strings: Vec::new(),
             styles: Vec::new(),
             fonts: Vec::new(),
+            animations: Vec::new(),
             scripts: Vec::new(),
             resources: Vec::new(),
             component_defs: Vec::new(),
-            component_ast_templates: HashMap::new(),
-            variables: HashMap::new(),
+            component_ast_templates: BTreeMap::new(),
+            variables: BTreeMap::new(),
             variable_context: VariableContext::new(),
             has_app: false,
             header_flags: 0,
             current_line_num: 0,
             current_file_path: String::new(),
+            embed_resources: false,
             element_offset: 0,
             style_offset: 0,
             component_def_offset: 0,
@@ -424,6 +639,7 @@ impl CompilerState {
             total_element_data_size: 0,
             total_style_data_size: 0,
             total_component_def_data_size: 0,
+            total_animation_data_size: 0,
             total_script_data_size: 0,
             total_string_data_size: 0,
             total_resource_table_size: 0,
@@ -436,15 +652,36 @@ impl CompilerState {
             transforms: Vec::new(),
             transform_offset: 0,
             total_transform_size: 0,
+            themes: Vec::new(),
+            theme_slots: Vec::new(),
+            theme_offset: 0,
+            total_theme_size: 0,
             function_templates: Vec::new(),
-            resolved_functions: HashMap::new(),
-            component_functions: HashMap::new(),
+            resolved_functions: BTreeMap::new(),
+            component_functions: BTreeMap::new(),
             next_template_id: 0,
-            component_scripts: HashMap::new(),
+            component_scripts: BTreeMap::new(),
+        }
+    }
+
+    /// Whether `name` is a token of the `@theme` blocks
+    pub fn is_theme_token(&self, name: &str) -> bool {
+        self.themes.iter().any(|theme| theme.token(name).is_some())
+    }
+
+    /// The slot holding `values` for `token`, reusing an identical one
+    pub fn add_theme_slot(&mut self, token: &str, value_type: ValueType, values: Vec<Vec<u8>>) -> Result<u16, CompilerError> {
+        if let Some(index) = self.theme_slots.iter()
+            .position(|slot| slot.token == token && slot.value_type == value_type && slot.values == values)
+        {
+            return Ok(index as u16);
         }
+        let name_index = self.add_string(token)?;
+        self.theme_slots.push(ThemeSlot { token: token.to_string(), name_index, value_type, values });
+        Ok((self.theme_slots.len() - 1) as u16)
     }
 
-    pub fn add_string<S: AsRef<str>>(&mut self, text: S) -> Result<u8, CompilerError> {
+    pub fn add_string<S: AsRef<str>>(&mut self, text: S) -> Result<u16, CompilerError> {
         let text_str = text.as_ref();
 
         if text_str.is_empty() {
@@ -460,7 +697,7 @@ impl CompilerState {
 
         for (index, existing) in self.strings.iter().enumerate() {
             if existing.text == text_str {
-                return Ok(index as u8);
+                return Ok(index as u16);
             }
         }
 
@@ -471,7 +708,7 @@ impl CompilerState {
             });
         }
 
-        let index = self.strings.len() as u8;
+        let index = self.strings.len() as u16;
         self.strings.push(StringEntry {
             text: text_str.to_string(),
             length: text_str.len(),
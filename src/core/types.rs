@@ -25,6 +25,37 @@ pub enum ElementType {
 }
 
 impl ElementType {
+    /// Element names accepted by `from_name`
+    pub const KNOWN_NAMES: &'static [&'static str] = &[
+        "App", "Container", "Text", "Link", "Image", "Canvas", "WasmView", "NativeRendererView",
+        "Button", "Input", "List", "Grid", "Scrollable", "Tabs", "Video",
+    ];
+
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::App),
+            0x01 => Some(Self::Container),
+            0x02 => Some(Self::Text),
+            0x03 => Some(Self::Link),
+            0x04 => Some(Self::Image),
+            0x05 => Some(Self::Canvas),
+            0x06 => Some(Self::WasmView),
+            0x07 => Some(Self::NativeRendererView),
+            0x10 => Some(Self::Button),
+            0x11 => Some(Self::Input),
+            0x20 => Some(Self::List),
+            0x21 => Some(Self::Grid),
+            0x22 => Some(Self::Scrollable),
+            0x23 => Some(Self::Tabs),
+            0x30 => Some(Self::Video),
+            0xFE => Some(Self::InternalComponentUsage),
+            0xFF => Some(Self::Unknown),
+            0x31 => Some(Self::CustomBase),
+            _ => None,
+        }
+    }
+
     pub fn from_name(name: &str) -> Self {
         match name {
             "App" => Self::App,
@@ -213,8 +244,68 @@ pub enum ValueType {
     
     // Template variable marker (to be resolved during compilation)
     TemplateVariable = 0x1C, // Holds variable name to be substituted
+    
+    // Slot of the theme table; the value comes from the active theme
+    ThemeToken = 0x1D,
+
+    // Linear or radial gradient with its color stops (see `core::gradient`)
+    Gradient = 0x1E,
+
+    // Shadow layers and filter chains (see `core::effects`)
+    Shadow = 0x1F,
+    Filter = 0x20,
+    // Unit arithmetic evaluated by the renderer (see `core::expression`)
+    Calc = 0x21,
+}
+impl ValueType {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::None),
+            0x01 => Some(Self::Byte),
+            0x02 => Some(Self::Short),
+            0x03 => Some(Self::Color),
+            0x04 => Some(Self::String),
+            0x05 => Some(Self::Resource),
+            0x06 => Some(Self::Percentage),
+            0x07 => Some(Self::Rect),
+            0x08 => Some(Self::EdgeInsets),
+            0x09 => Some(Self::Enum),
+            0x0A => Some(Self::Vector),
+            0x0B => Some(Self::Custom),
+            0x0C => Some(Self::StyleId),
+            0x0D => Some(Self::Float),
+            0x0E => Some(Self::Int),
+            0x0F => Some(Self::Bool),
+            0x10 => Some(Self::GridTrack),
+            0x11 => Some(Self::GridArea),
+            0x12 => Some(Self::FlexValue),
+            0x13 => Some(Self::AlignmentValue),
+            0x14 => Some(Self::PositionValue),
+            0x15 => Some(Self::LengthPercentage),
+            0x16 => Some(Self::Dimension),
+            0x17 => Some(Self::Transform),
+            0x18 => Some(Self::TransformMatrix),
+            0x19 => Some(Self::CSSUnit),
+            0x1A => Some(Self::Transform2D),
+            0x1B => Some(Self::Transform3D),
+            0x1C => Some(Self::TemplateVariable),
+            0x1D => Some(Self::ThemeToken),
+            0x1E => Some(Self::Gradient),
+            0x1F => Some(Self::Shadow),
+            0x20 => Some(Self::Filter),
+            0x21 => Some(Self::Calc),
+            _ => None,
+        }
+    }
+
+    /// Whether values of this type are a table index, one or two bytes wide depending on the file
+    pub fn holds_index(self) -> bool {
+        matches!(self, Self::String | Self::Resource | Self::TemplateVariable | Self::ThemeToken)
+    }
 }
 
+
 // Script language IDs
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -226,6 +317,17 @@ pub enum ScriptLanguage {
 }
 
 impl ScriptLanguage {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Lua),
+            0x02 => Some(Self::JavaScript),
+            0x03 => Some(Self::Python),
+            0x04 => Some(Self::Wren),
+            _ => None,
+        }
+    }
+
     pub fn from_name(name: &str) -> Option<Self> {
         match name.to_lowercase().as_str() {
             "lua" => Some(Self::Lua),
@@ -239,7 +341,7 @@ impl ScriptLanguage {
 
 
 // Resource types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum ResourceType {
     Image = 0x01,
@@ -249,10 +351,105 @@ pub enum ResourceType {
     Script = 0x05,
     Custom = 0x06,
 }
+impl ResourceType {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::Image),
+            0x02 => Some(Self::Font),
+            0x03 => Some(Self::Sound),
+            0x04 => Some(Self::Video),
+            0x05 => Some(Self::Script),
+            0x06 => Some(Self::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "image" => Some(Self::Image),
+            "font" => Some(Self::Font),
+            "sound" => Some(Self::Sound),
+            "video" => Some(Self::Video),
+            "script" => Some(Self::Script),
+            "custom" => Some(Self::Custom),
+            _ => None,
+        }
+    }
+
+    /// The kind of file a resource path names, judged by its extension
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "ico" => Some(Self::Image),
+            "ttf" | "otf" | "woff" | "woff2" => Some(Self::Font),
+            "wav" | "mp3" | "ogg" | "flac" | "opus" => Some(Self::Sound),
+            "mp4" | "webm" | "mov" | "mkv" => Some(Self::Video),
+            "lua" | "js" | "py" | "wren" => Some(Self::Script),
+            _ => None,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ResourceFormat {
     External = 0x00,
     Inline = 0x01,
+    /// Inline bytes identical to those of an earlier resource, stored once
+    Shared = 0x02,
+}
+impl ResourceFormat {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::External),
+            0x01 => Some(Self::Inline),
+            0x02 => Some(Self::Shared),
+            _ => None,
+        }
+    }
+}
+
+/// Families every renderer provides, usable without a `font` declaration
+pub const GENERIC_FONT_FAMILIES: &[&str] = &["serif", "sans-serif", "monospace", "cursive", "fantasy", "system-ui"];
+
+/// Style of a font face, as declared by `font` blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum FontStyle {
+    #[default]
+    Normal = 0x00,
+    Italic = 0x01,
+    Oblique = 0x02,
+}
+impl FontStyle {
+    /// Decode the byte written to KRB files
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::Normal),
+            0x01 => Some(Self::Italic),
+            0x02 => Some(Self::Oblique),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "normal" => Some(Self::Normal),
+            "italic" => Some(Self::Italic),
+            "oblique" => Some(Self::Oblique),
+            _ => None,
+        }
+    }
+
+    pub fn to_name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Italic => "italic",
+            Self::Oblique => "oblique",
+        }
+    }
 }
+
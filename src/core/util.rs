@@ -48,115 +48,10 @@ impl fmt::Display for Color {
 }
 
 
-/// Parse a color string (#RGB, #RRGGBB, #RRGGBBAA) or CSS color keywords
+/// Parse a hex color (#RGB, #RGBA, #RRGGBB, #RRGGBBAA), a CSS named color, or a call of one
+/// of the `core::color` functions with literal arguments
 pub fn parse_color(color_str: &str) -> Result<Color> {
-    let trimmed = color_str.trim().to_lowercase();
-    
-    // Handle CSS color keywords
-    match trimmed.as_str() {
-        "transparent" => return Ok(Color::new(0, 0, 0, 0)),
-        _ => {}
-    }
-    
-    if !trimmed.starts_with('#') {
-        return Err(CompilerError::InvalidFormat {
-            message: format!("Color must start with # or be a valid color keyword: {}", color_str),
-        });
-    }
-    
-    let hex_str = &trimmed[1..];
-    
-    match hex_str.len() {
-        3 => {
-            // RGB -> RRGGBB
-            let r = u8::from_str_radix(&hex_str[0..1].repeat(2), 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let g = u8::from_str_radix(&hex_str[1..2].repeat(2), 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let b = u8::from_str_radix(&hex_str[2..3].repeat(2), 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            Ok(Color::new(r, g, b, 255))
-        }
-        4 => {
-            // RGBA -> RRGGBBAA
-            let r = u8::from_str_radix(&hex_str[0..1].repeat(2), 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let g = u8::from_str_radix(&hex_str[1..2].repeat(2), 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let b = u8::from_str_radix(&hex_str[2..3].repeat(2), 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let a = u8::from_str_radix(&hex_str[3..4].repeat(2), 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            Ok(Color::new(r, g, b, a))
-        }
-        6 => {
-            // RRGGBB
-            let r = u8::from_str_radix(&hex_str[0..2], 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let g = u8::from_str_radix(&hex_str[2..4], 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let b = u8::from_str_radix(&hex_str[4..6], 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            Ok(Color::new(r, g, b, 255))
-        }
-        8 => {
-            // RRGGBBAA
-            let r = u8::from_str_radix(&hex_str[0..2], 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let g = u8::from_str_radix(&hex_str[2..4], 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let b = u8::from_str_radix(&hex_str[4..6], 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            let a = u8::from_str_radix(&hex_str[6..8], 16).map_err(|_| {
-                CompilerError::InvalidFormat {
-                    message: format!("Invalid hex color: {}", color_str),
-                }
-            })?;
-            Ok(Color::new(r, g, b, a))
-        }
-        _ => Err(CompilerError::InvalidFormat {
-            message: format!("Invalid color format: {} (expected 3, 4, 6, or 8 hex digits)", color_str),
-        }),
-    }
+    crate::core::color::evaluate_color(color_str, &|_| None).map_err(|message| CompilerError::InvalidFormat { message })
 }
 
 
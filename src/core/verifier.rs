@@ -0,0 +1,469 @@
+// FILE: src/core/verifier.rs
+
+//! Deep structural verification of KRB files
+//!
+//! `validate_krb_file` only looks at the header. The verifier decodes every
+//! section and checks the references between them, collecting every
+//! violation instead of stopping at the first one.
+
+use crate::core::constants::*;
+use crate::core::reader::SCRIPT_STORAGE_INLINE;
+use crate::core::types::{ElementType, FontStyle, ResourceFormat, ValueType};
+use crate::core::animation::*;
+use crate::core::color::color_size;
+use crate::core::effects::{decode_filters, decode_shadows};
+use crate::core::expression::Calc;
+use crate::core::fixed_point::NumberFormat;
+use crate::core::gradient::Gradient;
+use crate::core::index_width::{decode_index, IndexWidth};
+use crate::core::properties::PropertyId;
+use crate::core::{KrbAnimationRefRecord, KrbElementRecord, KrbFile, KrbFileInfo, KrbReader};
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A single problem found in a KRB file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KrbViolation {
+    /// Byte offset of the offending record
+    pub offset: u32,
+    pub message: String,
+}
+
+impl fmt::Display for KrbViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}: {}", self.offset, self.message)
+    }
+}
+
+/// Verify a KRB file, returning every violation sorted by offset
+pub fn verify_krb(data: &[u8]) -> Vec<KrbViolation> {
+    let reader = match KrbReader::new(data) {
+        Ok(reader) => reader,
+        Err(e) => return vec![KrbViolation { offset: 0, message: e.to_string() }],
+    };
+
+    let mut verifier = Verifier { violations: Vec::new() };
+    verifier.check_layout(reader.header(), reader.header().total_size as usize);
+
+    let header = reader.header().clone();
+    let file = KrbFile {
+        strings: verifier.section(header.string_offset, reader.read_strings()),
+        elements: verifier.elements(&reader),
+        styles: verifier.section(header.style_offset, reader.read_styles()),
+        components: verifier.section(header.component_offset, reader.read_components()),
+        animations: verifier.section(header.animation_offset, reader.read_animations()),
+        scripts: verifier.section(header.script_offset, reader.read_scripts()),
+        resources: verifier.section(header.resource_offset, reader.read_resources()),
+        template_variables: verifier.section(header.template_variable_offset, reader.read_template_variables()),
+        template_bindings: verifier.section(header.template_binding_offset, reader.read_template_bindings()),
+        transforms: verifier.section(header.transform_offset, reader.read_transforms()),
+        theme_table: verifier.section(header.theme_offset, reader.read_theme_table()),
+        debug_map: None,
+        header,
+    };
+
+    verifier.check_tree(&file);
+    verifier.check_references(&file);
+    verifier.check_themes(&file);
+
+    let mut violations = verifier.violations;
+    violations.sort_by_key(|v| v.offset);
+    violations
+}
+
+struct Verifier {
+    violations: Vec<KrbViolation>,
+}
+
+impl Verifier {
+    fn report(&mut self, offset: u32, message: String) {
+        self.violations.push(KrbViolation { offset, message });
+    }
+
+    /// Keep decoding the remaining sections when one of them is broken
+    fn section<T: Default>(&mut self, offset: u32, result: Result<T>) -> T {
+        result.unwrap_or_else(|e| {
+            self.report(offset, e.to_string());
+            T::default()
+        })
+    }
+
+    fn elements(&mut self, reader: &KrbReader) -> Vec<KrbElementRecord> {
+        match reader.read_element_section() {
+            Ok((elements, tree_end, section_end)) => {
+                if tree_end < section_end {
+                    self.report(
+                        tree_end,
+                        format!("{} bytes after the element tree are not reachable from the root", section_end - tree_end),
+                    );
+                }
+                elements
+            }
+            Err(e) => {
+                self.report(reader.header().element_offset, e.to_string());
+                Vec::new()
+            }
+        }
+    }
+
+    fn check_layout(&mut self, header: &KrbFileInfo, file_size: usize) {
+        let sections = [
+            ("string", header.string_count, header.string_offset),
+            ("element", header.element_count, header.element_offset),
+            ("style", header.style_count, header.style_offset),
+            ("component", header.component_count, header.component_offset),
+            ("animation", header.animation_count, header.animation_offset),
+            ("script", header.script_count, header.script_offset),
+            ("resource", header.resource_count, header.resource_offset),
+            ("template variable", header.template_variable_count, header.template_variable_offset),
+            ("template binding", header.template_binding_count, header.template_binding_offset),
+            ("transform", header.transform_count, header.transform_offset),
+            ("theme", header.theme_count, header.theme_offset),
+        ];
+
+        // Empty sections may point at the end of the file; populated ones must start inside it
+        for &(name, count, offset) in &sections {
+            let in_bounds = if count > 0 {
+                offset as usize >= KRB_HEADER_SIZE && (offset as usize) < file_size
+            } else {
+                offset as usize <= file_size
+            };
+            if !in_bounds {
+                self.report(
+                    offset,
+                    format!("{} section offset {} lies outside the file ({} bytes)", name, offset, file_size),
+                );
+            }
+        }
+
+        let populated: Vec<_> = sections.iter().filter(|(_, count, _)| *count > 0).collect();
+        for (i, &&(name, _, offset)) in populated.iter().enumerate() {
+            for &&(other, _, other_offset) in &populated[i + 1..] {
+                if offset == other_offset {
+                    self.report(offset, format!("{} and {} sections overlap at the same offset", name, other));
+                }
+            }
+        }
+    }
+
+    fn check_tree(&mut self, file: &KrbFile) {
+        let header = &file.header;
+        let Some(root) = file.elements.first() else {
+            if header.element_count > 0 {
+                self.report(header.element_offset, "element section is empty; expected an App root".to_string());
+            }
+            return;
+        };
+
+        if root.element_type != ElementType::App as u8 {
+            self.report(root.offset, format!("root element is {}, expected App", element_name(root.element_type)));
+        }
+        for element in &file.elements[1..] {
+            if element.element_type == ElementType::App as u8 {
+                self.report(element.offset, "App element is nested inside the tree".to_string());
+            }
+        }
+
+        if file.elements.len() != header.element_count as usize {
+            self.report(
+                root.offset,
+                format!(
+                    "header declares {} elements but the tree rooted here contains {}",
+                    header.element_count,
+                    file.elements.len()
+                ),
+            );
+        }
+
+        // Child offsets are relative to the parent; zero is an unresolved placeholder
+        for element in &file.elements {
+            for (&relative, &child) in element.child_offsets.iter().zip(&element.children) {
+                let expected = file.elements[child].offset;
+                if relative != 0 && element.offset + relative as u32 != expected {
+                    self.report(
+                        element.offset,
+                        format!(
+                            "child offset {} points to {:#06x} but the child starts at {:#06x}",
+                            relative,
+                            element.offset + relative as u32,
+                            expected
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_references(&mut self, file: &KrbFile) {
+        let string_count = file.header.string_count as usize;
+        let string = |index: u16| (index as usize) < string_count;
+
+        let entry_points: HashSet<&str> = file
+            .scripts
+            .iter()
+            .flat_map(|script| script.entry_points.iter())
+            .filter_map(|&index| file.string(index))
+            .collect();
+
+        for element in file.elements.iter().chain(file.components.iter().flat_map(|c| c.template.iter())) {
+            if element.id_string_index != 0 && !string(element.id_string_index) {
+                self.report_string(element.offset, "element id", element.id_string_index, string_count);
+            }
+            if element.style_id != 0 && file.style(element.style_id).is_none() {
+                self.report(element.offset, format!("style_id {} does not name a style", element.style_id));
+            }
+            for property in &element.properties {
+                self.check_property(file, property.offset, property.value_type, &property.value);
+            }
+            for property in &element.custom_properties {
+                if !string(property.key_index) {
+                    self.report_string(property.offset, "custom property key", property.key_index, string_count);
+                }
+                self.check_property(file, property.offset, property.value_type, &property.value);
+            }
+            for set in &element.state_property_sets {
+                for property in &set.properties {
+                    self.check_property(file, property.offset, property.value_type, &property.value);
+                }
+            }
+            for event in &element.events {
+                match file.string(event.callback_id) {
+                    None => self.report_string(event.offset, "event callback", event.callback_id, string_count),
+                    Some(name) if !entry_points.contains(name) => {
+                        self.report(event.offset, format!("event callback \"{}\" is not a script entry point", name))
+                    }
+                    Some(_) => {}
+                }
+            }
+            for animation in &element.animations {
+                self.check_animation_ref(file, animation);
+            }
+        }
+
+        let mut style_ids = HashSet::new();
+        for style in &file.styles {
+            if !style_ids.insert(style.id) {
+                self.report(style.offset, format!("style id {} is defined more than once", style.id));
+            }
+            if !string(style.name_index) {
+                self.report_string(style.offset, "style name", style.name_index, string_count);
+            }
+            for property in &style.properties {
+                self.check_property(file, property.offset, property.value_type, &property.value);
+            }
+        }
+
+        for component in &file.components {
+            if !string(component.name_index) {
+                self.report_string(component.offset, "component name", component.name_index, string_count);
+            }
+            for property in &component.properties {
+                if !string(property.name_index) {
+                    self.report_string(property.offset, "component property name", property.name_index, string_count);
+                }
+            }
+        }
+
+        for animation in &file.animations {
+            if !string(animation.name_index) {
+                self.report_string(animation.offset, "animation name", animation.name_index, string_count);
+            }
+            let mut previous = None;
+            for keyframe in &animation.keyframes {
+                if keyframe.position > 100 || previous.is_some_and(|p| p >= keyframe.position) {
+                    self.report(
+                        keyframe.offset,
+                        format!("keyframe at {}% is out of range or out of order", keyframe.position),
+                    );
+                }
+                previous = Some(keyframe.position);
+                for property in &keyframe.properties {
+                    self.check_property(file, property.offset, property.value_type, &property.value);
+                }
+            }
+        }
+
+        for script in &file.scripts {
+            if !string(script.name_index) {
+                self.report_string(script.offset, "script name", script.name_index, string_count);
+            }
+            for &entry_point in &script.entry_points {
+                if !string(entry_point) {
+                    self.report_string(script.offset, "script entry point", entry_point, string_count);
+                }
+            }
+            if script.storage_format != SCRIPT_STORAGE_INLINE && script.data_size as usize >= file.resources.len() {
+                self.report(
+                    script.offset,
+                    format!("external script refers to resource {} but there are {}", script.data_size, file.resources.len()),
+                );
+            }
+        }
+
+        for resource in &file.resources {
+            if !string(resource.name_index) {
+                self.report_string(resource.offset, "resource name", resource.name_index, string_count);
+            }
+            if !string(resource.data_string_index) {
+                self.report_string(resource.offset, "resource data", resource.data_string_index, string_count);
+            }
+            if ResourceFormat::from_u8(resource.format).is_none() {
+                self.report(resource.offset, format!("unknown resource format 0x{:02X}", resource.format));
+            }
+            if let Some(shared) = resource.shared_with {
+                let holds_data = file.resources.get(shared as usize).is_some_and(|r| r.format == ResourceFormat::Inline as u8);
+                if !holds_data {
+                    self.report(resource.offset, format!("shared resource refers to resource {} which is not inline", shared));
+                }
+            }
+            if let Some(font) = &resource.font {
+                if FontStyle::from_u8(font.style).is_none() {
+                    self.report(resource.offset, format!("unknown font style 0x{:02X}", font.style));
+                }
+                if !(1..=1000).contains(&font.weight) {
+                    self.report(resource.offset, format!("font weight {} is outside 1 to 1000", font.weight));
+                }
+                for &fallback in &font.fallbacks {
+                    if !string(fallback) {
+                        self.report_string(resource.offset, "font fallback", fallback, string_count);
+                    }
+                }
+            }
+        }
+
+        for variable in &file.template_variables {
+            if !string(variable.name_index) {
+                self.report_string(variable.offset, "template variable name", variable.name_index, string_count);
+            }
+            if !string(variable.default_value_index) {
+                self.report_string(variable.offset, "template variable default", variable.default_value_index, string_count);
+            }
+        }
+
+        for binding in &file.template_bindings {
+            if binding.element_index as usize >= file.elements.len() {
+                self.report(
+                    binding.offset,
+                    format!("binding targets element {} but there are {}", binding.element_index, file.elements.len()),
+                );
+            }
+            if !string(binding.expression_index) {
+                self.report_string(binding.offset, "binding expression", binding.expression_index, string_count);
+            }
+            for &variable in &binding.variable_indices {
+                if variable as usize >= file.template_variables.len() {
+                    self.report(
+                        binding.offset,
+                        format!("binding uses template variable {} but there are {}", variable, file.template_variables.len()),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_themes(&mut self, file: &KrbFile) {
+        let string_count = file.header.string_count as usize;
+        let table = &file.theme_table;
+        for slot in &table.slots {
+            if slot.name_index as usize >= string_count {
+                self.report_string(slot.offset, "theme slot name", slot.name_index, string_count);
+            }
+            if matches!(ValueType::from_u8(slot.value_type), None | Some(ValueType::ThemeToken)) {
+                self.report(slot.offset, format!("theme slot has value type 0x{:02X}, which a theme cannot hold", slot.value_type));
+            }
+        }
+        for theme in &table.themes {
+            if theme.name_index as usize >= string_count {
+                self.report_string(theme.offset, "theme name", theme.name_index, string_count);
+            }
+            for (slot, value) in table.slots.iter().zip(&theme.values) {
+                self.check_property(file, theme.offset, slot.value_type, value);
+            }
+        }
+    }
+
+    fn check_animation_ref(&mut self, file: &KrbFile, animation: &KrbAnimationRefRecord) {
+        match animation.kind {
+            ANIMATION_KIND_KEYFRAMES if animation.target as usize >= file.animations.len() => self.report(
+                animation.offset,
+                format!("element plays animation {} but there are {}", animation.target, file.animations.len()),
+            ),
+            ANIMATION_KIND_TRANSITION
+                if !u8::try_from(animation.target).ok().and_then(PropertyId::from_u8).is_some_and(PropertyId::is_animatable) =>
+            {
+                self.report(
+                    animation.offset,
+                    format!("transition targets property 0x{:02X}, which cannot be animated", animation.target),
+                )
+            }
+            ANIMATION_KIND_KEYFRAMES | ANIMATION_KIND_TRANSITION => {}
+            kind => self.report(animation.offset, format!("unknown animation kind 0x{:02X}", kind)),
+        }
+        if Easing::from_u8(animation.easing).is_none() {
+            self.report(animation.offset, format!("unknown easing 0x{:02X}", animation.easing));
+        }
+        if AnimationDirection::from_u8(animation.direction).is_none() {
+            self.report(animation.offset, format!("unknown animation direction 0x{:02X}", animation.direction));
+        }
+    }
+
+    fn check_property(&mut self, file: &KrbFile, offset: u32, value_type: u8, value: &[u8]) {
+        let value_type = ValueType::from_u8(value_type);
+        let width = IndexWidth::from_flags(file.header.flags);
+        if value_type.is_some_and(ValueType::holds_index) && value.len() != width.bytes() {
+            self.report(offset, format!("index value is {} bytes but the file uses {}-byte indices", value.len(), width.bytes()));
+            return;
+        }
+        let color_size = color_size(file.header.flags);
+        if value_type == Some(ValueType::Color) && value.len() != color_size {
+            self.report(offset, format!("color value is {} bytes but the file uses {}-byte colors", value.len(), color_size));
+            return;
+        }
+        if value_type == Some(ValueType::Gradient) && Gradient::decode(value, color_size).is_none() {
+            self.report(offset, format!("gradient value of {} bytes does not match its kind and stop count", value.len()));
+            return;
+        }
+        if value_type == Some(ValueType::Shadow) && decode_shadows(value, color_size).is_none() {
+            self.report(offset, format!("shadow value of {} bytes does not match its layer count", value.len()));
+            return;
+        }
+        if value_type == Some(ValueType::Filter) && decode_filters(value, color_size).is_none() {
+            self.report(offset, format!("filter value of {} bytes does not match its functions", value.len()));
+            return;
+        }
+        if value_type == Some(ValueType::Calc) && Calc::decode(value, NumberFormat::from_flags(file.header.flags)).is_none() {
+            self.report(offset, format!("calc value of {} bytes is not a complete expression", value.len()));
+            return;
+        }
+        match (value_type, decode_index(value)) {
+            (Some(ValueType::String), Some(index)) if index as usize >= file.strings.len() => {
+                self.report_string(offset, "property value", index, file.strings.len());
+            }
+            (Some(ValueType::Resource), Some(index)) if index as usize >= file.resources.len() => {
+                self.report(offset, format!("property refers to resource {} but there are {}", index, file.resources.len()));
+            }
+            (Some(ValueType::Transform), Some(index)) if index as usize >= file.transforms.len() => {
+                self.report(offset, format!("property refers to transform {} but there are {}", index, file.transforms.len()));
+            }
+            (Some(ValueType::ThemeToken), Some(index)) if index as usize >= file.theme_table.slots.len() => {
+                self.report(offset, format!("property refers to theme slot {} but there are {}", index, file.theme_table.slots.len()));
+            }
+            (Some(ValueType::StyleId), Some(id)) if value.len() == 1 && id != 0 && file.style(id).is_none() => {
+                self.report(offset, format!("property refers to style {} which does not exist", id));
+            }
+            _ => {}
+        }
+    }
+
+    fn report_string(&mut self, offset: u32, what: &str, index: u16, count: usize) {
+        self.report(offset, format!("{} string index {} is out of range ({} strings)", what, index, count));
+    }
+}
+
+fn element_name(element_type: u8) -> String {
+    ElementType::from_u8(element_type)
+        .map(|t| format!("{:?}", t))
+        .unwrap_or_else(|| format!("unknown type 0x{:02X}", element_type))
+}
@@ -0,0 +1,385 @@
+//! Compiler diagnostics: located errors and warnings rendered rustc-style
+//!
+//! Phases that can keep going after a problem push a `Diagnostic` into a
+//! `Diagnostics` collection instead of returning early, so a single run reports
+//! everything it found. The CLI renders them with the offending source line and
+//! a caret underline.
+
+use crate::compiler::file_provider::FileProvider;
+use crate::error::CompilerError;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// A location in an original source file. Lines and columns are 1-based;
+/// column 0 means only the line is known.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// Width of the underlined region in characters (at least 1 when rendered)
+    pub length: usize,
+}
+
+impl Span {
+    pub fn new(file: impl Into<String>, line: usize, column: usize, length: usize) -> Self {
+        Self { file: file.into(), line, column, length }
+    }
+
+    /// A span covering a whole line
+    pub fn line(file: impl Into<String>, line: usize) -> Self {
+        Self::new(file, line, 0, 0)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.column > 0 {
+            write!(f, "{}:{}:{}", self.file, self.line, self.column)
+        } else {
+            write!(f, "{}:{}", self.file, self.line)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// Secondary information attached to a diagnostic, e.g. "style defined here"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Note {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+    pub notes: Vec<Note>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self { severity, message: message.into(), span: None, notes: Vec::new() }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_note(mut self, message: impl Into<String>, span: Option<Span>) -> Self {
+        self.notes.push(Note { message: message.into(), span });
+        self
+    }
+
+    /// Convert a single compiler error, keeping whatever location it carries
+    pub fn from_error(error: &CompilerError) -> Self {
+        let located = |file: &String, line: &usize, column: usize, message: &String| {
+            let span = (*line > 0).then(|| Span::new(file.clone(), *line, column, 0));
+            Diagnostic::error(message.clone()).with_span(span)
+        };
+        match error {
+            CompilerError::Parse { file, line, column, message } => located(file, line, *column, message),
+            CompilerError::Semantic { file, line, message }
+            | CompilerError::Variable { file, line, message }
+            | CompilerError::Component { file, line, message }
+            | CompilerError::Script { file, line, message } => located(file, line, 0, message),
+            other => Diagnostic::error(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{}: {}: {}", span, self.severity, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// An ordered collection of diagnostics from one compilation
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.items.push(diagnostic);
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.items.extend(other.items);
+    }
+
+    /// Record a failure from a phase that stops at its first error. A phase that
+    /// already collected several reports (`CompilerError::Diagnostics`) is merged as-is.
+    pub fn push_error(&mut self, error: CompilerError) {
+        match error {
+            CompilerError::Diagnostics(diagnostics) => self.extend(diagnostics),
+            other => self.push(Diagnostic::from_error(&other)),
+        }
+    }
+
+    /// Combine the diagnostics gathered so far with a failing phase's error.
+    /// With nothing gathered the error is returned unchanged.
+    pub fn fail(&mut self, error: CompilerError) -> CompilerError {
+        if self.items.is_empty() {
+            return error;
+        }
+        let mut all = std::mem::take(self);
+        all.push_error(error);
+        CompilerError::Diagnostics(all)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.items.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.items.iter().filter(|d| d.severity == severity).count()
+    }
+
+    /// Only the warnings, e.g. to report alongside a successful build
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        self.items.iter().filter(|d| d.severity == Severity::Warning).cloned().collect()
+    }
+
+    /// `Err(CompilerError::Diagnostics)` if any error was collected
+    pub fn into_result(self) -> crate::error::Result<Self> {
+        if self.has_errors() {
+            Err(CompilerError::Diagnostics(self))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Render every diagnostic with source snippets read through `provider`
+    pub fn render(&self, provider: &dyn FileProvider) -> String {
+        self.items.iter().map(|d| render(d, provider)).collect::<Vec<_>>().join("\n")
+    }
+}
+
+impl From<CompilerError> for Diagnostics {
+    fn from(error: CompilerError) -> Self {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push_error(error);
+        diagnostics
+    }
+}
+
+impl FromIterator<Diagnostic> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = Diagnostic>>(iter: I) -> Self {
+        Self { items: iter.into_iter().collect() }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.items.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+/// Render one diagnostic the way rustc does:
+///
+/// ```text
+/// error: property 'text' is not valid for Container
+///  --> app.kry:4:9
+///   |
+/// 4 |         text: "hi"
+///   |         ^^^^
+///   = note: ...
+/// ```
+pub fn render(diagnostic: &Diagnostic, provider: &dyn FileProvider) -> String {
+    let mut out = format!("{}: {}\n", diagnostic.severity, diagnostic.message);
+
+    // Align every gutter in the diagnostic to the widest line number it shows
+    let width = std::iter::once(&diagnostic.span)
+        .chain(diagnostic.notes.iter().map(|n| &n.span))
+        .flatten()
+        .map(|span| span.line.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let gutter = " ".repeat(width);
+
+    if let Some(span) = &diagnostic.span {
+        out.push_str(&format!("{}--> {}\n", gutter, span));
+        out.push_str(&snippet(span, provider, &gutter, '^'));
+    }
+
+    for note in &diagnostic.notes {
+        match &note.span {
+            Some(span) => {
+                out.push_str(&format!("note: {}\n", note.message));
+                out.push_str(&format!("{}--> {}\n", gutter, span));
+                out.push_str(&snippet(span, provider, &gutter, '-'));
+            }
+            None => out.push_str(&format!("{} = note: {}\n", gutter, note.message)),
+        }
+    }
+    out
+}
+
+/// The source line of `span` with the spanned region underlined by `marker`.
+/// Empty if the file or line can't be read.
+fn snippet(span: &Span, provider: &dyn FileProvider, gutter: &str, marker: char) -> String {
+    let Some(source_line) = provider
+        .read_to_string(Path::new(&span.file))
+        .ok()
+        .and_then(|source| source.lines().nth(span.line.wrapping_sub(1)).map(str::to_string))
+    else {
+        return String::new();
+    };
+
+    let (start, length) = underline(&source_line, span);
+    format!(
+        "{gutter} |\n{line:>width$} | {source}\n{gutter} | {pad}{marks}\n",
+        gutter = gutter,
+        line = span.line,
+        width = gutter.len(),
+        source = source_line,
+        pad = " ".repeat(start),
+        marks = marker.to_string().repeat(length),
+    )
+}
+
+/// Character offset and width to underline. Without a column the whole
+/// (trimmed) line is marked; without a length the word or quoted string at the
+/// column is.
+fn underline(source_line: &str, span: &Span) -> (usize, usize) {
+    let chars: Vec<char> = source_line.chars().collect();
+    if span.column == 0 || span.column > chars.len() {
+        let start = chars.iter().take_while(|c| c.is_whitespace()).count();
+        let end = chars.len() - chars.iter().rev().take_while(|c| c.is_whitespace()).count();
+        return (start, end.saturating_sub(start).max(1));
+    }
+
+    let start = span.column - 1;
+    if span.length > 0 {
+        return (start, span.length.min(chars.len() - start));
+    }
+    let rest = &chars[start..];
+    let length = if rest[0] == '"' {
+        rest.iter().skip(1).position(|&c| c == '"').map_or(rest.len(), |end| end + 2)
+    } else {
+        rest.iter()
+            .position(|&c| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '#'))
+            .unwrap_or(rest.len())
+    };
+    (start, length.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::file_provider::MemoryFileProvider;
+
+    #[test]
+    fn test_render_snippet_with_notes() {
+        let provider = MemoryFileProvider::new()
+            .with_file("app.kry", "App {\n    Container {\n        style: \"missing\"\n    }\n}\n");
+        let diagnostic = Diagnostic::error("style 'missing' is not defined")
+            .with_span(Some(Span::new("app.kry", 3, 16, 0)))
+            .with_note("styles must be defined before they are compiled", None);
+
+        let rendered = render(&diagnostic, &provider);
+        assert_eq!(
+            rendered,
+            "error: style 'missing' is not defined\n \
+             --> app.kry:3:16\n  \
+             |\n\
+             3 |         style: \"missing\"\n  \
+             |                ^^^^^^^^^\n  \
+             = note: styles must be defined before they are compiled\n"
+        );
+    }
+
+    #[test]
+    fn test_counts_and_merging() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(Diagnostic::warning("unused style 'card'"));
+        assert_eq!(diagnostics.clone().into_result().unwrap().warning_count(), 1);
+
+        let merged = diagnostics.fail(CompilerError::parse("app.kry", 2, "Expected '}'"));
+        let CompilerError::Diagnostics(all) = merged else { panic!("expected merged diagnostics") };
+        assert_eq!((all.error_count(), all.warning_count()), (1, 1));
+        assert!(diagnostics.is_empty());
+        assert_eq!(all.to_string(), "warning: unused style 'card'\napp.kry:2: error: Expected '}'");
+    }
+
+    #[test]
+    fn test_compile_collects_every_semantic_error() {
+        let provider = MemoryFileProvider::new();
+        let source = "style \"card\" {\n    font_size: 14\n}\n\nApp {\n    Container {\n        text_color: \"#FFF\"\n        style: \"missing\"\n    }\n}\n";
+
+        let error = crate::compile_source(source, "app.kry", &provider, crate::CompilerOptions::default()).unwrap_err();
+        let CompilerError::Diagnostics(diagnostics) = error else { panic!("expected collected diagnostics") };
+        let spans: Vec<_> = diagnostics.iter().map(|d| (d.severity, d.span.clone().map(|s| (s.line, s.column)))).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (Severity::Error, Some((7, 9))),
+                (Severity::Error, Some((8, 9))),
+                (Severity::Warning, Some((1, 7))),
+            ]
+        );
+    }
+}
@@ -53,7 +53,7 @@ pub enum CompilerError {
     ScriptCompilation(#[from] crate::compiler::middle_end::script_compiler::ScriptCompilationError),
 
     #[error("Parse error in {file} at line {line}: {message}")]
-    Parse { file: String, line: usize, message: String },
+    Parse { file: String, line: usize, column: usize, message: String },
 
     #[error("Semantic error in {file} at line {line}: {message}")]
     Semantic { file: String, line: usize, message: String },
@@ -87,15 +87,25 @@ pub enum CompilerError {
 
     #[error("Invalid format: {message}")]
     InvalidFormat { message: String },
+
+    /// Several errors (and any warnings) collected by a phase that keeps going
+    #[error("{0}")]
+    Diagnostics(crate::diagnostics::Diagnostics),
 }
 
 pub type Result<T> = std::result::Result<T, CompilerError>;
 
 impl CompilerError {
     pub fn parse(file: impl Into<String>, line: usize, message: impl Into<String>) -> Self {
+        Self::parse_at(file, line, 0, message)
+    }
+
+    /// A parse error with a known 1-based column
+    pub fn parse_at(file: impl Into<String>, line: usize, column: usize, message: impl Into<String>) -> Self {
         Self::Parse {
             file: file.into(),
             line,
+            column,
             message: message.into(),
         }
     }
@@ -134,11 +144,7 @@ impl CompilerError {
 
     // Legacy methods for backward compatibility (when file info is not available)
     pub fn parse_legacy(line: usize, message: impl Into<String>) -> Self {
-        Self::Parse {
-            file: "<unknown>".to_string(),
-            line,
-            message: message.into(),
-        }
+        Self::parse("<unknown>", line, message)
     }
 
     pub fn semantic_legacy(line: usize, message: impl Into<String>) -> Self {
@@ -20,12 +20,15 @@
 pub mod cli;
 pub mod compiler;
 pub mod core;
+pub mod diagnostics;
 pub mod error;
 
 // 2. Define the public API by re-exporting the most important types and functions.
 //    This allows users to `use kryc::CompilerError` instead of `use kryc::error::CompilerError`.
 pub use cli::EnhancedCli;
 pub use core::*; // Re-exports Element, CompilerState, PropertyId, KrbFileInfo, etc.
+pub use compiler::file_provider::{DiskFileProvider, FileProvider, MemoryFileProvider};
+pub use diagnostics::{Diagnostic, Diagnostics, Severity, Span};
 pub use error::{CompilerError, Result};
 
 // --- Public-Facing Structs, Enums, and Constants for Library Users ---
@@ -52,7 +55,8 @@ pub const BUILD_INFO: CompilerInfo = CompilerInfo {
     target_krb_version: (core::constants::KRB_VERSION_MAJOR, core::constants::KRB_VERSION_MINOR),
     supported_features: &[
         "includes", "variables", "styles", "components", "scripting",
-        "pseudo-selectors", "animations", "resources",
+        "pseudo-selectors", "animations", "resources", "platforms", "media-queries", "themes", "color-functions",
+        "gradients", "shadows", "filters", "expressions",
     ],
 };
 
@@ -67,6 +71,37 @@ pub enum TargetPlatform {
     Universal,
 }
 
+impl TargetPlatform {
+    /// The platform named in `@platform(...)` blocks and `key@platform` overrides
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "desktop" => Some(Self::Desktop),
+            "mobile" => Some(Self::Mobile),
+            "web" => Some(Self::Web),
+            "embedded" => Some(Self::Embedded),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Desktop => "desktop",
+            Self::Mobile => "mobile",
+            Self::Web => "web",
+            Self::Embedded => "embedded",
+            Self::Universal => "universal",
+        }
+    }
+
+    /// Limits and capabilities of this platform's renderer
+    pub fn profile(self) -> &'static PlatformProfile {
+        match self {
+            Self::Embedded => &PlatformProfile::EMBEDDED,
+            Self::Desktop | Self::Mobile | Self::Web | Self::Universal => &PlatformProfile::STANDARD,
+        }
+    }
+}
+
 /// Compilation options and settings to control the compiler's behavior.
 #[derive(Debug, Clone, Default)]
 pub struct CompilerOptions {
@@ -75,17 +110,39 @@ pub struct CompilerOptions {
     pub target_platform: TargetPlatform,
     pub embed_scripts: bool,
     pub compress_output: bool,
+    /// Store font files inline in the KRB instead of referring to them by path
+    pub embed_fonts: bool,
+    /// Store every referenced image, font, sound and video file inline, making the KRB self-contained
+    pub embed_resources: bool,
+    /// Largest file, in bytes, that may be embedded for a resource type
+    pub resource_size_limits: std::collections::HashMap<core::types::ResourceType, u64>,
     pub max_file_size: u64,
+    /// Encode fractional numbers as Q16.16 fixed point; always on when the target platform requires it
+    pub fixed_point: bool,
+    /// Write colors with 16 bits per channel for wide-gamut renderers
+    pub extended_color: bool,
     pub include_directories: Vec<String>,
     pub generate_debug_info: bool,
+    /// Compile twice and fail unless both builds are byte-identical
+    pub verify_reproducible: bool,
     pub custom_variables: std::collections::HashMap<String, String>,
 }
 
+impl CompilerOptions {
+    /// Whether numbers are written as fixed point, by request or because the target has no FPU
+    pub fn uses_fixed_point(&self) -> bool {
+        self.fixed_point || self.target_platform.profile().fixed_point
+    }
+}
+
 /// Compilation statistics and metrics returned after a successful compilation.
 #[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct CompilationStats {
     pub source_size: u64,
+    /// Bytes written, after compression when it is enabled
     pub output_size: u64,
+    /// Size of the KRB image before compression; equal to `output_size` otherwise
+    pub uncompressed_size: u64,
     pub compression_ratio: f64,
     pub element_count: usize,
     pub style_count: usize,
@@ -97,6 +154,14 @@ pub struct CompilationStats {
     pub variable_count: usize,
     pub compile_time_ms: u64,
     pub peak_memory_usage: u64,
+    /// Canonical paths of every file the output was built from: included modules
+    /// and external script or resource files
+    pub dependencies: Vec<std::path::PathBuf>,
+    /// Warnings found while compiling; errors fail the compilation instead
+    pub warnings: Vec<Diagnostic>,
+    /// Source map of the output, built when `generate_debug_info` is set
+    #[serde(skip)]
+    pub debug_map: Option<KrbDebugMap>,
 }
 
 // --- Public-Facing Functions (The Library's API) ---
@@ -114,6 +179,19 @@ pub fn compile_file_with_options(
     compiler::compile_with_options(input_path, output_path, options)
 }
 
+/// Compile source text in memory, returning the KRB bytes.
+///
+/// Includes are resolved through `provider` relative to `filename`, so editors
+/// and test harnesses can compile unsaved buffers without touching the disk.
+pub fn compile_source(
+    source: &str,
+    filename: &str,
+    provider: &dyn FileProvider,
+    options: CompilerOptions,
+) -> Result<(Vec<u8>, CompilationStats)> {
+    compiler::compile_source(source, filename, provider, options)
+}
+
 /// A convenience function to compile a file with default options.
 pub fn compile_file(input_path: &str, output_path: &str) -> Result<CompilationStats> {
     compile_file_with_options(input_path, output_path, CompilerOptions::default())
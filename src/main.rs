@@ -1,14 +1,28 @@
 //! Kryon Compiler Binary
 
-use kryc::{compile_file, CompilerError, NAME, VERSION};
+use kryc::{compile_file, CompilerError, DiskFileProvider, EnhancedCli, NAME, VERSION};
 use std::env;
 use std::process;
 
+/// Subcommands handled by the full CLI; anything else is treated as an input file
+const SUBCOMMANDS: &[&str] = &["compile", "check", "analyze", "init", "benchmark", "fmt", "lsp", "disasm", "verify", "help"];
+
 fn main() {
-    env_logger::init();
-    
     let args: Vec<String> = env::args().collect();
     
+    if args.len() >= 2 && (SUBCOMMANDS.contains(&args[1].as_str()) || args[1].starts_with('-')) {
+        if let Err(e) = EnhancedCli::new().run() {
+            // Diagnostics were already rendered with their source snippets
+            if !matches!(e, CompilerError::Diagnostics(_)) {
+                eprintln!("Error: {}", e);
+            }
+            process::exit(1);
+        }
+        return;
+    }
+    
+    env_logger::init();
+    
     if args.len() < 2 || args.len() > 3 {
         eprintln!("Usage: {} <input.kry> [output.krb]", args[0]);
         eprintln!("  {NAME} v{VERSION} - Kryon UI Language Compiler");
@@ -41,6 +55,11 @@ fn main() {
             eprintln!("IO Error: {}", e);
             process::exit(1);
         }
+        Err(CompilerError::Diagnostics(diagnostics)) => {
+            eprintln!("{}", diagnostics.render(&DiskFileProvider));
+            eprintln!("Compilation failed with {} error(s)", diagnostics.error_count());
+            process::exit(1);
+        }
         Err(e) => {
             eprintln!("Compilation failed: {}", e);
             process::exit(1);